@@ -0,0 +1,233 @@
+//! Shared analysis model built in a single traversal of the input file
+//!
+//! Type registration, import mappings and per-method call information used
+//! to be gathered by separate walks over the syntax tree (one per analyzer,
+//! plus one visitor per method of every large impl block). `AnalysisModel`
+//! collects all of it in one pass, reading method bodies with the method
+//! analyzer's call visitor, so the downstream stages only read from it.
+//!
+//! The model holds no syntax trees, only names and summaries, so it is
+//! `Send + Sync` and can be shared by multi-threaded drivers (an editor
+//! server, batch runs over many files) as is.
+
+use crate::import_analyzer::ImportAnalyzer;
+use crate::method_analyzer::{analyze_impl_methods, MethodInfo, MethodSummary};
+use std::collections::HashMap;
+use syn::visit::Visit;
+use syn::{File, ImplItem, Item, ItemImpl};
+
+/// Everything the splitting stages need to know about the input file
+pub struct AnalysisModel {
+    /// Struct and enum names in declaration order
+    pub type_names: Vec<String>,
+
+    /// Import and type mappings of the file
+    pub imports: ImportAnalyzer,
+
+    /// Methods of each top-level inherent impl block, keyed by item index
+    pub impl_methods: HashMap<usize, Vec<MethodSummary>>,
+}
+
+impl AnalysisModel {
+    /// Build the model with a single traversal of `file`
+    pub fn build(file: &File) -> Self {
+        let mut builder = ModelBuilder {
+            model: AnalysisModel {
+                type_names: Vec::new(),
+                imports: ImportAnalyzer::new(),
+                impl_methods: HashMap::new(),
+            },
+            item_index: 0,
+            depth: 0,
+        };
+        builder.visit_file(file);
+        builder.model
+    }
+
+    /// Methods of the inherent impl at `item_index`, if it is one
+    pub fn methods_of(&self, item_index: usize) -> Option<&[MethodSummary]> {
+        self.impl_methods.get(&item_index).map(Vec::as_slice)
+    }
+
+    /// Methods of the inherent impl at `item_index`, with their syntax
+    ///
+    /// # Arguments
+    ///
+    /// * `item_index` - Index of the impl among the file's items
+    /// * `item` - The impl itself, as found in the file the model was built from
+    ///
+    /// # Returns
+    ///
+    /// The methods in source order; empty if `item` is not an analyzed impl
+    pub fn method_infos(&self, item_index: usize, item: &Item) -> Vec<MethodInfo> {
+        let (Some(summaries), Item::Impl(impl_block)) = (self.methods_of(item_index), item) else {
+            return Vec::new();
+        };
+        impl_block
+            .items
+            .iter()
+            .filter_map(|impl_item| match impl_item {
+                ImplItem::Fn(method) => Some(method),
+                _ => None,
+            })
+            .zip(summaries)
+            .map(|(method, summary)| MethodInfo::from_summary(summary, method))
+            .collect()
+    }
+}
+
+/// Visitor that fills an `AnalysisModel`
+struct ModelBuilder {
+    model: AnalysisModel,
+
+    /// Index of the top-level item being visited
+    item_index: usize,
+
+    /// Item nesting depth (1 for top-level items)
+    depth: usize,
+}
+
+impl<'ast> Visit<'ast> for ModelBuilder {
+    fn visit_file(&mut self, file: &'ast File) {
+        for (index, item) in file.items.iter().enumerate() {
+            self.item_index = index;
+            self.visit_item(item);
+        }
+    }
+
+    fn visit_item(&mut self, item: &'ast Item) {
+        self.depth += 1;
+        if self.depth == 1 {
+            self.model.imports.record_item(item);
+            match item {
+                Item::Struct(s) => self.model.type_names.push(s.ident.to_string()),
+                Item::Enum(e) => self.model.type_names.push(e.ident.to_string()),
+                _ => {}
+            }
+        }
+        syn::visit::visit_item(self, item);
+        self.depth -= 1;
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast ItemImpl) {
+        // Nothing inside an impl is recorded besides its methods
+        if self.depth == 1 && node.trait_.is_none() {
+            self.model
+                .impl_methods
+                .insert(self.item_index, analyze_impl_methods(node));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::method_analyzer::PARALLEL_METHOD_THRESHOLD;
+
+    #[test]
+    fn test_model_collects_types_imports_and_methods() {
+        let file: File = syn::parse_quote! {
+            use std::sync::Arc;
+
+            pub struct Engine { shared: Arc<u32> }
+            pub enum Mode { Fast, Slow }
+
+            impl Engine {
+                fn run(&self) { self.step(); helper(); }
+                fn step(&self) {}
+                fn report(&self) -> Vec<u32> {
+                    println!("{}", self.label());
+                    [1].iter().map(Self::scale).chain([<Self as Tuned>::tune()]).collect()
+                }
+            }
+
+            impl Clone for Engine {
+                fn clone(&self) -> Self { self.run(); todo!() }
+            }
+
+            fn helper() {}
+        };
+
+        let model = AnalysisModel::build(&file);
+        assert_eq!(model.type_names, vec!["Engine", "Mode"]);
+        assert!(model.imports.is_resolved("Arc"));
+        assert!(model.imports.is_resolved("Mode"));
+
+        let methods = model.methods_of(3).unwrap();
+        assert_eq!(methods.len(), 3);
+        assert!(methods[0].calls_methods.contains("step"));
+        assert!(methods[0].calls_methods.contains("helper"));
+        for call in ["label", "scale", "tune"] {
+            assert!(methods[2].calls_methods.contains(call), "missing {}", call);
+        }
+        assert!(model.methods_of(4).is_none());
+    }
+
+    #[test]
+    fn test_huge_impls_match_sequential_analysis() {
+        let methods: String = (0..PARALLEL_METHOD_THRESHOLD + 40)
+            .map(|i| {
+                format!(
+                    "    /// Step {i}\n    fn m{i}(&self) {{\n        self.m{}();\n        helper(self.count);\n    }}\n",
+                    i + 1
+                )
+            })
+            .collect();
+        let source = format!(
+            "struct Big {{ count: u32 }}\n\nimpl Big {{\n{}}}\n",
+            methods
+        );
+        // Parsed from text, with source positions, and built from tokens, without
+        let parsed = syn::parse_file(&source).unwrap();
+        let quoted: Vec<syn::ImplItem> = (0..PARALLEL_METHOD_THRESHOLD + 40)
+            .map(|i| {
+                let name = quote::format_ident!("m{}", i);
+                let next = quote::format_ident!("m{}", i + 1);
+                syn::parse_quote! { fn #name(&self) { self.#next(); helper(self.count); } }
+            })
+            .collect();
+        let built: File = syn::parse_quote! {
+            struct Big { count: u32 }
+            impl Big { #(#quoted)* }
+        };
+
+        for file in [&parsed, &built] {
+            let model = AnalysisModel::build(file);
+            let parallel = model.methods_of(1).unwrap();
+            let infos = model.method_infos(1, &file.items[1]);
+            let sequential: Vec<MethodSummary> = infos
+                .iter()
+                .map(|info| MethodSummary::new(&info.item, "Big"))
+                .collect();
+
+            assert_eq!(parallel, sequential.as_slice());
+            assert_eq!(parallel[5].name, "m5");
+            assert!(parallel[5].calls_methods.contains("m6"));
+            assert!(parallel[5].calls_methods.contains("helper"));
+            assert!(parallel[5].fields.contains("count"));
+        }
+        let model = AnalysisModel::build(&parsed);
+        assert_eq!(model.methods_of(1).unwrap()[5].line_count, 5);
+    }
+
+    #[test]
+    fn test_model_can_be_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<AnalysisModel>();
+        assert_send_sync::<crate::export_analyzer::ReexportPlan>();
+        assert_send_sync::<crate::migration::MigrationPlan>();
+
+        let file: File = syn::parse_quote! {
+            struct Engine;
+            impl Engine { fn run(&self) { self.step(); } fn step(&self) {} }
+        };
+        let model = AnalysisModel::build(&file);
+        let calls = std::thread::scope(|scope| {
+            scope
+                .spawn(|| model.methods_of(1).unwrap()[0].calls_methods.clone())
+                .join()
+                .unwrap()
+        });
+        assert!(calls.contains("step"));
+    }
+}
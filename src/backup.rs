@@ -0,0 +1,595 @@
+//! Backups of the inputs, and restoring them
+//!
+//! Before a split replaces anything, the inputs are copied into a run
+//! directory under the project's backup root (`.splitrs/backups/` by
+//! default), named after the time of the run. A `manifest.json` next to the
+//! copies records where each file came from, so `splitrs backups restore`
+//! can put them back. A `--crate` run splits many files; each gets a
+//! numbered backup inside the run directory (`<run>/001/`), and restoring
+//! the run restores them all. Only the newest runs are kept.
+
+use crate::existing_module::absolute;
+use crate::file_system::FileSystem;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest inside a backup directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// What one backup holds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// SplitRS version that took the backup
+    pub version: String,
+
+    /// Seconds since the Unix epoch when the backup was taken
+    pub created_at: u64,
+
+    /// Directory the run wrote its modules to
+    pub output_dir: PathBuf,
+
+    /// The backed-up inputs
+    pub files: Vec<BackedUpFile>,
+
+    /// Files the run generated in place of the inputs, which it removed
+    /// (`--in-place`, `--crate`); restoring deletes them again
+    #[serde(default)]
+    pub generated: Vec<PathBuf>,
+
+    /// Files the run changed besides the inputs, such as the parent module
+    /// patched to declare the split module
+    #[serde(default)]
+    pub patched: Vec<BackedUpFile>,
+}
+
+/// One backed-up input
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackedUpFile {
+    /// Absolute path of the input
+    pub original: PathBuf,
+
+    /// Name of the copy inside the backup directory
+    pub backup: String,
+}
+
+/// Root directory of the backups of the project `path` belongs to
+///
+/// A relative `configured` root is resolved against the nearest ancestor
+/// of `path` with a `Cargo.toml`, or against the directory of `path`
+/// without one.
+///
+/// # Arguments
+///
+/// * `file_system` - Where the project lives
+/// * `configured` - The `backup_root` setting
+/// * `path` - An input file, or the directory a command runs in
+pub fn resolve_root(file_system: &dyn FileSystem, configured: &Path, path: &Path) -> PathBuf {
+    if configured.is_absolute() {
+        return configured.to_path_buf();
+    }
+    let path = absolute(path);
+    let is_dir = |path: &Path| file_system.metadata(path).is_ok_and(|m| m.is_dir);
+    let start = match path.parent() {
+        Some(parent) if !is_dir(&path) => parent,
+        _ => &path,
+    };
+    let project = start
+        .ancestors()
+        .find(|dir| {
+            file_system
+                .metadata(&dir.join("Cargo.toml"))
+                .is_ok_and(|m| !m.is_dir)
+        })
+        .unwrap_or(start);
+    project.join(configured)
+}
+
+/// Name of the backup directory of a run started at `created_at`
+pub fn run_name(created_at: u64) -> String {
+    format!("{:010}-{}", created_at, std::process::id())
+}
+
+/// Copy the inputs into `dir` and record them in its manifest
+///
+/// A single input is copied to `original.rs`. Several keep their paths
+/// relative to the directory they share, so `a/mod.rs` and `b/mod.rs` do
+/// not overwrite each other.
+///
+/// # Arguments
+///
+/// * `file_system` - Where to write the copies
+/// * `dir` - The backup directory of this run
+/// * `inputs` - The inputs to copy
+/// * `output_dir` - Directory the run writes its modules to
+pub fn create(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+    inputs: &[&PathBuf],
+    output_dir: &Path,
+) -> Result<BackupManifest> {
+    file_system.create_dir(dir)?;
+    let originals: Vec<PathBuf> = inputs.iter().map(|input| absolute(input)).collect();
+    let shared = shared_directory(&originals);
+    let mut files = Vec::new();
+    for (input, original) in inputs.iter().zip(originals) {
+        let backup = if inputs.len() == 1 {
+            "original.rs".to_string()
+        } else {
+            let relative = original.strip_prefix(&shared).unwrap_or(&original);
+            relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        };
+        let copy = dir.join(&backup);
+        if let Some(parent) = copy.parent() {
+            file_system.create_dir(parent)?;
+        }
+        file_system.write(&copy, &file_system.read(input)?)?;
+        files.push(BackedUpFile { original, backup });
+    }
+    let manifest = BackupManifest {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        output_dir: absolute(output_dir),
+        files,
+        generated: Vec::new(),
+        patched: Vec::new(),
+    };
+    write_manifest(file_system, dir, &manifest)?;
+    Ok(manifest)
+}
+
+fn write_manifest(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+    manifest: &BackupManifest,
+) -> Result<()> {
+    let json = serde_json::to_string_pretty(manifest).context("Failed to serialize manifest")?;
+    file_system.write(&dir.join(MANIFEST_FILE), &(json + "\n"))
+}
+
+/// Record in the backup at `dir` that the run's output replaces its inputs
+///
+/// Call it before the inputs are removed and `patched` is changed, so that
+/// restoring the backup can undo both.
+///
+/// # Arguments
+///
+/// * `file_system` - Where the backup lives
+/// * `dir` - The backup directory of the run
+/// * `generated` - The files the run wrote
+/// * `patched` - Files about to be changed besides the inputs; their
+///   current content is copied into the backup
+pub fn record_replacement(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+    generated: &[PathBuf],
+    patched: &[&Path],
+) -> Result<()> {
+    let mut manifest =
+        read_manifest(file_system, dir)?.context(format!("No backup manifest in {:?}", dir))?;
+    manifest.generated = generated.iter().map(|path| absolute(path)).collect();
+    for path in patched {
+        let backup = format!("patched/{}", file_name(path));
+        file_system.create_dir(&dir.join("patched"))?;
+        file_system.write(&dir.join(&backup), &file_system.read(path)?)?;
+        manifest.patched.push(BackedUpFile {
+            original: absolute(path),
+            backup,
+        });
+    }
+    write_manifest(file_system, dir, &manifest)
+}
+
+/// Delete the files a run generated in place of its inputs, and the
+/// directories they leave empty
+///
+/// Other files in the output directory, such as modules it already held,
+/// are kept.
+fn remove_generated(file_system: &dyn FileSystem, manifest: &BackupManifest) -> Result<()> {
+    let mut dirs = std::collections::BTreeSet::new();
+    for path in &manifest.generated {
+        if file_system.metadata(path).is_ok_and(|m| !m.is_dir) {
+            file_system.remove(path)?;
+        }
+        dirs.extend(
+            path.ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(&manifest.output_dir))
+                .map(Path::to_path_buf),
+        );
+    }
+    // Deepest first, so parents are empty once their children are gone
+    for dir in dirs.iter().rev() {
+        if file_system
+            .read_dir(dir)
+            .is_ok_and(|entries| entries.is_empty())
+        {
+            file_system.remove_dir_all(dir)?;
+        }
+    }
+    Ok(())
+}
+
+/// The deepest directory holding all of `paths`
+fn shared_directory(paths: &[PathBuf]) -> PathBuf {
+    let mut shared = paths
+        .first()
+        .and_then(|path| path.parent())
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    while !paths.iter().all(|path| path.starts_with(&shared)) && shared.pop() {}
+    shared
+}
+
+/// The backups under `root` with their manifests, newest first
+///
+/// The backups of a batch are named `<run>/<number>`.
+pub fn list(file_system: &dyn FileSystem, root: &Path) -> Result<Vec<(String, BackupManifest)>> {
+    let mut backups = Vec::new();
+    let is_dir = |path: &Path| file_system.metadata(path).is_ok_and(|m| m.is_dir);
+    if !is_dir(root) {
+        return Ok(backups);
+    }
+    for run in file_system.read_dir(root)? {
+        let run_name = file_name(&run);
+        if let Some(manifest) = read_manifest(file_system, &run)? {
+            backups.push((run_name, manifest));
+            continue;
+        }
+        if !is_dir(&run) {
+            continue;
+        }
+        for dir in file_system.read_dir(&run)? {
+            if let Some(manifest) = read_manifest(file_system, &dir)? {
+                backups.push((format!("{}/{}", run_name, file_name(&dir)), manifest));
+            }
+        }
+    }
+    backups.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(backups)
+}
+
+/// The manifest of the backup in `dir`, if `dir` is a backup
+fn read_manifest(file_system: &dyn FileSystem, dir: &Path) -> Result<Option<BackupManifest>> {
+    let path = dir.join(MANIFEST_FILE);
+    let Ok(json) = file_system.read(&path) else {
+        return Ok(None);
+    };
+    let manifest = serde_json::from_str(&json).context(format!("Failed to parse {:?}", path))?;
+    Ok(Some(manifest))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Name of the run a backup belongs to: `<run>` of `<run>/<number>`
+fn run_of(name: &str) -> &str {
+    name.split('/').next().unwrap_or(name)
+}
+
+/// The backup of the newest run under `root` for the same output
+/// directory, if it already holds `inputs` as they are now
+///
+/// A re-run over unchanged inputs reuses it instead of taking another copy.
+pub fn find_identical(
+    file_system: &dyn FileSystem,
+    root: &Path,
+    inputs: &[&PathBuf],
+    output_dir: &Path,
+) -> Result<Option<PathBuf>> {
+    let backups = list(file_system, root)?;
+    let Some(newest_run) = backups.first().map(|(name, _)| run_of(name).to_string()) else {
+        return Ok(None);
+    };
+    let Some((name, manifest)) = backups.into_iter().find(|(name, manifest)| {
+        run_of(name) == newest_run && manifest.output_dir == absolute(output_dir)
+    }) else {
+        return Ok(None);
+    };
+    let dir = root.join(name);
+    let identical = manifest.files.len() == inputs.len()
+        && inputs.iter().all(|input| {
+            manifest.files.iter().any(|file| {
+                file.original == absolute(input)
+                    && match (
+                        file_system.read(input),
+                        file_system.read(&dir.join(&file.backup)),
+                    ) {
+                        (Ok(current), Ok(copy)) => current == copy,
+                        _ => false,
+                    }
+            })
+        });
+    Ok(identical.then_some(dir))
+}
+
+/// Delete all but the `keep` newest runs under `root`; 0 keeps all
+///
+/// The backups of a batch are one run, and are kept or deleted together.
+///
+/// # Returns
+///
+/// The deleted run directories
+pub fn prune(file_system: &dyn FileSystem, root: &Path, keep: usize) -> Result<Vec<PathBuf>> {
+    if keep == 0 {
+        return Ok(Vec::new());
+    }
+    let mut runs: Vec<String> = Vec::new();
+    for (name, _) in list(file_system, root)? {
+        let run = run_of(&name);
+        if runs.last().is_none_or(|last| last != run) {
+            runs.push(run.to_string());
+        }
+    }
+    let mut removed = Vec::new();
+    for run in runs.into_iter().skip(keep) {
+        let dir = root.join(run);
+        file_system.remove_dir_all(&dir)?;
+        removed.push(dir);
+    }
+    Ok(removed)
+}
+
+/// Write the files of a backup back to where they came from
+///
+/// # Arguments
+///
+/// * `file_system` - Where the backed-up inputs are written back
+/// * `root` - Root directory of the backups
+/// * `name` - The backup to restore, a run to restore all its backups, or
+///   `latest` for the newest run
+///
+/// # Returns
+///
+/// The restored backups with their manifests
+pub fn restore(
+    file_system: &dyn FileSystem,
+    root: &Path,
+    name: &str,
+) -> Result<Vec<(String, BackupManifest)>> {
+    let backups = list(file_system, root)?;
+    let run = match name {
+        "latest" => backups
+            .first()
+            .map(|(newest, _)| run_of(newest).to_string()),
+        _ => None,
+    };
+    let selected: Vec<(String, BackupManifest)> = backups
+        .into_iter()
+        .filter(|(backup, _)| match &run {
+            Some(run) => run_of(backup) == run,
+            None => backup == name || run_of(backup) == name,
+        })
+        .collect();
+    if selected.is_empty() {
+        anyhow::bail!("No backup {:?} in {:?}", name, root);
+    }
+
+    // Generated files go first: a directory that replaced `b.rs` may hold
+    // restored files of the same run, such as `b/c.rs`
+    for (_, manifest) in &selected {
+        remove_generated(file_system, manifest)?;
+    }
+    for (name, manifest) in &selected {
+        for file in manifest.files.iter().chain(&manifest.patched) {
+            let copy = root.join(name).join(&file.backup);
+            let content = file_system.read(&copy)?;
+            file_system
+                .write(&file.original, &content)
+                .context(format!("Failed to restore {:?}", file.original))?;
+        }
+    }
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::{MemoryFileSystem, RealFileSystem};
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_create_list_restore() {
+        let dir = env::temp_dir().join(format!("splitrs_backups_{}", std::process::id()));
+        let root = dir.join(".splitrs/backups");
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("big.rs");
+        fs::write(&input, "pub struct A;\n").unwrap();
+
+        let manifest = create(
+            &RealFileSystem,
+            &root.join(run_name(1_700_000_000)),
+            &[&input],
+            &dir.join("big"),
+        )
+        .unwrap();
+        assert_eq!(manifest.files[0].backup, "original.rs");
+        fs::write(&input, "pub mod types;\n").unwrap();
+        create(
+            &RealFileSystem,
+            &root.join(run_name(1_700_000_100)),
+            &[&input],
+            &dir.join("big"),
+        )
+        .unwrap();
+
+        let names: Vec<String> = list(&RealFileSystem, &root)
+            .unwrap()
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names[0].starts_with("1700000100-"));
+
+        restore(&RealFileSystem, &root, &names[1]).unwrap();
+        assert_eq!(fs::read_to_string(&input).unwrap(), "pub struct A;\n");
+        restore(&RealFileSystem, &root, "latest").unwrap();
+        assert_eq!(fs::read_to_string(&input).unwrap(), "pub mod types;\n");
+        assert!(restore(&RealFileSystem, &root, "nope").is_err());
+
+        let newest = root.join(&names[0]);
+        let found = find_identical(&RealFileSystem, &root, &[&input], &dir.join("big")).unwrap();
+        assert_eq!(found, Some(newest));
+        assert!(
+            find_identical(&RealFileSystem, &root, &[&input], &dir.join("other"))
+                .unwrap()
+                .is_none()
+        );
+        fs::write(&input, "pub struct B;\n").unwrap();
+        assert!(
+            find_identical(&RealFileSystem, &root, &[&input], &dir.join("big"))
+                .unwrap()
+                .is_none()
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_inputs_with_the_same_file_name() {
+        let dir = env::temp_dir().join(format!("splitrs_backup_names_{}", std::process::id()));
+        let root = dir.join(".splitrs/backups");
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::create_dir_all(dir.join("b/c")).unwrap();
+        let a = dir.join("a/mod.rs");
+        let b = dir.join("b/c/mod.rs");
+        fs::write(&a, "pub struct A;\n").unwrap();
+        fs::write(&b, "pub struct B;\n").unwrap();
+
+        let manifest = create(
+            &RealFileSystem,
+            &root.join(run_name(1_700_000_000)),
+            &[&a, &b],
+            &dir.join("merged"),
+        )
+        .unwrap();
+        let backups: Vec<&str> = manifest.files.iter().map(|f| f.backup.as_str()).collect();
+        assert_eq!(backups, vec!["a/mod.rs", "b/c/mod.rs"]);
+
+        fs::write(&a, "").unwrap();
+        fs::write(&b, "").unwrap();
+        restore(&RealFileSystem, &root, "latest").unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "pub struct A;\n");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "pub struct B;\n");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_resolve_root() {
+        let dir = env::temp_dir().join(format!("splitrs_backup_root_{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        let input = dir.join("src/big.rs");
+        fs::write(&input, "").unwrap();
+        let root = Path::new(".splitrs/backups");
+
+        let disk = &RealFileSystem;
+        assert_eq!(
+            resolve_root(disk, root, &input),
+            dir.join("src/.splitrs/backups")
+        );
+        fs::write(dir.join("Cargo.toml"), "").unwrap();
+        assert_eq!(
+            resolve_root(disk, root, &input),
+            dir.join(".splitrs/backups")
+        );
+        assert_eq!(resolve_root(disk, root, &dir), dir.join(".splitrs/backups"));
+        assert_eq!(
+            resolve_root(disk, Path::new("/b"), &input),
+            PathBuf::from("/b")
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_prune_keeps_newest() {
+        let root = PathBuf::from("/project/.splitrs/backups");
+        let input = PathBuf::from("/project/lib.rs");
+        let file_system = MemoryFileSystem::new().with_file(&input, "");
+        for created_at in [3, 1, 2] {
+            create(
+                &file_system,
+                &root.join(run_name(created_at)),
+                &[&input],
+                &root,
+            )
+            .unwrap();
+        }
+
+        assert!(prune(&file_system, &root, 0).unwrap().is_empty());
+        let removed = prune(&file_system, &root, 2).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(removed[0].ends_with(run_name(1)));
+        assert_eq!(list(&file_system, &root).unwrap().len(), 2);
+        assert!(!file_system.exists(&removed[0]));
+    }
+
+    #[test]
+    fn test_batch_backups_are_one_run() {
+        let root = PathBuf::from("/project/.splitrs/backups");
+        let a = PathBuf::from("/project/src/a.rs");
+        let b = PathBuf::from("/project/src/b.rs");
+        let file_system = MemoryFileSystem::new()
+            .with_file(&a, "pub struct A;\n")
+            .with_file(&b, "pub struct B;\n");
+        create(&file_system, &root.join(run_name(1)), &[&a], &root).unwrap();
+        let batch = root.join(run_name(2));
+        create(
+            &file_system,
+            &batch.join("001"),
+            &[&a],
+            Path::new("/project/src/a"),
+        )
+        .unwrap();
+        create(
+            &file_system,
+            &batch.join("002"),
+            &[&b],
+            Path::new("/project/src/b"),
+        )
+        .unwrap();
+
+        let names: Vec<String> = list(&file_system, &root)
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                format!("{}/002", run_name(2)),
+                format!("{}/001", run_name(2)),
+                run_name(1)
+            ]
+        );
+        assert_eq!(
+            find_identical(&file_system, &root, &[&a], Path::new("/project/src/a")).unwrap(),
+            Some(batch.join("001"))
+        );
+
+        file_system.write(&a, "").unwrap();
+        file_system.write(&b, "").unwrap();
+        assert_eq!(restore(&file_system, &root, "latest").unwrap().len(), 2);
+        assert_eq!(file_system.read(&a).unwrap(), "pub struct A;\n");
+        assert_eq!(file_system.read(&b).unwrap(), "pub struct B;\n");
+
+        assert_eq!(
+            prune(&file_system, &root, 1).unwrap(),
+            vec![root.join(run_name(1))]
+        );
+        assert_eq!(list(&file_system, &root).unwrap().len(), 2);
+    }
+}
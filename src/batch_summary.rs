@@ -0,0 +1,210 @@
+//! Summary of a `--crate` run
+//!
+//! Splitting a whole crate runs one split per oversized file, and each
+//! prints its own log. At the end, one summary covers the batch: the files
+//! that were split, left alone or failed, how many modules were created and
+//! how many lines moved out of the original files. It is printed for people
+//! and written as `.splitrs-summary.json` into the crate for tooling.
+
+use crate::crate_mode::{SkipReason, SkippedFile};
+use crate::file_system::FileSystem;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name of the summary file inside the crate directory
+pub const SUMMARY_FILE: &str = ".splitrs-summary.json";
+
+/// Outcome of splitting one file of the batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileOutcome {
+    /// The split file
+    pub input: PathBuf,
+
+    /// Module directory it was split into
+    pub output: PathBuf,
+
+    /// Line count of the file before the split
+    pub lines: usize,
+
+    /// Number of modules in the output directory, if its report was written
+    pub modules: Option<usize>,
+
+    /// Why the split failed, if it did
+    pub error: Option<String>,
+}
+
+/// Summary of a batch of splits
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchSummary {
+    /// SplitRS version that ran the batch
+    pub version: String,
+
+    /// Crate whose files were split
+    pub crate_dir: PathBuf,
+
+    /// Threshold files were split above
+    pub max_lines: usize,
+
+    /// Whether the batch was a preview that wrote nothing
+    pub dry_run: bool,
+
+    /// Files over the threshold, in the order they were split
+    pub processed: Vec<FileOutcome>,
+
+    /// Files within the threshold
+    pub skipped: Vec<PathBuf>,
+
+    /// Files under `src/bin/`, which are never split
+    pub binaries: Vec<PathBuf>,
+
+    /// Number of files that failed to split
+    pub failed: usize,
+
+    /// Number of modules the successful splits created
+    pub modules_created: usize,
+
+    /// Lines of the successfully split files, now spread over their modules
+    pub lines_moved: usize,
+}
+
+impl BatchSummary {
+    /// Start the summary of a batch
+    ///
+    /// # Arguments
+    ///
+    /// * `crate_dir` - Crate whose files are split
+    /// * `max_lines` - Threshold files are split above
+    /// * `dry_run` - Whether the batch writes nothing
+    /// * `skipped` - Files of the crate left alone
+    pub fn new(crate_dir: &Path, max_lines: usize, dry_run: bool, skipped: &[SkippedFile]) -> Self {
+        let files = |reason: SkipReason| {
+            skipped
+                .iter()
+                .filter(|file| file.reason == reason)
+                .map(|file| file.path.clone())
+                .collect()
+        };
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            crate_dir: crate_dir.to_path_buf(),
+            max_lines,
+            dry_run,
+            skipped: files(SkipReason::WithinLimit),
+            binaries: files(SkipReason::Binary),
+            ..Self::default()
+        }
+    }
+
+    /// Record the outcome of one file and update the totals
+    pub fn record(&mut self, outcome: FileOutcome) {
+        if outcome.error.is_some() {
+            self.failed += 1;
+        } else {
+            self.modules_created += outcome.modules.unwrap_or_default();
+            self.lines_moved += outcome.lines;
+        }
+        self.processed.push(outcome);
+    }
+
+    /// Human-readable summary, one fact per line
+    pub fn render(&self) -> String {
+        let mut out = format!("Summary of {:?}:\n", self.crate_dir);
+        out.push_str(&format!(
+            "  Processed: {} file(s) over {} lines, {} split, {} failed\n",
+            self.processed.len(),
+            self.max_lines,
+            self.processed.len() - self.failed,
+            self.failed
+        ));
+        out.push_str(&format!(
+            "  Skipped: {} file(s) within the limit, {} in src/bin/\n",
+            self.skipped.len(),
+            self.binaries.len()
+        ));
+        if !self.dry_run {
+            out.push_str(&format!("  Modules created: {}\n", self.modules_created));
+        }
+        out.push_str(&format!("  Lines moved: {}\n", self.lines_moved));
+        let failures: Vec<&FileOutcome> = self
+            .processed
+            .iter()
+            .filter(|outcome| outcome.error.is_some())
+            .collect();
+        if !failures.is_empty() {
+            out.push_str("  Failed:\n");
+            for outcome in failures {
+                out.push_str(&format!(
+                    "    {:?}: {}\n",
+                    outcome.input,
+                    outcome.error.as_deref().unwrap_or_default()
+                ));
+            }
+        }
+        out
+    }
+
+    /// Write the summary into the crate directory
+    ///
+    /// # Returns
+    ///
+    /// The path of the written summary
+    pub fn write(&self, file_system: &dyn FileSystem) -> Result<PathBuf> {
+        let path = self.crate_dir.join(SUMMARY_FILE);
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize summary")?;
+        file_system
+            .write(&path, &(json + "\n"))
+            .context(format!("Failed to write summary: {:?}", path))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::MemoryFileSystem;
+
+    #[test]
+    fn test_summary_totals_and_round_trip() {
+        let skipped = vec![
+            SkippedFile {
+                path: PathBuf::from("/app/src/small.rs"),
+                reason: SkipReason::WithinLimit,
+            },
+            SkippedFile {
+                path: PathBuf::from("/app/src/bin/tool.rs"),
+                reason: SkipReason::Binary,
+            },
+        ];
+        let mut summary = BatchSummary::new(Path::new("/app"), 500, false, &skipped);
+        summary.record(FileOutcome {
+            input: PathBuf::from("/app/src/engine.rs"),
+            output: PathBuf::from("/app/src/engine"),
+            lines: 1200,
+            modules: Some(4),
+            error: None,
+        });
+        summary.record(FileOutcome {
+            input: PathBuf::from("/app/src/parser.rs"),
+            output: PathBuf::from("/app/src/parser"),
+            lines: 900,
+            modules: None,
+            error: Some("Failed to parse".to_string()),
+        });
+
+        assert_eq!(
+            (summary.failed, summary.modules_created, summary.lines_moved),
+            (1, 4, 1200)
+        );
+        let human = summary.render();
+        assert!(human.contains("Processed: 2 file(s) over 500 lines, 1 split, 1 failed"));
+        assert!(human.contains("Skipped: 1 file(s) within the limit, 1 in src/bin/"));
+        assert!(human.contains("\"/app/src/parser.rs\": Failed to parse"));
+
+        let file_system = MemoryFileSystem::new().with_file("/app/Cargo.toml", "");
+        let path = summary.write(&file_system).unwrap();
+        let written: BatchSummary =
+            serde_json::from_str(&file_system.read(&path).unwrap()).unwrap();
+        assert_eq!(written, summary);
+    }
+}
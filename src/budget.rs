@@ -0,0 +1,296 @@
+//! Staged splits under a budget
+//!
+//! Splitting a 10,000-line file at once yields dozens of modules and a diff
+//! nobody can review. With `--top K` or `--max-moved-lines N`, a run only
+//! extracts the largest modules that fit the budget; everything else stays
+//! in `mod.rs`, which holds the module's code as before (same module path,
+//! same imports). `.splitrs-plan.md` lists what was moved and the steps
+//! left, each of which is a later run on `mod.rs` with the same budget.
+
+use crate::migration::item_labels;
+use quote::ToTokens;
+use std::collections::BTreeSet;
+use std::path::Path;
+use syn::{ImplItem, Item};
+
+/// Name of the plan file written into the output directory
+pub const PLAN_FILE: &str = ".splitrs-plan.md";
+
+/// How much a single run may move
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Budget {
+    /// Most lines the extracted modules may hold together
+    pub max_moved_lines: Option<usize>,
+
+    /// Most modules to extract
+    pub top: Option<usize>,
+}
+
+impl Budget {
+    /// Whether the run is limited at all
+    pub fn is_limited(&self) -> bool {
+        self.max_moved_lines.is_some() || self.top.is_some()
+    }
+
+    /// The largest modules that fit the budget
+    ///
+    /// Modules are taken largest first; one that would exceed the line
+    /// budget is skipped in favour of smaller ones. When no module fits, the
+    /// smallest is taken alone, so every run makes progress.
+    ///
+    /// # Arguments
+    ///
+    /// * `sizes` - Each candidate module with its line count
+    ///
+    /// # Returns
+    ///
+    /// The names of the modules to extract
+    pub fn select(&self, sizes: &[(String, usize)]) -> BTreeSet<String> {
+        let mut by_size: Vec<&(String, usize)> = sizes.iter().collect();
+        by_size.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let mut selected = BTreeSet::new();
+        let mut moved = 0;
+        for (name, lines) in by_size {
+            if self.top.is_some_and(|top| selected.len() >= top) {
+                break;
+            }
+            if self.max_moved_lines.is_some_and(|max| moved + lines > max) {
+                continue;
+            }
+            moved += lines;
+            selected.insert(name.clone());
+        }
+        if selected.is_empty() && self.top != Some(0) {
+            if let Some((name, _)) = sizes.iter().min_by_key(|(_, lines)| *lines) {
+                selected.insert(name.clone());
+            }
+        }
+        selected
+    }
+}
+
+/// The items of the input that stay in `mod.rs`
+///
+/// Inherent impls lose the methods that were extracted; other items stay
+/// unless an extracted module holds them. `use` items always stay, since
+/// the remaining code still relies on them.
+///
+/// # Arguments
+///
+/// * `items` - The input's items
+/// * `moved_labels` - Labels (see `migration::item_labels`) of the extracted items
+/// * `moved_items` - Token text of the extracted items without labels
+pub fn remainder(
+    items: &[Item],
+    moved_labels: &BTreeSet<String>,
+    moved_items: &BTreeSet<String>,
+) -> Vec<Item> {
+    let mut kept = Vec::new();
+    for item in items {
+        match item {
+            Item::Use(_) => kept.push(item.clone()),
+            Item::Impl(impl_block) if impl_block.trait_.is_none() => {
+                let self_ty = impl_block
+                    .self_ty
+                    .to_token_stream()
+                    .to_string()
+                    .replace(' ', "");
+                let mut impl_block = impl_block.clone();
+                let before = impl_block.items.len();
+                impl_block.items.retain(|impl_item| {
+                    let name = match impl_item {
+                        ImplItem::Fn(f) => f.sig.ident.to_string(),
+                        ImplItem::Const(c) => c.ident.to_string(),
+                        ImplItem::Type(t) => t.ident.to_string(),
+                        _ => return true,
+                    };
+                    !moved_labels.contains(&crate::migration::method_label(&self_ty, &name))
+                });
+                if before == 0 || !impl_block.items.is_empty() {
+                    kept.push(Item::Impl(impl_block));
+                }
+            }
+            _ => {
+                let labels = item_labels(item);
+                let moved = if labels.is_empty() {
+                    moved_items.contains(&item.to_token_stream().to_string())
+                } else {
+                    labels.iter().all(|label| moved_labels.contains(label))
+                };
+                if !moved {
+                    kept.push(item.clone());
+                }
+            }
+        }
+    }
+    kept
+}
+
+/// Point `super::module::` paths of extracted code at `super::` for modules
+/// whose items stayed in `mod.rs`
+pub fn redirect_to_parent(content: &str, deferred: &BTreeSet<String>) -> String {
+    let mut content = content.to_string();
+    for name in deferred {
+        content = content.replace(&format!("super::{}::", name), "super::");
+    }
+    content
+}
+
+/// A module of the full split that a run did not extract
+pub struct DeferredModule {
+    pub name: String,
+    pub lines: usize,
+    pub labels: Vec<String>,
+}
+
+/// Markdown plan of a staged split
+///
+/// # Arguments
+///
+/// * `input` - The file that was split
+/// * `output_dir` - The module directory; later steps run on its `mod.rs`
+/// * `budget` - The budget of this run, reused for the steps left
+/// * `extracted` - The modules this run wrote, with their line counts
+/// * `deferred` - The modules left for later runs
+pub fn render_plan(
+    input: &Path,
+    output_dir: &Path,
+    budget: &Budget,
+    extracted: &[(String, usize)],
+    deferred: &[DeferredModule],
+) -> String {
+    let moved: usize = extracted.iter().map(|(_, lines)| lines).sum();
+    let left: usize = deferred.iter().map(|m| m.lines).sum();
+    let mut out = format!(
+        "# Split plan for `{}`\n\nThis run extracted {} of {} modules ({} of {} lines). \
+         The rest stays in `mod.rs`.\n\n## Extracted\n\n",
+        input.display(),
+        extracted.len(),
+        extracted.len() + deferred.len(),
+        moved,
+        moved + left
+    );
+    for (name, lines) in extracted {
+        out.push_str(&format!("- `{}.rs` ({} lines)\n", name, lines));
+    }
+    if deferred.is_empty() {
+        out.push_str("\nNothing is left; the split is complete.\n");
+        return out;
+    }
+
+    let mut flags = String::new();
+    if let Some(top) = budget.top {
+        flags.push_str(&format!(" --top {}", top));
+    }
+    if let Some(max) = budget.max_moved_lines {
+        flags.push_str(&format!(" --max-moved-lines {}", max));
+    }
+    out.push_str(&format!(
+        "\n## Next steps\n\nEach step is one run (with the other options of this run) of\n\n```bash\nsplitrs --input {} --output {}{}\n```\n",
+        output_dir.join("mod.rs").display(),
+        output_dir.display(),
+        flags
+    ));
+
+    // Simulate the later runs to group the remaining modules into steps
+    let mut pending: Vec<&DeferredModule> = deferred.iter().collect();
+    let mut step = 0;
+    while !pending.is_empty() {
+        step += 1;
+        let sizes: Vec<(String, usize)> =
+            pending.iter().map(|m| (m.name.clone(), m.lines)).collect();
+        let selected = budget.select(&sizes);
+        if selected.is_empty() {
+            break;
+        }
+        out.push_str(&format!("\n### Step {}\n\n", step));
+        for module in pending.iter().filter(|m| selected.contains(&m.name)) {
+            out.push_str(&format!(
+                "- `{}.rs` ({} lines): {}\n",
+                module.name,
+                module.lines,
+                module.labels.join(", ")
+            ));
+        }
+        pending.retain(|m| !selected.contains(&m.name));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sizes(modules: &[(&str, usize)]) -> Vec<(String, usize)> {
+        modules
+            .iter()
+            .map(|(name, lines)| (name.to_string(), *lines))
+            .collect()
+    }
+
+    #[test]
+    fn test_select_largest_within_budget() {
+        let modules = sizes(&[("a", 100), ("b", 400), ("c", 250), ("d", 50)]);
+        let top = Budget {
+            top: Some(2),
+            ..Budget::default()
+        };
+        assert_eq!(
+            top.select(&modules).into_iter().collect::<Vec<_>>(),
+            vec!["b", "c"]
+        );
+        let lines = Budget {
+            max_moved_lines: Some(500),
+            ..Budget::default()
+        };
+        assert_eq!(
+            lines.select(&modules).into_iter().collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert!(!Budget::default().is_limited());
+        let tight = Budget {
+            max_moved_lines: Some(10),
+            ..Budget::default()
+        };
+        assert_eq!(
+            tight.select(&modules).into_iter().collect::<Vec<_>>(),
+            vec!["d"]
+        );
+    }
+
+    #[test]
+    fn test_remainder_keeps_what_was_not_moved() {
+        let file: syn::File = syn::parse_quote! {
+            use std::fmt;
+            pub struct Big;
+            impl Big {
+                pub fn parse(&self) {}
+                pub fn render(&self) {}
+            }
+            impl fmt::Debug for Big {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { Ok(()) }
+            }
+            fn helper() {}
+        };
+        let moved_labels: BTreeSet<String> = ["Big::parse", "helper", "fmt::Debug for Big"]
+            .iter()
+            .map(|label| label.to_string())
+            .collect();
+        let kept = remainder(&file.items, &moved_labels, &BTreeSet::new());
+        let rendered: Vec<String> = kept
+            .iter()
+            .map(|item| item.to_token_stream().to_string())
+            .collect();
+        assert_eq!(rendered.len(), 3);
+        assert!(rendered[0].starts_with("use std :: fmt"));
+        assert_eq!(rendered[1], "pub struct Big ;");
+        assert!(rendered[2].contains("render") && !rendered[2].contains("parse"));
+
+        let deferred: BTreeSet<String> = ["big_type".to_string()].into();
+        assert_eq!(
+            redirect_to_parent("use super::big_type::Big;\n", &deferred),
+            "use super::Big;\n"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Files that are Cargo targets of their own
+//!
+//! Every `.rs` file directly in `tests/`, `benches/` or `examples/` is
+//! auto-discovered as a separate crate (unless the manifest turns that off
+//! with `autotests`, `autobenches` or `autoexamples = false`), and so is
+//! every path a manifest lists under `[[test]]`, `[[bench]]` or
+//! `[[example]]`. Such a file cannot
+//! become a module directory: a `mod.rs` next to it is no target, and a new
+//! `.rs` file in the same directory would be compiled as one more. These
+//! files are split into inline modules within the file instead.
+
+use crate::existing_module::absolute;
+use std::fs;
+use std::path::{Component, Path};
+
+/// Kind of a target file outside `src/`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetKind {
+    Test,
+    Bench,
+    Example,
+}
+
+impl TargetKind {
+    /// Directory Cargo discovers these targets in
+    pub fn directory(self) -> &'static str {
+        match self {
+            TargetKind::Test => "tests",
+            TargetKind::Bench => "benches",
+            TargetKind::Example => "examples",
+        }
+    }
+
+    /// Manifest table listing these targets
+    fn manifest_key(self) -> &'static str {
+        match self {
+            TargetKind::Test => "test",
+            TargetKind::Bench => "bench",
+            TargetKind::Example => "example",
+        }
+    }
+
+    /// `[package]` flag that turns auto-discovery of these targets off
+    fn auto_discovery_key(self) -> &'static str {
+        match self {
+            TargetKind::Test => "autotests",
+            TargetKind::Bench => "autobenches",
+            TargetKind::Example => "autoexamples",
+        }
+    }
+}
+
+/// The target kind of `path`, if it is the root of a test, bench or example
+///
+/// The crate is the nearest ancestor directory with a `Cargo.toml`.
+pub fn detect(path: &Path) -> Option<TargetKind> {
+    let path = absolute(path);
+    let crate_root = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").is_file())?;
+    let relative = path.strip_prefix(crate_root).ok()?;
+    let kinds = [TargetKind::Test, TargetKind::Bench, TargetKind::Example];
+
+    let components: Vec<&str> = relative
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .collect();
+    let manifest: toml::Table = fs::read_to_string(crate_root.join("Cargo.toml"))
+        .ok()
+        .and_then(|manifest| toml::from_str(&manifest).ok())
+        .unwrap_or_default();
+    if let [directory, file] = components.as_slice() {
+        if file.ends_with(".rs") {
+            let discovered = kinds.iter().find(|kind| {
+                kind.directory() == *directory
+                    && manifest
+                        .get("package")
+                        .and_then(|package| package.get(kind.auto_discovery_key()))
+                        .and_then(toml::Value::as_bool)
+                        .unwrap_or(true)
+            });
+            if let Some(kind) = discovered {
+                return Some(*kind);
+            }
+        }
+    }
+
+    kinds.into_iter().find(|kind| {
+        manifest
+            .get(kind.manifest_key())
+            .and_then(toml::Value::as_array)
+            .is_some_and(|targets| {
+                targets.iter().any(|target| {
+                    target
+                        .get("path")
+                        .and_then(toml::Value::as_str)
+                        .is_some_and(|target_path| {
+                            Path::new(target_path) == Path::new(&components.join("/"))
+                        })
+                })
+            })
+    })
+}
+
+/// A target file with the generated modules inlined
+///
+/// Each `pub mod name;` declaration of `mod_rs` becomes `pub mod name { ... }`
+/// holding the module's content; `#[path]` attributes are dropped. The
+/// content is indented unless it holds multi-line literals, whose text
+/// indenting would change.
+///
+/// # Arguments
+///
+/// * `mod_rs` - The generated `mod.rs`
+/// * `modules` - Name and content of each generated module
+pub fn render_inline(mod_rs: &str, modules: &[(String, String)]) -> String {
+    let mut content = String::new();
+    for line in mod_rs.lines() {
+        if line.starts_with("#[path = ") {
+            continue;
+        }
+        let module = line
+            .strip_prefix("pub mod ")
+            .and_then(|rest| rest.strip_suffix(';'))
+            .and_then(|name| modules.iter().find(|(module, _)| module == name));
+        let Some((name, body)) = module else {
+            content.push_str(line);
+            content.push('\n');
+            continue;
+        };
+
+        content.push_str(&format!("pub mod {} {{\n", name));
+        let indent = indentable(body);
+        for body_line in body.trim_end().lines() {
+            if indent && !body_line.is_empty() {
+                content.push_str("    ");
+            }
+            content.push_str(body_line);
+            content.push('\n');
+        }
+        content.push_str("}\n");
+    }
+    content
+}
+
+/// Whether indenting every line leaves the code's meaning unchanged
+///
+/// Tokens print on one line, so a newline in the printed token stream can
+/// only come from a literal spanning lines.
+fn indentable(code: &str) -> bool {
+    use quote::ToTokens;
+    syn::parse_file(code).is_ok_and(|file| !file.to_token_stream().to_string().contains('\n'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_detects_targets() {
+        let root = env::temp_dir().join(format!("splitrs_targets_{}", std::process::id()));
+        fs::create_dir_all(root.join("tests/common")).unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"x\"\n\n[[bench]]\nname = \"speed\"\npath = \"perf/speed.rs\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(detect(&root.join("tests/api.rs")), Some(TargetKind::Test));
+        assert_eq!(
+            detect(&root.join("examples/demo.rs")),
+            Some(TargetKind::Example)
+        );
+        assert_eq!(detect(&root.join("perf/speed.rs")), Some(TargetKind::Bench));
+        assert_eq!(detect(&root.join("tests/common/mod.rs")), None);
+        assert_eq!(detect(&root.join("src/lib.rs")), None);
+
+        // Files in a directory whose auto-discovery is off are no targets
+        // unless the manifest lists them
+        fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"x\"\nautoexamples = false\n\n[[example]]\nname = \"demo\"\npath = \"examples/demo.rs\"\n",
+        )
+        .unwrap();
+        assert_eq!(detect(&root.join("examples/fixture.rs")), None);
+        assert_eq!(
+            detect(&root.join("examples/demo.rs")),
+            Some(TargetKind::Example)
+        );
+        assert_eq!(detect(&root.join("tests/api.rs")), Some(TargetKind::Test));
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_render_inline() {
+        let mod_rs = "//! Auto-generated module structure\n\n#[path = \"r#type.rs\"]\npub mod r#type;\npub mod helpers;\n\npub use helpers::*;\n";
+        let modules = vec![
+            (
+                "r#type".to_string(),
+                "//! Types\n\npub struct A;\n".to_string(),
+            ),
+            (
+                "helpers".to_string(),
+                "pub fn f() -> &'static str {\n    \"a\nb\"\n}\n".to_string(),
+            ),
+        ];
+        let inline = render_inline(mod_rs, &modules);
+        assert!(inline.contains("pub mod r#type {\n    //! Types\n\n    pub struct A;\n}\n"));
+        assert!(inline
+            .contains("pub mod helpers {\npub fn f() -> &'static str {\n    \"a\nb\"\n}\n}\n"));
+        assert!(!inline.contains("#[path"));
+        assert!(syn::parse_file(&inline).is_ok());
+    }
+}
@@ -0,0 +1,287 @@
+//! Feature-gated trait impls
+//!
+//! A trait impl under `#[cfg(feature = "serde")]` only exists when the
+//! feature is on. Gated impls of a type get a module of their own per
+//! `#[cfg]`, and that module's declaration and re-export carry the same
+//! `#[cfg]`, so the split compiles with and without the feature. The
+//! `#[cfg_attr(docsrs, doc(cfg(...)))]` attributes of the impls go on the
+//! declaration as well, so rustdoc marks the module as feature-gated.
+//!
+//! The modules of a gated type (its trait impls, the groups of a split impl)
+//! carry the type's `#[cfg]` too, since they do not compile without it. Impls
+//! gated differently from their type are reported, and their module gets
+//! both gates.
+
+use quote::ToTokens;
+use std::collections::BTreeMap;
+use syn::{Attribute, File, Item};
+
+/// Whether an attribute is `#[cfg(...)]`
+fn is_cfg(attr: &Attribute) -> bool {
+    attr.path().is_ident("cfg")
+}
+
+/// Whether an attribute is a `#[cfg_attr(..., doc(cfg(...)))]`
+fn is_doc_cfg(attr: &Attribute) -> bool {
+    attr.path().is_ident("cfg_attr")
+        && attr
+            .meta
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "")
+            .contains("doc(cfg")
+}
+
+/// The `#[cfg]` attributes of an item, rendered; empty when it is not gated
+pub fn cfg_key(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .filter(|attr| is_cfg(attr))
+        .map(|attr| attr.to_token_stream().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Attributes of gated impls to repeat on their module's declaration
+///
+/// The `#[cfg]` attributes of the first impl (all impls of a module share
+/// them) and the distinct doc(cfg) attributes of all of them.
+pub fn gate_attributes<'a>(impls: impl IntoIterator<Item = &'a [Attribute]>) -> Vec<Attribute> {
+    let mut gate: Vec<Attribute> = Vec::new();
+    for (index, attrs) in impls.into_iter().enumerate() {
+        for attr in attrs {
+            let wanted = (index == 0 && is_cfg(attr)) || is_doc_cfg(attr);
+            let rendered = attr.to_token_stream().to_string();
+            if wanted
+                && !gate
+                    .iter()
+                    .any(|kept| kept.to_token_stream().to_string() == rendered)
+            {
+                gate.push(attr.clone());
+            }
+        }
+    }
+    gate
+}
+
+/// Module name part describing a gate (`serde` for `feature = "serde"`,
+/// `not_std` for `not(feature = "std")`, `unix` for `unix`)
+pub fn gate_suffix(gate: &[Attribute]) -> String {
+    let mut words = Vec::new();
+    for attr in gate.iter().filter(|attr| is_cfg(attr)) {
+        let tokens = attr.meta.to_token_stream().to_string();
+        for word in tokens.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '"')) {
+            let word = word.trim_matches('"');
+            if !word.is_empty() && !matches!(word, "cfg" | "feature" | "all" | "any") {
+                words.push(word.to_lowercase());
+            }
+        }
+    }
+    if words.is_empty() {
+        "cfg".to_string()
+    } else {
+        words.join("_")
+    }
+}
+
+/// A line of `mod.rs` (`pub mod x;`, `pub use x::*;`) with attributes
+///
+/// # Arguments
+///
+/// * `line` - The item, as rendered without attributes
+/// * `attrs` - The attributes to put on it
+///
+/// # Returns
+///
+/// The item with its attributes, ending in a newline; `line` unchanged
+/// (plus the newline) when there are no attributes or it does not parse
+pub fn with_attributes(line: &str, attrs: &[Attribute]) -> String {
+    if attrs.is_empty() {
+        return format!("{}\n", line);
+    }
+    let Ok(mut item) = syn::parse_str::<Item>(line) else {
+        return format!("{}\n", line);
+    };
+    match &mut item {
+        Item::Mod(item) => item.attrs.extend(attrs.iter().cloned()),
+        Item::Use(item) => item.attrs.extend(attrs.iter().cloned()),
+        _ => return format!("{}\n", line),
+    }
+    prettyplease::unparse(&syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![item],
+    })
+}
+
+/// Only the `#[cfg]` attributes of a gate, for re-export lines
+pub fn cfg_only(gate: &[Attribute]) -> Vec<Attribute> {
+    gate.iter().filter(|attr| is_cfg(attr)).cloned().collect()
+}
+
+/// The gate of a module holding impls of a type
+///
+/// # Arguments
+///
+/// * `type_gate` - The `#[cfg]` attributes of the type
+/// * `impl_gate` - The gate of the impls
+///
+/// # Returns
+///
+/// The type's attributes, followed by those of the impls the type lacks
+pub fn merge_gates(type_gate: &[Attribute], impl_gate: &[Attribute]) -> Vec<Attribute> {
+    let mut gate = type_gate.to_vec();
+    for attr in impl_gate {
+        let rendered = attr.to_token_stream().to_string();
+        if !gate
+            .iter()
+            .any(|kept| kept.to_token_stream().to_string() == rendered)
+        {
+            gate.push(attr.clone());
+        }
+    }
+    gate
+}
+
+/// Impls of a file whose `#[cfg]` differs from the one of their type
+///
+/// Only types defined under a `#[cfg]` are checked; gating an impl of an
+/// ungated type is fine.
+///
+/// # Returns
+///
+/// The name of each such type with a description of the mismatch
+pub fn mismatched_impls(file: &File) -> Vec<(String, String)> {
+    let mut type_keys = BTreeMap::new();
+    for item in &file.items {
+        let (ident, attrs) = match item {
+            Item::Struct(i) => (&i.ident, &i.attrs),
+            Item::Enum(i) => (&i.ident, &i.attrs),
+            Item::Union(i) => (&i.ident, &i.attrs),
+            _ => continue,
+        };
+        let key = cfg_key(attrs);
+        if !key.is_empty() {
+            type_keys.insert(ident.to_string(), key);
+        }
+    }
+
+    let mut mismatches = Vec::new();
+    for item in &file.items {
+        let Item::Impl(impl_block) = item else {
+            continue;
+        };
+        let syn::Type::Path(self_ty) = &*impl_block.self_ty else {
+            continue;
+        };
+        let Some(type_name) = self_ty.path.segments.last().map(|s| s.ident.to_string()) else {
+            continue;
+        };
+        let Some(type_key) = type_keys.get(&type_name) else {
+            continue;
+        };
+        let impl_key = cfg_key(&impl_block.attrs);
+        if impl_key == *type_key {
+            continue;
+        }
+        let header = match &impl_block.trait_ {
+            Some((_, path, _)) => format!(
+                "impl {} for {}",
+                path.segments
+                    .last()
+                    .map(|s| s.ident.to_string())
+                    .unwrap_or_default(),
+                type_name
+            ),
+            None => format!("impl {}", type_name),
+        };
+        let detail = if impl_key.is_empty() {
+            format!(
+                "`{}` is not under the `#[cfg]` of `{}`; its module takes the type's",
+                header, type_name
+            )
+        } else {
+            format!(
+                "`{}` is gated differently from `{}`; its module takes both gates",
+                header, type_name
+            )
+        };
+        mismatches.push((type_name, detail));
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gate_attributes() {
+        let first: syn::ItemImpl = syn::parse_quote! {
+            #[cfg(feature = "serde")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+            #[allow(unused)]
+            impl Serialize for Id {}
+        };
+        let second: syn::ItemImpl = syn::parse_quote! {
+            #[cfg(feature = "serde")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+            impl<'de> Deserialize<'de> for Id {}
+        };
+        assert_eq!(cfg_key(&first.attrs), cfg_key(&second.attrs));
+        assert_eq!(cfg_key(&[]), "");
+
+        let gate = gate_attributes([first.attrs.as_slice(), second.attrs.as_slice()]);
+        assert_eq!(gate.len(), 2);
+        assert_eq!(gate_suffix(&gate), "serde");
+        let not_std: Vec<Attribute> = vec![syn::parse_quote!(#[cfg(not(feature = "std"))])];
+        assert_eq!(gate_suffix(&not_std), "not_std");
+    }
+
+    #[test]
+    fn test_with_attributes() {
+        let gate: Vec<Attribute> = vec![
+            syn::parse_quote!(#[cfg(feature = "serde")]),
+            syn::parse_quote!(#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]),
+        ];
+        assert_eq!(
+            with_attributes("pub mod id_serde_traits;", &gate),
+            "#[cfg(feature = \"serde\")]\n#[cfg_attr(docsrs, doc(cfg(feature = \"serde\")))]\n\
+             pub mod id_serde_traits;\n"
+        );
+        assert_eq!(
+            with_attributes("pub use id_serde_traits::*;", &cfg_only(&gate)),
+            "#[cfg(feature = \"serde\")]\npub use id_serde_traits::*;\n"
+        );
+        assert_eq!(with_attributes("pub mod types;", &[]), "pub mod types;\n");
+    }
+
+    #[test]
+    fn test_mismatched_impls() {
+        let file: File = syn::parse_quote! {
+            #[cfg(feature = "wire")]
+            pub struct Wire;
+            #[cfg(feature = "wire")]
+            impl Wire { fn send(&self) {} }
+            impl Clone for Wire { fn clone(&self) -> Self { Wire } }
+            #[cfg(unix)]
+            impl Wire { fn fd(&self) {} }
+            pub struct Plain;
+            #[cfg(unix)]
+            impl Plain {}
+        };
+        let mismatches = mismatched_impls(&file);
+        assert_eq!(mismatches.len(), 2);
+        assert!(mismatches[0]
+            .1
+            .starts_with("`impl Clone for Wire` is not under"));
+        assert!(mismatches[1]
+            .1
+            .starts_with("`impl Wire` is gated differently"));
+
+        let wire: Vec<Attribute> = vec![syn::parse_quote!(#[cfg(feature = "wire")])];
+        let unix: Vec<Attribute> = vec![syn::parse_quote!(#[cfg(unix)])];
+        assert_eq!(merge_gates(&wire, &wire).len(), 1);
+        assert_eq!(gate_suffix(&merge_gates(&wire, &unix)), "wire_unix");
+    }
+}
@@ -0,0 +1,295 @@
+//! Checking files against size limits
+//!
+//! `splitrs check` reports files that have grown past the configured limits
+//! without changing anything, for CI gates and pre-commit hooks. Each
+//! finding gets a severity from the `[check]` policy; any error makes the
+//! command fail.
+
+use crate::config::{CheckConfig, Severity};
+use crate::diagnostics::{severity_label, Diagnostic, Span};
+use crate::lenient::item_spans;
+use anyhow::{Context, Result};
+use quote::ToTokens;
+use std::collections::BTreeMap;
+use std::fmt;
+use syn::Item;
+
+/// Kind of finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingKind {
+    /// The file has more lines than `warn_lines` / `error_lines`
+    OversizedFile,
+
+    /// An inherent impl block has more lines than `max_impl_lines`
+    OversizedImpl,
+
+    /// A struct, enum, trait or free function has more lines than its
+    /// `max_<kind>_lines` limit
+    OversizedItem,
+
+    /// A type has more trait impls than `max_trait_impls`
+    TooManyTraitImpls,
+}
+
+impl FindingKind {
+    /// Name used in messages and in the `[check.severity]` table
+    pub fn key(self) -> &'static str {
+        match self {
+            FindingKind::OversizedFile => "oversized_file",
+            FindingKind::OversizedImpl => "oversized_impl",
+            FindingKind::OversizedItem => "oversized_item",
+            FindingKind::TooManyTraitImpls => "too_many_trait_impls",
+        }
+    }
+}
+
+/// A limit a file exceeds
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub kind: FindingKind,
+    pub severity: Severity,
+
+    /// The file, and the type for impl findings (`src/big.rs: Parser`)
+    pub location: String,
+
+    /// Lines of the file or item, when the finding has them
+    pub span: Option<Span>,
+
+    pub message: String,
+}
+
+impl Finding {
+    /// The finding as a diagnostic about `file`
+    pub fn to_diagnostic(&self, file: &str) -> Diagnostic {
+        let item = self
+            .location
+            .strip_prefix(file)
+            .and_then(|rest| rest.strip_prefix(": "));
+        Diagnostic {
+            file: file.to_string(),
+            span: self.span,
+            code: self.kind.key().to_string(),
+            severity: severity_label(self.severity).to_string(),
+            message: match item {
+                Some(item) => format!("{}: {}", item, self.message),
+                None => self.message.clone(),
+            },
+            suggested_fix: None,
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}]: {}: {}",
+            severity_label(self.severity),
+            self.kind.key(),
+            self.location,
+            self.message
+        )
+    }
+}
+
+/// Kind, name and line limit of an item with a per-kind limit
+fn item_limit(item: &Item, policy: &CheckConfig) -> Option<(&'static str, String, usize)> {
+    match item {
+        Item::Struct(s) => Some(("struct", s.ident.to_string(), policy.max_struct_lines)),
+        Item::Enum(e) => Some(("enum", e.ident.to_string(), policy.max_enum_lines)),
+        Item::Trait(t) => Some(("trait", t.ident.to_string(), policy.max_trait_lines)),
+        Item::Fn(f) => Some(("fn", f.sig.ident.to_string(), policy.max_fn_lines)),
+        _ => None,
+    }
+}
+
+/// Check one file's source against the policy
+///
+/// # Arguments
+///
+/// * `path` - Path of the file, for messages
+/// * `source` - Its content
+/// * `policy` - The `[check]` configuration
+///
+/// # Returns
+///
+/// The findings that are not allowed by the policy, in source order
+pub fn check_source(path: &str, source: &str, policy: &CheckConfig) -> Result<Vec<Finding>> {
+    syn::parse_file(source).context(format!("Failed to parse {}", path))?;
+    let mut findings = Vec::new();
+
+    let lines = source.lines().count();
+    let file_severity = if lines > policy.error_lines {
+        Some(Severity::Error)
+    } else if lines > policy.warn_lines {
+        Some(Severity::Warn)
+    } else {
+        None
+    };
+    if let Some(severity) = file_severity {
+        findings.push(Finding {
+            kind: FindingKind::OversizedFile,
+            severity: policy.severity.oversized_file.unwrap_or(severity),
+            location: path.to_string(),
+            span: Some(Span {
+                start_line: 1,
+                end_line: lines,
+            }),
+            message: format!(
+                "{} lines (warn above {}, error above {})",
+                lines, policy.warn_lines, policy.error_lines
+            ),
+        });
+    }
+
+    let mut trait_impls: BTreeMap<String, usize> = BTreeMap::new();
+    for (start, end) in item_spans(source) {
+        let text = &source[start..end];
+        let Ok(item) = syn::parse_str::<Item>(text.trim()) else {
+            continue;
+        };
+        let item_lines = text.trim().lines().count();
+        let start_line = source[..start + (text.len() - text.trim_start().len())]
+            .matches('\n')
+            .count()
+            + 1;
+        let span = Some(Span {
+            start_line,
+            end_line: start_line + item_lines - 1,
+        });
+        let Item::Impl(impl_block) = item else {
+            if let Some((kind, name, limit)) = item_limit(&item, policy) {
+                if item_lines > limit {
+                    findings.push(Finding {
+                        kind: FindingKind::OversizedItem,
+                        severity: policy.severity.oversized_item.unwrap_or(Severity::Warn),
+                        location: format!("{}: {}", path, name),
+                        span,
+                        message: format!("{} of {} lines (limit {})", kind, item_lines, limit),
+                    });
+                }
+            }
+            continue;
+        };
+        let self_ty = impl_block
+            .self_ty
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        if impl_block.trait_.is_some() {
+            *trait_impls.entry(self_ty).or_default() += 1;
+            continue;
+        }
+        if item_lines > policy.max_impl_lines {
+            findings.push(Finding {
+                kind: FindingKind::OversizedImpl,
+                severity: policy.severity.oversized_impl.unwrap_or(Severity::Warn),
+                location: format!("{}: {}", path, self_ty),
+                span,
+                message: format!(
+                    "impl block of {} lines (limit {})",
+                    item_lines, policy.max_impl_lines
+                ),
+            });
+        }
+    }
+    for (self_ty, count) in trait_impls {
+        if count > policy.max_trait_impls {
+            findings.push(Finding {
+                kind: FindingKind::TooManyTraitImpls,
+                severity: policy
+                    .severity
+                    .too_many_trait_impls
+                    .unwrap_or(Severity::Warn),
+                location: format!("{}: {}", path, self_ty),
+                span: None,
+                message: format!("{} trait impls (limit {})", count, policy.max_trait_impls),
+            });
+        }
+    }
+
+    findings.retain(|finding| finding.severity != Severity::Allow);
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> CheckConfig {
+        CheckConfig {
+            warn_lines: 10,
+            error_lines: 20,
+            max_impl_lines: 4,
+            max_trait_impls: 1,
+            ..CheckConfig::default()
+        }
+    }
+
+    const SOURCE: &str = "pub struct A;\n\
+        impl A {\n    fn a(&self) {}\n    fn b(&self) {}\n    fn c(&self) {}\n}\n\
+        impl Clone for A { fn clone(&self) -> Self { A } }\n\
+        impl Default for A { fn default() -> Self { A } }\n\
+        \n\n\n";
+
+    #[test]
+    fn test_default_severities() {
+        let findings = check_source("big.rs", SOURCE, &policy()).unwrap();
+        let kinds: Vec<(FindingKind, Severity)> =
+            findings.iter().map(|f| (f.kind, f.severity)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (FindingKind::OversizedFile, Severity::Warn),
+                (FindingKind::OversizedImpl, Severity::Warn),
+                (FindingKind::TooManyTraitImpls, Severity::Warn),
+            ]
+        );
+        assert_eq!(
+            findings[1].to_string(),
+            "warning[oversized_impl]: big.rs: A: impl block of 5 lines (limit 4)"
+        );
+    }
+
+    #[test]
+    fn test_severity_overrides() {
+        let mut policy = policy();
+        policy.severity.oversized_file = Some(Severity::Allow);
+        policy.severity.oversized_impl = Some(Severity::Error);
+        policy.max_trait_impls = 2;
+
+        let findings = check_source("big.rs", SOURCE, &policy).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, FindingKind::OversizedImpl);
+        assert_eq!(findings[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_per_kind_limits() {
+        let source = "pub enum Error {\n    A,\n    B,\n    C,\n}\n\
+            pub fn run() {\n    let a = 1;\n    let b = 2;\n    let _ = a + b;\n}\n";
+        let policy = CheckConfig {
+            max_enum_lines: 10,
+            max_fn_lines: 3,
+            ..CheckConfig::default()
+        };
+
+        let findings = check_source("lib.rs", source, &policy).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].to_string(),
+            "warning[oversized_item]: lib.rs: run: fn of 5 lines (limit 3)"
+        );
+
+        let diagnostic = findings[0].to_diagnostic("lib.rs");
+        assert_eq!(
+            diagnostic.span,
+            Some(Span {
+                start_line: 6,
+                end_line: 10
+            })
+        );
+        assert_eq!(diagnostic.code, "oversized_item");
+        assert_eq!(diagnostic.message, "run: fn of 5 lines (limit 3)");
+    }
+}
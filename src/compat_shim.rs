@@ -0,0 +1,115 @@
+//! Compatibility shims for the original module path
+//!
+//! When the split output lives next to the original file (e.g. `big.rs` is
+//! split into `big_split/`), the original file can be replaced by a thin shim
+//! that glob re-exports the new module. Paths like `crate::big::Thing` keep
+//! compiling and callers migrate at their own pace. The shim module is marked
+//! `#![deprecated]` so rustdoc flags it.
+
+use crate::existing_module::absolute;
+use std::path::{Component, Path};
+
+/// Module path (`crate::a::b`) of a source file or module directory
+///
+/// The crate is the nearest ancestor directory with a `Cargo.toml`; paths
+/// outside its `src/` directory have no module path.
+pub fn module_path(path: &Path) -> Option<String> {
+    // Lexical, so it also works for an output directory not created yet
+    let path = absolute(path);
+    let crate_root = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").is_file())?;
+    let relative = path.strip_prefix(crate_root.join("src")).ok()?;
+
+    let mut segments = vec!["crate".to_string()];
+    for component in relative.components() {
+        let Component::Normal(name) = component else {
+            return None;
+        };
+        let name = name.to_str()?;
+        let name = name.strip_suffix(".rs").unwrap_or(name);
+        segments.push(name.to_string());
+    }
+    if matches!(
+        segments.last().map(String::as_str),
+        Some("mod" | "lib" | "main")
+    ) {
+        segments.pop();
+    }
+    Some(segments.join("::"))
+}
+
+/// Module path given by the user (`core::storage` or `crate::core::storage`)
+/// as an absolute `crate::` path
+pub fn prefixed_module_path(prefix: &str) -> String {
+    let prefix = prefix.trim().trim_end_matches("::");
+    match prefix.strip_prefix("crate") {
+        Some("") => "crate".to_string(),
+        Some(rest) if rest.starts_with("::") => prefix.to_string(),
+        _ => format!("crate::{}", prefix),
+    }
+}
+
+/// Content of the shim that replaces the original file
+pub fn render_shim(target_module: &str) -> String {
+    format!(
+        "//! Compatibility shim: the contents of this module moved to `{target}`\n\
+         //!\n\
+         //! Kept so existing paths keep compiling while callers migrate; import\n\
+         //! from `{target}` instead.\n\
+         //!\n\
+         //! 🤖 Generated with [SplitRS](https://github.com/cool-japan/splitrs)\n\
+         \n\
+         #![deprecated(note = \"moved to `{target}`\")]\n\
+         \n\
+         #[allow(unused_imports)]\n\
+         pub use {target}::*;\n",
+        target = target_module
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_module_path() {
+        let root = env::temp_dir().join(format!("splitrs_shim_{}", std::process::id()));
+        fs::create_dir_all(root.join("src/net/big_split")).unwrap();
+        fs::write(root.join("Cargo.toml"), "").unwrap();
+        fs::write(root.join("src/net/big.rs"), "").unwrap();
+
+        assert_eq!(
+            module_path(&root.join("src/net/big.rs")).as_deref(),
+            Some("crate::net::big")
+        );
+        assert_eq!(
+            module_path(&root.join("src/net/big_split")).as_deref(),
+            Some("crate::net::big_split")
+        );
+        assert_eq!(module_path(&root.join("Cargo.toml")), None);
+
+        assert_eq!(
+            prefixed_module_path("core::storage"),
+            "crate::core::storage"
+        );
+        assert_eq!(
+            prefixed_module_path("crate::core::storage::"),
+            "crate::core::storage"
+        );
+        assert_eq!(prefixed_module_path("crates::x"), "crate::crates::x");
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_render_shim_parses() {
+        let shim = render_shim("crate::big_split");
+        let file = syn::parse_file(&shim).unwrap();
+        assert_eq!(file.items.len(), 1);
+        assert!(shim.contains("pub use crate::big_split::*;"));
+    }
+}
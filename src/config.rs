@@ -22,8 +22,9 @@
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Main configuration structure loaded from `.splitrs.toml`
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +39,12 @@ pub struct Config {
 
     /// Output generation settings
     pub output: OutputConfig,
+
+    /// Limits and severities for `splitrs check`
+    pub check: CheckConfig,
+
+    /// How each class of semantic risk affects a split
+    pub risks: RiskPolicies,
 }
 
 impl Config {
@@ -143,6 +150,72 @@ pub struct SplitRsConfig {
 
     /// Whether to enable impl block splitting
     pub split_impl_blocks: bool,
+
+    /// Method groups with fewer methods stay in the type's main module
+    /// instead of getting a module of their own
+    pub min_group_methods: usize,
+
+    /// Method groups whose share of methods related to another method of
+    /// the group (by calls or shared fields) is lower stay in the type's
+    /// main module; groups chosen by variant, state or visitor family are
+    /// exempt
+    pub min_group_cohesion: f64,
+
+    /// Index the rest of the crate so imports for crate-internal types
+    /// defined in other files are emitted
+    pub resolve_crate_types: bool,
+
+    /// Route impls of types not defined in the input (extension impls) to
+    /// a module per target type instead of `functions.rs`
+    pub extension_impl_modules: bool,
+
+    /// Module name overrides for extension impls, by target type name
+    /// (e.g., `Order = "order_ext"`)
+    pub extension_modules: BTreeMap<String, String>,
+
+    /// Move `#[doc(hidden)]` items and private helpers called from several
+    /// modules into an internal module that is not re-exported
+    pub isolate_internal_items: bool,
+
+    /// Move `#[deprecated]` types, methods and standalone items into a
+    /// dedicated module (still re-exported)
+    pub segregate_deprecated: bool,
+
+    /// Split each type's trait impls into std (`_std_traits`), external-crate
+    /// (`_external_traits`) and local (`_local_traits`) trait modules
+    pub group_trait_impls_by_origin: bool,
+
+    /// Move each type's formatting-trait impls (`Display`, `Debug`,
+    /// `LowerHex`, ...) into a `{type}_fmt` module
+    pub group_fmt_impls: bool,
+
+    /// Pack the trait impls of small types into one module per trait
+    /// (`display_impls.rs`) instead of a `_traits` module per type
+    pub pack_trait_impls: bool,
+
+    /// Most lines of trait impls a type may have for `pack_trait_impls` to
+    /// pack them
+    pub max_packed_impl_lines: usize,
+
+    /// Split large enum impls into clusters of methods that handle the same
+    /// variants, instead of by method calls alone
+    pub group_enum_methods_by_variants: bool,
+
+    /// Split large impls of state-machine types (`on_*`/`enter_*` methods,
+    /// `*State` enums) into one module per state or event family
+    pub group_state_machines: bool,
+
+    /// Split large impls of visitor types (`visit_*`, `walk_*`, `fold_*`
+    /// methods) into one module per visited node category
+    pub group_visitor_methods: bool,
+
+    /// Move private helpers that only `#[cfg(test)]` code uses into (or
+    /// next to) the test module instead of `functions.rs`
+    pub test_helpers_with_tests: bool,
+
+    /// Attributes whose items are never relocated (matched by full path or
+    /// last segment); they stay in the crate root or in `mod.rs`
+    pub anchor_attributes: Vec<String>,
 }
 
 impl Default for SplitRsConfig {
@@ -151,10 +224,145 @@ impl Default for SplitRsConfig {
             max_lines: 1000,
             max_impl_lines: 500,
             split_impl_blocks: false,
+            min_group_methods: 2,
+            min_group_cohesion: 0.5,
+            resolve_crate_types: false,
+            extension_impl_modules: false,
+            extension_modules: BTreeMap::new(),
+            isolate_internal_items: false,
+            segregate_deprecated: false,
+            group_trait_impls_by_origin: false,
+            group_fmt_impls: false,
+            pack_trait_impls: false,
+            max_packed_impl_lines: 40,
+            group_enum_methods_by_variants: false,
+            group_state_machines: false,
+            group_visitor_methods: false,
+            test_helpers_with_tests: true,
+            anchor_attributes: vec![
+                "ctor".to_string(),
+                "dtor".to_string(),
+                "entry".to_string(),
+                "test_case".to_string(),
+            ],
         }
     }
 }
 
+/// Limits `splitrs check` enforces, and how severe each finding is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CheckConfig {
+    /// Files above this many lines get a warning
+    pub warn_lines: usize,
+
+    /// Files above this many lines get an error
+    pub error_lines: usize,
+
+    /// Inherent impl blocks above this many lines are reported
+    pub max_impl_lines: usize,
+
+    /// Struct definitions above this many lines are reported
+    pub max_struct_lines: usize,
+
+    /// Enum definitions above this many lines are reported
+    pub max_enum_lines: usize,
+
+    /// Trait definitions above this many lines are reported
+    pub max_trait_lines: usize,
+
+    /// Free functions above this many lines are reported
+    pub max_fn_lines: usize,
+
+    /// Types with more trait impls than this are reported
+    pub max_trait_impls: usize,
+
+    /// Severity overrides per finding
+    pub severity: CheckSeverities,
+}
+
+impl Default for CheckConfig {
+    fn default() -> Self {
+        Self {
+            warn_lines: 800,
+            error_lines: 1500,
+            max_impl_lines: 500,
+            max_struct_lines: 300,
+            max_enum_lines: 1000,
+            max_trait_lines: 400,
+            max_fn_lines: 150,
+            max_trait_impls: 8,
+            severity: CheckSeverities::default(),
+        }
+    }
+}
+
+/// Severity overrides for `splitrs check` findings; unset ones keep their
+/// default (line thresholds for files, warnings for the rest)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CheckSeverities {
+    pub oversized_file: Option<Severity>,
+    pub oversized_impl: Option<Severity>,
+    pub oversized_item: Option<Severity>,
+    pub too_many_trait_impls: Option<Severity>,
+}
+
+/// How a `splitrs check` finding affects the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Not reported
+    Allow,
+
+    /// Reported, exit code unaffected
+    Warn,
+
+    /// Reported, the check fails
+    Error,
+}
+
+/// Policy for each class of semantic risk found while planning a split
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskPolicies {
+    /// `macro_rules!` macros used outside their defining module
+    pub macro_scoping: RiskPolicy,
+
+    /// Items whose visibility SplitRS widens
+    pub visibility_widening: RiskPolicy,
+
+    /// Names a generated module cannot import
+    pub unresolved_import: RiskPolicy,
+
+    /// Split impl blocks that lose the `#[cfg]` of their original
+    pub cfg_split: RiskPolicy,
+
+    /// Private functions or methods called from a sibling module
+    pub private_call: RiskPolicy,
+
+    /// Private fields used from a sibling module
+    pub private_field: RiskPolicy,
+
+    /// Paths rewritten for the new layout
+    pub path_rewrite: RiskPolicy,
+}
+
+/// How a class of semantic risk affects a split
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskPolicy {
+    /// Left out of the safety report
+    Ignore,
+
+    /// Reported; only `--strict` refuses to write output
+    #[default]
+    Warn,
+
+    /// Reported, and no output is written while one remains
+    Error,
+}
+
 /// Module naming configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -165,6 +373,22 @@ pub struct NamingConfig {
     /// Suffix for impl block modules (e.g., "user_impl")
     pub impl_module_suffix: String,
 
+    /// Suffix for extension impl modules (e.g., "order_ext")
+    pub ext_module_suffix: String,
+
+    /// Module receiving items copied verbatim in lenient mode
+    pub unparsed_module: String,
+
+    /// Module holding internal items (see `isolate_internal_items`)
+    pub internal_module: String,
+
+    /// Module the extern blocks (`#[link(...)] extern "C" { ... }`) are
+    /// moved to, whole and with their attributes
+    pub ffi_module: String,
+
+    /// Module holding deprecated items (see `segregate_deprecated`)
+    pub deprecated_module: String,
+
     /// Whether to use snake_case for module names
     pub use_snake_case: bool,
 }
@@ -174,6 +398,11 @@ impl Default for NamingConfig {
         Self {
             type_module_suffix: "_type".to_string(),
             impl_module_suffix: "_impl".to_string(),
+            ext_module_suffix: "_ext".to_string(),
+            unparsed_module: "unparsed".to_string(),
+            internal_module: "internal".to_string(),
+            ffi_module: "ffi".to_string(),
+            deprecated_module: "deprecated".to_string(),
             use_snake_case: true,
         }
     }
@@ -195,6 +424,197 @@ pub struct OutputConfig {
 
     /// Whether to format output with prettyplease
     pub format_output: bool,
+
+    /// Re-export explicit name lists (`pub use m::{A, B};`) instead of globs
+    pub explicit_reexports: bool,
+
+    /// Withhold names exported by several modules from the `mod.rs`
+    /// re-exports, leaving them reachable only as `module::Name`
+    pub qualify_conflicting_exports: bool,
+
+    /// List each module's types, functions and methods (with their first
+    /// doc line) in the module's `//!` docs
+    pub module_index: bool,
+
+    /// Rustdoc attribute placed on the `mod.rs` re-exports
+    pub reexport_doc: ReexportDoc,
+
+    /// Replace the input file with a shim re-exporting the new module
+    pub compat_shim: bool,
+
+    /// Module path of the output directory (`crate::core::storage`); unset
+    /// derives it from the directory's place under `src/`
+    pub module_prefix: Option<String>,
+
+    /// Line endings of the generated files
+    pub line_ending: LineEnding,
+
+    /// Copy items SplitRS only moves byte-for-byte from the input instead
+    /// of reformatting them
+    pub verbatim_items: bool,
+
+    /// How module contents are written; `preserve` slices the input so
+    /// comments and blank lines between items and methods survive
+    pub format: OutputFormat,
+
+    /// Directory the inputs are backed up to before they are replaced
+    ///
+    /// Unset, each run gets a directory of its own under `backup_root`.
+    /// Build scripts and xtask automation set it to keep runs hermetic and
+    /// reproducible.
+    pub backup_dir: Option<PathBuf>,
+
+    /// Directory holding one backup per run; relative to the project root
+    /// (the nearest directory with a `Cargo.toml`)
+    pub backup_root: PathBuf,
+
+    /// Number of runs whose backups are kept under `backup_root`; 0 keeps all
+    pub backup_retention: usize,
+
+    /// How moved code keeps paths through imported modules (`fmt::Result`
+    /// after `use std::fmt;`) valid
+    pub short_paths: ShortPaths,
+
+    /// JSON Lines file each run appends its statistics to (timestamp, input
+    /// and output sizes, module count, warnings); unset disables it
+    pub stats_file: Option<PathBuf>,
+
+    /// Order of the items in each generated module
+    pub item_order: ItemOrder,
+
+    /// Order of the methods in impl blocks rebuilt from split impls
+    pub impl_item_order: ItemOrder,
+
+    /// Cluster related modules into subdirectories with their own `mod.rs`
+    /// when there are more than `max_modules_per_directory` of them
+    pub subdirectories: bool,
+
+    /// Preferred maximum number of modules per directory
+    pub max_modules_per_directory: usize,
+
+    /// Deepest directory level below the output directory; deeper
+    /// directories are flattened into a file of inline modules
+    pub max_depth: Option<usize>,
+
+    /// How to split a test, bench or example target file, which cannot
+    /// become a module directory
+    pub target_files: TargetFiles,
+}
+
+/// How rustdoc should present the re-exports in `mod.rs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReexportDoc {
+    /// No attribute; rustdoc decides
+    #[default]
+    Default,
+
+    /// `#[doc(inline)]`: document the items where they are re-exported,
+    /// as they were before the split
+    Inline,
+
+    /// `#[doc(no_inline)]`: list the re-exports and link to the modules
+    NoInline,
+}
+
+impl ReexportDoc {
+    /// The attribute line to emit, if any
+    pub fn attribute(self) -> Option<&'static str> {
+        match self {
+            ReexportDoc::Default => None,
+            ReexportDoc::Inline => Some("#[doc(inline)]"),
+            ReexportDoc::NoInline => Some("#[doc(no_inline)]"),
+        }
+    }
+}
+
+/// Line endings of the generated files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineEnding {
+    /// Whatever most lines of the input use
+    #[default]
+    Auto,
+
+    /// `\n`
+    Lf,
+
+    /// `\r\n`
+    Crlf,
+}
+
+/// How the contents of generated modules are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// prettyplease, with unmodified items copied verbatim when
+    /// `verbatim_items` is set
+    #[default]
+    Pretty,
+
+    /// Source slices: items and the methods of split impls keep their
+    /// comments, layout and the blank lines between them
+    Preserve,
+}
+
+/// Handling of paths that start with an imported module (`fmt::Result`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortPaths {
+    /// Import the module where the short form is used (`use std::fmt;`)
+    #[default]
+    Import,
+
+    /// Rewrite the short form to the full path (`std::fmt::Result`)
+    Qualify,
+}
+
+/// Order of the items in a generated module; a type always stays together
+/// with its impls
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemOrder {
+    /// The order of the input
+    #[default]
+    Source,
+
+    /// Public items first, each group in the order of the input
+    PublicFirst,
+
+    /// Alphabetical by name
+    Alphabetical,
+}
+
+/// Output for files Cargo compiles as targets of their own (`tests/*.rs`,
+/// `benches/*.rs`, `examples/*.rs` and manifest-listed targets)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetFiles {
+    /// Rewrite the file with the modules inlined (`pub mod name { ... }`)
+    #[default]
+    Inline,
+
+    /// Stop with guidance instead of splitting
+    Refuse,
+}
+
+impl LineEnding {
+    /// The newline sequence to write, detected from `source` for `Auto`
+    pub fn newline(self, source: &str) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+            LineEnding::Auto => {
+                let crlf = source.matches("\r\n").count();
+                let lf = source.matches('\n').count() - crlf;
+                if crlf > lf {
+                    "\r\n"
+                } else {
+                    "\n"
+                }
+            }
+        }
+    }
 }
 
 impl Default for OutputConfig {
@@ -203,6 +623,26 @@ impl Default for OutputConfig {
             module_doc_template: "//! Auto-generated module\n".to_string(),
             preserve_comments: true,
             format_output: true,
+            explicit_reexports: false,
+            qualify_conflicting_exports: false,
+            module_index: false,
+            reexport_doc: ReexportDoc::Default,
+            compat_shim: false,
+            module_prefix: None,
+            line_ending: LineEnding::Auto,
+            verbatim_items: true,
+            format: OutputFormat::Pretty,
+            backup_dir: None,
+            backup_root: PathBuf::from(".splitrs/backups"),
+            backup_retention: 10,
+            short_paths: ShortPaths::Import,
+            stats_file: None,
+            item_order: ItemOrder::Source,
+            impl_item_order: ItemOrder::Source,
+            subdirectories: false,
+            max_modules_per_directory: 12,
+            max_depth: None,
+            target_files: TargetFiles::Inline,
         }
     }
 }
@@ -220,6 +660,13 @@ mod tests {
         assert!(!config.splitrs.split_impl_blocks);
     }
 
+    #[test]
+    fn test_line_ending_detection() {
+        assert_eq!(LineEnding::Auto.newline("a\r\nb\r\nc\n"), "\r\n");
+        assert_eq!(LineEnding::Auto.newline("a\nb\r\nc\n"), "\n");
+        assert_eq!(LineEnding::Crlf.newline("a\nb\n"), "\r\n");
+    }
+
     #[test]
     fn test_config_serialization() {
         let config = Config::default();
@@ -242,6 +689,11 @@ mod tests {
 
             [output]
             preserve_comments = false
+            reexport_doc = "no_inline"
+
+            [risks]
+            visibility_widening = "error"
+            path_rewrite = "ignore"
         "#;
 
         let config: Config = toml::from_str(toml_str).unwrap();
@@ -250,6 +702,29 @@ mod tests {
         assert!(config.splitrs.split_impl_blocks);
         assert_eq!(config.naming.type_module_suffix, "_types");
         assert!(!config.output.preserve_comments);
+        assert_eq!(
+            config.output.reexport_doc.attribute(),
+            Some("#[doc(no_inline)]")
+        );
+        assert_eq!(config.risks.visibility_widening, RiskPolicy::Error);
+        assert_eq!(config.risks.path_rewrite, RiskPolicy::Ignore);
+        assert_eq!(config.risks.macro_scoping, RiskPolicy::Warn);
+    }
+
+    #[test]
+    fn test_extension_module_overrides() {
+        let toml_str = r#"
+            [splitrs]
+            extension_impl_modules = true
+
+            [splitrs.extension_modules]
+            Order = "orders"
+        "#;
+
+        let config: Config = toml::from_str(toml_str).unwrap();
+        assert!(config.splitrs.extension_impl_modules);
+        assert_eq!(config.splitrs.extension_modules["Order"], "orders");
+        assert_eq!(config.naming.ext_module_suffix, "_ext");
     }
 
     #[test]
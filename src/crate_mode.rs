@@ -0,0 +1,285 @@
+//! Splitting every oversized file of a crate
+//!
+//! `splitrs --crate <path>` walks the crate's `src/` and splits each `.rs`
+//! file above `max_lines` into a module directory of its own: `src/a/b.rs`
+//! becomes `src/a/b/`, which `mod b;` in the parent still finds, and a
+//! `mod.rs` is split in place. Crate roots cannot become their own
+//! directory; `lib.rs` is split into a module named after the package and
+//! `main.rs` into `app`, with a suffix when a sibling already has the
+//! name, and the root is rewritten to declare it. A split `b.rs` is
+//! removed (its backup stays), since `mod b;` would otherwise be ambiguous.
+//! Files are split deepest first, so a parent sees the directories its
+//! children turned into and keeps its generated modules clear of their
+//! names.
+
+use crate::existing_module::child_module_names;
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// One file of the crate to split
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitTarget {
+    /// The oversized file
+    pub input: PathBuf,
+
+    /// The module directory it is split into
+    pub output: PathBuf,
+
+    /// Line count of the file
+    pub lines: usize,
+}
+
+/// A file of the crate that is not split
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedFile {
+    /// The file
+    pub path: PathBuf,
+
+    /// Why it is left alone
+    pub reason: SkipReason,
+}
+
+/// Why a file of the crate is not split
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The file has at most `max_lines` lines
+    WithinLimit,
+
+    /// The file is under `src/bin/`
+    Binary,
+}
+
+/// The files of a crate to split and the ones left alone
+#[derive(Debug, Clone, Default)]
+pub struct CratePlan {
+    /// Files to split, deepest modules first
+    pub targets: Vec<SplitTarget>,
+
+    /// Files left alone, in walk order
+    pub skipped: Vec<SkippedFile>,
+}
+
+impl SplitTarget {
+    /// Whether the module directory takes the place of the file
+    ///
+    /// `mod b;` cannot find both `b.rs` and `b/mod.rs`, so once `b.rs` is
+    /// split it has to go. `mod.rs` files and crate roots stay.
+    pub fn replaces_input(&self) -> bool {
+        self.output == self.input.with_extension("")
+    }
+}
+
+/// Package name of the crate at `crate_dir`, in snake case
+pub fn package_name(crate_dir: &Path) -> Option<String> {
+    let manifest = fs::read_to_string(crate_dir.join("Cargo.toml")).ok()?;
+    let manifest: toml::Table = toml::from_str(&manifest).ok()?;
+    let name = manifest.get("package")?.get("name")?.as_str()?;
+    Some(name.replace('-', "_"))
+}
+
+/// `name`, or `name_N` with the smallest `N >= 2` when `name` is taken
+fn free_name(name: &str, taken: &BTreeSet<String>) -> String {
+    if !taken.contains(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{}_{}", name, n))
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// The oversized files of a crate and where each is split to
+///
+/// Files under `src/bin/` are left out: each is a crate root of its own
+/// whose modules would be shared with the other binaries.
+///
+/// # Arguments
+///
+/// * `crate_dir` - Directory holding the crate's `Cargo.toml`
+/// * `max_lines` - Files with more lines than this are split
+///
+/// # Returns
+///
+/// The targets, deepest modules first, and the files left alone
+pub fn plan_targets(crate_dir: &Path, max_lines: usize) -> Result<CratePlan> {
+    let src = crate_dir.join("src");
+    if !src.is_dir() {
+        anyhow::bail!("{:?} has no src/ directory", crate_dir);
+    }
+
+    let mut targets = Vec::new();
+    let mut skipped = Vec::new();
+    let mut root_names: BTreeSet<String> = child_module_names(&src);
+    for entry in WalkDir::new(&src).sort_by_file_name() {
+        let entry = entry.context(format!("Failed to walk {:?}", src))?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        if path.strip_prefix(&src).is_ok_and(|p| p.starts_with("bin")) {
+            skipped.push(SkippedFile {
+                path: path.to_path_buf(),
+                reason: SkipReason::Binary,
+            });
+            continue;
+        }
+        let lines = fs::read_to_string(path)
+            .context(format!("Failed to read {:?}", path))?
+            .lines()
+            .count();
+        if lines <= max_lines {
+            skipped.push(SkippedFile {
+                path: path.to_path_buf(),
+                reason: SkipReason::WithinLimit,
+            });
+            continue;
+        }
+
+        targets.push(SplitTarget {
+            input: path.to_path_buf(),
+            output: output_for(crate_dir, path, &mut root_names),
+            lines,
+        });
+    }
+    targets.sort_by_key(|target| std::cmp::Reverse(module_depth(&target.input)));
+    Ok(CratePlan { targets, skipped })
+}
+
+/// Depth of the module a file holds
+///
+/// A `mod.rs` holds its directory's module, one level above the files next
+/// to it, so those are split before it.
+fn module_depth(path: &Path) -> usize {
+    let depth = path.components().count();
+    if path.file_name().is_some_and(|name| name == "mod.rs") {
+        depth - 1
+    } else {
+        depth
+    }
+}
+
+/// Module directory a file of the crate is split into
+///
+/// A `mod.rs` is split in place, a crate root into a new module of the
+/// crate, and any other `b.rs` into the `b/` next to it.
+///
+/// # Arguments
+///
+/// * `crate_dir` - Directory holding the crate's `Cargo.toml`
+/// * `path` - The file to split
+/// * `root_names` - Names taken under `src/`; a crate root's new module is
+///   added to them
+pub fn output_for(crate_dir: &Path, path: &Path, root_names: &mut BTreeSet<String>) -> PathBuf {
+    let src = crate_dir.join("src");
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let parent = path.parent().unwrap_or(&src);
+    match stem.as_ref() {
+        "mod" => parent.to_path_buf(),
+        "lib" | "main" if parent == src => {
+            let base = if stem == "lib" {
+                package_name(crate_dir).unwrap_or_else(|| "core".to_string())
+            } else {
+                "app".to_string()
+            };
+            let name = free_name(&base, root_names);
+            root_names.insert(name.clone());
+            src.join(name)
+        }
+        _ => parent.join(stem.as_ref()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_plan_targets() {
+        let dir = env::temp_dir().join(format!("splitrs_crate_{}", std::process::id()));
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("engine")).unwrap();
+        fs::create_dir_all(src.join("bin")).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]\nname = \"my-app\"\n").unwrap();
+        let big = "pub fn f() {}\n".repeat(20);
+        fs::write(src.join("lib.rs"), &big).unwrap();
+        fs::write(src.join("main.rs"), &big).unwrap();
+        fs::write(src.join("my_app.rs"), "").unwrap();
+        fs::write(src.join("engine/mod.rs"), &big).unwrap();
+        fs::write(src.join("engine/parser.rs"), &big).unwrap();
+        fs::write(src.join("engine/small.rs"), "pub fn g() {}\n").unwrap();
+        fs::write(src.join("bin/tool.rs"), &big).unwrap();
+
+        let plan = plan_targets(&dir, 10).unwrap();
+        let targets = plan.targets;
+        let pairs: Vec<(PathBuf, PathBuf)> = targets
+            .iter()
+            .map(|t| {
+                (
+                    t.input.strip_prefix(&src).unwrap().to_path_buf(),
+                    t.output.strip_prefix(&src).unwrap().to_path_buf(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("engine/parser.rs".into(), "engine/parser".into()),
+                ("engine/mod.rs".into(), "engine".into()),
+                ("lib.rs".into(), "my_app_2".into()),
+                ("main.rs".into(), "app".into()),
+            ]
+        );
+        let replaced: Vec<bool> = targets.iter().map(SplitTarget::replaces_input).collect();
+        assert_eq!(replaced, vec![true, false, false, false]);
+        let skipped: Vec<(PathBuf, SkipReason)> = plan
+            .skipped
+            .iter()
+            .map(|s| (s.path.strip_prefix(&src).unwrap().to_path_buf(), s.reason))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ("bin/tool.rs".into(), SkipReason::Binary),
+                ("engine/small.rs".into(), SkipReason::WithinLimit),
+                ("my_app.rs".into(), SkipReason::WithinLimit),
+            ]
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_mod_rs_is_split_after_its_children() {
+        let dir = env::temp_dir().join(format!("splitrs_crate_order_{}", std::process::id()));
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("engine/render")).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]\nname = \"order\"\n").unwrap();
+        let big = "pub fn f() {}\n".repeat(20);
+        // `mod.rs` is walked before the files next to it
+        fs::write(src.join("engine/mod.rs"), &big).unwrap();
+        fs::write(src.join("engine/zoom.rs"), &big).unwrap();
+        fs::write(src.join("engine/render/mod.rs"), &big).unwrap();
+        fs::write(src.join("engine/render/paint.rs"), &big).unwrap();
+
+        let targets = plan_targets(&dir, 10).unwrap().targets;
+        let inputs: Vec<PathBuf> = targets
+            .iter()
+            .map(|t| t.input.strip_prefix(&src).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            inputs,
+            vec![
+                PathBuf::from("engine/render/paint.rs"),
+                PathBuf::from("engine/render/mod.rs"),
+                PathBuf::from("engine/zoom.rs"),
+                PathBuf::from("engine/mod.rs"),
+            ]
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,420 @@
+//! Splitting a crate root (`lib.rs` / `main.rs`)
+//!
+//! Some items only work, or only mean what they say, at the crate root:
+//! inner attributes like `#![feature(...)]` and `#![no_std]`, `extern crate`
+//! (whose `#[macro_use]` is crate-wide only there), `#[global_allocator]` and
+//! `#[panic_handler]`. When the input is a crate root these stay behind: the
+//! root file is rewritten to keep them, declare the generated module and
+//! glob re-export it so every existing path keeps working.
+//!
+//! Entry points stay as well: `fn main` of a binary (with attributes like
+//! `#[tokio::main]`) and the `#[proc_macro*]` functions of a proc-macro
+//! crate, which cannot be re-exported from a module. They keep the `use`
+//! statements they need, and everything they name is made at least
+//! `pub(crate)`, since they now reach the code through the generated module.
+
+use crate::compat_shim::module_path;
+use crate::internal_items::item_attrs;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use syn::visit::Visit;
+use syn::{Attribute, File, Item, UseTree, Visibility};
+
+/// Attributes that tie an item to the crate root
+const ROOT_ONLY_ATTRIBUTES: &[&str] = &["global_allocator", "panic_handler", "alloc_error_handler"];
+
+/// Whether `path` is a crate root (`src/lib.rs`, `src/main.rs` or a binary
+/// in `src/bin/`)
+pub fn is_crate_root(path: &Path) -> bool {
+    let in_bin_dir = path.extension().is_some_and(|ext| ext == "rs")
+        && path
+            .parent()
+            .and_then(Path::file_name)
+            .is_some_and(|dir| dir == "bin");
+    in_bin_dir || module_path(path).as_deref() == Some("crate")
+}
+
+/// Attributes of proc-macro entry points
+const PROC_MACRO_ATTRIBUTES: &[&str] = &["proc_macro", "proc_macro_derive", "proc_macro_attribute"];
+
+/// What kind of crate a root belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootKind {
+    Library,
+    Binary,
+    ProcMacro,
+}
+
+impl RootKind {
+    /// Whether the root can re-export the generated module
+    ///
+    /// A proc-macro crate may only export its macros, so its root imports
+    /// the module privately instead.
+    pub fn reexports(self) -> bool {
+        self != RootKind::ProcMacro
+    }
+
+    /// Whether an item is an entry point that has to stay in the root
+    fn is_entry_point(self, item: &Item) -> bool {
+        let Item::Fn(function) = item else {
+            return false;
+        };
+        match self {
+            RootKind::Library => false,
+            RootKind::Binary => function.sig.ident == "main",
+            RootKind::ProcMacro => function.attrs.iter().any(|attr| {
+                PROC_MACRO_ATTRIBUTES
+                    .iter()
+                    .any(|name| attr.path().is_ident(name))
+            }),
+        }
+    }
+}
+
+/// Kind of the crate whose root is `path`
+///
+/// Binaries are `src/main.rs` and `src/bin/*.rs`; a library is a proc-macro
+/// crate when its manifest sets `proc-macro = true` under `[lib]`.
+pub fn root_kind(path: &Path) -> RootKind {
+    let in_bin_dir = path
+        .parent()
+        .and_then(Path::file_name)
+        .is_some_and(|dir| dir == "bin");
+    if in_bin_dir || path.file_name().is_some_and(|name| name == "main.rs") {
+        return RootKind::Binary;
+    }
+
+    let path = crate::existing_module::absolute(path);
+    let manifest = path
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|manifest| manifest.is_file())
+        .and_then(|manifest| fs::read_to_string(manifest).ok())
+        .and_then(|text| toml::from_str::<toml::Table>(&text).ok());
+    let proc_macro = manifest
+        .as_ref()
+        .and_then(|manifest| manifest.get("lib"))
+        .and_then(|lib| lib.get("proc-macro").or(lib.get("proc_macro")))
+        .and_then(toml::Value::as_bool)
+        .unwrap_or(false);
+    if proc_macro {
+        RootKind::ProcMacro
+    } else {
+        RootKind::Library
+    }
+}
+
+/// Whether an item has to stay in the crate root
+///
+/// # Arguments
+///
+/// * `item` - The item to check
+/// * `anchors` - Configured attributes whose items are never relocated
+pub fn is_root_only(item: &Item, anchors: &[String]) -> bool {
+    matches!(item, Item::ExternCrate(_))
+        || item_attrs(item).iter().any(|attr| {
+            ROOT_ONLY_ATTRIBUTES
+                .iter()
+                .any(|name| attr.path().is_ident(name))
+        })
+        || has_anchor_attribute(item, anchors)
+}
+
+/// Whether an item carries one of the anchor attributes
+///
+/// An anchor matches an attribute by its full path (`ctor::ctor`) or its
+/// last segment (`ctor`).
+pub fn has_anchor_attribute(item: &Item, anchors: &[String]) -> bool {
+    item_attrs(item).iter().any(|attr| {
+        let segments: Vec<String> = attr
+            .path()
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        let full = segments.join("::");
+        anchors
+            .iter()
+            .any(|anchor| *anchor == full || segments.last() == Some(anchor))
+    })
+}
+
+/// Remove the crate-root-only items from `file`
+///
+/// The entry points of binaries and proc-macro crates are removed as well,
+/// and the `use` statements they need are copied along with them.
+///
+/// # Arguments
+///
+/// * `file` - The parsed root
+/// * `kind` - Kind of the crate
+/// * `anchors` - Configured attributes whose items are never relocated
+///
+/// # Returns
+///
+/// The removed items, in source order
+pub fn take_root_items(file: &mut File, kind: RootKind, anchors: &[String]) -> Vec<Item> {
+    let mut entry_names = entry_point_names(&file.items, kind);
+    // `Args::parse()` needs the `Parser` trait that `Args` derives
+    for item in &file.items {
+        let (ident, attrs) = match item {
+            Item::Struct(s) => (&s.ident, &s.attrs),
+            Item::Enum(e) => (&e.ident, &e.attrs),
+            _ => continue,
+        };
+        if entry_names.contains(&ident.to_string()) {
+            entry_names.extend(derived_traits(attrs));
+        }
+    }
+
+    let mut root_items = Vec::new();
+    let mut items = Vec::new();
+    for item in std::mem::take(&mut file.items) {
+        if is_root_only(&item, anchors) || kind.is_entry_point(&item) {
+            root_items.push(item);
+            continue;
+        }
+        if let Item::Use(use_item) = &item {
+            if imports_any(&use_item.tree, &entry_names) {
+                root_items.push(item.clone());
+            }
+        }
+        items.push(item);
+    }
+    file.items = items;
+    root_items
+}
+
+/// Make what the entry points name reachable from the root
+///
+/// Private items, struct fields and inherent methods sharing a name with
+/// anything an entry point mentions become `pub(crate)`.
+///
+/// # Returns
+///
+/// The number of widened items
+pub fn expose_to_entry_points(file: &mut File, root_items: &[Item], kind: RootKind) -> usize {
+    let names = entry_point_names(root_items, kind);
+    if names.is_empty() {
+        return 0;
+    }
+    let mut widened = 0;
+    let mut widen = |vis: &mut Visibility, name: String| {
+        if matches!(vis, Visibility::Inherited) && names.contains(&name) {
+            *vis = syn::parse_quote!(pub(crate));
+            widened += 1;
+        }
+    };
+    for item in &mut file.items {
+        match item {
+            Item::Struct(s) => {
+                widen(&mut s.vis, s.ident.to_string());
+                for field in &mut s.fields {
+                    if let Some(ident) = &field.ident {
+                        widen(&mut field.vis, ident.to_string());
+                    }
+                }
+            }
+            Item::Enum(e) => widen(&mut e.vis, e.ident.to_string()),
+            Item::Fn(f) => widen(&mut f.vis, f.sig.ident.to_string()),
+            Item::Const(c) => widen(&mut c.vis, c.ident.to_string()),
+            Item::Static(s) => widen(&mut s.vis, s.ident.to_string()),
+            Item::Type(t) => widen(&mut t.vis, t.ident.to_string()),
+            Item::Trait(t) => widen(&mut t.vis, t.ident.to_string()),
+            Item::Union(u) => widen(&mut u.vis, u.ident.to_string()),
+            Item::Mod(m) => widen(&mut m.vis, m.ident.to_string()),
+            Item::Impl(i) if i.trait_.is_none() => {
+                for impl_item in &mut i.items {
+                    match impl_item {
+                        syn::ImplItem::Fn(f) => widen(&mut f.vis, f.sig.ident.to_string()),
+                        syn::ImplItem::Const(c) => widen(&mut c.vis, c.ident.to_string()),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    widened
+}
+
+/// Every identifier the entry points among `items` mention, macro
+/// arguments included
+fn entry_point_names(items: &[Item], kind: RootKind) -> BTreeSet<String> {
+    let mut collector = IdentCollector::default();
+    for item in items.iter().filter(|item| kind.is_entry_point(item)) {
+        collector.visit_item(item);
+    }
+    collector.names
+}
+
+#[derive(Default)]
+struct IdentCollector {
+    names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for IdentCollector {
+    fn visit_ident(&mut self, ident: &'ast syn::Ident) {
+        self.names.insert(ident.to_string());
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if let Ok(args) = node.parse_body_with(
+            syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+        ) {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+        syn::visit::visit_macro(self, node);
+    }
+}
+
+/// Last segments of the paths in `#[derive(...)]` attributes
+fn derived_traits(attrs: &[Attribute]) -> Vec<String> {
+    let mut traits = Vec::new();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("derive")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            if let Some(segment) = meta.path.segments.last() {
+                traits.push(segment.ident.to_string());
+            }
+            Ok(())
+        });
+    }
+    traits
+}
+
+/// Whether a `use` tree imports one of `names` (globs always count)
+fn imports_any(tree: &UseTree, names: &BTreeSet<String>) -> bool {
+    match tree {
+        UseTree::Path(path) => imports_any(&path.tree, names),
+        UseTree::Name(name) => names.contains(&name.ident.to_string()),
+        UseTree::Rename(rename) => names.contains(&rename.rename.to_string()),
+        UseTree::Glob(_) => true,
+        UseTree::Group(group) => group.items.iter().any(|tree| imports_any(tree, names)),
+    }
+}
+
+/// Content of the rewritten crate root
+///
+/// # Arguments
+///
+/// * `attrs` - Inner attributes and docs of the original root
+/// * `root_items` - Items that stay in the root
+/// * `module` - Name of the generated module, declared next to the root
+/// * `reexport` - Whether to glob re-export the module rather than import it
+pub fn render_root(
+    attrs: &[Attribute],
+    root_items: &[Item],
+    module: &str,
+    reexport: bool,
+) -> String {
+    let kept = prettyplease::unparse(&File {
+        shebang: None,
+        attrs: attrs.to_vec(),
+        items: root_items.to_vec(),
+    });
+    let mut content = kept.trim_end().to_string();
+    if !content.is_empty() {
+        content.push_str("\n\n");
+    }
+    let vis = if reexport { "pub " } else { "" };
+    content.push_str(&format!(
+        "mod {module};\n{vis}use {module}::*;\n",
+        module = module,
+        vis = vis
+    ));
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_takes_root_only_items() {
+        let mut file: File = syn::parse_quote! {
+            #![no_std]
+            extern crate alloc;
+            #[global_allocator]
+            static ALLOC: Bump = Bump;
+            #[panic_handler]
+            fn panic(_: &core::panic::PanicInfo) -> ! { loop {} }
+            #[ctor::ctor]
+            fn init() {}
+            pub struct Bump;
+            pub fn helper() {}
+        };
+        let root_items = take_root_items(&mut file, RootKind::Library, &["ctor".to_string()]);
+        assert_eq!(root_items.len(), 4);
+        assert_eq!(file.items.len(), 2);
+    }
+
+    #[test]
+    fn test_binary_root_keeps_main() {
+        let mut file: File = syn::parse_quote! {
+            use clap::Parser;
+            use std::collections::HashMap;
+            #[derive(Parser)]
+            struct Args { verbose: bool }
+            fn run(args: &Args) -> HashMap<String, u32> { HashMap::new() }
+            #[tokio::main]
+            async fn main() {
+                let args = Args::parse();
+                println!("{:?}", run(&args).len() + args.verbose as usize);
+            }
+        };
+        let root_items = take_root_items(&mut file, RootKind::Binary, &[]);
+        assert_eq!(root_items.len(), 2);
+        assert!(matches!(&root_items[0], Item::Use(_)));
+        assert!(matches!(&root_items[1], Item::Fn(f) if f.sig.ident == "main"));
+        assert_eq!(file.items.len(), 4);
+
+        assert_eq!(
+            expose_to_entry_points(&mut file, &root_items, RootKind::Binary),
+            3
+        );
+        assert_eq!(root_kind(Path::new("src/bin/tool.rs")), RootKind::Binary);
+    }
+
+    #[test]
+    fn test_proc_macro_root_keeps_entry_points() {
+        let mut file: File = syn::parse_quote! {
+            use proc_macro::TokenStream;
+            fn expand(input: TokenStream) -> TokenStream { input }
+            #[proc_macro_derive(Builder)]
+            pub fn derive_builder(input: TokenStream) -> TokenStream { expand(input) }
+        };
+        let root_items = take_root_items(&mut file, RootKind::ProcMacro, &[]);
+        assert_eq!(root_items.len(), 2);
+        assert_eq!(file.items.len(), 2);
+        assert_eq!(
+            expose_to_entry_points(&mut file, &root_items, RootKind::ProcMacro),
+            1
+        );
+
+        let content = render_root(&[], &root_items, "expand", RootKind::ProcMacro.reexports());
+        assert!(content.ends_with("mod expand;\nuse expand::*;\n"));
+    }
+
+    #[test]
+    fn test_detects_binary_roots() {
+        assert!(is_crate_root(Path::new("src/bin/tool.rs")));
+        assert!(!is_crate_root(Path::new("src/bin/tool/parse.rs")));
+    }
+
+    #[test]
+    fn test_render_root_keeps_attributes() {
+        let file: File = syn::parse_quote! {
+            //! The kernel
+            #![no_std]
+            extern crate alloc;
+        };
+        let content = render_root(&file.attrs, &file.items, "kernel", true);
+        assert!(content.starts_with("//! The kernel\n#![no_std]\nextern crate alloc;\n\n"));
+        assert!(content.ends_with("mod kernel;\npub use kernel::*;\n"));
+        assert!(syn::parse_file(&content).is_ok());
+    }
+}
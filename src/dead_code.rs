@@ -0,0 +1,128 @@
+//! Finding private items nothing references
+//!
+//! Before a large file is split it is worth knowing which private functions
+//! and methods nothing calls: deleting them is often better than moving
+//! them. The analysis output lists them. Names are matched without
+//! resolution, so an item sharing its name with a used one is not listed.
+
+use crate::test_helpers::UsedNames;
+use syn::visit::Visit;
+use syn::{File, ImplItem, Item, Visibility};
+
+/// Attributes that make an item reachable without a reference in the file
+const ENTRY_ATTRIBUTES: &[&str] = &["test", "no_mangle", "export_name", "used", "ctor"];
+
+/// Whether the attributes mark an entry point
+fn is_entry(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        ENTRY_ATTRIBUTES
+            .iter()
+            .any(|name| attr.path().is_ident(name))
+    })
+}
+
+/// A private function or method, and the unit holding it
+struct Candidate {
+    label: String,
+    name: String,
+    unit: usize,
+}
+
+/// Private free functions and inherent methods the file never references
+///
+/// # Returns
+///
+/// Labels (`helper`, `Parser::reset`) in source order
+pub fn unreferenced_items(file: &File) -> Vec<String> {
+    // Every top-level item is a unit, except inherent impls, whose items
+    // are units of their own so methods calling each other count
+    let mut units: Vec<UsedNames> = Vec::new();
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for item in &file.items {
+        match item {
+            Item::Fn(f) => {
+                if matches!(f.vis, Visibility::Inherited)
+                    && f.sig.ident != "main"
+                    && !is_entry(&f.attrs)
+                {
+                    candidates.push(Candidate {
+                        label: f.sig.ident.to_string(),
+                        name: f.sig.ident.to_string(),
+                        unit: units.len(),
+                    });
+                }
+                let mut used = UsedNames::default();
+                used.visit_item(item);
+                units.push(used);
+            }
+            Item::Impl(impl_block) if impl_block.trait_.is_none() => {
+                let self_ty = quote::ToTokens::to_token_stream(&impl_block.self_ty)
+                    .to_string()
+                    .replace(' ', "");
+                for impl_item in &impl_block.items {
+                    if let ImplItem::Fn(method) = impl_item {
+                        if matches!(method.vis, Visibility::Inherited) && !is_entry(&method.attrs) {
+                            candidates.push(Candidate {
+                                label: format!("{}::{}", self_ty, method.sig.ident),
+                                name: method.sig.ident.to_string(),
+                                unit: units.len(),
+                            });
+                        }
+                    }
+                    let mut used = UsedNames::default();
+                    used.visit_impl_item(impl_item);
+                    units.push(used);
+                }
+            }
+            _ => {
+                let mut used = UsedNames::default();
+                used.visit_item(item);
+                units.push(used);
+            }
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|candidate| {
+            !units
+                .iter()
+                .enumerate()
+                .any(|(unit, used)| unit != candidate.unit && used.names.contains(&candidate.name))
+        })
+        .map(|candidate| candidate.label)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unreferenced_private_items() {
+        let file: File = syn::parse_quote! {
+            pub struct Parser;
+            impl Parser {
+                pub fn parse(&self) -> u32 { self.step() + helper() }
+                fn step(&self) -> u32 { 1 }
+                fn reset(&mut self) { self.reset() }
+            }
+            impl Drop for Parser { fn drop(&mut self) {} }
+            fn helper() -> u32 { 2 }
+            fn unused() {}
+            fn main() {}
+            #[test]
+            fn parses() {}
+        };
+        assert_eq!(unreferenced_items(&file), vec!["Parser::reset", "unused"]);
+    }
+
+    #[test]
+    fn test_references_inside_macros_count() {
+        let file: File = syn::parse_quote! {
+            fn helper() -> u32 { 2 }
+            pub fn show() { println!("{}", helper()); }
+        };
+        assert!(unreferenced_items(&file).is_empty());
+    }
+}
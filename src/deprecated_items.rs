@@ -0,0 +1,122 @@
+//! Collecting `#[deprecated]` items in a module of their own
+//!
+//! Legacy surface is easier to review (and eventually delete) when it lives
+//! in one place. With deprecated segregation enabled, deprecated types (with
+//! all their impls), deprecated methods of other types and deprecated
+//! standalone items move to a dedicated module. `mod.rs` still re-exports it,
+//! so every existing path keeps working.
+
+use crate::internal_items::item_attrs;
+use std::collections::{BTreeMap, BTreeSet};
+use syn::{Attribute, ImplItem, Item, ItemImpl};
+
+/// Whether the attributes contain `#[deprecated]`
+pub fn is_deprecated(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("deprecated"))
+}
+
+/// Whether a top-level item is deprecated
+pub fn is_deprecated_item(item: &Item) -> bool {
+    is_deprecated(item_attrs(item))
+}
+
+/// Split the deprecated methods out of an inherent impl block
+///
+/// # Returns
+///
+/// The impl without its deprecated methods and an impl with the same
+/// header holding only them, or `None` when nothing is deprecated.
+pub fn split_deprecated_methods(impl_block: &ItemImpl) -> Option<(ItemImpl, ItemImpl)> {
+    if impl_block.trait_.is_some() {
+        return None;
+    }
+    let (deprecated, kept): (Vec<ImplItem>, Vec<ImplItem>) = impl_block
+        .items
+        .iter()
+        .cloned()
+        .partition(|item| match item {
+            ImplItem::Fn(f) => is_deprecated(&f.attrs),
+            ImplItem::Const(c) => is_deprecated(&c.attrs),
+            _ => false,
+        });
+    if deprecated.is_empty() {
+        return None;
+    }
+
+    let mut remaining = impl_block.clone();
+    remaining.items = kept;
+    let mut moved = impl_block.clone();
+    moved.items = deprecated;
+    Some((remaining, moved))
+}
+
+/// `use` statements a module needs for names defined in its siblings
+///
+/// # Arguments
+///
+/// * `layout` - Module each name is declared in
+/// * `used` - Names the module uses
+/// * `own_module` - The module itself, whose names need no import
+pub fn sibling_use_statements(
+    layout: &BTreeMap<String, String>,
+    used: &BTreeSet<String>,
+    own_module: &str,
+) -> Vec<String> {
+    let mut by_module: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for name in used {
+        if let Some(module) = layout.get(name) {
+            if module != own_module {
+                by_module.entry(module).or_default().push(name);
+            }
+        }
+    }
+    by_module
+        .into_iter()
+        .map(|(module, names)| match names.as_slice() {
+            [name] => format!("use super::{}::{};", module, name),
+            _ => format!("use super::{}::{{{}}};", module, names.join(", ")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_deprecated_methods() {
+        let impl_block: ItemImpl = syn::parse_quote! {
+            impl<T> Store<T> {
+                pub fn get(&self) {}
+                #[deprecated(note = "use get")]
+                pub fn fetch(&self) {}
+            }
+        };
+        let (remaining, moved) = split_deprecated_methods(&impl_block).unwrap();
+        assert_eq!(remaining.items.len(), 1);
+        assert_eq!(moved.items.len(), 1);
+        assert_eq!(moved.generics.params.len(), 1);
+
+        let clean: ItemImpl = syn::parse_quote! { impl Store { fn get(&self) {} } };
+        assert!(split_deprecated_methods(&clean).is_none());
+    }
+
+    #[test]
+    fn test_sibling_use_statements() {
+        let layout: BTreeMap<String, String> = [
+            ("Store".to_string(), "types".to_string()),
+            ("Cache".to_string(), "types".to_string()),
+            ("Old".to_string(), "deprecated".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let used: BTreeSet<String> = ["Store", "Cache", "Old", "String"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            sibling_use_statements(&layout, &used, "deprecated"),
+            vec!["use super::types::{Cache, Store};"]
+        );
+    }
+}
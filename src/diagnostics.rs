@@ -0,0 +1,179 @@
+//! Machine-readable diagnostics
+//!
+//! With `--diagnostics-format json`, every finding of a run (unparseable
+//! items, semantic risks, conflicting re-exports, `check` findings) is also
+//! written to stderr as one JSON object per line, so wrappers, bots and
+//! editors can consume them without scraping the human-oriented output on
+//! stdout. Library callers receive them through their `Reporter`.
+
+use crate::config::Severity;
+use crate::reporter::Reporter;
+use crate::risk::{Risk, RiskKind};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// How diagnostics are emitted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DiagnosticsFormat {
+    /// Only the human-oriented output
+    #[default]
+    Human,
+
+    /// Additionally one JSON record per line on stderr
+    Json,
+}
+
+/// Lines a diagnostic refers to (1-based, inclusive)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// One finding, as emitted in the JSON stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// File the finding is about; a generated module for semantic risks
+    pub file: String,
+
+    /// Lines within `file`, when known
+    pub span: Option<Span>,
+
+    /// Stable identifier of the kind of finding (`unresolved_import`, ...)
+    pub code: String,
+
+    /// `error` or `warning`
+    pub severity: String,
+
+    pub message: String,
+
+    /// What would resolve the finding, when SplitRS can tell
+    pub suggested_fix: Option<String>,
+}
+
+impl Diagnostic {
+    /// A diagnostic for a semantic risk of a split
+    ///
+    /// # Arguments
+    ///
+    /// * `risk` - The risk
+    /// * `file` - The generated file the risk's module is written to
+    /// * `error` - Whether the `[risks]` policy makes the risk an error
+    pub fn from_risk(risk: &Risk, file: String, error: bool) -> Self {
+        Diagnostic {
+            file,
+            span: None,
+            code: risk_code(risk.kind).to_string(),
+            severity: severity_label(if error {
+                Severity::Error
+            } else {
+                Severity::Warn
+            })
+            .to_string(),
+            message: risk.detail.clone(),
+            suggested_fix: risk_fix(risk.kind).map(str::to_string),
+        }
+    }
+
+    /// The record as one line of JSON, without the newline
+    pub fn to_json_line(&self) -> String {
+        serde_json::to_string(self).expect("diagnostics always serialize")
+    }
+}
+
+/// Name of a severity in the JSON stream
+pub fn severity_label(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warn => "warning",
+        Severity::Allow => "allowed",
+    }
+}
+
+/// Code of a risk kind; the same key names its `[risks]` policy
+fn risk_code(kind: RiskKind) -> &'static str {
+    match kind {
+        RiskKind::MacroScoping => "macro_scoping",
+        RiskKind::VisibilityEscalation => "visibility_widening",
+        RiskKind::UnresolvedImport => "unresolved_import",
+        RiskKind::CfgSplit => "cfg_split",
+        RiskKind::PrivateCall => "private_call",
+        RiskKind::PrivateField => "private_field",
+        RiskKind::PathRewrite => "path_rewrite",
+    }
+}
+
+/// Generic fix for a kind of risk
+fn risk_fix(kind: RiskKind) -> Option<&'static str> {
+    match kind {
+        RiskKind::MacroScoping => {
+            Some("add #[macro_export] to the macro, or move it into mod.rs above the module declarations")
+        }
+        RiskKind::UnresolvedImport => Some("add a `use` for the name to the generated module"),
+        RiskKind::CfgSplit => Some("add the type's #[cfg] attribute to the generated impl block"),
+        RiskKind::PrivateCall => Some("make the function pub(super), or keep it with its callers"),
+        RiskKind::PrivateField => {
+            Some("make the field pub(super), or keep the impl in the module of its type")
+        }
+        RiskKind::VisibilityEscalation | RiskKind::PathRewrite => None,
+    }
+}
+
+/// Hand diagnostics to the reporter of the run
+pub fn emit(reporter: &mut dyn Reporter, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        reporter.diagnostic(diagnostic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_risk_diagnostic() {
+        let risk = Risk {
+            kind: RiskKind::MacroScoping,
+            module: "functions".to_string(),
+            detail: "macro `helper!` is defined in `macros`".to_string(),
+        };
+        let diagnostic = Diagnostic::from_risk(&risk, "out/functions.rs".to_string(), true);
+        assert_eq!(diagnostic.code, "macro_scoping");
+        assert_eq!(diagnostic.severity, "error");
+        assert!(diagnostic.suggested_fix.is_some());
+
+        let warning = Diagnostic::from_risk(
+            &Risk {
+                kind: RiskKind::PathRewrite,
+                ..risk
+            },
+            "out/functions.rs".to_string(),
+            false,
+        );
+        assert_eq!(warning.severity, "warning");
+        assert_eq!(warning.suggested_fix, None);
+    }
+
+    #[test]
+    fn test_json_line() {
+        let diagnostic = Diagnostic {
+            file: "src/big.rs".to_string(),
+            span: Some(Span {
+                start_line: 3,
+                end_line: 5,
+            }),
+            code: "unparsed_item".to_string(),
+            severity: "warning".to_string(),
+            message: "expected `;`".to_string(),
+            suggested_fix: None,
+        };
+        let line = diagnostic.to_json_line();
+        assert!(!line.contains('\n'));
+        assert_eq!(
+            line,
+            "{\"file\":\"src/big.rs\",\"span\":{\"start_line\":3,\"end_line\":5},\
+             \"code\":\"unparsed_item\",\"severity\":\"warning\",\"message\":\"expected `;`\",\
+             \"suggested_fix\":null}"
+        );
+    }
+}
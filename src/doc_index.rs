@@ -0,0 +1,174 @@
+//! Contents index for generated module docs
+//!
+//! The template header of a generated module only says what kind of module
+//! it is. With `output.module_index` enabled, the `//!` block also lists the
+//! types, functions and methods the module contains together with the first
+//! line of their docs, so rustdoc renders a useful overview page for every
+//! module.
+
+use quote::ToTokens;
+use syn::{Attribute, ImplItem, ImplItemFn, Item};
+
+/// One line of the index: the item's name and its first doc line
+pub type IndexEntry = (String, Option<String>);
+
+/// First non-empty line of the `///` docs in `attrs`
+pub fn first_doc_line(attrs: &[Attribute]) -> Option<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(nv) => match &nv.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(s),
+                    ..
+                }) => Some(s.value()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .map(|line| line.trim().to_string())
+        .find(|line| !line.is_empty())
+}
+
+/// Index entries for top-level items, including the methods of inherent impls
+pub fn item_entries(items: &[Item]) -> Vec<IndexEntry> {
+    let mut entries = Vec::new();
+    for item in items {
+        match item {
+            Item::Struct(s) => entries.push((s.ident.to_string(), first_doc_line(&s.attrs))),
+            Item::Enum(e) => entries.push((e.ident.to_string(), first_doc_line(&e.attrs))),
+            Item::Trait(t) => entries.push((t.ident.to_string(), first_doc_line(&t.attrs))),
+            Item::Fn(f) => entries.push((format!("{}()", f.sig.ident), first_doc_line(&f.attrs))),
+            Item::Const(c) => entries.push((c.ident.to_string(), first_doc_line(&c.attrs))),
+            Item::Static(s) => entries.push((s.ident.to_string(), first_doc_line(&s.attrs))),
+            Item::Type(t) => entries.push((t.ident.to_string(), first_doc_line(&t.attrs))),
+            Item::Impl(i) => {
+                let self_ty = i.self_ty.to_token_stream().to_string().replace(' ', "");
+                if let Some((_, path, _)) = &i.trait_ {
+                    let trait_name = path.to_token_stream().to_string().replace(' ', "");
+                    entries.push((format!("{} for {}", trait_name, self_ty), None));
+                } else {
+                    let methods: Vec<&ImplItemFn> = i
+                        .items
+                        .iter()
+                        .filter_map(|item| match item {
+                            ImplItem::Fn(f) => Some(f),
+                            _ => None,
+                        })
+                        .collect();
+                    entries.extend(method_entries(&self_ty, &methods));
+                }
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+/// Index entries for methods of `self_ty`
+pub fn method_entries(self_ty: &str, methods: &[&ImplItemFn]) -> Vec<IndexEntry> {
+    methods
+        .iter()
+        .map(|m| {
+            (
+                format!("{}::{}()", self_ty, m.sig.ident),
+                first_doc_line(&m.attrs),
+            )
+        })
+        .collect()
+}
+
+/// Render the index as `//!` lines (empty when there are no entries)
+pub fn render_index(entries: &[IndexEntry]) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+    let mut out = String::from("//!\n//! ## Contents\n//!\n");
+    for (name, doc) in entries {
+        match doc {
+            Some(doc) => out.push_str(&format!("//! - `{}`: {}\n", name, doc)),
+            None => out.push_str(&format!("//! - `{}`\n", name)),
+        }
+    }
+    out
+}
+
+/// Append `index` to the leading `//!` block of `content`
+pub fn insert_index(content: &str, index: &str) -> String {
+    if index.is_empty() {
+        return content.to_string();
+    }
+    let docs_end: usize = content
+        .split_inclusive('\n')
+        .take_while(|line| line.starts_with("//!"))
+        .map(str::len)
+        .sum();
+
+    // Keep the generated-by line last, right before the code
+    let docs = &content[..docs_end];
+    let (body, footer) = match docs.rfind("//! 🤖") {
+        Some(pos) => (&docs[..pos], &docs[pos..]),
+        None => (docs, ""),
+    };
+    let body = body.trim_end_matches("//!\n");
+
+    let mut result = String::with_capacity(content.len() + index.len());
+    result.push_str(body);
+    result.push_str(index);
+    if !footer.is_empty() {
+        result.push_str("//!\n");
+        result.push_str(footer);
+    }
+    result.push_str(&content[docs_end..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_entries_use_first_doc_line() {
+        let file: syn::File = syn::parse_quote! {
+            /// A user
+            ///
+            /// More details.
+            pub struct User;
+
+            impl User {
+                /// Creates a user
+                pub fn new() -> Self { User }
+                fn undocumented(&self) {}
+            }
+
+            impl Clone for User { fn clone(&self) -> Self { User } }
+        };
+        let entries = item_entries(&file.items);
+        assert_eq!(
+            entries,
+            vec![
+                ("User".to_string(), Some("A user".to_string())),
+                (
+                    "User::new()".to_string(),
+                    Some("Creates a user".to_string())
+                ),
+                ("User::undocumented()".to_string(), None),
+                ("Clone for User".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_index_keeps_footer_last() {
+        let content =
+            "//! Auto-generated module\n//!\n//! 🤖 Generated with [SplitRS](x)\n\nstruct A;\n";
+        let index = render_index(&[("A".to_string(), Some("An A".to_string()))]);
+        let result = insert_index(content, &index);
+        assert_eq!(
+            result,
+            "//! Auto-generated module\n//!\n//! ## Contents\n//!\n//! - `A`: An A\n\
+             //!\n//! 🤖 Generated with [SplitRS](x)\n\nstruct A;\n"
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! Rewriting intra-doc links for the new module layout
+//!
+//! Doc links are resolved relative to the module an item lives in. Once
+//! items move into separate files, `[`OtherType`]` no longer resolves unless
+//! the new module happens to import `OtherType`, and `self::`/`super::`
+//! paths point one level too shallow. Links that would break are rewritten
+//! to paths that resolve from the item's new module; the link text is kept.
+
+use crate::import_analyzer::ImportAnalyzer;
+use std::collections::HashMap;
+use syn::visit_mut::VisitMut;
+use syn::{Attribute, Expr, ExprLit, Lit, Meta};
+
+/// Where the items of the original file ended up
+pub struct LinkContext<'a> {
+    /// Module each top-level name of the original file was placed in
+    pub layout: &'a HashMap<String, String>,
+
+    /// Module whose items are being rewritten
+    pub module: &'a str,
+
+    /// Imports of the original file
+    pub imports: &'a ImportAnalyzer,
+}
+
+impl LinkContext<'_> {
+    /// New target for a link path, or `None` when it still resolves
+    pub fn rewrite_path(&self, target: &str) -> Option<String> {
+        // Keep rustdoc disambiguators (`struct@`, `fn()`, `macro!`) in place
+        let (prefix, rest) = match target.split_once('@') {
+            Some((kind, rest)) => (format!("{}@", kind), rest),
+            None => (String::new(), target),
+        };
+        let path_end = rest.find(['(', '!']).unwrap_or(rest.len());
+        let (path, suffix) = rest.split_at(path_end);
+        if path.is_empty()
+            || !path
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == ':')
+        {
+            return None;
+        }
+
+        let first = path.split("::").next().unwrap_or(path);
+        let rest_of_path = &path[first.len()..];
+        let rewritten = match first {
+            "Self" | "crate" | "std" | "core" | "alloc" => return None,
+            "self" => format!("super{}", rest_of_path),
+            "super" => format!("super::{}", path),
+            name => match self.layout.get(name) {
+                Some(module) if module == self.module => return None,
+                Some(module) => format!("super::{}::{}", module, path),
+                None => format!("{}{}", self.imports.external_path(name)?, rest_of_path),
+            },
+        };
+        Some(format!("{}{}{}", prefix, rewritten, suffix))
+    }
+
+    /// Rewrite the links in one line of documentation
+    pub fn rewrite_line(&self, line: &str) -> String {
+        let mut out = String::with_capacity(line.len());
+        let mut rest = line;
+
+        while let Some(open) = rest.find('[') {
+            let Some(close) = rest[open..].find(']').map(|c| open + c) else {
+                break;
+            };
+            let at_line_start = out.is_empty() && rest[..open].trim().is_empty();
+            out.push_str(&rest[..=close]);
+            let text = &rest[open + 1..close];
+            let after = &rest[close + 1..];
+
+            if let Some(inline) = after.strip_prefix('(') {
+                // [text](target)
+                if let Some(end) = inline.find(')') {
+                    let target = &inline[..end];
+                    let new_target = self.rewrite_path(target);
+                    out.push('(');
+                    out.push_str(new_target.as_deref().unwrap_or(target));
+                    out.push(')');
+                    rest = &inline[end + 1..];
+                    continue;
+                }
+            } else if let Some(definition) = after.strip_prefix(": ") {
+                // [text]: target
+                if at_line_start {
+                    let target = definition.trim();
+                    out.push_str(": ");
+                    out.push_str(
+                        &self
+                            .rewrite_path(target)
+                            .unwrap_or_else(|| target.to_string()),
+                    );
+                    return out;
+                }
+            } else if !after.starts_with('[') {
+                // Shortcut link: [`Name`] or [Name]
+                let path = text.trim_matches('`');
+                if let Some(target) = self.rewrite_path(path) {
+                    out.push_str(&format!("({})", target));
+                }
+            }
+            rest = after;
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Rewrite the doc attributes of everything visited
+    pub fn rewriter(&self) -> DocLinkRewriter<'_, '_> {
+        DocLinkRewriter {
+            context: self,
+            in_code_block: false,
+        }
+    }
+}
+
+/// Visitor rewriting links in `#[doc = "..."]` attributes
+pub struct DocLinkRewriter<'c, 'a> {
+    context: &'c LinkContext<'a>,
+
+    /// Inside a fenced code block, where brackets are code
+    in_code_block: bool,
+}
+
+impl VisitMut for DocLinkRewriter<'_, '_> {
+    fn visit_attribute_mut(&mut self, attr: &mut Attribute) {
+        if !attr.path().is_ident("doc") {
+            return;
+        }
+        let Meta::NameValue(nv) = &mut attr.meta else {
+            return;
+        };
+        let Expr::Lit(ExprLit {
+            lit: Lit::Str(doc), ..
+        }) = &mut nv.value
+        else {
+            return;
+        };
+
+        let value = doc.value();
+        let rewritten: Vec<String> = value
+            .split('\n')
+            .map(|line| {
+                if line.trim_start().starts_with("```") {
+                    self.in_code_block = !self.in_code_block;
+                    line.to_string()
+                } else if self.in_code_block || !line.contains('[') {
+                    line.to_string()
+                } else {
+                    self.context.rewrite_line(line)
+                }
+            })
+            .collect();
+        let rewritten = rewritten.join("\n");
+        if rewritten != value {
+            *doc = syn::LitStr::new(&rewritten, doc.span());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(line: &str) -> String {
+        let file: syn::File = syn::parse_quote! {
+            use std::sync::Arc;
+        };
+        let imports = crate::analysis::AnalysisModel::build(&file).imports;
+        let layout: HashMap<String, String> = [
+            ("Engine".to_string(), "engine_type".to_string()),
+            ("Config".to_string(), "types".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let context = LinkContext {
+            layout: &layout,
+            module: "types",
+            imports: &imports,
+        };
+        context.rewrite_line(line)
+    }
+
+    #[test]
+    fn test_rewrites_links_that_would_break() {
+        assert_eq!(
+            rewrite("See [`Engine::run`] and [`Config`]."),
+            "See [`Engine::run`](super::engine_type::Engine::run) and [`Config`]."
+        );
+        assert_eq!(
+            rewrite("Shared via [`Arc`], see [the engine](self::Engine)."),
+            "Shared via [`Arc`](std::sync::Arc), see [the engine](super::Engine)."
+        );
+        assert_eq!(
+            rewrite("[`Self::new`], [`crate::x::Y`], [docs](https://docs.rs), a[0]"),
+            "[`Self::new`], [`crate::x::Y`], [docs](https://docs.rs), a[0]"
+        );
+    }
+
+    #[test]
+    fn test_code_blocks_are_left_alone() {
+        let file: syn::File = syn::parse_quote! {
+            /// Uses [`Engine`].
+            ///
+            /// ```
+            /// let x = [Engine];
+            /// ```
+            pub struct Config;
+        };
+        let imports = ImportAnalyzer::new();
+        let layout: HashMap<String, String> = [("Engine".to_string(), "engine_type".to_string())]
+            .into_iter()
+            .collect();
+        let context = LinkContext {
+            layout: &layout,
+            module: "types",
+            imports: &imports,
+        };
+        let mut item = file.items[0].clone();
+        context.rewriter().visit_item_mut(&mut item);
+
+        let docs: Vec<String> = match &item {
+            syn::Item::Struct(s) => s
+                .attrs
+                .iter()
+                .filter_map(|a| match &a.meta {
+                    Meta::NameValue(nv) => match &nv.value {
+                        Expr::Lit(ExprLit {
+                            lit: Lit::Str(s), ..
+                        }) => Some(s.value()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect(),
+            _ => unreachable!(),
+        };
+        assert_eq!(docs[0], " Uses [`Engine`](super::engine_type::Engine).");
+        assert_eq!(docs[3], " let x = [Engine];");
+    }
+}
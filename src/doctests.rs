@@ -0,0 +1,353 @@
+//! Doc examples of moved items
+//!
+//! Doctests are compiled as crates of their own, so they name items by
+//! their public path (`use mycrate::big::Foo;`). After a split the path
+//! still works when `big` re-exports `Foo`, but not when the name is no
+//! longer reachable there: an internal helper, a name withheld as
+//! ambiguous, or a module written to another location. The code blocks in
+//! the doc comments of moved items are scanned for paths into the split
+//! module, and those that no longer resolve are reported.
+
+use crate::existing_module::absolute;
+use quote::ToTokens;
+use std::collections::BTreeSet;
+use std::path::Path;
+use syn::visit::Visit;
+use syn::{Attribute, Expr, Lit, Meta, UseTree};
+
+/// Code block attributes that keep a block a compiled Rust doctest
+const DOCTEST_ATTRIBUTES: &[&str] = &["rust", "should_panic", "no_run", "test_harness"];
+
+/// Name doctests use for the crate holding `path`
+///
+/// The package name of the nearest `Cargo.toml`, in snake case.
+pub fn crate_name(path: &Path) -> Option<String> {
+    let path = absolute(path);
+    let crate_dir = path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").is_file())?;
+    crate::crate_mode::package_name(crate_dir)
+}
+
+/// Text of the `#[doc = "..."]` attributes among `attrs`
+fn doc_text(attrs: &[Attribute]) -> String {
+    let mut lines = Vec::new();
+    for attr in attrs {
+        if let Meta::NameValue(name_value) = &attr.meta {
+            if name_value.path.is_ident("doc") {
+                if let Expr::Lit(syn::ExprLit {
+                    lit: Lit::Str(text),
+                    ..
+                }) = &name_value.value
+                {
+                    lines.push(text.value());
+                }
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+/// Whether a fence info string (`rust,no_run`) marks a compiled doctest
+fn is_doctest(info: &str) -> bool {
+    info.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .all(|token| DOCTEST_ATTRIBUTES.contains(&token) || token.starts_with("edition"))
+}
+
+/// Code of the doctests in a doc comment, with hidden `# ` lines shown
+pub fn doctest_blocks(docs: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(String, bool)> = None;
+    for line in docs.lines() {
+        let trimmed = line.trim();
+        let fence = trimmed.starts_with("```") || trimmed.starts_with("~~~");
+        match (&mut current, fence) {
+            (None, true) => current = Some((String::new(), is_doctest(&trimmed[3..]))),
+            (Some((code, compiled)), true) => {
+                if *compiled {
+                    blocks.push(std::mem::take(code));
+                }
+                current = None;
+            }
+            (Some((code, _)), false) => {
+                let shown = match trimmed.strip_prefix('#') {
+                    Some(rest) if rest.is_empty() || rest.starts_with(' ') => rest,
+                    _ => trimmed,
+                };
+                code.push_str(shown);
+                code.push('\n');
+            }
+            (None, false) => {}
+        }
+    }
+    blocks
+}
+
+/// Paths named by a doctest, as segments (`*` for a glob import)
+fn code_paths(code: &str) -> Vec<Vec<String>> {
+    #[derive(Default)]
+    struct Paths(Vec<Vec<String>>);
+
+    impl Paths {
+        fn add_tree(&mut self, prefix: &mut Vec<String>, tree: &UseTree) {
+            match tree {
+                UseTree::Path(path) => {
+                    prefix.push(path.ident.to_string());
+                    self.add_tree(prefix, &path.tree);
+                    prefix.pop();
+                }
+                UseTree::Name(name) => self.add(prefix, name.ident.to_string()),
+                UseTree::Rename(rename) => self.add(prefix, rename.ident.to_string()),
+                UseTree::Glob(_) => self.add(prefix, "*".to_string()),
+                UseTree::Group(group) => {
+                    for tree in &group.items {
+                        self.add_tree(prefix, tree);
+                    }
+                }
+            }
+        }
+
+        fn add(&mut self, prefix: &[String], last: String) {
+            let mut path = prefix.to_vec();
+            path.push(last);
+            self.0.push(path);
+        }
+    }
+
+    impl<'ast> Visit<'ast> for Paths {
+        fn visit_item_use(&mut self, node: &'ast syn::ItemUse) {
+            self.add_tree(&mut Vec::new(), &node.tree);
+        }
+
+        fn visit_path(&mut self, node: &'ast syn::Path) {
+            self.0.push(
+                node.segments
+                    .iter()
+                    .map(|segment| segment.ident.to_string())
+                    .collect(),
+            );
+            syn::visit::visit_path(self, node);
+        }
+    }
+
+    let mut paths = Paths::default();
+    if let Ok(block) = syn::parse_str::<syn::Block>(&format!("{{\n{}\n}}", code)) {
+        paths.visit_block(&block);
+    } else if let Ok(file) = syn::parse_file(code) {
+        paths.visit_file(&file);
+    }
+    paths.0
+}
+
+/// Doctests found in the items of a module
+#[derive(Default)]
+pub struct DoctestScan {
+    /// Type whose methods are visited without their impl block
+    owner: Option<String>,
+
+    /// Number of doctests
+    pub doctests: usize,
+
+    /// Each path a doctest names, with the item documenting it
+    pub paths: Vec<(String, Vec<String>)>,
+}
+
+impl DoctestScan {
+    /// Scan of a module whose loose methods belong to `owner`
+    pub fn new(owner: Option<String>) -> Self {
+        Self {
+            owner,
+            ..Self::default()
+        }
+    }
+
+    fn record(&mut self, item: String, attrs: &[Attribute]) {
+        for block in doctest_blocks(&doc_text(attrs)) {
+            self.doctests += 1;
+            for path in code_paths(&block) {
+                self.paths.push((item.clone(), path));
+            }
+        }
+    }
+
+    fn member(&self, name: &syn::Ident) -> String {
+        match &self.owner {
+            Some(owner) => format!("{}::{}", owner, name),
+            None => name.to_string(),
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for DoctestScan {
+    fn visit_item(&mut self, node: &'ast syn::Item) {
+        if let syn::Item::Impl(impl_block) = node {
+            let owner = self.owner.replace(
+                impl_block
+                    .self_ty
+                    .to_token_stream()
+                    .to_string()
+                    .replace(' ', ""),
+            );
+            syn::visit::visit_item(self, node);
+            self.owner = owner;
+            return;
+        }
+        let label = crate::migration::item_labels(node)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| "item".to_string());
+        self.record(label, crate::internal_items::item_attrs(node));
+        syn::visit::visit_item(self, node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        self.record(self.member(&node.sig.ident), &node.attrs);
+    }
+
+    fn visit_impl_item_const(&mut self, node: &'ast syn::ImplItemConst) {
+        self.record(self.member(&node.ident), &node.attrs);
+    }
+
+    fn visit_trait_item_fn(&mut self, node: &'ast syn::TraitItemFn) {
+        self.record(node.sig.ident.to_string(), &node.attrs);
+    }
+}
+
+/// Doctest paths into the split module that no longer resolve
+///
+/// # Arguments
+///
+/// * `paths` - Paths from [`DoctestScan::paths`]
+/// * `crate_name` - Name doctests use for the crate
+/// * `original` - Module path of the input (`crate::big`)
+/// * `new` - Module path of the output directory
+/// * `reachable` - Names the new module exposes (re-exports, modules, kept items)
+///
+/// # Returns
+///
+/// The documented item and a description of each stale path
+pub fn stale_paths(
+    paths: &[(String, Vec<String>)],
+    crate_name: &str,
+    original: &str,
+    new: &str,
+    reachable: &BTreeSet<String>,
+) -> Vec<(String, String)> {
+    let public = |module: &str| {
+        let mut segments: Vec<String> = module.split("::").map(str::to_string).collect();
+        segments[0] = crate_name.to_string();
+        segments
+    };
+    let original = public(original);
+    let new = public(new);
+
+    let mut stale = Vec::new();
+    let mut seen = BTreeSet::new();
+    for (item, path) in paths {
+        let Some(rest) = path.strip_prefix(original.as_slice()) else {
+            continue;
+        };
+        let Some(name) = rest.first() else {
+            continue;
+        };
+        let detail = if original != new {
+            format!(
+                "doctest of `{}` uses `{}`; the module is now `{}`",
+                item,
+                path.join("::"),
+                new.join("::")
+            )
+        } else if name != "*" && !reachable.contains(name) {
+            format!(
+                "doctest of `{}` uses `{}`, which `{}` no longer exposes",
+                item,
+                path.join("::"),
+                original.join("::")
+            )
+        } else {
+            continue;
+        };
+        if seen.insert(detail.clone()) {
+            stale.push((item.clone(), detail));
+        }
+    }
+    stale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scans_doctests_of_items() {
+        let file: syn::File = syn::parse_quote! {
+            /// Parses input.
+            ///
+            /// ```
+            /// # use mycrate::big::{Parser, Options};
+            /// let parser = Parser::new(mycrate::big::helper());
+            /// ```
+            ///
+            /// ```text
+            /// mycrate::big::NotCode
+            /// ```
+            pub struct Parser;
+            impl Parser {
+                /// ```ignore
+                /// mycrate::big::Skipped::new();
+                /// ```
+                pub fn new() -> Self { Parser }
+                /// ```no_run
+                /// use mycrate::big::*;
+                /// ```
+                pub fn run(&self) {}
+            }
+        };
+        let mut scan = DoctestScan::default();
+        scan.visit_file(&file);
+        assert_eq!(scan.doctests, 2);
+        let paths: Vec<(&str, String)> = scan
+            .paths
+            .iter()
+            .map(|(item, path)| (item.as_str(), path.join("::")))
+            .collect();
+        assert!(paths.contains(&("Parser", "mycrate::big::Parser".to_string())));
+        assert!(paths.contains(&("Parser", "mycrate::big::Options".to_string())));
+        assert!(paths.contains(&("Parser", "mycrate::big::helper".to_string())));
+        assert!(paths.contains(&("Parser::run", "mycrate::big::*".to_string())));
+        assert!(!paths.iter().any(|(_, path)| path.contains("NotCode")));
+        assert!(!paths.iter().any(|(_, path)| path.contains("Skipped")));
+    }
+
+    #[test]
+    fn test_stale_paths() {
+        let path = |p: &str| p.split("::").map(str::to_string).collect::<Vec<_>>();
+        let paths = vec![
+            ("Parser".to_string(), path("mycrate::big::Parser")),
+            ("Parser".to_string(), path("mycrate::big::helper")),
+            (
+                "Parser".to_string(),
+                path("mycrate::big::parser_type::Parser"),
+            ),
+            ("Parser".to_string(), path("mycrate::other::Thing")),
+        ];
+        let reachable: BTreeSet<String> = ["Parser", "parser_type"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+
+        let stale = stale_paths(&paths, "mycrate", "crate::big", "crate::big", &reachable);
+        assert_eq!(
+            stale,
+            vec![(
+                "Parser".to_string(),
+                "doctest of `Parser` uses `mycrate::big::helper`, which `mycrate::big` no longer exposes"
+                    .to_string()
+            )]
+        );
+        let moved = stale_paths(&paths, "mycrate", "crate::big", "crate::core", &reachable);
+        assert_eq!(moved.len(), 3);
+    }
+}
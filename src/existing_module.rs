@@ -0,0 +1,369 @@
+//! Module declarations of the input and splitting a `mod.rs` in place
+//!
+//! `mod child;` declarations in the input resolve relative to the input's
+//! location, so they stay in the generated `mod.rs` instead of moving into
+//! `functions.rs` (where they would look for `functions/child.rs`). When the
+//! output directory is not where the children were found, or a declaration
+//! carries `#[path = "..."]`, the path is rewritten for the new location.
+//! Re-exports (`pub use self::detail::Thing;`) are part of the module's
+//! surface rather than code, so they move to the generated `mod.rs` too.
+//!
+//! When the input is `src/engine/mod.rs` and the output is `src/engine/`,
+//! the generated files land next to the module's existing children, so
+//! generated modules avoid the children's names and the new submodules are
+//! declared in the existing `mod.rs` below its docs and attributes.
+
+use crate::crate_root::has_anchor_attribute;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use syn::visit::Visit;
+use syn::{Attribute, File, Item, ItemMod, Visibility};
+
+/// Whether `input` is the `mod.rs` of `output_dir` itself
+pub fn is_mod_rs_of(input: &Path, output_dir: &Path) -> bool {
+    if input.file_name().is_none_or(|name| name != "mod.rs") {
+        return false;
+    }
+    let (Some(parent), Ok(output)) = (input.parent(), fs::canonicalize(output_dir)) else {
+        return false;
+    };
+    let parent = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent
+    };
+    fs::canonicalize(parent).is_ok_and(|parent| parent == output)
+}
+
+/// Names of the child modules that exist as files in `dir`
+pub fn child_module_names(dir: &Path) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return names;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let is_child_dir = path.is_dir() && path.join("mod.rs").is_file();
+        let is_child_file = path.extension().is_some_and(|ext| ext == "rs") && name != "mod";
+        if is_child_dir || is_child_file {
+            names.insert(name.to_string());
+        }
+    }
+    names
+}
+
+/// Remove the `mod child;` declarations from `file`
+///
+/// # Returns
+///
+/// The removed declarations, in source order
+pub fn take_child_declarations(file: &mut File) -> Vec<Item> {
+    let (declarations, items): (Vec<Item>, Vec<Item>) = std::mem::take(&mut file.items)
+        .into_iter()
+        .partition(|item| matches!(item, Item::Mod(m) if m.content.is_none()));
+    file.items = items;
+    declarations
+}
+
+/// Remove the re-exports (`pub use`, `pub(crate) use`, ...) from `file`
+///
+/// # Returns
+///
+/// The removed re-exports, in source order
+pub fn take_reexports(file: &mut File) -> Vec<Item> {
+    let (reexports, items): (Vec<Item>, Vec<Item>) = std::mem::take(&mut file.items)
+        .into_iter()
+        .partition(|item| matches!(item, Item::Use(u) if !matches!(u.vis, Visibility::Inherited)));
+    file.items = items;
+    reexports
+}
+
+/// Remove the items carrying an anchor attribute from `file`
+///
+/// Anchored items (`#[ctor]` functions, custom test harness entries) are
+/// never relocated: they stay in `mod.rs`, in source order.
+///
+/// # Returns
+///
+/// The removed items, in source order
+pub fn take_anchored_items(file: &mut File, anchors: &[String]) -> Vec<Item> {
+    let (anchored, items): (Vec<Item>, Vec<Item>) = std::mem::take(&mut file.items)
+        .into_iter()
+        .partition(|item| has_anchor_attribute(item, anchors));
+    file.items = items;
+    anchored
+}
+
+/// Declared names of `mod child;` items
+pub fn declared_names(declarations: &[Item]) -> BTreeSet<String> {
+    declarations
+        .iter()
+        .filter_map(|item| match item {
+            Item::Mod(m) => Some(m.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Directory that `mod child;` declarations of `input` resolve against
+pub fn child_dir(input: &Path) -> PathBuf {
+    let parent = input.parent().unwrap_or(Path::new(""));
+    match input.file_stem().and_then(|s| s.to_str()) {
+        Some("mod" | "lib" | "main") | None => parent.to_path_buf(),
+        Some(stem) => parent.join(stem),
+    }
+}
+
+/// Point a declaration moved from `input` to `output_dir/mod.rs` at the
+/// file it referred to before
+pub fn relocate_declaration(item: &mut Item, input: &Path, output_dir: &Path) {
+    let Item::Mod(module) = item else {
+        return;
+    };
+    let input_dir = absolute(input.parent().unwrap_or(Path::new("")));
+    let output_dir = absolute(output_dir);
+
+    let target = match path_attribute(module) {
+        // `#[path]` is relative to the directory of the declaring file
+        Some(path) => input_dir.join(path),
+        None => {
+            let children = absolute(&child_dir(input));
+            if children == output_dir {
+                return;
+            }
+            let name = module.ident.to_string();
+            let file = children.join(format!("{}.rs", name));
+            let dir_module = children.join(&name).join("mod.rs");
+            if file.is_file() {
+                file
+            } else if dir_module.is_file() {
+                dir_module
+            } else {
+                return;
+            }
+        }
+    };
+
+    let relative = relative_path(&target, &output_dir);
+    let relative = relative.to_string_lossy().replace('\\', "/");
+    set_path_attribute(module, &relative);
+}
+
+fn path_attribute(module: &ItemMod) -> Option<String> {
+    module.attrs.iter().find_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(nv) if nv.path.is_ident("path") => match &nv.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(s),
+                ..
+            }) => Some(s.value()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn set_path_attribute(module: &mut ItemMod, path: &str) {
+    module.attrs.retain(|attr| !attr.path().is_ident("path"));
+    let attr: Attribute = syn::parse_quote!(#[path = #path]);
+    module.attrs.insert(0, attr);
+}
+
+/// Absolute, lexically normalized form of `path`
+pub fn absolute(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// `target` relative to the directory `base` (both absolute and normalized)
+pub fn relative_path(target: &Path, base: &Path) -> PathBuf {
+    let target: Vec<Component> = target.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = target.iter().zip(&base).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &target[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+/// First segments of the paths in the visited code
+#[derive(Default)]
+pub struct PathRoots {
+    /// First segments of multi-segment paths (`child::f()` records `child`)
+    pub roots: BTreeSet<String>,
+
+    /// First segments of all paths, including single names
+    pub names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for PathRoots {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if path.leading_colon.is_none() {
+            if let Some(first) = path.segments.first() {
+                if path.segments.len() > 1 {
+                    self.roots.insert(first.ident.to_string());
+                }
+                self.names.insert(first.ident.to_string());
+            }
+        }
+        syn::visit::visit_path(self, path);
+    }
+}
+
+/// `use` statements bringing names kept in `mod.rs` (child modules,
+/// hoisted re-exports) into a generated module that uses them
+pub fn mod_rs_uses(kept: &BTreeSet<String>, used: &BTreeSet<String>) -> Vec<String> {
+    kept.intersection(used)
+        .map(|name| format!("use super::{};", name))
+        .collect()
+}
+
+/// Content of the generated `mod.rs` with the kept declarations
+///
+/// # Arguments
+///
+/// * `attrs` - Inner attributes and docs to keep (those of an input
+///   `mod.rs` split in place); without them the generated header stays
+/// * `declarations` - The input's `mod child;` declarations and re-exports
+/// * `generated` - The generated `mod.rs` content for the new submodules
+pub fn render_mod_rs(attrs: &[Attribute], declarations: &[Item], generated: &str) -> String {
+    const HEADER: &str = "//! Auto-generated module structure\n";
+    let kept = prettyplease::unparse(&File {
+        shebang: None,
+        attrs: attrs.to_vec(),
+        items: declarations.to_vec(),
+    });
+    let body = generated.strip_prefix(HEADER).unwrap_or(generated);
+
+    let mut content = String::new();
+    if attrs.is_empty() {
+        content.push_str(HEADER);
+        content.push('\n');
+    }
+    content.push_str(kept.trim_end());
+    if !kept.trim().is_empty() {
+        content.push('\n');
+    }
+    content.push_str("\n// Modules generated by SplitRS\n");
+    content.push_str(body.trim_start());
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_detects_mod_rs_layout() {
+        let dir = env::temp_dir().join(format!("splitrs_modrs_{}", std::process::id()));
+        fs::create_dir_all(dir.join("child")).unwrap();
+        fs::write(dir.join("mod.rs"), "").unwrap();
+        fs::write(dir.join("types.rs"), "").unwrap();
+        fs::write(dir.join("child/mod.rs"), "").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        assert!(is_mod_rs_of(&dir.join("mod.rs"), &dir));
+        assert!(!is_mod_rs_of(&dir.join("types.rs"), &dir));
+        assert!(!is_mod_rs_of(&dir.join("mod.rs"), &dir.join("child")));
+        assert_eq!(
+            child_module_names(&dir).into_iter().collect::<Vec<_>>(),
+            vec!["child", "types"]
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_render_keeps_docs_and_declarations() {
+        let mut file: File = syn::parse_quote! {
+            //! The engine
+            pub mod types;
+            mod inline { fn f() {} }
+            pub struct Engine;
+        };
+        let declarations = take_child_declarations(&mut file);
+        assert_eq!(declarations.len(), 1);
+        assert_eq!(file.items.len(), 2);
+        assert!(take_reexports(&mut file).is_empty());
+
+        let content = render_mod_rs(
+            &file.attrs,
+            &declarations,
+            "//! Auto-generated module structure\n\npub mod types_2;\n",
+        );
+        assert_eq!(
+            content,
+            "//! The engine\npub mod types;\n\n// Modules generated by SplitRS\npub mod types_2;\n"
+        );
+    }
+
+    #[test]
+    fn test_relocates_declarations_for_new_directory() {
+        let root = env::temp_dir().join(format!("splitrs_relocate_{}", std::process::id()));
+        fs::create_dir_all(root.join("src/big")).unwrap();
+        fs::write(root.join("src/big/child.rs"), "").unwrap();
+
+        let input = root.join("src/big.rs");
+        let mut items: Vec<Item> = vec![
+            syn::parse_quote! { #[path = "legacy/foo.rs"] mod foo; },
+            syn::parse_quote! { mod child; },
+        ];
+        for item in &mut items {
+            relocate_declaration(item, &input, &root.join("src/big_split"));
+        }
+        assert_eq!(
+            path_attribute_of(&items[0]),
+            Some("../legacy/foo.rs".to_string())
+        );
+        assert_eq!(
+            path_attribute_of(&items[1]),
+            Some("../big/child.rs".to_string())
+        );
+
+        // Splitting into the natural directory needs no attribute
+        let mut child: Item = syn::parse_quote! { mod child; };
+        relocate_declaration(&mut child, &input, &root.join("src/big"));
+        assert_eq!(path_attribute_of(&child), None);
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_takes_only_reexports() {
+        let mut file: File = syn::parse_quote! {
+            use std::fmt;
+            pub use self::detail::Thing;
+            pub(crate) use other::X as Y;
+            pub fn f() {}
+        };
+        let reexports = take_reexports(&mut file);
+        assert_eq!(reexports.len(), 2);
+        assert_eq!(file.items.len(), 2);
+    }
+
+    fn path_attribute_of(item: &Item) -> Option<String> {
+        match item {
+            Item::Mod(m) => path_attribute(m),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,590 @@
+//! Public symbol analysis for generated modules
+//!
+//! The generated `mod.rs` re-exports every module with `pub use module::*;`,
+//! so two modules exporting the same name in the same namespace produce a
+//! conflict (E0252 once the name is used). This module computes the exported
+//! symbols of each planned module and reports collisions before any file is
+//! written.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use syn::{Fields, Item, UseTree, Visibility};
+
+/// Rust namespace an exported name lives in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Namespace {
+    /// Types, traits, and modules
+    Type,
+
+    /// Functions, constants, statics, and tuple/unit struct constructors
+    Value,
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Namespace::Type => write!(f, "type"),
+            Namespace::Value => write!(f, "value"),
+        }
+    }
+}
+
+/// How far a re-export of an item may reach
+///
+/// `pub use` only accepts `pub` items in explicit lists (E0364/E0365);
+/// restricted items are re-exported at their own visibility instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExportVisibility {
+    /// `pub`, re-exported with `pub use`
+    Public,
+
+    /// `pub(crate)`, re-exported with `pub(crate) use`
+    Crate,
+
+    /// `pub(super)` and `pub(in path)`, imported into the parent with a
+    /// private `use`
+    Parent,
+}
+
+impl ExportVisibility {
+    /// Visibility of an item, if it is visible outside its module at all
+    pub fn of(vis: &Visibility) -> Option<Self> {
+        match vis {
+            Visibility::Public(_) => Some(ExportVisibility::Public),
+            Visibility::Inherited => None,
+            Visibility::Restricted(restricted) => {
+                if restricted.path.is_ident("crate") {
+                    Some(ExportVisibility::Crate)
+                } else if restricted.path.is_ident("self") {
+                    None
+                } else {
+                    Some(ExportVisibility::Parent)
+                }
+            }
+        }
+    }
+
+    /// Keyword starting the `use` line of a re-export
+    pub fn use_keyword(self) -> &'static str {
+        match self {
+            ExportVisibility::Public => "pub use",
+            ExportVisibility::Crate => "pub(crate) use",
+            ExportVisibility::Parent => "use",
+        }
+    }
+}
+
+/// A name made visible to the parent module by a glob re-export
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExportedSymbol {
+    /// The exported identifier
+    pub name: String,
+
+    /// Namespace the identifier occupies
+    pub namespace: Namespace,
+
+    /// Visibility of the exported item
+    pub visibility: ExportVisibility,
+}
+
+impl ExportedSymbol {
+    fn new(name: impl Into<String>, namespace: Namespace) -> Self {
+        Self {
+            name: name.into(),
+            namespace,
+            visibility: ExportVisibility::Public,
+        }
+    }
+
+    fn restricted(mut self, visibility: ExportVisibility) -> Self {
+        self.visibility = visibility;
+        self
+    }
+}
+
+/// The same name exported by more than one planned module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolCollision {
+    /// The conflicting identifier
+    pub name: String,
+
+    /// Namespace in which the names collide
+    pub namespace: Namespace,
+
+    /// Modules exporting the name, in plan order
+    pub modules: Vec<String>,
+}
+
+impl fmt::Display for SymbolCollision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let files: Vec<String> = self.modules.iter().map(|m| format!("{}.rs", m)).collect();
+        write!(
+            f,
+            "`{}` ({} namespace) is exported by {}",
+            self.name,
+            self.namespace,
+            files.join(", ")
+        )
+    }
+}
+
+/// Compute the symbols a glob re-export of these items would expose
+///
+/// Private items are not glob-imported and are skipped. Impl blocks never
+/// export names.
+pub fn exported_symbols(items: &[Item]) -> Vec<ExportedSymbol> {
+    let mut symbols = Vec::new();
+    for item in items {
+        collect_item_symbols(item, &mut symbols);
+    }
+    symbols.sort();
+    symbols.dedup();
+    symbols
+}
+
+fn collect_item_symbols(item: &Item, symbols: &mut Vec<ExportedSymbol>) {
+    let (vis, names): (&Visibility, Vec<(String, Namespace)>) = match item {
+        Item::Struct(s) => {
+            let mut names = vec![(s.ident.to_string(), Namespace::Type)];
+            if !matches!(s.fields, Fields::Named(_)) {
+                names.push((s.ident.to_string(), Namespace::Value));
+            }
+            (&s.vis, names)
+        }
+        Item::Enum(e) => (&e.vis, vec![(e.ident.to_string(), Namespace::Type)]),
+        Item::Union(u) => (&u.vis, vec![(u.ident.to_string(), Namespace::Type)]),
+        Item::Trait(t) => (&t.vis, vec![(t.ident.to_string(), Namespace::Type)]),
+        Item::Type(t) => (&t.vis, vec![(t.ident.to_string(), Namespace::Type)]),
+        Item::Mod(m) => (&m.vis, vec![(m.ident.to_string(), Namespace::Type)]),
+        Item::Fn(f) => (&f.vis, vec![(f.sig.ident.to_string(), Namespace::Value)]),
+        Item::Const(c) => (&c.vis, vec![(c.ident.to_string(), Namespace::Value)]),
+        Item::Static(s) => (&s.vis, vec![(s.ident.to_string(), Namespace::Value)]),
+        Item::Use(u) => {
+            // A re-export may name an item of either namespace
+            let mut leaves = Vec::new();
+            collect_use_leaves(&u.tree, &mut leaves);
+            let names = leaves
+                .into_iter()
+                .flat_map(|name| [(name.clone(), Namespace::Type), (name, Namespace::Value)])
+                .collect();
+            (&u.vis, names)
+        }
+        _ => return,
+    };
+    let Some(visibility) = ExportVisibility::of(vis) else {
+        return;
+    };
+    for (name, namespace) in names {
+        symbols.push(ExportedSymbol::new(name, namespace).restricted(visibility));
+    }
+}
+
+/// Collect the names a `use` tree brings into scope (globs are skipped)
+pub fn collect_use_leaves(tree: &UseTree, names: &mut Vec<String>) {
+    match tree {
+        UseTree::Path(p) => collect_use_leaves(&p.tree, names),
+        UseTree::Name(n) => {
+            if n.ident != "self" {
+                names.push(n.ident.to_string());
+            }
+        }
+        UseTree::Rename(r) => {
+            if r.rename != "_" {
+                names.push(r.rename.to_string());
+            }
+        }
+        UseTree::Group(g) => {
+            for item in &g.items {
+                collect_use_leaves(item, names);
+            }
+        }
+        UseTree::Glob(_) => {}
+    }
+}
+
+/// Find names exported by more than one module in the same namespace
+///
+/// # Arguments
+///
+/// * `modules` - Module names paired with their exported symbols, in plan order
+pub fn find_collisions(modules: &[(String, Vec<ExportedSymbol>)]) -> Vec<SymbolCollision> {
+    let mut owners: BTreeMap<(&str, Namespace), Vec<String>> = BTreeMap::new();
+    for (module, symbols) in modules {
+        for symbol in symbols {
+            let entry = owners
+                .entry((symbol.name.as_str(), symbol.namespace))
+                .or_default();
+            if !entry.contains(module) {
+                entry.push(module.clone());
+            }
+        }
+    }
+
+    owners
+        .into_iter()
+        .filter(|(_, modules)| modules.len() > 1)
+        .map(|((name, namespace), modules)| SymbolCollision {
+            name: name.to_string(),
+            namespace,
+            modules,
+        })
+        .collect()
+}
+
+/// How a generated module is re-exported from `mod.rs`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReexportStyle {
+    /// `pub use module::*;`
+    Glob,
+
+    /// `pub use module::{A, B};` (no line when the list is empty)
+    Explicit(Vec<String>),
+}
+
+/// Re-export line planned for a single module
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReexportEntry {
+    /// Name of the generated module
+    pub module: String,
+
+    /// How the module's items are re-exported
+    pub style: ReexportStyle,
+
+    /// Names of `pub(crate)` and narrower items listed by an explicit
+    /// style, re-exported on lines of their own visibility
+    pub restricted: Vec<(ExportVisibility, String)>,
+}
+
+impl ReexportEntry {
+    /// Render the `use` lines for this entry, if it re-exports anything
+    pub fn render(&self) -> Option<String> {
+        let lines = self.render_lines();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    /// The `use` lines for this entry, `pub use` first
+    pub fn render_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        match &self.style {
+            ReexportStyle::Glob => lines.push(format!("pub use {}::*;", self.module)),
+            ReexportStyle::Explicit(names) => {
+                lines.extend(self.use_line(ExportVisibility::Public.use_keyword(), names))
+            }
+        }
+        for visibility in [ExportVisibility::Crate, ExportVisibility::Parent] {
+            let names: Vec<String> = self
+                .restricted
+                .iter()
+                .filter(|(v, _)| *v == visibility)
+                .map(|(_, name)| name.clone())
+                .collect();
+            lines.extend(self.use_line(visibility.use_keyword(), &names));
+        }
+        lines
+    }
+
+    fn use_line(&self, keyword: &str, names: &[String]) -> Option<String> {
+        match names {
+            [] => None,
+            [name] => Some(format!("{} {}::{};", keyword, self.module, name)),
+            names => Some(format!(
+                "{} {}::{{{}}};",
+                keyword,
+                self.module,
+                names.join(", ")
+            )),
+        }
+    }
+}
+
+/// Result of computing the re-export set of every generated module
+#[derive(Debug, Clone, Default)]
+pub struct ReexportPlan {
+    /// One entry per module, in plan order
+    pub entries: Vec<ReexportEntry>,
+
+    /// Names exported by more than one module
+    pub ambiguities: Vec<SymbolCollision>,
+
+    /// Names withheld from the parent and only reachable as `module::Name`
+    pub qualified: Vec<(String, String)>,
+
+    /// Modules that themselves glob re-export another path (`pub use x::*;`)
+    ///
+    /// The names such globs expose are unknown at planning time and may
+    /// overlap with sibling modules.
+    pub opaque_globs: Vec<(String, String)>,
+
+    /// Rustdoc attribute (`#[doc(inline)]`, ...) placed on every re-export line
+    pub doc_attribute: Option<String>,
+}
+
+impl ReexportPlan {
+    /// Compute the re-export plan for a set of modules
+    ///
+    /// # Arguments
+    ///
+    /// * `modules` - Module names paired with their top-level items, in plan order
+    /// * `explicit` - Emit explicit name lists instead of glob re-exports
+    /// * `qualify_conflicts` - Withhold ambiguous names from the parent so they
+    ///   are only reachable through module-qualified paths
+    pub fn build(modules: &[(String, Vec<Item>)], explicit: bool, qualify_conflicts: bool) -> Self {
+        let exports: Vec<(String, Vec<ExportedSymbol>)> = modules
+            .iter()
+            .map(|(name, items)| (name.clone(), exported_symbols(items)))
+            .collect();
+        let ambiguities = find_collisions(&exports);
+
+        let mut opaque_globs = Vec::new();
+        for (name, items) in modules {
+            for glob in glob_reexports(items) {
+                opaque_globs.push((name.clone(), glob));
+            }
+        }
+
+        let conflicting: Vec<&str> = ambiguities.iter().map(|c| c.name.as_str()).collect();
+        let mut qualified = Vec::new();
+        let mut entries = Vec::new();
+
+        for (module, symbols) in &exports {
+            let mut restricted = Vec::new();
+            let has_conflict = symbols
+                .iter()
+                .any(|s| conflicting.contains(&s.name.as_str()));
+            let style = if explicit || (qualify_conflicts && has_conflict) {
+                let mut names: Vec<String> = Vec::new();
+                for symbol in symbols {
+                    if qualify_conflicts && conflicting.contains(&symbol.name.as_str()) {
+                        let entry = (module.clone(), symbol.name.clone());
+                        if !qualified.contains(&entry) {
+                            qualified.push(entry);
+                        }
+                    } else if symbol.visibility != ExportVisibility::Public {
+                        let entry = (symbol.visibility, symbol.name.clone());
+                        if !restricted.contains(&entry) {
+                            restricted.push(entry);
+                        }
+                    } else if !names.contains(&symbol.name) {
+                        names.push(symbol.name.clone());
+                    }
+                }
+                ReexportStyle::Explicit(names)
+            } else {
+                ReexportStyle::Glob
+            };
+            entries.push(ReexportEntry {
+                module: module.clone(),
+                style,
+                restricted,
+            });
+        }
+
+        Self {
+            entries,
+            ambiguities,
+            qualified,
+            opaque_globs,
+            doc_attribute: None,
+        }
+    }
+
+    /// Ambiguities that remain in the re-exported surface
+    pub fn unresolved_ambiguities(&self) -> Vec<&SymbolCollision> {
+        self.ambiguities
+            .iter()
+            .filter(|c| {
+                !c.modules
+                    .iter()
+                    .all(|m| self.qualified.contains(&(m.clone(), c.name.clone())))
+            })
+            .collect()
+    }
+}
+
+/// Paths a set of items glob re-exports with `pub use path::*;`
+pub fn glob_reexports(items: &[Item]) -> Vec<String> {
+    let mut globs = Vec::new();
+    for item in items {
+        if let Item::Use(u) = item {
+            if !matches!(u.vis, Visibility::Inherited) {
+                collect_globs(&u.tree, String::new(), &mut globs);
+            }
+        }
+    }
+    globs
+}
+
+fn collect_globs(tree: &UseTree, prefix: String, globs: &mut Vec<String>) {
+    match tree {
+        UseTree::Path(p) => {
+            let prefix = if prefix.is_empty() {
+                p.ident.to_string()
+            } else {
+                format!("{}::{}", prefix, p.ident)
+            };
+            collect_globs(&p.tree, prefix, globs);
+        }
+        UseTree::Group(g) => {
+            for item in &g.items {
+                collect_globs(item, prefix.clone(), globs);
+            }
+        }
+        UseTree::Glob(_) => globs.push(prefix),
+        UseTree::Name(_) | UseTree::Rename(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_items_not_exported() {
+        let file: syn::File = syn::parse_quote! {
+            pub struct Visible { x: u32 }
+            struct Hidden;
+            fn helper() {}
+            pub fn parse() {}
+        };
+
+        let symbols = exported_symbols(&file.items);
+        assert_eq!(
+            symbols,
+            vec![
+                ExportedSymbol::new("Visible", Namespace::Type),
+                ExportedSymbol::new("parse", Namespace::Value),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collision_between_modules() {
+        let types: syn::File = syn::parse_quote! {
+            pub struct Parser;
+            pub type parse = u32;
+        };
+        let functions: syn::File = syn::parse_quote! {
+            pub fn parse() {}
+            pub struct Parser;
+        };
+
+        let modules = vec![
+            ("types".to_string(), exported_symbols(&types.items)),
+            ("functions".to_string(), exported_symbols(&functions.items)),
+        ];
+        let collisions = find_collisions(&modules);
+
+        // `Parser` collides in both namespaces (unit struct); the `parse`
+        // alias and function live in different namespaces and do not.
+        assert_eq!(collisions.len(), 2);
+        assert!(collisions.iter().all(|c| c.name == "Parser"));
+        assert_eq!(collisions[0].modules, vec!["types", "functions"]);
+    }
+
+    #[test]
+    fn test_use_reexport_leaves() {
+        let file: syn::File = syn::parse_quote! {
+            pub use inner::{Thing, Other as Renamed, glob::*};
+        };
+        let symbols = exported_symbols(&file.items);
+        let names: Vec<_> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert!(names.contains(&"Thing"));
+        assert!(names.contains(&"Renamed"));
+        assert!(!names.contains(&"Other"));
+    }
+
+    fn plan_fixture() -> Vec<(String, Vec<Item>)> {
+        let types: syn::File = syn::parse_quote! {
+            pub struct Config { a: u32 }
+            pub struct User { b: u32 }
+        };
+        let functions: syn::File = syn::parse_quote! {
+            pub fn Config() {}
+            pub fn helper() {}
+            pub use crate::prelude::*;
+        };
+        vec![
+            ("types".to_string(), types.items),
+            ("functions".to_string(), functions.items),
+        ]
+    }
+
+    #[test]
+    fn test_reexport_plan_flags_ambiguities() {
+        let plan = ReexportPlan::build(&plan_fixture(), false, false);
+
+        // Struct with named fields and fn live in different namespaces
+        assert!(plan.ambiguities.is_empty());
+        assert_eq!(
+            plan.opaque_globs,
+            vec![("functions".to_string(), "crate::prelude".to_string())]
+        );
+        assert_eq!(
+            plan.entries[0].render().unwrap(),
+            "pub use types::*;".to_string()
+        );
+    }
+
+    #[test]
+    fn test_qualify_conflicts_downgrades_names() {
+        let a: syn::File = syn::parse_quote! {
+            pub struct Shared;
+            pub struct OnlyA;
+        };
+        let b: syn::File = syn::parse_quote! {
+            pub fn Shared() {}
+        };
+        let modules = vec![("a".to_string(), a.items), ("b".to_string(), b.items)];
+
+        let plan = ReexportPlan::build(&modules, false, true);
+        assert_eq!(plan.ambiguities.len(), 1);
+        assert!(plan.unresolved_ambiguities().is_empty());
+        assert_eq!(plan.entries[0].render().unwrap(), "pub use a::OnlyA;");
+        assert_eq!(plan.entries[1].render(), None);
+        assert!(plan
+            .qualified
+            .contains(&("b".to_string(), "Shared".to_string())));
+    }
+
+    #[test]
+    fn test_explicit_lists() {
+        let plan = ReexportPlan::build(&plan_fixture(), true, false);
+        assert_eq!(
+            plan.entries[0].render().unwrap(),
+            "pub use types::{Config, User};"
+        );
+    }
+
+    #[test]
+    fn test_explicit_lists_keep_restricted_visibility() {
+        let source = "pub struct Engine;\npub(crate) fn helper() {}\npub(super) fn tune() {}\nfn private() {}\n";
+        let items = syn::parse_file(source).unwrap().items;
+        let plan = ReexportPlan::build(&[("engine".to_string(), items)], true, false);
+        let lines = plan.entries[0].render_lines();
+        assert_eq!(
+            lines,
+            vec![
+                "pub use engine::Engine;",
+                "pub(crate) use engine::helper;",
+                "use engine::tune;",
+            ]
+        );
+
+        // The generated `mod.rs` compiles with these lines
+        let dir =
+            std::env::temp_dir().join(format!("splitrs_restricted_exports_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("engine.rs"), source).unwrap();
+        std::fs::write(
+            dir.join("mod.rs"),
+            format!(
+                "pub mod engine;\n{}\n\npub fn run() {{\n    helper();\n    tune();\n}}\n",
+                lines.join("\n")
+            ),
+        )
+        .unwrap();
+        let errors = crate::verify::check_scaffold(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+}
@@ -0,0 +1,96 @@
+//! Extern blocks
+//!
+//! `#[link(name = "ssl")] extern "C" { ... }` is moved as one item: its
+//! attributes stay on the block and its foreign items are never spread over
+//! several modules. All extern blocks of the input go to one FFI module
+//! (`naming.ffi_module`); modules using a foreign function, static or type
+//! import it from there, and private foreign items they use are widened to
+//! `pub(super)`.
+
+use std::collections::BTreeSet;
+use syn::{ForeignItem, Item, Visibility};
+
+/// Names declared by the foreign items of an extern block
+pub fn foreign_names(item: &Item) -> Vec<String> {
+    let Item::ForeignMod(block) = item else {
+        return Vec::new();
+    };
+    block
+        .items
+        .iter()
+        .filter_map(|foreign| match foreign {
+            ForeignItem::Fn(f) => Some(f.sig.ident.to_string()),
+            ForeignItem::Static(s) => Some(s.ident.to_string()),
+            ForeignItem::Type(t) => Some(t.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Make the private foreign items among `names` `pub(super)`
+///
+/// # Returns
+///
+/// The names that were widened
+pub fn widen_foreign_items(item: &mut Item, names: &BTreeSet<String>) -> Vec<String> {
+    let Item::ForeignMod(block) = item else {
+        return Vec::new();
+    };
+    let mut widened = Vec::new();
+    for foreign in &mut block.items {
+        let (vis, ident) = match foreign {
+            ForeignItem::Fn(f) => (&mut f.vis, &f.sig.ident),
+            ForeignItem::Static(s) => (&mut s.vis, &s.ident),
+            ForeignItem::Type(t) => (&mut t.vis, &t.ident),
+            _ => continue,
+        };
+        let name = ident.to_string();
+        if matches!(vis, Visibility::Inherited) && names.contains(&name) {
+            *vis = syn::parse_quote!(pub(super));
+            widened.push(name);
+        }
+    }
+    widened
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::ToTokens;
+
+    #[test]
+    fn test_widen_foreign_items() {
+        let mut item: Item = syn::parse_quote! {
+            #[link(name = "ssl")]
+            extern "C" {
+                fn SSL_library_init() -> i32;
+                pub fn OpenSSL_version(t: i32) -> *const u8;
+                static mut ERR_count: i32;
+                type SSL_CTX;
+            }
+        };
+        assert_eq!(
+            foreign_names(&item),
+            vec![
+                "SSL_library_init",
+                "OpenSSL_version",
+                "ERR_count",
+                "SSL_CTX"
+            ]
+        );
+
+        let used: BTreeSet<String> = ["SSL_library_init", "OpenSSL_version", "SSL_CTX"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        assert_eq!(
+            widen_foreign_items(&mut item, &used),
+            vec!["SSL_library_init", "SSL_CTX"]
+        );
+        let tokens = item.to_token_stream().to_string();
+        assert!(tokens.starts_with("# [link (name = \"ssl\")] extern \"C\""));
+        assert!(tokens.contains("pub (super) fn SSL_library_init"));
+        assert!(tokens.contains("static mut ERR_count"));
+        assert!(!tokens.contains("pub (super) static"));
+    }
+}
@@ -0,0 +1,435 @@
+//! Which methods read and write which fields
+//!
+//! `splitrs fields` builds a matrix of methods × fields for every struct of
+//! a file. A type whose methods each touch a disjoint set of fields splits
+//! cleanly; one where every method touches every field is the god-object
+//! the split will not fix. Writes are assignments, compound assignments,
+//! `&mut self.field`, struct literals of the type, and calls of common
+//! mutating methods (`self.items.push(..)`); everything else is a read.
+
+use clap::ValueEnum;
+use quote::ToTokens;
+use std::collections::{BTreeMap, BTreeSet};
+use syn::visit::Visit;
+use syn::{Expr, File, ImplItem, Item, Member};
+
+/// Output format of the matrix
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum MatrixFormat {
+    #[default]
+    Csv,
+    Html,
+}
+
+/// Methods whose receiver they modify, for fields of collection types
+const MUTATING_METHODS: &[&str] = &[
+    "append",
+    "clear",
+    "drain",
+    "entry",
+    "extend",
+    "get_mut",
+    "insert",
+    "iter_mut",
+    "pop",
+    "pop_back",
+    "pop_front",
+    "push",
+    "push_back",
+    "push_front",
+    "push_str",
+    "remove",
+    "replace",
+    "retain",
+    "sort",
+    "sort_by",
+    "sort_by_key",
+    "swap",
+    "take",
+    "truncate",
+];
+
+/// How a method uses a field
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Access {
+    pub read: bool,
+    pub write: bool,
+}
+
+impl Access {
+    /// Cell text: `R`, `W`, `RW` or empty
+    pub fn label(self) -> &'static str {
+        match (self.read, self.write) {
+            (true, true) => "RW",
+            (true, false) => "R",
+            (false, true) => "W",
+            (false, false) => "",
+        }
+    }
+}
+
+/// Field usage of one struct's methods
+#[derive(Debug, Clone)]
+pub struct FieldMatrix {
+    pub type_name: String,
+
+    /// Field names, in declaration order (`0`, `1`, ... for tuple structs)
+    pub fields: Vec<String>,
+
+    /// Each method with its access to every field, in the order of `fields`;
+    /// trait methods are labelled `Trait::method`
+    pub methods: Vec<(String, Vec<Access>)>,
+}
+
+/// Field accesses through `self` in one method
+struct FieldAccesses<'a> {
+    type_name: &'a str,
+    accesses: BTreeMap<String, Access>,
+}
+
+impl FieldAccesses<'_> {
+    fn mark(&mut self, field: String, write: bool) {
+        let access = self.accesses.entry(field).or_default();
+        if write {
+            access.write = true;
+        } else {
+            access.read = true;
+        }
+    }
+
+    /// The field of `self` a place expression starts from (`a` in `self.a.b[i]`)
+    fn root_field(expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Field(field) if is_self(&field.base) => Some(member_name(&field.member)),
+            Expr::Field(field) => Self::root_field(&field.base),
+            Expr::Index(index) => Self::root_field(&index.expr),
+            Expr::Paren(paren) => Self::root_field(&paren.expr),
+            _ => None,
+        }
+    }
+}
+
+fn is_self(expr: &Expr) -> bool {
+    matches!(expr, Expr::Path(path) if path.path.is_ident("self"))
+}
+
+fn member_name(member: &Member) -> String {
+    match member {
+        Member::Named(ident) => ident.to_string(),
+        Member::Unnamed(index) => index.index.to_string(),
+    }
+}
+
+impl<'ast> Visit<'ast> for FieldAccesses<'_> {
+    fn visit_expr_field(&mut self, node: &'ast syn::ExprField) {
+        if is_self(&node.base) {
+            self.mark(member_name(&node.member), false);
+        }
+        syn::visit::visit_expr_field(self, node);
+    }
+
+    fn visit_expr_assign(&mut self, node: &'ast syn::ExprAssign) {
+        match Self::root_field(&node.left) {
+            Some(field) => self.mark(field, true),
+            None => self.visit_expr(&node.left),
+        }
+        self.visit_expr(&node.right);
+    }
+
+    fn visit_expr_binary(&mut self, node: &'ast syn::ExprBinary) {
+        let compound = node.op.to_token_stream().to_string().ends_with('=')
+            && !matches!(
+                node.op,
+                syn::BinOp::Eq(_) | syn::BinOp::Ne(_) | syn::BinOp::Le(_) | syn::BinOp::Ge(_)
+            );
+        if compound {
+            if let Some(field) = Self::root_field(&node.left) {
+                self.mark(field, true);
+            }
+        }
+        syn::visit::visit_expr_binary(self, node);
+    }
+
+    fn visit_expr_reference(&mut self, node: &'ast syn::ExprReference) {
+        if node.mutability.is_some() {
+            if let Some(field) = Self::root_field(&node.expr) {
+                self.mark(field, true);
+                return;
+            }
+        }
+        syn::visit::visit_expr_reference(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        if MUTATING_METHODS.contains(&node.method.to_string().as_str()) {
+            if let Some(field) = Self::root_field(&node.receiver) {
+                self.mark(field, true);
+            }
+        }
+        syn::visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_expr_struct(&mut self, node: &'ast syn::ExprStruct) {
+        if node.path.is_ident("Self") || node.path.is_ident(self.type_name) {
+            for field in &node.fields {
+                self.mark(member_name(&field.member), true);
+            }
+        }
+        syn::visit::visit_expr_struct(self, node);
+    }
+}
+
+/// Fields of `self` a method reads or writes
+///
+/// # Arguments
+///
+/// * `method` - The method
+/// * `type_name` - The type the method belongs to, for `TypeName { .. }` literals
+pub fn self_fields(method: &syn::ImplItemFn, type_name: &str) -> BTreeSet<String> {
+    let mut visitor = FieldAccesses {
+        type_name,
+        accesses: BTreeMap::new(),
+    };
+    visitor.visit_impl_item_fn(method);
+    visitor.accesses.into_keys().collect()
+}
+
+/// The field matrix of every struct of `file` that has fields and methods
+pub fn field_matrices(file: &File) -> Vec<FieldMatrix> {
+    let mut matrices = Vec::new();
+    for item in &file.items {
+        let Item::Struct(item_struct) = item else {
+            continue;
+        };
+        let type_name = item_struct.ident.to_string();
+        let fields: Vec<String> = item_struct
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| match &field.ident {
+                Some(ident) => ident.to_string(),
+                None => index.to_string(),
+            })
+            .collect();
+
+        let mut methods = Vec::new();
+        for item in &file.items {
+            let Item::Impl(impl_block) = item else {
+                continue;
+            };
+            let self_ty = impl_block.self_ty.to_token_stream().to_string();
+            if self_ty.split(['<', ' ']).next() != Some(type_name.as_str()) {
+                continue;
+            }
+            // Generic arguments stay so `From<u64>` and `From<u32>` differ
+            let trait_name = impl_block.trait_.as_ref().and_then(|(_, path, _)| {
+                path.segments
+                    .last()
+                    .map(|segment| crate::compact_tokens(&segment.to_token_stream().to_string()))
+            });
+            for impl_item in &impl_block.items {
+                let ImplItem::Fn(method) = impl_item else {
+                    continue;
+                };
+                let mut visitor = FieldAccesses {
+                    type_name: &type_name,
+                    accesses: BTreeMap::new(),
+                };
+                visitor.visit_impl_item_fn(method);
+                let label = match &trait_name {
+                    Some(trait_name) => format!("{}::{}", trait_name, method.sig.ident),
+                    None => method.sig.ident.to_string(),
+                };
+                let row = fields
+                    .iter()
+                    .map(|field| visitor.accesses.get(field).copied().unwrap_or_default())
+                    .collect();
+                methods.push((label, row));
+            }
+        }
+
+        if !fields.is_empty() && !methods.is_empty() {
+            matrices.push(FieldMatrix {
+                type_name,
+                fields,
+                methods,
+            });
+        }
+    }
+    matrices
+}
+
+/// Render the matrices as CSV, one block with a header row per type
+pub fn render_csv(matrices: &[FieldMatrix]) -> String {
+    let mut out = String::new();
+    for (index, matrix) in matrices.iter().enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        out.push_str(&format!(
+            "{},{}\n",
+            csv_field(&matrix.type_name),
+            matrix
+                .fields
+                .iter()
+                .map(|field| csv_field(field))
+                .collect::<Vec<_>>()
+                .join(",")
+        ));
+        for (method, row) in &matrix.methods {
+            let cells: Vec<&str> = row.iter().map(|access| access.label()).collect();
+            out.push_str(&format!("{},{}\n", csv_field(method), cells.join(",")));
+        }
+    }
+    out
+}
+
+/// Render the matrices as a standalone HTML page, one table per type
+pub fn render_html(matrices: &[FieldMatrix]) -> String {
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Field usage</title>\n<style>\n\
+         table { border-collapse: collapse; margin-bottom: 2em; }\n\
+         th, td { border: 1px solid #ccc; padding: 2px 8px; text-align: center; }\n\
+         td.R { background: #d8eaff; } td.W { background: #ffd8d8; } td.RW { background: #f0d0ff; }\n\
+         </style>\n</head>\n<body>\n",
+    );
+    for matrix in matrices {
+        out.push_str(&format!(
+            "<h2>{}</h2>\n<table>\n<tr><th></th>",
+            html_escape(&matrix.type_name)
+        ));
+        for field in &matrix.fields {
+            out.push_str(&format!("<th>{}</th>", html_escape(field)));
+        }
+        out.push_str("</tr>\n");
+        for (method, row) in &matrix.methods {
+            out.push_str(&format!("<tr><th>{}</th>", html_escape(method)));
+            for access in row {
+                out.push_str(&format!(
+                    "<td class=\"{}\">{}</td>",
+                    access.label(),
+                    access.label()
+                ));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// A CSV field, quoted when it holds a comma, quote or line break
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// Text with the characters HTML gives meaning to escaped
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_and_writes() {
+        let file: File = syn::parse_quote! {
+            pub struct Queue { items: Vec<u32>, limit: usize, hits: u64 }
+            impl Queue {
+                pub fn new(limit: usize) -> Self { Self { items: Vec::new(), limit, hits: 0 } }
+                pub fn push(&mut self, item: u32) {
+                    if self.items.len() < self.limit { self.items.push(item); }
+                    self.hits += 1;
+                }
+                pub fn reset(&mut self) { self.limit = 0; }
+            }
+            impl std::fmt::Debug for Queue {
+                fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    write!(f, "{}", self.limit)
+                }
+            }
+            pub struct Empty;
+        };
+
+        let matrices = field_matrices(&file);
+        assert_eq!(matrices.len(), 1);
+        let matrix = &matrices[0];
+        assert_eq!(matrix.fields, vec!["items", "limit", "hits"]);
+        let rows: Vec<(&str, Vec<&str>)> = matrix
+            .methods
+            .iter()
+            .map(|(method, row)| (method.as_str(), row.iter().map(|a| a.label()).collect()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("new", vec!["W", "W", "W"]),
+                ("push", vec!["RW", "R", "RW"]),
+                ("reset", vec!["", "W", ""]),
+                ("Debug::fmt", vec!["", "", ""]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let matrix = FieldMatrix {
+            type_name: "Point".to_string(),
+            fields: vec!["x".to_string(), "y".to_string()],
+            methods: vec![(
+                "shift".to_string(),
+                vec![
+                    Access {
+                        read: true,
+                        write: true,
+                    },
+                    Access::default(),
+                ],
+            )],
+        };
+        assert_eq!(
+            render_csv(std::slice::from_ref(&matrix)),
+            "Point,x,y\nshift,RW,\n"
+        );
+        let html = render_html(&[matrix]);
+        assert!(html.contains("<tr><th></th><th>x</th><th>y</th></tr>"));
+        assert!(
+            html.contains("<tr><th>shift</th><td class=\"RW\">RW</td><td class=\"\"></td></tr>")
+        );
+    }
+
+    #[test]
+    fn test_trait_labels_keep_generic_arguments() {
+        let file: File = syn::parse_quote! {
+            pub struct Span { start: u32, end: u32 }
+            impl From<(u32, u32)> for Span {
+                fn from((start, end): (u32, u32)) -> Self { Self { start, end } }
+            }
+            impl From<u64> for Span {
+                fn from(packed: u64) -> Self { Self { start: packed as u32, end: 0 } }
+            }
+        };
+        let matrices = field_matrices(&file);
+        let labels: Vec<&str> = matrices[0]
+            .methods
+            .iter()
+            .map(|(m, _)| m.as_str())
+            .collect();
+        assert_eq!(labels, vec!["From<(u32, u32)>::from", "From<u64>::from"]);
+
+        let csv = render_csv(&matrices);
+        assert!(csv.contains("\n\"From<(u32, u32)>::from\",W,W\n"));
+        let html = render_html(&matrices);
+        assert!(html.contains("<tr><th>From&lt;u64&gt;::from</th>"));
+    }
+}
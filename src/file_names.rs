@@ -0,0 +1,158 @@
+//! Portable file names for generated modules
+//!
+//! Module names come from type names, which may contain non-ASCII letters
+//! (`Größe` becomes `größe_type`). Such names are valid identifiers but make
+//! awkward file names, and rustc refuses to look up a file for a non-ASCII
+//! module name without `#[path]`. Generated files therefore get a
+//! transliterated ASCII stem, and `mod.rs` points at it with `#[path]`
+//! whenever the stem differs from the module name.
+//!
+//! Windows also refuses files named after devices (`con.rs`, `aux.rs`,
+//! `com1.rs`, ...), whatever the extension. Such modules keep their name
+//! but are written to a suffixed file (`con_mod.rs`).
+
+use std::collections::BTreeSet;
+
+/// Device names Windows reserves regardless of extension
+const WINDOWS_RESERVED: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Suffix appended to stems that are reserved on Windows
+const RESERVED_SUFFIX: &str = "_mod";
+
+/// Whether `stem` names a Windows device (case-insensitive)
+pub fn is_windows_reserved(stem: &str) -> bool {
+    WINDOWS_RESERVED.contains(&stem.to_ascii_lowercase().as_str())
+}
+
+/// Whether a module name can be used as its own file stem
+fn is_portable(name: &str) -> bool {
+    portable_stem(name) == name && !is_windows_reserved(name)
+}
+
+/// ASCII spelling of common Latin letters
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'å' | 'ā' | 'ą' => "a",
+        'ä' | 'æ' => "ae",
+        'ç' | 'č' | 'ć' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => "e",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' => "i",
+        'ł' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ø' | 'ō' => "o",
+        'ö' | 'œ' => "oe",
+        'ř' => "r",
+        'ß' => "ss",
+        'š' | 'ś' => "s",
+        'ť' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ů' | 'ū' => "u",
+        'ü' => "ue",
+        'ý' | 'ÿ' => "y",
+        'ž' | 'ź' | 'ż' => "z",
+        _ => return None,
+    })
+}
+
+/// Portable file stem for a module name
+///
+/// Lowercase ASCII letters, digits and `_` are kept; common Latin letters
+/// are transliterated and anything else is spelled as its code point
+/// (`名` becomes `u540d`).
+pub fn portable_stem(name: &str) -> String {
+    let mut stem = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            stem.push(c);
+        } else if let Some(ascii) = transliterate(c) {
+            stem.push_str(ascii);
+        } else {
+            stem.push_str(&format!("u{:x}", c as u32));
+        }
+    }
+    stem
+}
+
+/// File stems for the given module names
+///
+/// # Arguments
+///
+/// * `names` - Module names, in `mod.rs` order
+/// * `taken` - Stems already in use in the output directory
+///
+/// # Returns
+///
+/// One stem per name. Names that are already portable keep their spelling;
+/// the others get a transliterated stem (suffixed if Windows reserves it),
+/// numbered (`_2`, `_3`, ...) if it collides with another module's file.
+pub fn assign_file_stems(names: &[String], taken: &BTreeSet<String>) -> Vec<String> {
+    let mut used: BTreeSet<String> = taken.clone();
+    used.extend(names.iter().filter(|name| is_portable(name)).cloned());
+
+    names
+        .iter()
+        .map(|name| {
+            if is_portable(name) {
+                return name.clone();
+            }
+            let mut stem = portable_stem(name);
+            if is_windows_reserved(&stem) {
+                stem.push_str(RESERVED_SUFFIX);
+            }
+            let stem = (1..)
+                .map(|n| match n {
+                    1 => stem.clone(),
+                    n => format!("{}_{}", stem, n),
+                })
+                .find(|candidate| !used.contains(candidate))
+                .unwrap_or(stem);
+            used.insert(stem.clone());
+            stem
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_portable_stem() {
+        assert_eq!(portable_stem("user_type"), "user_type");
+        assert_eq!(portable_stem("größe_type"), "groesse_type");
+        assert_eq!(portable_stem("café_traits"), "cafe_traits");
+        assert_eq!(portable_stem("名前_type"), "u540du524d_type");
+    }
+
+    #[test]
+    fn test_assign_file_stems_avoids_collisions() {
+        let names: Vec<String> = ["cafe_type", "café_type", "cafè_type"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let taken: BTreeSet<String> = ["cafe_type_2".to_string()].into_iter().collect();
+        assert_eq!(
+            assign_file_stems(&names, &taken),
+            vec!["cafe_type", "cafe_type_3", "cafe_type_4"]
+        );
+    }
+
+    #[test]
+    fn test_windows_reserved_names_get_a_suffix() {
+        assert!(is_windows_reserved("CON"));
+        assert!(!is_windows_reserved("con_type"));
+
+        let names: Vec<String> = ["con", "com1", "console"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            assign_file_stems(&names, &BTreeSet::new()),
+            vec!["con_mod", "com1_mod", "console"]
+        );
+    }
+}
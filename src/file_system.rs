@@ -0,0 +1,285 @@
+//! File system access for writing a split
+//!
+//! The writer, backup, merge, re-balance, check and in-place steps go
+//! through the `FileSystem` trait instead of `std::fs`, so the same code can
+//! run against the disk or against an in-memory tree (hermetic tests,
+//! previews with full file contents, editor integrations that must not touch
+//! disk).
+
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The file operations a split needs
+pub trait FileSystem: Send + Sync {
+    /// Read a file as UTF-8 text
+    fn read(&self, path: &Path) -> Result<String>;
+
+    /// Create or replace a file; its directory must exist
+    fn write(&self, path: &Path, contents: &str) -> Result<()>;
+
+    /// Whether a file or directory exists at `path`
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Create a directory and any missing parents
+    fn create_dir(&self, path: &Path) -> Result<()>;
+
+    /// Remove a file
+    fn remove(&self, path: &Path) -> Result<()>;
+
+    /// The entries directly inside a directory, sorted by path
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>>;
+
+    /// Remove a directory and everything in it
+    fn remove_dir_all(&self, path: &Path) -> Result<()>;
+
+    /// Whether `path` is a file or a directory, and its size
+    fn metadata(&self, path: &Path) -> Result<Metadata>;
+}
+
+/// What is at a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    /// Whether the path is a directory rather than a file
+    pub is_dir: bool,
+
+    /// Size of a file in bytes; 0 for a directory
+    pub len: u64,
+}
+
+/// The disk, through `std::fs`
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn read(&self, path: &Path) -> Result<String> {
+        fs::read_to_string(path).context(format!("Failed to read {:?}", path))
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> Result<()> {
+        fs::write(path, contents).context(format!("Failed to write {:?}", path))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        fs::create_dir_all(path).context(format!("Failed to create directory {:?}", path))
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        fs::remove_file(path).context(format!("Failed to remove {:?}", path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(path).context(format!("Failed to read {:?}", path))? {
+            entries.push(entry.context(format!("Failed to read {:?}", path))?.path());
+        }
+        entries.sort();
+        Ok(entries)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        fs::remove_dir_all(path).context(format!("Failed to remove {:?}", path))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        let metadata = fs::metadata(path).context(format!("Failed to read {:?}", path))?;
+        Ok(Metadata {
+            is_dir: metadata.is_dir(),
+            len: if metadata.is_dir() { 0 } else { metadata.len() },
+        })
+    }
+}
+
+/// A file tree held in memory
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    files: Mutex<BTreeMap<PathBuf, String>>,
+    dirs: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl MemoryFileSystem {
+    /// Create an empty file tree
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file (and its directories) to the tree
+    pub fn with_file(self, path: impl Into<PathBuf>, contents: &str) -> Self {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            self.add_dirs(parent);
+        }
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path, contents.to_string());
+        self
+    }
+
+    /// All files in the tree, by path
+    pub fn files(&self) -> BTreeMap<PathBuf, String> {
+        self.files.lock().unwrap().clone()
+    }
+
+    fn add_dirs(&self, path: &Path) {
+        let mut dirs = self.dirs.lock().unwrap();
+        for dir in path.ancestors().filter(|dir| !dir.as_os_str().is_empty()) {
+            dirs.insert(dir.to_path_buf());
+        }
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read(&self, path: &Path) -> Result<String> {
+        self.files
+            .lock()
+            .unwrap()
+            .get(path)
+            .cloned()
+            .context(format!("Failed to read {:?}: no such file", path))
+    }
+
+    fn write(&self, path: &Path, contents: &str) -> Result<()> {
+        let parent = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        if let Some(parent) = parent {
+            if !self.dirs.lock().unwrap().contains(parent) {
+                anyhow::bail!("Failed to write {:?}: no such directory", path);
+            }
+        }
+        self.files
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), contents.to_string());
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.lock().unwrap().contains_key(path) || self.dirs.lock().unwrap().contains(path)
+    }
+
+    fn create_dir(&self, path: &Path) -> Result<()> {
+        if self.files.lock().unwrap().contains_key(path) {
+            anyhow::bail!("Failed to create directory {:?}: a file exists", path);
+        }
+        self.add_dirs(path);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        self.files
+            .lock()
+            .unwrap()
+            .remove(path)
+            .map(|_| ())
+            .context(format!("Failed to remove {:?}: no such file", path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let files = self.files.lock().unwrap();
+        let dirs = self.dirs.lock().unwrap();
+        if !dirs.contains(path) {
+            anyhow::bail!("Failed to read {:?}: no such directory", path);
+        }
+        let entries: BTreeSet<PathBuf> = files
+            .keys()
+            .chain(dirs.iter())
+            .filter(|entry| entry.parent() == Some(path))
+            .cloned()
+            .collect();
+        Ok(entries.into_iter().collect())
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        let mut files = self.files.lock().unwrap();
+        let mut dirs = self.dirs.lock().unwrap();
+        if !dirs.contains(path) {
+            anyhow::bail!("Failed to remove {:?}: no such directory", path);
+        }
+        files.retain(|file, _| !file.starts_with(path));
+        dirs.retain(|dir| !dir.starts_with(path));
+        Ok(())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata> {
+        if let Some(contents) = self.files.lock().unwrap().get(path) {
+            return Ok(Metadata {
+                is_dir: false,
+                len: contents.len() as u64,
+            });
+        }
+        if self.dirs.lock().unwrap().contains(path) {
+            return Ok(Metadata {
+                is_dir: true,
+                len: 0,
+            });
+        }
+        anyhow::bail!("Failed to read {:?}: no such file or directory", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_file_system_round_trip() {
+        let file_system = MemoryFileSystem::new().with_file("src/big.rs", "pub struct A;\n");
+        assert!(file_system.exists(Path::new("src")));
+        assert_eq!(
+            file_system.read(Path::new("src/big.rs")).unwrap(),
+            "pub struct A;\n"
+        );
+
+        file_system.create_dir(Path::new("src/big_split")).unwrap();
+        file_system
+            .write(Path::new("src/big_split/mod.rs"), "pub mod types;\n")
+            .unwrap();
+        assert_eq!(file_system.files().len(), 2);
+
+        assert_eq!(
+            file_system.read_dir(Path::new("src")).unwrap(),
+            vec![PathBuf::from("src/big.rs"), PathBuf::from("src/big_split")]
+        );
+        assert!(
+            file_system
+                .metadata(Path::new("src/big_split"))
+                .unwrap()
+                .is_dir
+        );
+        assert_eq!(
+            file_system.metadata(Path::new("src/big.rs")).unwrap(),
+            Metadata {
+                is_dir: false,
+                len: 14
+            }
+        );
+
+        file_system.remove(Path::new("src/big.rs")).unwrap();
+        assert!(!file_system.exists(Path::new("src/big.rs")));
+        assert!(file_system.remove(Path::new("src/big.rs")).is_err());
+
+        file_system
+            .remove_dir_all(Path::new("src/big_split"))
+            .unwrap();
+        assert!(file_system.files().is_empty());
+        assert!(!file_system.exists(Path::new("src/big_split")));
+        assert!(file_system.metadata(Path::new("src/big_split")).is_err());
+    }
+
+    #[test]
+    fn test_memory_file_system_needs_directories() {
+        let file_system = MemoryFileSystem::new();
+        assert!(file_system
+            .write(Path::new("out/mod.rs"), "")
+            .unwrap_err()
+            .to_string()
+            .contains("no such directory"));
+        assert!(file_system.read(Path::new("out/mod.rs")).is_err());
+        assert!(!file_system.exists(Path::new("out")));
+    }
+}
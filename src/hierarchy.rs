@@ -0,0 +1,447 @@
+//! Clustering generated modules into subdirectories
+//!
+//! A file with dozens of loosely related types splits into dozens of
+//! modules, and one flat directory of them is barely easier to navigate
+//! than the original file. When enabled, modules are clustered by the type
+//! they belong to and by how much they reference each other, and every
+//! cluster of two or more modules moves into its own subdirectory
+//! (`models/user/`, `models/billing/`) with an intermediate `mod.rs`.
+//!
+//! With a maximum depth, directories deeper than that are flattened again:
+//! such a directory becomes a single file next to it (`user.rs` instead of
+//! `user/`) holding its modules inline, each under a section comment, so
+//! every module keeps its path.
+
+use crate::cargo_target;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// What the clustering needs to know about one generated module
+#[derive(Debug, Clone, Default)]
+pub struct ModuleSummary {
+    /// Name of the module
+    pub name: String,
+
+    /// Type the module belongs to; modules without one stay at the top level
+    pub owner: Option<String>,
+
+    /// Names of the items the module declares
+    pub declared: BTreeSet<String>,
+
+    /// Names the module's code refers to
+    pub referenced: BTreeSet<String>,
+}
+
+/// Assign modules to subdirectories
+///
+/// Modules of the same type always share a directory. Clusters of
+/// different types are then merged, strongest cross-references first,
+/// while the merged cluster stays within `max_per_directory` modules.
+/// Nothing moves when the modules already fit in one directory.
+///
+/// # Arguments
+///
+/// * `modules` - The generated modules, in output order
+/// * `max_per_directory` - Preferred maximum number of modules per directory
+///
+/// # Returns
+///
+/// The directory of each module, in the order of `modules`
+pub fn plan_directories(
+    modules: &[ModuleSummary],
+    max_per_directory: usize,
+) -> Vec<Option<String>> {
+    if modules.len() <= max_per_directory {
+        return vec![None; modules.len()];
+    }
+
+    let mut cluster: Vec<usize> = (0..modules.len()).collect();
+    let mut owners: BTreeMap<&str, usize> = BTreeMap::new();
+    for (index, module) in modules.iter().enumerate() {
+        if let Some(owner) = &module.owner {
+            let first = *owners.entry(owner.as_str()).or_insert(index);
+            cluster[index] = first;
+        }
+    }
+
+    let mut edges = Vec::new();
+    for (a, left) in modules.iter().enumerate() {
+        for (b, right) in modules.iter().enumerate().skip(a + 1) {
+            if left.owner.is_none() || right.owner.is_none() || cluster[a] == cluster[b] {
+                continue;
+            }
+            let weight = left.declared.intersection(&right.referenced).count()
+                + right.declared.intersection(&left.referenced).count();
+            if weight > 0 {
+                edges.push((weight, a, b));
+            }
+        }
+    }
+    edges.sort_by(|x, y| y.0.cmp(&x.0).then((x.1, x.2).cmp(&(y.1, y.2))));
+    for (_, a, b) in edges {
+        let (from, to) = (cluster[a].max(cluster[b]), cluster[a].min(cluster[b]));
+        if from == to {
+            continue;
+        }
+        let size = cluster.iter().filter(|&&c| c == from || c == to).count();
+        if size <= max_per_directory {
+            for c in cluster.iter_mut().filter(|c| **c == from) {
+                *c = to;
+            }
+        }
+    }
+
+    let mut taken: BTreeSet<String> = modules.iter().map(|m| m.name.clone()).collect();
+    let mut names: BTreeMap<usize, String> = BTreeMap::new();
+    let mut directories = vec![None; modules.len()];
+    for (index, module) in modules.iter().enumerate() {
+        if module.owner.is_none() || cluster.iter().filter(|&&c| c == cluster[index]).count() < 2 {
+            continue;
+        }
+        let name = names.entry(cluster[index]).or_insert_with(|| {
+            let name = directory_name(modules, &cluster, cluster[index], &taken);
+            taken.insert(name.clone());
+            name
+        });
+        directories[index] = Some(name.clone());
+    }
+    directories
+}
+
+/// Name of a cluster's directory: its most common type, lowercased
+fn directory_name(
+    modules: &[ModuleSummary],
+    cluster: &[usize],
+    id: usize,
+    taken: &BTreeSet<String>,
+) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for (module, _) in modules.iter().zip(cluster).filter(|(_, &c)| c == id) {
+        let Some(owner) = &module.owner else { continue };
+        match counts.iter_mut().find(|(name, _)| name == owner) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((owner, 1)),
+        }
+    }
+    let mut dominant = String::new();
+    let mut most = 0;
+    for (name, count) in counts {
+        if count > most {
+            dominant = name.to_lowercase();
+            most = count;
+        }
+    }
+
+    let base = if taken.contains(&dominant) {
+        format!("{}_group", dominant)
+    } else {
+        dominant
+    };
+    let mut name = base.clone();
+    let mut counter = 2;
+    while taken.contains(&name) {
+        name = format!("{}{}", base, counter);
+        counter += 1;
+    }
+    name
+}
+
+/// Adjust a module's relative paths after the move into subdirectories
+///
+/// A module inside a directory is one level deeper, so `super::` chains
+/// gain a `super` and `pub(super)` becomes `pub(in super::super)`. Paths
+/// to sibling modules (`super::user_impl::`) follow those modules into
+/// their directories.
+///
+/// # Arguments
+///
+/// * `content` - Generated source of the module
+/// * `directory` - Directory of the module, `None` at the top level
+/// * `directories` - Directory of every module that moved
+///
+/// # Returns
+///
+/// The source with its paths adjusted
+pub fn relocate_paths(
+    content: &str,
+    directory: Option<&str>,
+    directories: &BTreeMap<String, String>,
+) -> String {
+    let bytes = content.as_bytes();
+    let mut result = String::with_capacity(content.len());
+    let mut copied = 0;
+    let mut position = 0;
+    while let Some(offset) = content[position..].find("super") {
+        let start = position + offset;
+        let end = start + "super".len();
+        position = end;
+        let preceded = start > 0 && (is_ident_byte(bytes[start - 1]) || bytes[start - 1] == b':');
+        let followed = bytes.get(end).is_some_and(|&b| is_ident_byte(b));
+        if preceded || followed {
+            continue;
+        }
+
+        // `pub(super)`
+        if directory.is_some()
+            && content[..start].ends_with("pub(")
+            && content[end..].starts_with(')')
+        {
+            result.push_str(&content[copied..start]);
+            result.push_str("in super::super");
+            copied = end;
+            continue;
+        }
+
+        // Measure the `super::super::...` chain and the segment after it
+        let mut chain_end = end;
+        let mut depth = 1;
+        while content[chain_end..].starts_with("::super")
+            && !bytes
+                .get(chain_end + "::super".len())
+                .is_some_and(|&b| is_ident_byte(b))
+        {
+            chain_end += "::super".len();
+            depth += 1;
+        }
+        position = chain_end;
+        let segment: String = content[chain_end..]
+            .strip_prefix("::")
+            .map(|rest| {
+                rest.chars()
+                    .take_while(|&c| c.is_alphanumeric() || c == '_')
+                    .collect()
+            })
+            .unwrap_or_default();
+        let target = if depth == 1 {
+            directories.get(&segment)
+        } else {
+            None
+        };
+
+        let replacement = match (directory, target) {
+            (Some(own), Some(other)) if own == other => continue,
+            (Some(_), Some(other)) => format!("super::super::{}", other),
+            (Some(_), None) => format!("super::{}", &content[start..chain_end]),
+            (None, Some(other)) => format!("super::{}", other),
+            (None, None) => continue,
+        };
+        result.push_str(&content[copied..start]);
+        result.push_str(&replacement);
+        copied = chain_end;
+    }
+    result.push_str(&content[copied..]);
+    result
+}
+
+/// Flatten planned directories nested deeper than `max_depth`
+///
+/// Directories are flattened deepest first, so a directory holding
+/// flattened ones is flattened in turn when it is too deep itself.
+///
+/// # Arguments
+///
+/// * `output_dir` - Output directory of the split, depth 0
+/// * `planned` - Paths and contents about to be written; flattened files
+///   replace the files of their directory
+/// * `max_depth` - Deepest directory level below `output_dir` to keep
+///
+/// # Returns
+///
+/// Each flattened directory paired with the file that replaces it
+pub fn flatten_deep_directories(
+    output_dir: &Path,
+    planned: &mut Vec<(PathBuf, String)>,
+    max_depth: usize,
+) -> Vec<(PathBuf, PathBuf)> {
+    let depth = |dir: &Path| {
+        dir.strip_prefix(output_dir)
+            .map_or(0, |relative| relative.components().count())
+    };
+    let mut directories: Vec<PathBuf> = planned
+        .iter()
+        .filter(|(path, _)| path.file_name().is_some_and(|name| name == "mod.rs"))
+        .filter_map(|(path, _)| path.parent().map(Path::to_path_buf))
+        .filter(|dir| depth(dir) > max_depth)
+        .collect();
+    directories.sort_by_key(|dir| std::cmp::Reverse(depth(dir)));
+
+    let mut flattened = Vec::new();
+    for dir in directories {
+        let Some(position) = planned
+            .iter()
+            .position(|(path, _)| *path == dir.join("mod.rs"))
+        else {
+            continue;
+        };
+        let mod_rs = planned.remove(position).1.replace("\r\n", "\n");
+        let mut modules = Vec::new();
+        planned.retain(|(path, content)| {
+            let child = path.parent() == Some(dir.as_path())
+                && path.extension().is_some_and(|ext| ext == "rs");
+            if child {
+                let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                modules.push((name.to_string(), content.replace("\r\n", "\n")));
+            }
+            !child
+        });
+        let file = dir.with_extension("rs");
+        planned.push((file.clone(), render_sections(&mod_rs, &modules)));
+        flattened.push((dir, file));
+    }
+    flattened
+}
+
+/// A directory's `mod.rs` with its modules inlined, each under a section
+/// comment naming the file it would have been
+fn render_sections(mod_rs: &str, modules: &[(String, String)]) -> String {
+    let inlined = cargo_target::render_inline(mod_rs, modules);
+    let mut content = String::with_capacity(inlined.len());
+    for line in inlined.lines() {
+        let name = line
+            .strip_prefix("pub mod ")
+            .and_then(|rest| rest.strip_suffix(" {"));
+        if let Some(name) = name.filter(|name| modules.iter().any(|(m, _)| m == name)) {
+            if !content.is_empty() && !content.ends_with("\n\n") {
+                content.push('\n');
+            }
+            content.push_str(&format!("// ---- {}.rs ----\n", name));
+        }
+        content.push_str(line);
+        content.push('\n');
+    }
+    content
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(
+        name: &str,
+        owner: Option<&str>,
+        declared: &[&str],
+        referenced: &[&str],
+    ) -> ModuleSummary {
+        ModuleSummary {
+            name: name.to_string(),
+            owner: owner.map(str::to_string),
+            declared: declared.iter().map(|s| s.to_string()).collect(),
+            referenced: referenced.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_clusters_related_types() {
+        let modules = vec![
+            summary("user_type", Some("User"), &["User"], &["Address"]),
+            summary("user_impl", Some("User"), &[], &["User"]),
+            summary("address_type", Some("Address"), &["Address"], &[]),
+            summary("invoice_type", Some("Invoice"), &["Invoice"], &["Payment"]),
+            summary("payment_type", Some("Payment"), &["Payment"], &[]),
+            summary("logging_type", Some("Logging"), &["Logging"], &[]),
+            summary("functions", None, &["helper"], &["User"]),
+        ];
+        let directories = plan_directories(&modules, 3);
+        let user = Some("user".to_string());
+        let invoice = Some("invoice".to_string());
+        assert_eq!(directories[0], user);
+        assert_eq!(directories[1], user);
+        assert_eq!(directories[2], user);
+        assert_eq!(directories[3], invoice);
+        assert_eq!(directories[4], invoice);
+        assert_eq!(directories[5], None);
+        assert_eq!(directories[6], None);
+
+        assert!(plan_directories(&modules, 10).iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn test_directory_name_avoids_module_names() {
+        let modules = vec![
+            summary("user", Some("User"), &["User"], &[]),
+            summary("user_impl", Some("User"), &[], &["User"]),
+            summary("functions", None, &[], &[]),
+        ];
+        let directories = plan_directories(&modules, 2);
+        assert_eq!(directories[0].as_deref(), Some("user_group"));
+        assert_eq!(directories[1].as_deref(), Some("user_group"));
+    }
+
+    #[test]
+    fn test_relocate_paths() {
+        let directories: BTreeMap<String, String> = [
+            ("user_impl".to_string(), "user".to_string()),
+            ("invoice_type".to_string(), "invoice".to_string()),
+        ]
+        .into();
+
+        let code = "use super::user_impl::Helper;\nuse super::invoice_type::Invoice;\n\
+                    use super::functions::helper;\npub(super) fn f() { super::super::g() }\n";
+        assert_eq!(
+            relocate_paths(code, Some("user"), &directories),
+            "use super::user_impl::Helper;\nuse super::super::invoice::invoice_type::Invoice;\n\
+             use super::super::functions::helper;\npub(in super::super) fn f() { super::super::super::g() }\n"
+        );
+        assert_eq!(
+            relocate_paths(code, None, &directories),
+            "use super::user::user_impl::Helper;\nuse super::invoice::invoice_type::Invoice;\n\
+             use super::functions::helper;\npub(super) fn f() { super::super::g() }\n"
+        );
+    }
+
+    #[test]
+    fn test_flattens_directories_beyond_max_depth() {
+        let output_dir = PathBuf::from("src/big");
+        let planned = vec![
+            (
+                output_dir.join("user/mod.rs"),
+                "pub mod user_type;\npub mod user_impl;\n".to_string(),
+            ),
+            (
+                output_dir.join("user/user_type.rs"),
+                "pub struct User;\n".to_string(),
+            ),
+            (
+                output_dir.join("user/user_impl.rs"),
+                "use super::user_type::User;\nimpl User {}\n".to_string(),
+            ),
+            (
+                output_dir.join("functions.rs"),
+                "pub fn f() {}\n".to_string(),
+            ),
+            (
+                output_dir.join("mod.rs"),
+                "pub mod user;\npub mod functions;\n".to_string(),
+            ),
+        ];
+
+        let mut kept = planned.clone();
+        assert!(flatten_deep_directories(&output_dir, &mut kept, 1).is_empty());
+        assert_eq!(kept, planned);
+
+        let mut flat = planned;
+        let flattened = flatten_deep_directories(&output_dir, &mut flat, 0);
+        assert_eq!(
+            flattened,
+            vec![(output_dir.join("user"), output_dir.join("user.rs"))]
+        );
+        let paths: Vec<&PathBuf> = flat.iter().map(|(path, _)| path).collect();
+        assert_eq!(
+            paths,
+            vec![
+                &output_dir.join("functions.rs"),
+                &output_dir.join("mod.rs"),
+                &output_dir.join("user.rs"),
+            ]
+        );
+        assert_eq!(
+            flat[2].1,
+            "// ---- user_type.rs ----\npub mod user_type {\n    pub struct User;\n}\n\n\
+             // ---- user_impl.rs ----\npub mod user_impl {\n    use super::user_type::User;\n    impl User {}\n}\n"
+        );
+    }
+}
@@ -1,10 +1,83 @@
 //! Import statement analysis and generation for refactored modules
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use syn::{
-    visit::Visit, Expr, GenericArgument, ImplItemFn, Item, PathArguments, Stmt, Type, TypePath,
+    visit::Visit, visit_mut::VisitMut, Expr, GenericArgument, ImplItemFn, Item, PathArguments,
+    Stmt, Type, TypeImplTrait, TypeParamBound, TypePath, TypeTraitObject,
 };
 
+/// Names that are in scope in every module through the Rust 2021 prelude
+const PRELUDE_NAMES: &[&str] = &[
+    "Option",
+    "Some",
+    "None",
+    "Result",
+    "Ok",
+    "Err",
+    "String",
+    "ToString",
+    "ToOwned",
+    "Vec",
+    "Box",
+    "Clone",
+    "Copy",
+    "Send",
+    "Sync",
+    "Sized",
+    "Unpin",
+    "Drop",
+    "Fn",
+    "FnMut",
+    "FnOnce",
+    "Default",
+    "Eq",
+    "PartialEq",
+    "Ord",
+    "PartialOrd",
+    "Iterator",
+    "IntoIterator",
+    "DoubleEndedIterator",
+    "ExactSizeIterator",
+    "Extend",
+    "FromIterator",
+    "From",
+    "Into",
+    "TryFrom",
+    "TryInto",
+    "AsRef",
+    "AsMut",
+];
+
+/// Methods of std traits that must be in scope to be called, by trait path
+const STD_TRAIT_METHODS: &[(&str, &[&str])] = &[
+    (
+        "io::Read",
+        &[
+            "read",
+            "read_to_end",
+            "read_to_string",
+            "read_exact",
+            "bytes",
+        ],
+    ),
+    ("io::Write", &["write", "write_all", "flush", "write_fmt"]),
+    (
+        "io::BufRead",
+        &["read_line", "read_until", "lines", "fill_buf", "consume"],
+    ),
+    ("io::Seek", &["seek", "rewind", "stream_position"]),
+    ("fmt::Write", &["write_str", "write_char", "write_fmt"]),
+    ("str::FromStr", &["from_str"]),
+    ("hash::Hash", &["hash"]),
+    (
+        "hash::Hasher",
+        &["finish", "write_u8", "write_u32", "write_u64"],
+    ),
+];
+
+/// Collections that modules import through a combined `std::collections` use
+const STD_COLLECTIONS: &[&str] = &["HashMap", "HashSet", "BTreeMap", "BTreeSet", "VecDeque"];
+
 /// Tracks type usage and generates appropriate use statements
 pub struct ImportAnalyzer {
     /// Types referenced in methods (type name -> potential paths)
@@ -20,6 +93,16 @@ pub struct ImportAnalyzer {
 
     /// Type alias definitions (alias name -> underlying type)
     type_aliases: HashMap<String, String>,
+
+    /// Items defined in other files of the same crate (name -> full path)
+    crate_symbols: HashMap<String, String>,
+
+    /// Modules the original file glob imports, as seen from one level deeper
+    glob_imports: Vec<String>,
+
+    /// Lowercase names the original file imports (modules, functions),
+    /// name -> full path
+    module_imports: HashMap<String, String>,
 }
 
 impl ImportAnalyzer {
@@ -45,36 +128,45 @@ impl ImportAnalyzer {
             type_mappings: HashMap::new(),
             std_types,
             type_aliases: HashMap::new(),
+            crate_symbols: HashMap::new(),
+            glob_imports: Vec::new(),
+            module_imports: HashMap::new(),
         }
     }
 
     /// Analyze a file to build type mappings
+    #[allow(dead_code)]
     pub fn analyze_file(&mut self, file: &syn::File) {
         for item in &file.items {
-            match item {
-                Item::Use(use_item) => {
-                    self.extract_use_mapping(use_item);
-                }
-                Item::Struct(s) => {
-                    self.type_mappings
-                        .insert(s.ident.to_string(), format!("super::types::{}", s.ident));
-                }
-                Item::Enum(e) => {
-                    self.type_mappings
-                        .insert(e.ident.to_string(), format!("super::types::{}", e.ident));
-                }
-                Item::Type(t) => {
-                    // Type alias - store both mapping and underlying type
-                    let alias_name = t.ident.to_string();
-                    self.type_mappings
-                        .insert(alias_name.clone(), format!("super::types::{}", t.ident));
-
-                    // Extract underlying type for resolution
-                    let underlying_type = quote::quote!(#t).to_string();
-                    self.type_aliases.insert(alias_name, underlying_type);
-                }
-                _ => {}
+            self.record_item(item);
+        }
+    }
+
+    /// Record the mappings introduced by a single top-level item
+    pub fn record_item(&mut self, item: &Item) {
+        match item {
+            Item::Use(use_item) => {
+                self.extract_use_mapping(use_item);
+            }
+            Item::Struct(s) => {
+                self.type_mappings
+                    .insert(s.ident.to_string(), format!("super::types::{}", s.ident));
+            }
+            Item::Enum(e) => {
+                self.type_mappings
+                    .insert(e.ident.to_string(), format!("super::types::{}", e.ident));
             }
+            Item::Type(t) => {
+                // Type alias - store both mapping and underlying type
+                let alias_name = t.ident.to_string();
+                self.type_mappings
+                    .insert(alias_name.clone(), format!("super::types::{}", t.ident));
+
+                // Extract underlying type for resolution
+                let underlying_type = quote::quote!(#t).to_string();
+                self.type_aliases.insert(alias_name, underlying_type);
+            }
+            _ => {}
         }
     }
 
@@ -102,23 +194,60 @@ impl ImportAnalyzer {
     }
 
     fn extract_use_mapping(&mut self, use_item: &syn::ItemUse) {
-        // Extract use statement to build mappings
-        // This is simplified - full implementation would parse the use tree
-        let use_str = quote::quote!(#use_item).to_string();
-
-        // Extract simple patterns like "use foo::Bar"
-        if let Some(last_segment) = use_str.split("::").last() {
-            let type_name = last_segment.trim_end_matches(';').trim();
-            if !type_name.is_empty() && type_name.chars().next().unwrap().is_uppercase() {
-                self.type_mappings.insert(
-                    type_name.to_string(),
-                    use_str
-                        .replace("use ", "")
-                        .trim_end_matches(';')
-                        .trim()
-                        .to_string(),
-                );
+        let prefix = if use_item.leading_colon.is_some() {
+            "::"
+        } else {
+            ""
+        };
+        self.record_use_tree(&use_item.tree, prefix.to_string());
+    }
+
+    /// Map each name a `use` tree brings into scope to its full path
+    ///
+    /// Renamed imports are keyed by their alias and keep the rename in the
+    /// path (`Client` -> `crate::model::Customer as Client`), so modules that
+    /// use the alias get the same `use` statement back.
+    fn record_use_tree(&mut self, tree: &syn::UseTree, prefix: String) {
+        let join = |name: &syn::Ident| {
+            if prefix.is_empty() || prefix == "::" {
+                format!("{}{}", prefix, name)
+            } else {
+                format!("{}::{}", prefix, name)
+            }
+        };
+        let (name, path) = match tree {
+            syn::UseTree::Path(p) => return self.record_use_tree(&p.tree, join(&p.ident)),
+            syn::UseTree::Group(g) => {
+                for item in &g.items {
+                    self.record_use_tree(item, prefix.clone());
+                }
+                return;
+            }
+            syn::UseTree::Name(n) if n.ident == "self" => {
+                match prefix.rsplit("::").next().filter(|name| !name.is_empty()) {
+                    Some(name) => (name.to_string(), prefix.clone()),
+                    None => return,
+                }
+            }
+            syn::UseTree::Name(n) => (n.ident.to_string(), join(&n.ident)),
+            syn::UseTree::Rename(r) => (
+                r.rename.to_string(),
+                format!("{} as {}", join(&r.ident), r.rename),
+            ),
+            syn::UseTree::Glob(_) => {
+                let Some(path) = one_level_deeper(&prefix) else {
+                    return;
+                };
+                if !self.glob_imports.contains(&path) {
+                    self.glob_imports.push(path);
+                }
+                return;
             }
+        };
+        if name.chars().next().is_some_and(|c| c.is_uppercase()) {
+            self.type_mappings.insert(name, path);
+        } else {
+            self.module_imports.insert(name, path);
         }
     }
 
@@ -234,6 +363,186 @@ impl ImportAnalyzer {
         )
     }
 
+    /// Register items defined elsewhere in the crate
+    ///
+    /// Names the original file defines itself keep their existing mapping.
+    pub fn add_crate_symbols(&mut self, symbols: impl IntoIterator<Item = (String, String)>) {
+        for (name, path) in symbols {
+            let defined_locally = self
+                .type_mappings
+                .get(&name)
+                .is_some_and(|p| p.starts_with("super::"));
+            if !defined_locally {
+                self.crate_symbols.insert(name, path);
+            }
+        }
+    }
+
+    /// `use` statements for crate-internal names referenced by a module
+    pub fn crate_use_statements(&self, references: &ReferencedNames) -> Vec<String> {
+        references
+            .external_names()
+            .iter()
+            .filter_map(|name| self.crate_symbols.get(name))
+            .map(|path| format!("use {};", path))
+            .collect()
+    }
+
+    /// Check whether a referenced name can be mapped to an import
+    ///
+    /// A name is resolved when it is a primitive, part of the prelude, a known
+    /// std collection, has a mapping from the original file's items and
+    /// `use` statements, or is a known item of the rest of the crate.
+    pub fn is_resolved(&self, name: &str) -> bool {
+        self.is_primitive(name)
+            || PRELUDE_NAMES.contains(&name)
+            || self.std_types.contains(name)
+            || self.type_mappings.contains_key(name)
+            || self.crate_symbols.contains_key(name)
+    }
+
+    /// Return the referenced names a generated module leaves unresolved
+    ///
+    /// A mapping from the original file only helps when the module emits
+    /// the import, so names are resolved against the module's own `use`
+    /// items and definitions. Primitives, the prelude and names of the rest
+    /// of the crate (whose imports are added when the file is written) need
+    /// none. Behind a glob import any name [`Self::is_resolved`] knows may
+    /// come from the glob.
+    ///
+    /// # Arguments
+    ///
+    /// * `references` - Names the module's code references
+    /// * `module` - The generated source of the module
+    pub fn unresolved_names(
+        &self,
+        references: &ReferencedNames,
+        module: &syn::File,
+    ) -> Vec<String> {
+        let mut in_scope = HashSet::new();
+        let mut has_glob = false;
+        for item in &module.items {
+            if let Item::Use(u) = item {
+                let mut names = Vec::new();
+                crate::export_analyzer::collect_use_leaves(&u.tree, &mut names);
+                in_scope.extend(names);
+                has_glob |= has_glob_import(&u.tree);
+            } else if let Some(name) = declared_ident(item) {
+                in_scope.insert(name);
+            }
+        }
+        references
+            .external_names()
+            .into_iter()
+            .filter(|name| {
+                !(self.is_primitive(name)
+                    || PRELUDE_NAMES.contains(&name.as_str())
+                    || self.crate_symbols.contains_key(name)
+                    || in_scope.contains(name)
+                    || has_glob && self.is_resolved(name))
+            })
+            .collect()
+    }
+
+    /// `use` statements for names the original file imported from outside
+    ///
+    /// Only mappings that came from the original file's `use` statements with
+    /// absolute paths are returned; names of the split file's own items are
+    /// left to `mod.rs` re-exports, and `super::`/`self::` imports would
+    /// resolve differently one level deeper.
+    pub fn external_use_statements(&self, names: &HashSet<String>) -> Vec<String> {
+        let mut statements: Vec<String> = names
+            .iter()
+            .filter(|name| !STD_COLLECTIONS.contains(&name.as_str()))
+            .filter_map(|name| self.type_mappings.get(name))
+            .filter(|path| !path.starts_with("super") && !path.starts_with("self"))
+            .map(|path| format!("use {};", path.replace(" :: ", "::")))
+            .collect();
+        statements.sort();
+        statements.dedup();
+        statements
+    }
+
+    /// Imports of the traits whose methods a module calls
+    ///
+    /// Only traits the original file imports are considered. The traits are
+    /// imported anonymously (`use std::io::Write as _;`), so the statements
+    /// never clash with a named import of the same trait.
+    ///
+    /// # Arguments
+    ///
+    /// * `called` - Method names called by the module's code
+    pub fn trait_use_statements(&self, called: &BTreeSet<String>) -> Vec<String> {
+        let mut statements: Vec<String> = self
+            .type_mappings
+            .values()
+            .filter_map(|path| {
+                let path = path.split(" as ").next()?.replace(" :: ", "::");
+                let (_, methods) = STD_TRAIT_METHODS.iter().find(|(trait_path, _)| {
+                    path == *trait_path || path.ends_with(&format!("::{}", trait_path))
+                })?;
+                methods
+                    .iter()
+                    .any(|method| called.contains(*method))
+                    .then(|| format!("use {} as _;", path))
+            })
+            .collect();
+        statements.sort();
+        statements.dedup();
+        statements
+    }
+
+    /// Imports of the modules that multi-segment paths start from
+    ///
+    /// # Arguments
+    ///
+    /// * `roots` - First segments of the module's multi-segment paths
+    ///   (`fmt` for `fmt::Result`)
+    pub fn module_use_statements(&self, roots: &BTreeSet<String>) -> Vec<String> {
+        let mut statements: Vec<String> = roots
+            .iter()
+            .filter_map(|root| self.module_imports.get(root))
+            .filter_map(|path| one_level_deeper(path))
+            .map(|path| format!("use {};", path))
+            .collect();
+        statements.sort();
+        statements
+    }
+
+    /// Full path of an imported module name, without any rename
+    pub fn module_path(&self, name: &str) -> Option<&str> {
+        let path = self.module_imports.get(name)?;
+        Some(path.split(" as ").next().unwrap_or(path))
+    }
+
+    /// Glob imports of the original file, adjusted for the generated modules
+    ///
+    /// Names these globs provide cannot be mapped one by one, so modules with
+    /// otherwise unresolved names carry the globs instead.
+    pub fn glob_use_statements(&self) -> Vec<String> {
+        self.glob_imports
+            .iter()
+            .map(|path| format!("use {}::*;", path))
+            .collect()
+    }
+
+    /// Absolute path of a name imported by the original file or defined
+    /// elsewhere in the crate
+    ///
+    /// For renamed imports this is the path of the original item.
+    pub fn external_path(&self, name: &str) -> Option<String> {
+        self.type_mappings
+            .get(name)
+            .filter(|path| !path.starts_with("super") && !path.starts_with("self"))
+            .or_else(|| self.crate_symbols.get(name))
+            .map(|path| {
+                path.split(" as ")
+                    .next()
+                    .unwrap_or(path)
+                    .replace(" :: ", "::")
+            })
+    }
+
     /// Infer common imports for impl blocks
     #[allow(dead_code)]
     pub fn infer_common_imports(&self) -> Vec<String> {
@@ -252,14 +561,219 @@ impl ImportAnalyzer {
     }
 }
 
+/// Visitor collecting the names that code needs in scope
+///
+/// Only the root segment of each path matters for imports (`Color::Red`
+/// needs `Color`, `Arc<Foo>` needs `Arc` and `Foo`), so this records the
+/// first segment of type, expression, pattern, and trait-bound paths that
+/// start with an uppercase letter. Generic parameters and items defined by
+/// the visited code are tracked separately so they can be excluded.
+#[derive(Default)]
+pub struct ReferencedNames {
+    /// Root names referenced by the visited code
+    pub referenced: BTreeSet<String>,
+
+    /// Names declared by the visited code (items and generic parameters)
+    pub declared: BTreeSet<String>,
+}
+
+impl ReferencedNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an item and everything it references
+    pub fn add_item(&mut self, item: &Item) {
+        if let Some(ident) = declared_ident(item) {
+            self.declared.insert(ident);
+        }
+        self.visit_item(item);
+    }
+
+    /// Referenced names that are not declared by the visited code itself
+    pub fn external_names(&self) -> Vec<String> {
+        self.referenced
+            .difference(&self.declared)
+            .cloned()
+            .collect()
+    }
+
+    fn record_path(&mut self, path: &syn::Path) {
+        if path.leading_colon.is_some() {
+            return;
+        }
+        if let Some(first) = path.segments.first() {
+            let name = first.ident.to_string();
+            if name != "Self" && name.chars().next().is_some_and(|c| c.is_uppercase()) {
+                self.referenced.insert(name);
+            }
+        }
+    }
+}
+
+/// Whether a `use` tree imports a glob anywhere
+fn has_glob_import(tree: &syn::UseTree) -> bool {
+    match tree {
+        syn::UseTree::Path(p) => has_glob_import(&p.tree),
+        syn::UseTree::Group(g) => g.items.iter().any(has_glob_import),
+        syn::UseTree::Glob(_) => true,
+        syn::UseTree::Name(_) | syn::UseTree::Rename(_) => false,
+    }
+}
+
+/// Name introduced by a top-level item, if it declares one
+pub fn declared_ident(item: &Item) -> Option<String> {
+    match item {
+        Item::Struct(s) => Some(s.ident.to_string()),
+        Item::Enum(e) => Some(e.ident.to_string()),
+        Item::Union(u) => Some(u.ident.to_string()),
+        Item::Trait(t) => Some(t.ident.to_string()),
+        Item::Type(t) => Some(t.ident.to_string()),
+        Item::Const(c) => Some(c.ident.to_string()),
+        Item::Static(s) => Some(s.ident.to_string()),
+        Item::Fn(f) => Some(f.sig.ident.to_string()),
+        Item::Mod(m) => Some(m.ident.to_string()),
+        _ => None,
+    }
+}
+
+impl<'ast> Visit<'ast> for ReferencedNames {
+    fn visit_type_path(&mut self, node: &'ast TypePath) {
+        if node.qself.is_none() {
+            self.record_path(&node.path);
+        }
+        syn::visit::visit_type_path(self, node);
+    }
+
+    fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+        if node.qself.is_none() {
+            self.record_path(&node.path);
+        }
+        syn::visit::visit_expr_path(self, node);
+    }
+
+    fn visit_expr_struct(&mut self, node: &'ast syn::ExprStruct) {
+        self.record_path(&node.path);
+        syn::visit::visit_expr_struct(self, node);
+    }
+
+    fn visit_pat_struct(&mut self, node: &'ast syn::PatStruct) {
+        self.record_path(&node.path);
+        syn::visit::visit_pat_struct(self, node);
+    }
+
+    fn visit_pat_tuple_struct(&mut self, node: &'ast syn::PatTupleStruct) {
+        self.record_path(&node.path);
+        syn::visit::visit_pat_tuple_struct(self, node);
+    }
+
+    fn visit_trait_bound(&mut self, node: &'ast syn::TraitBound) {
+        self.record_path(&node.path);
+        syn::visit::visit_trait_bound(self, node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        if let Some((_, path, _)) = &node.trait_ {
+            self.record_path(path);
+        }
+        syn::visit::visit_item_impl(self, node);
+    }
+
+    fn visit_type_param(&mut self, node: &'ast syn::TypeParam) {
+        self.declared.insert(node.ident.to_string());
+        syn::visit::visit_type_param(self, node);
+    }
+
+    fn visit_const_param(&mut self, node: &'ast syn::ConstParam) {
+        self.declared.insert(node.ident.to_string());
+        syn::visit::visit_const_param(self, node);
+    }
+}
+
+/// `path` as seen from a module one level below the original file
+///
+/// `self::` paths become `super::` paths and `super::` paths gain a level;
+/// absolute paths are unchanged. Returns `None` for a bare `self`.
+fn one_level_deeper(path: &str) -> Option<String> {
+    let root = path.split("::").next().unwrap_or_default();
+    match root {
+        _ if path.is_empty() || path == "self" => None,
+        "self" => Some(path.replacen("self", "super", 1)),
+        "super" => Some(format!("super::{}", path)),
+        _ => Some(path.to_string()),
+    }
+}
+
+/// Rewrites paths through imported modules to their full form
+/// (`fmt::Result` becomes `std::fmt::Result`)
+pub struct PathQualifier<'a> {
+    imports: &'a ImportAnalyzer,
+
+    /// Number of rewritten paths
+    pub count: usize,
+}
+
+impl<'a> PathQualifier<'a> {
+    pub fn new(imports: &'a ImportAnalyzer) -> Self {
+        Self { imports, count: 0 }
+    }
+}
+
+impl VisitMut for PathQualifier<'_> {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        if path.leading_colon.is_none() && path.segments.len() > 1 {
+            let full = path.segments[0].ident.to_string();
+            let full = self
+                .imports
+                .module_path(&full)
+                .and_then(|module| syn::parse_str::<syn::Path>(module).ok());
+            if let Some(full) = full {
+                let rest: Vec<syn::PathSegment> = path.segments.iter().skip(1).cloned().collect();
+                path.leading_colon = full.leading_colon;
+                path.segments = full.segments;
+                path.segments.extend(rest);
+                self.count += 1;
+            }
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+}
+
+/// Method names called by the visited code (`x.method()` and
+/// `Type::method()`)
+#[derive(Default)]
+pub struct CalledMethods {
+    pub names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for CalledMethods {
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        self.names.insert(node.method.to_string());
+        syn::visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+        if node.path.segments.len() > 1 {
+            if let Some(last) = node.path.segments.last() {
+                self.names.insert(last.ident.to_string());
+            }
+        }
+        syn::visit::visit_expr_path(self, node);
+    }
+}
+
 /// Visitor to collect type references in methods
-#[allow(dead_code)]
-struct TypeVisitor {
-    types_used: HashSet<String>,
+///
+/// Records the last segment of every type path with its generic arguments,
+/// and the traits of `impl Trait` / `dyn Trait` types together with their
+/// generic arguments and associated type bindings (`Foo` and `Iterator` in
+/// `impl Iterator<Item = Foo>`).
+pub struct TypeVisitor {
+    pub types_used: HashSet<String>,
 }
 
 impl TypeVisitor {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             types_used: HashSet::new(),
         }
@@ -289,6 +803,42 @@ impl TypeVisitor {
                     self.extract_type_name(elem);
                 }
             }
+            Type::TraitObject(TypeTraitObject { bounds, .. })
+            | Type::ImplTrait(TypeImplTrait { bounds, .. }) => {
+                for bound in bounds {
+                    let TypeParamBound::Trait(trait_bound) = bound else {
+                        continue;
+                    };
+                    let Some(segment) = trait_bound.path.segments.last() else {
+                        continue;
+                    };
+                    self.types_used.insert(segment.ident.to_string());
+                    match &segment.arguments {
+                        PathArguments::AngleBracketed(args) => {
+                            for arg in &args.args {
+                                match arg {
+                                    GenericArgument::Type(inner_ty) => {
+                                        self.extract_type_name(inner_ty)
+                                    }
+                                    GenericArgument::AssocType(binding) => {
+                                        self.extract_type_name(&binding.ty)
+                                    }
+                                    _ => {}
+                                }
+                            }
+                        }
+                        PathArguments::Parenthesized(args) => {
+                            for input in &args.inputs {
+                                self.extract_type_name(input);
+                            }
+                            if let syn::ReturnType::Type(_, output) = &args.output {
+                                self.extract_type_name(output);
+                            }
+                        }
+                        PathArguments::None => {}
+                    }
+                }
+            }
             _ => {}
         }
     }
@@ -335,6 +885,7 @@ impl<'ast> Visit<'ast> for TypeVisitor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use quote::ToTokens;
 
     #[test]
     fn test_import_analyzer_std_types() {
@@ -360,4 +911,245 @@ mod tests {
         // Should not generate use statements for primitives and std types
         assert!(statements.is_empty() || statements.iter().all(|s| !s.contains("i32")));
     }
+
+    #[test]
+    fn test_referenced_names_uses_path_roots() {
+        let item: Item = syn::parse_quote! {
+            fn paint<T: Brush>(canvas: &mut Canvas, brush: T) -> Option<Color> {
+                match canvas.mode {
+                    Mode::Fill => Some(Color::Red),
+                    _ => None,
+                }
+            }
+        };
+
+        let mut names = ReferencedNames::new();
+        names.add_item(&item);
+        let external = names.external_names();
+
+        assert!(external.contains(&"Brush".to_string()));
+        assert!(external.contains(&"Canvas".to_string()));
+        assert!(external.contains(&"Mode".to_string()));
+        assert!(external.contains(&"Color".to_string()));
+        assert!(!external.contains(&"Red".to_string()));
+        assert!(!external.contains(&"T".to_string()));
+    }
+
+    #[test]
+    fn test_external_use_statements() {
+        let file: syn::File = syn::parse_quote! {
+            use std::sync::Arc;
+            use std::collections::HashMap;
+            use crate::model::Order;
+            pub struct Local;
+        };
+        let mut analyzer = ImportAnalyzer::new();
+        analyzer.analyze_file(&file);
+
+        let names: HashSet<String> = ["Arc", "Order", "Local", "HashMap"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            analyzer.external_use_statements(&names),
+            vec!["use crate::model::Order;", "use std::sync::Arc;"]
+        );
+    }
+
+    #[test]
+    fn test_crate_symbols_resolve_and_emit_imports() {
+        let file: syn::File = syn::parse_quote! {
+            pub struct Local;
+        };
+        let mut analyzer = ImportAnalyzer::new();
+        analyzer.analyze_file(&file);
+        analyzer.add_crate_symbols([
+            ("Order".to_string(), "crate::model::Order".to_string()),
+            ("Local".to_string(), "crate::other::Local".to_string()),
+        ]);
+
+        let method: syn::ImplItemFn = syn::parse_quote! {
+            fn place(&self, order: Order, local: Local) {}
+        };
+        let mut names = ReferencedNames::new();
+        names.visit_impl_item_fn(&method);
+
+        let module: syn::File = syn::parse_quote! {
+            use super::types::Local;
+        };
+        assert!(analyzer.unresolved_names(&names, &module).is_empty());
+        assert_eq!(
+            analyzer.crate_use_statements(&names),
+            vec!["use crate::model::Order;"]
+        );
+    }
+
+    #[test]
+    fn test_type_visitor_sees_trait_objects() {
+        let method: ImplItemFn = syn::parse_quote! {
+            fn handlers(&self, err: &dyn Error) -> impl Iterator<Item = Box<dyn Handler>> {
+                todo!()
+            }
+        };
+        let mut visitor = TypeVisitor::new();
+        visitor.visit_impl_item_fn(&method);
+
+        for name in ["Error", "Iterator", "Box", "Handler"] {
+            assert!(visitor.types_used.contains(name), "missing {}", name);
+        }
+
+        // Without the visitor's recursion, bounds are still descended into
+        let mut visitor = TypeVisitor::new();
+        let ty: Type = syn::parse_quote!(impl Into<Label> + Fn(Request) -> Response);
+        visitor.extract_type_name(&ty);
+        for name in ["Into", "Label", "Fn", "Request", "Response"] {
+            assert!(visitor.types_used.contains(name), "missing {}", name);
+        }
+    }
+
+    #[test]
+    fn test_use_aliases_are_keyed_by_alias() {
+        let file: syn::File = syn::parse_quote! {
+            use std::io::{self, Result as IoResult};
+            use crate::model::{Order, Customer as Client};
+        };
+        let mut analyzer = ImportAnalyzer::new();
+        analyzer.analyze_file(&file);
+
+        let names: HashSet<String> = ["IoResult", "Client", "Order"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            analyzer.external_use_statements(&names),
+            vec![
+                "use crate::model::Customer as Client;",
+                "use crate::model::Order;",
+                "use std::io::Result as IoResult;",
+            ]
+        );
+        assert!(!analyzer.is_resolved("Customer"));
+        assert_eq!(
+            analyzer.external_path("Client").as_deref(),
+            Some("crate::model::Customer")
+        );
+    }
+
+    #[test]
+    fn test_glob_imports_are_adjusted_for_submodules() {
+        let file: syn::File = syn::parse_quote! {
+            use crate::prelude::*;
+            use self::detail::*;
+            use super::shared::{consts::*, Config};
+            use std::io::prelude::*;
+        };
+        let mut analyzer = ImportAnalyzer::new();
+        analyzer.analyze_file(&file);
+
+        assert_eq!(
+            analyzer.glob_use_statements(),
+            vec![
+                "use crate::prelude::*;",
+                "use super::detail::*;",
+                "use super::super::shared::consts::*;",
+                "use std::io::prelude::*;",
+            ]
+        );
+        assert!(analyzer.is_resolved("Config"));
+    }
+
+    #[test]
+    fn test_trait_imports_follow_method_calls() {
+        let file: syn::File = syn::parse_quote! {
+            use std::io::{Read, Write as IoWrite};
+            use std::str::FromStr;
+            use std::fmt::Write;
+        };
+        let mut analyzer = ImportAnalyzer::new();
+        analyzer.analyze_file(&file);
+
+        let method: ImplItemFn = syn::parse_quote! {
+            fn save(&self, out: &mut File) -> io::Result<u32> {
+                out.write_all(b"x")?;
+                Ok(u32::from_str("1").unwrap())
+            }
+        };
+        let mut calls = CalledMethods::default();
+        calls.visit_impl_item_fn(&method);
+
+        assert_eq!(
+            analyzer.trait_use_statements(&calls.names),
+            vec!["use std::io::Write as _;", "use std::str::FromStr as _;"]
+        );
+    }
+
+    #[test]
+    fn test_module_paths_are_imported_or_qualified() {
+        let file: syn::File = syn::parse_quote! {
+            use std::fmt;
+            use std::io::{self, Write};
+            use self::detail as d;
+        };
+        let mut analyzer = ImportAnalyzer::new();
+        analyzer.analyze_file(&file);
+
+        let roots: BTreeSet<String> = ["fmt", "d", "other"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            analyzer.module_use_statements(&roots),
+            vec!["use std::fmt;", "use super::detail as d;"]
+        );
+
+        let mut item: Item = syn::parse_quote! {
+            fn show(f: &mut fmt::Formatter) -> fmt::Result { io::stdout(); Ok(()) }
+        };
+        let mut qualifier = PathQualifier::new(&analyzer);
+        qualifier.visit_item_mut(&mut item);
+        assert_eq!(qualifier.count, 3);
+        let expected: Item = syn::parse_quote! {
+            fn show(f: &mut std::fmt::Formatter) -> std::fmt::Result { std::io::stdout(); Ok(()) }
+        };
+        assert_eq!(
+            item.to_token_stream().to_string(),
+            expected.to_token_stream().to_string()
+        );
+    }
+
+    #[test]
+    fn test_unresolved_names_follow_the_emitted_imports() {
+        let file: syn::File = syn::parse_quote! {
+            use std::sync::Arc;
+            struct Local;
+            fn uses(a: Arc<Local>, b: Missing) -> Option<String> { None }
+        };
+
+        let mut analyzer = ImportAnalyzer::new();
+        analyzer.analyze_file(&file);
+
+        let mut names = ReferencedNames::new();
+        names.add_item(&file.items[2]);
+
+        let mut unresolved = analyzer.unresolved_names(&names, &file);
+        assert_eq!(unresolved, vec!["Missing".to_string()]);
+
+        // Known to the original file is not enough without the import
+        let module: syn::File = syn::parse_quote! {
+            fn uses(a: Arc<Local>, b: Missing) -> Option<String> { None }
+        };
+        unresolved = analyzer.unresolved_names(&names, &module);
+        unresolved.sort();
+        assert_eq!(unresolved, vec!["Arc", "Local", "Missing"]);
+
+        // Behind a glob, names the original file knows may come from it
+        let module: syn::File = syn::parse_quote! {
+            use super::*;
+            fn uses(a: Arc<Local>, b: Missing) -> Option<String> { None }
+        };
+        assert_eq!(
+            analyzer.unresolved_names(&names, &module),
+            vec!["Missing".to_string()]
+        );
+    }
 }
@@ -0,0 +1,452 @@
+//! Import suggestions for names SplitRS cannot resolve on its own
+//!
+//! When moved code references a name that the original file never imported
+//! (typically because it relied on a glob import or on the name being in
+//! scope through the parent module), this module looks for public items with
+//! that name in the rest of the crate, in the standard library, and in the
+//! well-known exports of the crate's dependencies, and suggests a `use` path
+//! in the spirit of rustc's "consider importing" hints.
+
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use syn::{Item, Visibility};
+use walkdir::WalkDir;
+
+/// Commonly used standard library items that are not in the prelude
+const STD_ITEMS: &[(&str, &str)] = &[
+    ("Arc", "std::sync::Arc"),
+    ("Mutex", "std::sync::Mutex"),
+    ("RwLock", "std::sync::RwLock"),
+    ("Condvar", "std::sync::Condvar"),
+    ("Once", "std::sync::Once"),
+    ("OnceLock", "std::sync::OnceLock"),
+    ("AtomicBool", "std::sync::atomic::AtomicBool"),
+    ("AtomicUsize", "std::sync::atomic::AtomicUsize"),
+    ("AtomicU64", "std::sync::atomic::AtomicU64"),
+    ("Rc", "std::rc::Rc"),
+    ("Weak", "std::rc::Weak"),
+    ("Cell", "std::cell::Cell"),
+    ("RefCell", "std::cell::RefCell"),
+    ("OnceCell", "std::cell::OnceCell"),
+    ("HashMap", "std::collections::HashMap"),
+    ("HashSet", "std::collections::HashSet"),
+    ("BTreeMap", "std::collections::BTreeMap"),
+    ("BTreeSet", "std::collections::BTreeSet"),
+    ("VecDeque", "std::collections::VecDeque"),
+    ("BinaryHeap", "std::collections::BinaryHeap"),
+    ("Path", "std::path::Path"),
+    ("PathBuf", "std::path::PathBuf"),
+    ("Duration", "std::time::Duration"),
+    ("Instant", "std::time::Instant"),
+    ("SystemTime", "std::time::SystemTime"),
+    ("Ordering", "std::cmp::Ordering"),
+    ("Reverse", "std::cmp::Reverse"),
+    ("PhantomData", "std::marker::PhantomData"),
+    ("Cow", "std::borrow::Cow"),
+    ("Borrow", "std::borrow::Borrow"),
+    ("Hash", "std::hash::Hash"),
+    ("Hasher", "std::hash::Hasher"),
+    ("FromStr", "std::str::FromStr"),
+    ("Display", "std::fmt::Display"),
+    ("Debug", "std::fmt::Debug"),
+    ("Formatter", "std::fmt::Formatter"),
+    ("Error", "std::error::Error"),
+    ("Read", "std::io::Read"),
+    ("Write", "std::io::Write"),
+    ("BufRead", "std::io::BufRead"),
+    ("BufReader", "std::io::BufReader"),
+    ("BufWriter", "std::io::BufWriter"),
+    ("Deref", "std::ops::Deref"),
+    ("DerefMut", "std::ops::DerefMut"),
+    ("Range", "std::ops::Range"),
+    ("Add", "std::ops::Add"),
+    ("Sub", "std::ops::Sub"),
+    ("Mul", "std::ops::Mul"),
+    ("Index", "std::ops::Index"),
+    ("IndexMut", "std::ops::IndexMut"),
+    ("Any", "std::any::Any"),
+    ("TypeId", "std::any::TypeId"),
+    ("Pin", "std::pin::Pin"),
+    ("Future", "std::future::Future"),
+    ("NonZeroUsize", "std::num::NonZeroUsize"),
+    ("Infallible", "std::convert::Infallible"),
+    ("Peekable", "std::iter::Peekable"),
+];
+
+/// Well-known public items of popular crates, keyed by crate name
+///
+/// Only consulted when the crate is declared as a dependency of the crate
+/// being split.
+const DEPENDENCY_ITEMS: &[(&str, &[(&str, &str)])] = &[
+    (
+        "serde",
+        &[
+            ("Serialize", "serde::Serialize"),
+            ("Deserialize", "serde::Deserialize"),
+            ("Serializer", "serde::Serializer"),
+            ("Deserializer", "serde::Deserializer"),
+        ],
+    ),
+    (
+        "anyhow",
+        &[("Context", "anyhow::Context"), ("Error", "anyhow::Error")],
+    ),
+    ("thiserror", &[("Error", "thiserror::Error")]),
+    (
+        "syn",
+        &[
+            ("Item", "syn::Item"),
+            ("File", "syn::File"),
+            ("Type", "syn::Type"),
+            ("Expr", "syn::Expr"),
+        ],
+    ),
+    ("quote", &[("ToTokens", "quote::ToTokens")]),
+    ("clap", &[("Parser", "clap::Parser")]),
+    (
+        "tokio",
+        &[
+            ("JoinHandle", "tokio::task::JoinHandle"),
+            ("AsyncReadExt", "tokio::io::AsyncReadExt"),
+            ("AsyncWriteExt", "tokio::io::AsyncWriteExt"),
+        ],
+    ),
+    (
+        "rand",
+        &[("Rng", "rand::Rng"), ("SeedableRng", "rand::SeedableRng")],
+    ),
+];
+
+/// Where a suggested import comes from, in order of preference
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SuggestionSource {
+    /// A public item defined elsewhere in the same crate
+    Crate,
+
+    /// A standard library item
+    Std,
+
+    /// A well-known export of a declared dependency
+    Dependency,
+}
+
+/// A candidate `use` path for an unresolved name
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ImportSuggestion {
+    /// Where the candidate was found
+    pub source: SuggestionSource,
+
+    /// Full path to import, e.g. `crate::model::Order`
+    pub path: String,
+}
+
+/// Index of importable names used to suggest `use` paths
+#[derive(Default)]
+pub struct ImportSuggester {
+    /// Candidate paths for every known name
+    candidates: BTreeMap<String, BTreeSet<ImportSuggestion>>,
+}
+
+impl ImportSuggester {
+    /// Creates a suggester that only knows about standard library items
+    pub fn new() -> Self {
+        let mut suggester = Self::default();
+        for (name, path) in STD_ITEMS {
+            suggester.add(name, path, SuggestionSource::Std);
+        }
+        suggester
+    }
+
+    /// Creates a suggester for the crate containing the input files
+    ///
+    /// Walks up from the first input file to the nearest `Cargo.toml`,
+    /// indexes the public items of every other source file under `src/`, and
+    /// registers the known exports of declared dependencies. Falls back to
+    /// std-only suggestions when no manifest is found.
+    pub fn for_inputs(inputs: &[PathBuf]) -> Self {
+        let mut suggester = Self::new();
+        if let Some(root) = inputs.first().and_then(|input| find_crate_root(input)) {
+            let _ = suggester.index_dependencies(&root.join("Cargo.toml"));
+            suggester.index_crate_sources(&root.join("src"), inputs);
+        }
+        suggester
+    }
+
+    fn add(&mut self, name: &str, path: &str, source: SuggestionSource) {
+        self.candidates
+            .entry(name.to_string())
+            .or_default()
+            .insert(ImportSuggestion {
+                source,
+                path: path.to_string(),
+            });
+    }
+
+    /// Register the known exports of every dependency declared in a manifest
+    pub fn index_dependencies(&mut self, manifest: &Path) -> Result<()> {
+        let contents = fs::read_to_string(manifest).context("Failed to read Cargo.toml")?;
+        let value: toml::Value = toml::from_str(&contents).context("Failed to parse Cargo.toml")?;
+
+        let mut declared = BTreeSet::new();
+        for table in ["dependencies", "dev-dependencies"] {
+            if let Some(deps) = value.get(table).and_then(|d| d.as_table()) {
+                declared.extend(deps.keys().cloned());
+            }
+        }
+
+        for (crate_name, items) in DEPENDENCY_ITEMS {
+            if declared.contains(*crate_name) {
+                for (name, path) in *items {
+                    self.add(name, path, SuggestionSource::Dependency);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Index the public items of every source file under `src_dir`
+    ///
+    /// The files being split are skipped because their items are already
+    /// known to the import analyzer.
+    pub fn index_crate_sources(&mut self, src_dir: &Path, skip: &[PathBuf]) {
+        let skip: Vec<PathBuf> = skip
+            .iter()
+            .map(|p| p.canonicalize().unwrap_or_else(|_| p.clone()))
+            .collect();
+
+        for entry in WalkDir::new(src_dir).into_iter().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "rs") {
+                continue;
+            }
+            if path.canonicalize().is_ok_and(|p| skip.contains(&p)) {
+                continue;
+            }
+            let Some(module_path) = module_path_for_file(src_dir, path) else {
+                continue;
+            };
+            let Ok(source) = fs::read_to_string(path) else {
+                continue;
+            };
+            if let Ok(file) = syn::parse_file(&source) {
+                self.index_items(&module_path, &file.items);
+            }
+        }
+    }
+
+    /// Index public items under the given module path, descending into inline modules
+    pub fn index_items(&mut self, module_path: &str, items: &[Item]) {
+        for item in items {
+            let (vis, ident) = match item {
+                Item::Struct(s) => (&s.vis, s.ident.to_string()),
+                Item::Enum(e) => (&e.vis, e.ident.to_string()),
+                Item::Union(u) => (&u.vis, u.ident.to_string()),
+                Item::Trait(t) => (&t.vis, t.ident.to_string()),
+                Item::Type(t) => (&t.vis, t.ident.to_string()),
+                Item::Const(c) => (&c.vis, c.ident.to_string()),
+                Item::Static(s) => (&s.vis, s.ident.to_string()),
+                Item::Mod(m) => {
+                    if let Some((_, content)) = &m.content {
+                        if !matches!(m.vis, Visibility::Inherited) {
+                            let nested = format!("{}::{}", module_path, m.ident);
+                            self.index_items(&nested, content);
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            if !matches!(vis, Visibility::Inherited) {
+                let path = format!("{}::{}", module_path, ident);
+                self.add(&ident, &path, SuggestionSource::Crate);
+            }
+        }
+    }
+
+    /// Names defined exactly once elsewhere in the crate, with their paths
+    ///
+    /// Names with several crate-local candidates are left out, since picking
+    /// one would be a guess.
+    pub fn crate_symbols(&self) -> BTreeMap<String, String> {
+        self.candidates
+            .iter()
+            .filter_map(|(name, candidates)| {
+                let mut crate_paths = candidates
+                    .iter()
+                    .filter(|c| c.source == SuggestionSource::Crate);
+                match (crate_paths.next(), crate_paths.next()) {
+                    (Some(only), None) => Some((name.clone(), only.path.clone())),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// All candidate paths for a name, most likely first
+    ///
+    /// Crate-local items rank before std, which ranks before dependencies;
+    /// within a source, shorter paths are preferred.
+    pub fn suggestions(&self, name: &str) -> Vec<ImportSuggestion> {
+        let mut found: Vec<ImportSuggestion> = self
+            .candidates
+            .get(name)
+            .map(|set| set.iter().cloned().collect())
+            .unwrap_or_default();
+        found.sort_by(|a, b| {
+            a.source
+                .cmp(&b.source)
+                .then(
+                    a.path
+                        .matches("::")
+                        .count()
+                        .cmp(&b.path.matches("::").count()),
+                )
+                .then(a.path.cmp(&b.path))
+        });
+        found
+    }
+
+    /// The most likely `use` path for a name, if any
+    pub fn best(&self, name: &str) -> Option<ImportSuggestion> {
+        self.suggestions(name).into_iter().next()
+    }
+}
+
+/// Find the directory containing the nearest `Cargo.toml` above `path`
+pub fn find_crate_root(path: &Path) -> Option<PathBuf> {
+    let start = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut dir = start.parent()?.to_path_buf();
+    loop {
+        if dir.join("Cargo.toml").is_file() {
+            return Some(dir);
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
+}
+
+/// Compute the `crate::...` module path of a source file under `src_dir`
+fn module_path_for_file(src_dir: &Path, file: &Path) -> Option<String> {
+    let relative = file.strip_prefix(src_dir).ok()?;
+    let mut segments: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+
+    let file_name = segments.pop()?;
+    let stem = file_name.strip_suffix(".rs")?;
+    let is_root = segments.is_empty() && (stem == "lib" || stem == "main");
+    if stem != "mod" && !is_root {
+        segments.push(stem.to_string());
+    }
+
+    let mut path = String::from("crate");
+    for segment in segments {
+        path.push_str("::");
+        path.push_str(&segment);
+    }
+    Some(path)
+}
+
+/// Insert `use` statements into generated module content
+///
+/// The statements are placed after the leading `//!` documentation block so
+/// the module docs stay first in the file.
+pub fn insert_use_statements(content: &str, statements: &[String]) -> String {
+    if statements.is_empty() {
+        return content.to_string();
+    }
+
+    let mut docs_end = 0;
+    let mut header_end = 0;
+    for line in content.split_inclusive('\n') {
+        if line.starts_with("//!") {
+            header_end += line.len();
+            docs_end = header_end;
+        } else if line.trim().is_empty() {
+            header_end += line.len();
+        } else {
+            break;
+        }
+    }
+
+    let mut result = String::with_capacity(content.len() + statements.len() * 32);
+    result.push_str(&content[..docs_end]);
+    if docs_end > 0 {
+        result.push('\n');
+    }
+    for statement in statements {
+        result.push_str(statement);
+        result.push('\n');
+    }
+    result.push('\n');
+    result.push_str(&content[header_end..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_std_suggestion() {
+        let suggester = ImportSuggester::new();
+        let best = suggester.best("Arc").unwrap();
+        assert_eq!(best.path, "std::sync::Arc");
+        assert_eq!(best.source, SuggestionSource::Std);
+        assert!(suggester.best("NoSuchThing").is_none());
+    }
+
+    #[test]
+    fn test_crate_items_rank_before_std() {
+        let mut suggester = ImportSuggester::new();
+        let file: syn::File = syn::parse_quote! {
+            pub struct Error;
+            struct Private;
+            pub mod nested {
+                pub struct Deep;
+            }
+        };
+        suggester.index_items("crate::errors", &file.items);
+
+        assert_eq!(
+            suggester.best("Error").unwrap().path,
+            "crate::errors::Error"
+        );
+        assert_eq!(
+            suggester.best("Deep").unwrap().path,
+            "crate::errors::nested::Deep"
+        );
+        assert!(suggester.best("Private").is_none());
+    }
+
+    #[test]
+    fn test_module_path_for_file() {
+        let src = Path::new("/p/src");
+        assert_eq!(
+            module_path_for_file(src, Path::new("/p/src/lib.rs")).unwrap(),
+            "crate"
+        );
+        assert_eq!(
+            module_path_for_file(src, Path::new("/p/src/model/order.rs")).unwrap(),
+            "crate::model::order"
+        );
+        assert_eq!(
+            module_path_for_file(src, Path::new("/p/src/model/mod.rs")).unwrap(),
+            "crate::model"
+        );
+    }
+
+    #[test]
+    fn test_insert_use_statements_after_docs() {
+        let content = "//! Docs\n//!\n\nstruct A;\n";
+        let result = insert_use_statements(content, &["use std::sync::Arc;".to_string()]);
+        assert_eq!(
+            result,
+            "//! Docs\n//!\n\nuse std::sync::Arc;\n\nstruct A;\n"
+        );
+    }
+}
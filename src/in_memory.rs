@@ -0,0 +1,149 @@
+//! Splitting a source string without touching the disk
+//!
+//! [`split_source`] runs the same split as the command line over a string
+//! and returns the files it would write, so SplitRS can run where there is
+//! no filesystem or process: in the browser through WASM, in a language
+//! server, or in tests. The string is split like a file split in place
+//! into a module directory next to it. Everything that needs the crate
+//! around the file (crate-wide type resolution, backups, verification,
+//! reports) is left to the command line.
+
+use crate::config::Config;
+use crate::pipeline::{self, SplitInputs};
+use crate::reporter::CollectingReporter;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Split one Rust source file in memory
+///
+/// # Arguments
+///
+/// * `source` - Content of the file to split
+/// * `config` - Settings of the split, as read from `.splitrs.toml`
+///
+/// # Returns
+///
+/// Each file of the module directory as its path relative to that
+/// directory (`mod.rs`, `types.rs`, `widget/mod.rs`) with its content, in
+/// the order the command line would write them
+pub fn split_source(source: &str, config: &Config) -> Result<Vec<(String, String)>> {
+    let output_dir = Path::new(".").join("input");
+    let inputs = SplitInputs::new(
+        vec![(output_dir.with_extension("rs"), source.to_string())],
+        output_dir.clone(),
+    );
+    // Progress is only of interest on the command line
+    let mut reporter = CollectingReporter::default();
+    let plan = pipeline::plan(&inputs, config, &mut reporter)?;
+    plan.check_writable(config, false)?;
+    let rendered = plan.render(&inputs, config, &BTreeMap::new(), &mut reporter)?;
+
+    Ok(rendered
+        .planned
+        .into_iter()
+        .map(|(path, content)| {
+            let relative = path
+                .strip_prefix(&output_dir)
+                .map(PathBuf::from)
+                .unwrap_or(path);
+            let path = relative
+                .components()
+                .fold(String::new(), |path, component| {
+                    let component = component.as_os_str().to_string_lossy();
+                    if path.is_empty() {
+                        component.into_owned()
+                    } else {
+                        format!("{}/{}", path, component)
+                    }
+                });
+            (path, content)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_source_returns_the_module_files() {
+        let source = r#"
+//! Shapes
+
+mod tests_support;
+
+pub struct Circle {
+    pub radius: f64,
+}
+
+impl Circle {
+    pub fn area(&self) -> f64 {
+        3.14 * self.radius * self.radius
+    }
+}
+
+pub enum Shape {
+    Circle(Circle),
+    Square(f64),
+}
+
+pub fn describe(shape: &Shape) -> String {
+    match shape {
+        Shape::Circle(c) => format!("circle {}", c.area()),
+        Shape::Square(side) => format!("square {}", side),
+    }
+}
+"#;
+        let mut config = Config::default();
+        config.splitrs.max_lines = 10;
+        let files = split_source(source, &config).unwrap();
+
+        let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths.last(), Some(&"mod.rs"));
+        assert!(paths.len() > 2);
+        let mod_rs = &files.last().unwrap().1;
+        assert!(mod_rs.contains("mod tests_support;"));
+        for (path, content) in &files[..files.len() - 1] {
+            let module = path.trim_end_matches(".rs");
+            assert!(mod_rs.contains(&format!("pub mod {};", module)));
+            syn::parse_file(content).unwrap();
+        }
+        let all: String = files.iter().map(|(_, content)| content.as_str()).collect();
+        assert!(all.contains("pub fn area"));
+        assert!(all.contains("pub fn describe"));
+
+        assert!(split_source("fn broken(", &config).is_err());
+    }
+
+    #[test]
+    fn test_split_source_rewrites_relative_paths() {
+        let source = r#"
+pub struct Engine {
+    pub power: u32,
+}
+
+impl Engine {
+    pub fn start(&self) -> u32 {
+        self::boost(self.power) + super::base()
+    }
+}
+
+pub fn boost(power: u32) -> u32 {
+    power * 2
+}
+
+pub fn banner() -> &'static str {
+    include_str!("banner.txt")
+}
+"#;
+        let mut config = Config::default();
+        config.splitrs.max_lines = 5;
+        let files = split_source(source, &config).unwrap();
+
+        let all: String = files.iter().map(|(_, content)| content.as_str()).collect();
+        assert!(all.contains("super::super::base()"), "{}", all);
+        assert!(!all.contains("self::boost"), "{}", all);
+        assert!(all.contains(r#"include_str!("../banner.txt")"#), "{}", all);
+    }
+}
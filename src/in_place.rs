@@ -0,0 +1,197 @@
+//! Replacing one file with its split
+//!
+//! `--in-place` splits a file into the module directory `--crate` would
+//! pick for it (`src/a/b.rs` becomes `src/a/b/`, a `mod.rs` is split in
+//! place, a crate root into a new module the root then declares). A split
+//! `b.rs` is removed, its backup stays, and the parent module (`mod.rs`,
+//! `lib.rs`, `main.rs` or `a.rs`) is patched so the crate builds without
+//! manual fixup: a missing `mod b;` is declared after the parent's other
+//! module declarations, and a `#[path]` naming the file is pointed at the
+//! new `mod.rs`. Paths such as `b::Thing` keep resolving through the
+//! re-exports of the generated `mod.rs`.
+
+use crate::crate_mode::{self, SplitTarget};
+use crate::existing_module::{absolute, child_module_names};
+use crate::file_system::FileSystem;
+use crate::lenient::item_spans;
+use std::path::{Path, PathBuf};
+use syn::Item;
+
+/// Where `input` is split to
+///
+/// Outside a crate's `src/`, `b.rs` still becomes the `b/` next to it.
+pub fn target(input: &Path) -> SplitTarget {
+    let absolute_input = absolute(input);
+    let crate_dir = absolute_input.ancestors().skip(1).find(|dir| {
+        dir.join("Cargo.toml").is_file() && absolute_input.starts_with(dir.join("src"))
+    });
+    let output = match crate_dir {
+        Some(crate_dir) => {
+            let mut root_names = child_module_names(&crate_dir.join("src"));
+            let output = crate_mode::output_for(crate_dir, &absolute_input, &mut root_names);
+            // Keep the output relative when the input is
+            match absolute_input
+                .parent()
+                .and_then(|parent| output.strip_prefix(parent).ok())
+            {
+                Some(relative) => input.parent().unwrap_or(Path::new("")).join(relative),
+                None => output,
+            }
+        }
+        None if input.file_name().is_some_and(|name| name == "mod.rs") => {
+            input.parent().unwrap_or(Path::new("")).to_path_buf()
+        }
+        None => input.with_extension(""),
+    };
+    SplitTarget {
+        input: input.to_path_buf(),
+        output,
+        lines: 0,
+    }
+}
+
+/// The file declaring `input` as a module, if there is one
+///
+/// For `a/b.rs` the first of `a/mod.rs`, `a/lib.rs`, `a/main.rs` and
+/// `a.rs` that declares `mod b`, or else the first that exists.
+pub fn parent_module(file_system: &dyn FileSystem, input: &Path) -> Option<PathBuf> {
+    let dir = input.parent()?;
+    let name = input.file_stem()?.to_str()?;
+    let candidates: Vec<PathBuf> = ["mod.rs", "lib.rs", "main.rs"]
+        .iter()
+        .map(|file| dir.join(file))
+        .chain([dir.with_extension("rs")])
+        .filter(|path| path != input && file_system.read(path).is_ok())
+        .collect();
+    candidates
+        .iter()
+        .find(|path| {
+            file_system
+                .read(path)
+                .is_ok_and(|source| declaration(&source, name).is_some())
+        })
+        .or(candidates.first())
+        .cloned()
+}
+
+/// The top-level items of `source` with their byte ranges
+///
+/// The first range also holds the file's inner docs and attributes.
+fn spanned_items(source: &str) -> Vec<((usize, usize), Option<Item>)> {
+    item_spans(source)
+        .into_iter()
+        .map(|(start, end)| {
+            let item = syn::parse_file(&source[start..end])
+                .ok()
+                .and_then(|mut file| file.items.pop());
+            ((start, end), item)
+        })
+        .collect()
+}
+
+/// Byte range and item of the `mod name;` declaration in `source`
+fn declaration(source: &str, name: &str) -> Option<((usize, usize), syn::ItemMod)> {
+    spanned_items(source)
+        .into_iter()
+        .find_map(|(span, item)| match item {
+            Some(Item::Mod(module)) if module.ident == name && module.content.is_none() => {
+                Some((span, module))
+            }
+            _ => None,
+        })
+}
+
+/// Patch a parent module's source to declare the split module
+///
+/// # Arguments
+///
+/// * `source` - Source of the parent module
+/// * `name` - Name of the module (`b` for `b.rs`)
+/// * `file_name` - File name of the split input (`b.rs`)
+///
+/// # Returns
+///
+/// The patched source, or `None` if the parent already declares the module
+/// in a way that finds its directory
+pub fn declare_module(source: &str, name: &str, file_name: &str) -> Option<String> {
+    if let Some(((start, end), module)) = declaration(source, name) {
+        // `#[path = "b.rs"]` would still look for the removed file
+        let path = module.attrs.iter().find_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(nv) if nv.path.is_ident("path") => match &nv.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(s),
+                    ..
+                }) => Some(s.value()),
+                _ => None,
+            },
+            _ => None,
+        })?;
+        let directory = path
+            .strip_suffix(file_name)
+            .filter(|directory| directory.is_empty() || directory.ends_with('/'))?;
+        let literal = format!("{:?}", path);
+        let offset = start + source[start..end].find(&literal)?;
+        let mut patched = source.to_string();
+        patched.replace_range(
+            offset..offset + literal.len(),
+            &format!("{:?}", format!("{}{}/mod.rs", directory, name)),
+        );
+        return Some(patched);
+    }
+
+    // After the last module declaration, else after the last import, else
+    // at the end of the file
+    let items = spanned_items(source);
+    let after = |matches: fn(&Item) -> bool| {
+        items
+            .iter()
+            .rev()
+            .find(|(_, item)| item.as_ref().is_some_and(matches))
+            .map(|((_, end), _)| *end)
+    };
+    let mut patched = source.to_string();
+    if let Some(end) = after(|item| matches!(item, Item::Mod(m) if m.content.is_none())) {
+        patched.insert_str(end, &format!("\nmod {};", name));
+    } else if let Some(end) = after(|item| matches!(item, Item::Use(_) | Item::ExternCrate(_))) {
+        patched.insert_str(end, &format!("\n\nmod {};", name));
+    } else {
+        if !patched.is_empty() && !patched.ends_with('\n') {
+            patched.push('\n');
+        }
+        if !patched.trim().is_empty() {
+            patched.push('\n');
+        }
+        patched.push_str(&format!("mod {};\n", name));
+    }
+    Some(patched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_declare_module() {
+        let lib =
+            "//! The crate\n\nmod config;\npub mod engine;\n\nuse std::fmt;\n\npub fn run() {}\n";
+        assert_eq!(
+            declare_module(lib, "big", "big.rs").unwrap(),
+            "//! The crate\n\nmod config;\npub mod engine;\nmod big;\n\nuse std::fmt;\n\npub fn run() {}\n"
+        );
+        assert_eq!(
+            declare_module("use std::fmt;\n\nfn f() {}\n", "big", "big.rs").unwrap(),
+            "use std::fmt;\n\nmod big;\n\nfn f() {}\n"
+        );
+        assert_eq!(
+            declare_module("fn f() {}", "big", "big.rs").unwrap(),
+            "fn f() {}\n\nmod big;\n"
+        );
+
+        assert!(declare_module("//! Root\n\npub mod big;\n", "big", "big.rs").is_none());
+        assert_eq!(
+            declare_module("#[path = \"gen/big.rs\"]\nmod big;\n", "big", "big.rs").unwrap(),
+            "#[path = \"gen/big/mod.rs\"]\nmod big;\n"
+        );
+        assert!(declare_module("#[path = \"not_big.rs\"]\nmod big;\n", "big", "big.rs").is_none());
+    }
+}
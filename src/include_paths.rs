@@ -0,0 +1,202 @@
+//! Relative paths in moved items
+//!
+//! `include_str!`, `include_bytes!` and `include!` resolve a relative path
+//! against the directory of the file invoking them, and so does
+//! `#[path = "..."]` on a `mod` declaration. Items moving from `src/big.rs`
+//! into `src/big/docs.rs` land one directory deeper, so
+//! `#[doc = include_str!("../docs/big.md")]` has to become
+//! `include_str!("../../docs/big.md")`. Calls nested in the arguments of
+//! other macros (`println!("{}", include_str!("usage.txt"))`) are rewritten
+//! too. Paths built from `env!("CARGO_MANIFEST_DIR")` or `env!("OUT_DIR")`
+//! do not depend on the file and stay. Other computed paths, paths inside
+//! `#[cfg_attr]` and `file!()`, whose value changes with the move, cannot
+//! be rewritten and are reported instead.
+
+use quote::ToTokens;
+use std::path::Path;
+use syn::punctuated::Punctuated;
+use syn::visit_mut::VisitMut;
+use syn::{Attribute, Expr, Lit, LitStr, Macro, Meta, Token};
+
+/// Macros taking a path relative to the invoking file
+const INCLUDE_MACROS: &[&str] = &["include", "include_bytes", "include_str"];
+
+/// Environment variables holding absolute directories
+const ABSOLUTE_DIRS: &[&str] = &["CARGO_MANIFEST_DIR", "OUT_DIR"];
+
+/// Whether `expr` is `concat!(env!("CARGO_MANIFEST_DIR"), ...)` or the like
+fn is_anchored_concat(expr: &Expr) -> bool {
+    let Expr::Macro(concat) = expr else {
+        return false;
+    };
+    if !concat.mac.path.is_ident("concat") {
+        return false;
+    }
+    let Ok(args) = concat
+        .mac
+        .parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+    else {
+        return false;
+    };
+    matches!(args.first(), Some(Expr::Macro(env))
+        if env.mac.path.is_ident("env")
+            && env
+                .mac
+                .parse_body::<LitStr>()
+                .is_ok_and(|name| ABSOLUTE_DIRS.contains(&name.value().as_str())))
+}
+
+/// Rewrites relative include paths for a module in another directory
+pub struct IncludePathRewriter<'a> {
+    /// The original directory, relative to the module's directory
+    prefix: &'a Path,
+
+    /// Number of paths rewritten
+    pub rewritten: usize,
+
+    /// Paths that could not be rewritten, as written in the source
+    pub unresolved: Vec<String>,
+}
+
+impl<'a> IncludePathRewriter<'a> {
+    /// Rewriter for a module whose directory reaches the original file's
+    /// directory through `prefix` (`..` for a module one level deeper)
+    pub fn new(prefix: &'a Path) -> Self {
+        Self {
+            prefix,
+            rewritten: 0,
+            unresolved: Vec::new(),
+        }
+    }
+
+    /// `path` as seen from the module's directory; absolute paths stay
+    fn rebase(&mut self, literal: &LitStr) -> Option<LitStr> {
+        let path = literal.value();
+        if Path::new(&path).is_absolute() || self.prefix.as_os_str().is_empty() {
+            return None;
+        }
+        let rebased = self.prefix.join(&path).to_string_lossy().replace('\\', "/");
+        self.rewritten += 1;
+        Some(LitStr::new(&rebased, literal.span()))
+    }
+}
+
+impl VisitMut for IncludePathRewriter<'_> {
+    fn visit_macro_mut(&mut self, node: &mut Macro) {
+        let name = node
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default();
+        if name == "file" {
+            self.unresolved.push("file!()".to_string());
+            return;
+        }
+        if !INCLUDE_MACROS.contains(&name.as_str()) {
+            // Include calls in the arguments of `println!`, `vec!`, ...
+            let Ok(mut args) =
+                node.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+            else {
+                return;
+            };
+            let rewritten = self.rewritten;
+            for arg in &mut args {
+                self.visit_expr_mut(arg);
+            }
+            if self.rewritten > rewritten {
+                node.tokens = args.to_token_stream();
+            }
+            return;
+        }
+        match node.parse_body::<LitStr>() {
+            Ok(literal) => {
+                if let Some(rebased) = self.rebase(&literal) {
+                    node.tokens = quote::quote!(#rebased);
+                }
+            }
+            Err(_)
+                if node
+                    .parse_body::<Expr>()
+                    .is_ok_and(|e| is_anchored_concat(&e)) => {}
+            Err(_) => self.unresolved.push(format!("{}!({})", name, node.tokens)),
+        }
+    }
+
+    fn visit_attribute_mut(&mut self, node: &mut Attribute) {
+        match &mut node.meta {
+            Meta::NameValue(name_value) if name_value.path.is_ident("path") => {
+                if let Expr::Lit(syn::ExprLit {
+                    lit: Lit::Str(literal),
+                    ..
+                }) = &mut name_value.value
+                {
+                    if let Some(rebased) = self.rebase(literal) {
+                        *literal = rebased;
+                    }
+                }
+            }
+            Meta::List(list) if list.path.is_ident("cfg_attr") => {
+                let tokens = list.tokens.to_string();
+                if INCLUDE_MACROS
+                    .iter()
+                    .any(|name| tokens.contains(&format!("{} !", name)))
+                    || tokens.contains("path =")
+                {
+                    self.unresolved.push(format!("#[cfg_attr({})]", tokens));
+                }
+            }
+            _ => syn::visit_mut::visit_attribute_mut(self, node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrites_relative_paths() {
+        let mut item: syn::Item = syn::parse_quote! {
+            #[doc = include_str!("../docs/big.md")]
+            pub fn banner() -> &'static [u8] {
+                let _ = include_str!(concat!(env!("OUT_DIR"), "/gen.txt"));
+                let _ = include_str!("/etc/motd");
+                println!("{}", include_str!("usage.txt"));
+                include_bytes!("banner.bin")
+            }
+        };
+        let mut rewriter = IncludePathRewriter::new(Path::new(".."));
+        rewriter.visit_item_mut(&mut item);
+
+        let tokens = item.to_token_stream().to_string();
+        assert!(tokens.contains("include_str ! (\"../../docs/big.md\")"));
+        assert!(tokens.contains("include_bytes ! (\"../banner.bin\")"));
+        assert!(tokens.contains("\"/etc/motd\""));
+        assert!(tokens.contains("include_str ! (\"../usage.txt\")"));
+        assert_eq!(rewriter.rewritten, 3);
+        assert!(rewriter.unresolved.is_empty());
+    }
+
+    #[test]
+    fn test_reports_what_cannot_be_rewritten() {
+        let mut item: syn::Item = syn::parse_quote! {
+            #[cfg_attr(docsrs, doc = include_str!("../README.md"))]
+            pub mod extra {
+                #[path = "extra/impls.rs"]
+                mod impls;
+                const NOTES: &str = include_str!(notes_path!());
+                const DATA: &str = include_str!(concat!("data/", "x.txt"));
+                fn here() { log!("{}", file!()); }
+            }
+        };
+        let mut rewriter = IncludePathRewriter::new(Path::new(".."));
+        rewriter.visit_item_mut(&mut item);
+
+        let tokens = item.to_token_stream().to_string();
+        assert!(tokens.contains("# [path = \"../extra/impls.rs\"]"));
+        assert_eq!(rewriter.rewritten, 1);
+        assert_eq!(rewriter.unresolved.len(), 4);
+        assert_eq!(rewriter.unresolved[3], "file!()");
+    }
+}
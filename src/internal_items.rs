@@ -0,0 +1,201 @@
+//! Keeping internal items out of the generated public surface
+//!
+//! `#[doc(hidden)]` items and private helpers that several generated modules
+//! call end up either glob re-exported from `mod.rs` or unreachable from
+//! their callers. With internal isolation enabled they move to a dedicated
+//! module that `mod.rs` declares `#[doc(hidden)]` and never re-exports;
+//! callers import them explicitly.
+
+use crate::import_analyzer::declared_ident;
+use crate::rebalance::widen_private;
+use std::collections::BTreeSet;
+use syn::visit::Visit;
+use syn::{Attribute, Item, Visibility};
+
+/// A private helper becomes internal once this many modules call it
+pub const MIN_INTERNAL_USERS: usize = 2;
+
+/// Whether the attributes contain `#[doc(hidden)]`
+pub fn is_doc_hidden(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("doc")
+            && attr
+                .parse_args::<syn::Ident>()
+                .is_ok_and(|ident| ident == "hidden")
+    })
+}
+
+/// Attributes of a top-level item
+pub fn item_attrs(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Const(i) => &i.attrs,
+        Item::Enum(i) => &i.attrs,
+        Item::Fn(i) => &i.attrs,
+        Item::Macro(i) => &i.attrs,
+        Item::Static(i) => &i.attrs,
+        Item::Struct(i) => &i.attrs,
+        Item::Trait(i) => &i.attrs,
+        Item::Type(i) => &i.attrs,
+        Item::Union(i) => &i.attrs,
+        _ => &[],
+    }
+}
+
+/// Single-segment value paths used by the visited code (function calls,
+/// constants, statics)
+#[derive(Default)]
+pub struct CalledNames {
+    pub names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for CalledNames {
+    fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+        if node.qself.is_none() && node.path.segments.len() == 1 {
+            self.names.insert(node.path.segments[0].ident.to_string());
+        }
+        syn::visit::visit_expr_path(self, node);
+    }
+
+    // Macro arguments (`write!(f, "{}", fmt_money(cents))`), when they
+    // parse as comma-separated expressions
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if let Ok(args) = node.parse_body_with(
+            syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+        ) {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+    }
+}
+
+/// Whether an item is a private function, constant or static
+pub fn is_private_helper(item: &Item) -> bool {
+    match item {
+        Item::Fn(f) => matches!(f.vis, Visibility::Inherited),
+        Item::Const(c) => matches!(c.vis, Visibility::Inherited),
+        Item::Static(s) => matches!(s.vis, Visibility::Inherited),
+        _ => false,
+    }
+}
+
+/// Names called by a single item
+pub fn called_names(item: &Item) -> BTreeSet<String> {
+    let mut called = CalledNames::default();
+    called.visit_item(item);
+    called.names
+}
+
+/// Split standalone items into public ones and internal ones
+///
+/// # Arguments
+///
+/// * `items` - Standalone items that would go to `functions.rs`
+/// * `module_usage` - Names called by each other generated module
+///
+/// # Returns
+///
+/// The items that stay public and the internal items, with private
+/// internal items widened to `pub(super)` so sibling modules can use them.
+pub fn partition_internal(
+    items: Vec<Item>,
+    module_usage: &[BTreeSet<String>],
+) -> (Vec<Item>, Vec<Item>) {
+    let standalone_usage: Vec<(Option<String>, BTreeSet<String>)> = items
+        .iter()
+        .map(|item| (declared_ident(item), called_names(item)))
+        .collect();
+
+    let mut public = Vec::new();
+    let mut internal = Vec::new();
+    for mut item in items {
+        let hidden = is_doc_hidden(item_attrs(&item));
+        let shared_helper = match &item {
+            Item::Fn(f) if matches!(f.vis, Visibility::Inherited) => {
+                let name = f.sig.ident.to_string();
+                let module_users = module_usage.iter().filter(|u| u.contains(&name)).count();
+                let standalone_user = standalone_usage
+                    .iter()
+                    .any(|(owner, used)| owner.as_ref() != Some(&name) && used.contains(&name));
+                module_users + usize::from(standalone_user) >= MIN_INTERNAL_USERS
+            }
+            _ => false,
+        };
+
+        if hidden || shared_helper {
+            widen_private(&mut item);
+            internal.push(item);
+        } else {
+            public.push(item);
+        }
+    }
+    (public, internal)
+}
+
+/// The `use` statement a module needs for the internal items it calls
+pub fn internal_use_statement(
+    internal_module: &str,
+    internal_names: &BTreeSet<String>,
+    called: &BTreeSet<String>,
+) -> Option<String> {
+    let used: Vec<&str> = internal_names
+        .intersection(called)
+        .map(String::as_str)
+        .collect();
+    match used.len() {
+        0 => None,
+        1 => Some(format!("use super::{}::{};", internal_module, used[0])),
+        _ => Some(format!(
+            "use super::{}::{{{}}};",
+            internal_module,
+            used.join(", ")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_internal() {
+        let file: syn::File = syn::parse_quote! {
+            #[doc(hidden)]
+            pub fn __private_macro_support() {}
+
+            fn shared_helper() -> u32 { 1 }
+
+            fn lonely_helper() -> u32 { 2 }
+
+            pub fn api() -> u32 { shared_helper() + lonely_helper() }
+        };
+        let usage = vec![["shared_helper".to_string()].into_iter().collect()];
+
+        let (public, internal) = partition_internal(file.items, &usage);
+        let internal_names: Vec<String> = internal.iter().filter_map(declared_ident).collect();
+        assert_eq!(
+            internal_names,
+            vec!["__private_macro_support", "shared_helper"]
+        );
+        assert_eq!(public.len(), 2);
+
+        let Item::Fn(helper) = &internal[1] else {
+            panic!("expected fn");
+        };
+        assert!(matches!(helper.vis, Visibility::Restricted(_)));
+    }
+
+    #[test]
+    fn test_internal_use_statement() {
+        let internal: BTreeSet<String> = ["a".to_string(), "b".to_string()].into_iter().collect();
+        let called: BTreeSet<String> = ["b".to_string(), "c".to_string()].into_iter().collect();
+        assert_eq!(
+            internal_use_statement("internal", &internal, &called),
+            Some("use super::internal::b;".to_string())
+        );
+        assert_eq!(
+            internal_use_statement("internal", &internal, &BTreeSet::new()),
+            None
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Ordering the items of a generated module
+//!
+//! A module's items are ordered as units: a type together with its impls,
+//! or a single standalone item. Source order keeps the input's order so
+//! the output reads like the original; public-first and alphabetical
+//! orders suit crates with their own conventions. Items without a name
+//! (impls of other types, macro invocations) are placed by their label or
+//! tokens, so they keep their input position as well.
+
+use crate::config::ItemOrder;
+use crate::import_analyzer::declared_ident;
+use quote::ToTokens;
+use std::collections::HashMap;
+use syn::{ImplItem, Item, Visibility};
+
+/// Whether a unit's leading item is private
+fn is_private(item: &Item) -> bool {
+    let vis = match item {
+        Item::Const(i) => &i.vis,
+        Item::Enum(i) => &i.vis,
+        Item::Fn(i) => &i.vis,
+        Item::Mod(i) => &i.vis,
+        Item::Static(i) => &i.vis,
+        Item::Struct(i) => &i.vis,
+        Item::Trait(i) => &i.vis,
+        Item::Type(i) => &i.vis,
+        Item::Union(i) => &i.vis,
+        Item::Use(i) => &i.vis,
+        _ => return false,
+    };
+    matches!(vis, Visibility::Inherited)
+}
+
+/// Key the input position of an item is recorded under
+///
+/// Named items go by name and impls by their first label (`Display for
+/// Meters`, `Meters::new`); macro invocations, `use` items and extern
+/// blocks by their tokens.
+pub fn position_key(item: &Item) -> Option<String> {
+    if let Some(name) = declared_ident(item) {
+        return Some(name);
+    }
+    if let Some(label) = crate::migration::item_labels(item).into_iter().next() {
+        return Some(label);
+    }
+    match item {
+        Item::Macro(_) | Item::Use(_) | Item::ForeignMod(_) => {
+            Some(item.to_token_stream().to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Order units of items and flatten them
+///
+/// # Arguments
+///
+/// * `units` - Items that stay together (a type and its impls), each led by
+///   the item that decides its place
+/// * `order` - The configured order
+/// * `positions` - Position of each top-level name in the input
+///
+/// # Returns
+///
+/// The items in order. Units without a position keep their place
+/// relative to each other, after the others.
+pub fn order_units(
+    mut units: Vec<Vec<Item>>,
+    order: ItemOrder,
+    positions: &HashMap<String, usize>,
+) -> Vec<Item> {
+    let position = |unit: &Vec<Item>| -> usize {
+        unit.first()
+            .and_then(position_key)
+            .and_then(|name| positions.get(&name).copied())
+            .unwrap_or(usize::MAX)
+    };
+    match order {
+        ItemOrder::Source => units.sort_by_key(position),
+        ItemOrder::PublicFirst => {
+            units.sort_by_key(|unit| (unit.first().is_some_and(is_private), position(unit)))
+        }
+        ItemOrder::Alphabetical => units.sort_by_key(|unit| {
+            unit.first()
+                .and_then(declared_ident)
+                .map(|name| (name.to_lowercase(), name))
+        }),
+    }
+    units.into_iter().flatten().collect()
+}
+
+/// Sort the items of a reconstructed impl block
+///
+/// Source order leaves them as they are; the other orders sort stably by
+/// visibility or by name, so every split module of a type follows the same
+/// convention.
+pub fn order_impl_items(items: &mut [ImplItem], order: ItemOrder) {
+    let name = |item: &ImplItem| match item {
+        ImplItem::Fn(f) => f.sig.ident.to_string(),
+        ImplItem::Const(c) => c.ident.to_string(),
+        ImplItem::Type(t) => t.ident.to_string(),
+        _ => String::new(),
+    };
+    match order {
+        ItemOrder::Source => {}
+        ItemOrder::PublicFirst => items.sort_by_key(|item| match item {
+            ImplItem::Fn(f) => matches!(f.vis, Visibility::Inherited),
+            ImplItem::Const(c) => matches!(c.vis, Visibility::Inherited),
+            ImplItem::Type(t) => matches!(t.vis, Visibility::Inherited),
+            _ => false,
+        }),
+        ItemOrder::Alphabetical => items.sort_by_key(|item| {
+            let name = name(item);
+            (name.to_lowercase(), name)
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(order: ItemOrder) -> Vec<String> {
+        let file: syn::File = syn::parse_quote! {
+            fn zeta() {}
+            pub struct Beta;
+            impl Beta { pub fn new() -> Self { Beta } }
+            pub fn alpha() {}
+        };
+        let positions: HashMap<String, usize> = file
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| Some((declared_ident(item)?, i)))
+            .collect();
+        let mut items = file.items.into_iter();
+        let zeta = items.next().unwrap();
+        let beta = vec![items.next().unwrap(), items.next().unwrap()];
+        let alpha = items.next().unwrap();
+
+        // Types come first in a module, as `generate_body` collects them
+        let units = vec![beta, vec![zeta], vec![alpha]];
+        order_units(units, order, &positions)
+            .iter()
+            .map(|item| declared_ident(item).unwrap_or_else(|| "impl".to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_item_orders() {
+        assert_eq!(names(ItemOrder::Source), ["zeta", "Beta", "impl", "alpha"]);
+        assert_eq!(
+            names(ItemOrder::PublicFirst),
+            ["Beta", "impl", "alpha", "zeta"]
+        );
+        assert_eq!(
+            names(ItemOrder::Alphabetical),
+            ["alpha", "Beta", "impl", "zeta"]
+        );
+    }
+
+    #[test]
+    fn test_impl_item_orders() {
+        let impl_block: syn::ItemImpl = syn::parse_quote! {
+            impl Parser {
+                fn step(&self) {}
+                pub fn parse(&self) {}
+                fn advance(&self) {}
+                pub fn Reset(&self) {}
+            }
+        };
+        let order = |order: ItemOrder| -> Vec<String> {
+            let mut items = impl_block.items.clone();
+            order_impl_items(&mut items, order);
+            items
+                .iter()
+                .map(|item| match item {
+                    ImplItem::Fn(f) => f.sig.ident.to_string(),
+                    _ => String::new(),
+                })
+                .collect()
+        };
+        assert_eq!(
+            order(ItemOrder::Source),
+            ["step", "parse", "advance", "Reset"]
+        );
+        assert_eq!(
+            order(ItemOrder::PublicFirst),
+            ["parse", "Reset", "step", "advance"]
+        );
+        assert_eq!(
+            order(ItemOrder::Alphabetical),
+            ["advance", "parse", "Reset", "step"]
+        );
+    }
+
+    #[test]
+    fn test_unnamed_items_keep_their_place() {
+        let file: syn::File = syn::parse_quote! {
+            thread_local! { static DEPTH: u32 = 0; }
+            pub fn first() {}
+            impl fmt::Display for Foreign {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { Ok(()) }
+            }
+            pub fn second() {}
+        };
+        let positions: HashMap<String, usize> = file
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| Some((position_key(item)?, i)))
+            .collect();
+        let mut units: Vec<Vec<Item>> = file.items.iter().map(|item| vec![item.clone()]).collect();
+        units.reverse();
+
+        let ordered = order_units(units, ItemOrder::Source, &positions);
+        let tokens = |items: &[Item]| -> Vec<String> {
+            items
+                .iter()
+                .map(|item| item.to_token_stream().to_string())
+                .collect()
+        };
+        assert_eq!(tokens(&ordered), tokens(&file.items));
+    }
+}
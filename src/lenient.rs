@@ -0,0 +1,417 @@
+//! Best-effort parsing for files that `syn` cannot parse as a whole
+//!
+//! A single syntax error (unfinished code, syntax behind a disabled `cfg`
+//! that `syn` does not understand, ...) makes `syn::parse_file` reject the
+//! entire file. In lenient mode the source is cut into top-level item spans
+//! with a small scanner that only tracks brackets, strings and comments.
+//! Each span is parsed on its own; the spans that still fail are kept
+//! verbatim so they can be written to a designated module untouched.
+
+use syn::File;
+
+/// A top-level span that could not be parsed
+#[derive(Debug, Clone)]
+pub struct SkippedSpan {
+    /// First line of the span (1-based)
+    pub start_line: usize,
+
+    /// Last line of the span (1-based)
+    pub end_line: usize,
+
+    /// The span's source text, verbatim
+    pub text: String,
+
+    /// Parser error for the span
+    pub error: String,
+}
+
+/// Result of a lenient parse
+pub struct LenientParse {
+    /// Everything that parsed
+    pub file: File,
+
+    /// Spans that were skipped, in source order
+    pub skipped: Vec<SkippedSpan>,
+}
+
+/// Parse `source`, skipping top-level items that fail to parse
+///
+/// Files that parse as a whole are returned unchanged with no skipped spans.
+pub fn parse_lenient(source: &str) -> LenientParse {
+    if let Ok(file) = syn::parse_file(source) {
+        return LenientParse {
+            file,
+            skipped: Vec::new(),
+        };
+    }
+
+    let mut file = File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: Vec::new(),
+    };
+    let mut skipped = Vec::new();
+
+    for (index, (start, end)) in item_spans(source).into_iter().enumerate() {
+        let text = &source[start..end];
+        if text.trim().is_empty() {
+            continue;
+        }
+        match syn::parse_file(text) {
+            Ok(parsed) => {
+                if index == 0 {
+                    file.shebang = parsed.shebang;
+                }
+                file.attrs.extend(parsed.attrs);
+                file.items.extend(parsed.items);
+            }
+            Err(error) => {
+                let leading = text.len() - text.trim_start().len();
+                let trailing = text.len() - text.trim_end().len();
+                skipped.push(SkippedSpan {
+                    start_line: line_of(source, start + leading),
+                    end_line: line_of(source, end - trailing - 1),
+                    text: text.trim().to_string(),
+                    error: error.to_string(),
+                });
+            }
+        }
+    }
+
+    LenientParse { file, skipped }
+}
+
+/// Render skipped spans as the content of the pass-through module
+pub fn render_skipped_module(skipped: &[(String, SkippedSpan)]) -> String {
+    let mut content = String::from(
+        "//! Items SplitRS could not parse, copied verbatim\n//!\n\
+         //! 🤖 Generated with [SplitRS](https://github.com/cool-japan/splitrs)\n\n\
+         #[allow(unused_imports)]\nuse super::*;\n",
+    );
+    for (input, span) in skipped {
+        content.push_str(&format!(
+            "\n// splitrs: {} lines {}-{}: {}\n{}\n",
+            input, span.start_line, span.end_line, span.error, span.text
+        ));
+    }
+    content
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+/// Byte ranges of the top-level items of `source`
+///
+/// An item ends at a `;` or a closing `}` at bracket depth zero (a `}`
+/// directly followed by `;` extends to the `;`, as in `static X: T = T { .. };`).
+/// Leading comments and attributes belong to the following item. When
+/// brackets never balance, scanning resynchronizes at the next line that
+/// starts an item without indentation.
+pub fn item_spans(source: &str) -> Vec<(usize, usize)> {
+    let bytes = source.as_bytes();
+    let mut spans = Vec::new();
+    let mut depth: usize = 0;
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = source[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = skip_block_comment(bytes, i);
+                continue;
+            }
+            b'"' => {
+                i = skip_string(bytes, i + 1);
+                continue;
+            }
+            b'r' | b'b' | b'c' if starts_raw_string(bytes, i) => {
+                i = skip_raw_string(bytes, i);
+                continue;
+            }
+            b'\'' => {
+                i = skip_char_literal(bytes, i);
+                continue;
+            }
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' => depth = depth.saturating_sub(1),
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    let rest = source[i + 1..].trim_start();
+                    if !rest.starts_with(';') {
+                        spans.push((start, i + 1));
+                        start = i + 1;
+                    }
+                }
+            }
+            b';' if depth == 0 => {
+                spans.push((start, i + 1));
+                start = i + 1;
+            }
+            b'\n' if depth > 0 && starts_item(&source[i + 1..]) => {
+                // Unbalanced brackets: resynchronize at the next unindented item
+                spans.push((start, i + 1));
+                start = i + 1;
+                depth = 0;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    if start < bytes.len() {
+        spans.push((start, bytes.len()));
+    }
+    spans
+}
+
+/// Offset of the `{` opening the body of an item (`impl X {`, `trait T {`)
+///
+/// The first `{` outside parentheses, brackets, strings and comments.
+pub fn body_start(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let mut depth: usize = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = text[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = skip_block_comment(bytes, i);
+                continue;
+            }
+            b'"' => {
+                i = skip_string(bytes, i + 1);
+                continue;
+            }
+            b'r' | b'b' | b'c' if starts_raw_string(bytes, i) => {
+                i = skip_raw_string(bytes, i);
+                continue;
+            }
+            b'\'' => {
+                i = skip_char_literal(bytes, i);
+                continue;
+            }
+            b'(' | b'[' => depth += 1,
+            b')' | b']' => depth = depth.saturating_sub(1),
+            b'{' if depth == 0 => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Offset of the first token of an item after its comments and attributes
+///
+/// Brackets inside the attributes' strings, char literals and comments do
+/// not count (`#[doc = "]"]`).
+pub fn item_start(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut depth: usize = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = text[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = skip_block_comment(bytes, i);
+                continue;
+            }
+            b'"' if depth > 0 => {
+                i = skip_string(bytes, i + 1);
+                continue;
+            }
+            b'r' | b'b' | b'c' if depth > 0 && starts_raw_string(bytes, i) => {
+                i = skip_raw_string(bytes, i);
+                continue;
+            }
+            b'\'' if depth > 0 => {
+                i = skip_char_literal(bytes, i);
+                continue;
+            }
+            b'#' if depth == 0 && bytes.get(i + 1) == Some(&b'[') => {}
+            b'[' => depth += 1,
+            b']' => depth = depth.saturating_sub(1),
+            byte if depth == 0 && !byte.is_ascii_whitespace() => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Whether an unindented line starts a new top-level item
+fn starts_item(line: &str) -> bool {
+    const ITEM_STARTS: &[&str] = &[
+        "fn ",
+        "pub ",
+        "pub(",
+        "struct ",
+        "enum ",
+        "union ",
+        "impl",
+        "trait ",
+        "mod ",
+        "use ",
+        "const ",
+        "static ",
+        "type ",
+        "unsafe ",
+        "async ",
+        "extern ",
+        "macro_rules!",
+        "#[",
+        "///",
+    ];
+    ITEM_STARTS.iter().any(|start| line.starts_with(start))
+}
+
+fn skip_block_comment(bytes: &[u8], mut i: usize) -> usize {
+    let mut nesting = 0;
+    while i + 1 < bytes.len() {
+        if bytes[i] == b'/' && bytes[i + 1] == b'*' {
+            nesting += 1;
+            i += 2;
+        } else if bytes[i] == b'*' && bytes[i + 1] == b'/' {
+            nesting -= 1;
+            i += 2;
+            if nesting == 0 {
+                return i;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// Skip a string body starting after the opening quote
+fn skip_string(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn starts_raw_string(bytes: &[u8], i: usize) -> bool {
+    if i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_') {
+        return false;
+    }
+    let mut j = i;
+    if matches!(bytes[j], b'b' | b'c') {
+        j += 1;
+    }
+    if bytes.get(j) != Some(&b'r') {
+        return false;
+    }
+    j += 1;
+    while bytes.get(j) == Some(&b'#') {
+        j += 1;
+    }
+    bytes.get(j) == Some(&b'"')
+}
+
+fn skip_raw_string(bytes: &[u8], mut i: usize) -> usize {
+    while bytes[i] != b'#' && bytes[i] != b'"' {
+        i += 1;
+    }
+    let mut hashes = 0;
+    while bytes[i] == b'#' {
+        hashes += 1;
+        i += 1;
+    }
+    i += 1;
+    while i < bytes.len() {
+        if bytes[i] == b'"'
+            && bytes[i + 1..]
+                .iter()
+                .take(hashes)
+                .filter(|b| **b == b'#')
+                .count()
+                == hashes
+        {
+            return i + 1 + hashes;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// Skip a char literal, or just the quote of a lifetime
+fn skip_char_literal(bytes: &[u8], i: usize) -> usize {
+    if bytes.get(i + 1) == Some(&b'\\') {
+        let mut j = i + 2;
+        while j < bytes.len() && bytes[j] != b'\'' {
+            j += 1;
+        }
+        return j + 1;
+    }
+    // One (possibly multi-byte) character followed by a closing quote
+    let width = match bytes.get(i + 1) {
+        Some(b) if *b < 0x80 => 1,
+        Some(b) if *b >= 0xF0 => 4,
+        Some(b) if *b >= 0xE0 => 3,
+        Some(_) => 2,
+        None => return bytes.len(),
+    };
+    if bytes.get(i + 1 + width) == Some(&b'\'') {
+        i + 2 + width
+    } else {
+        i + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_file_is_untouched() {
+        let parsed = parse_lenient("struct A;\nfn f() {}\n");
+        assert!(parsed.skipped.is_empty());
+        assert_eq!(parsed.file.items.len(), 2);
+    }
+
+    #[test]
+    fn test_broken_item_is_skipped_verbatim() {
+        let source =
+            "//! Docs\nuse std::fmt;\n\n/// Broken\nfn broken( {\n    let s = \"}\";\n}\n\n\
+                      static X: Point = Point { x: 1 };\nstruct Ok<'a> { c: char, r: &'a str }\n\
+                      fn chars() -> char { '{' }\n";
+        let parsed = parse_lenient(source);
+
+        assert_eq!(parsed.skipped.len(), 1);
+        let span = &parsed.skipped[0];
+        assert_eq!((span.start_line, span.end_line), (4, 7));
+        assert!(span.text.starts_with("/// Broken\nfn broken( {"));
+        assert_eq!(parsed.file.items.len(), 4);
+        assert_eq!(parsed.file.attrs.len(), 1);
+    }
+
+    #[test]
+    fn test_render_skipped_module() {
+        let span = SkippedSpan {
+            start_line: 3,
+            end_line: 4,
+            text: "fn broken( {}".to_string(),
+            error: "expected `)`".to_string(),
+        };
+        let content = render_skipped_module(&[("src/big.rs".to_string(), span)]);
+        assert!(content.contains("// splitrs: src/big.rs lines 3-4: expected `)`"));
+        assert!(content.contains("fn broken( {}"));
+    }
+}
@@ -0,0 +1,5583 @@
+//! # SplitRS - Production-Ready Rust Code Refactoring Tool
+//!
+//! SplitRS is an AST-based tool that intelligently splits large Rust files into
+//! maintainable modules while preserving semantics and proper module structure.
+//!
+//! ## Features
+//!
+//! - **AST-Based Analysis**: Uses `syn` for accurate parsing, ensuring valid Rust code
+//! - **Smart Impl Block Splitting**: Detects method dependencies and splits large impl blocks
+//! - **Automatic Import Generation**: Generates proper `use` statements for split modules
+//! - **Scope-Aware Organization**: Understands Rust's module system and places impl blocks correctly
+//! - **Preserves Semantics**: Maintains doc comments, attributes, and type hierarchies
+//! - **Module Re-exports**: Creates proper `mod.rs` with public re-exports
+//!
+//! ## Usage
+//!
+//! ```bash
+//! # Basic usage: split a large file into modules
+//! splitrs -i large_file.rs -o output_dir/
+//!
+//! # Control maximum lines per module
+//! splitrs -i large_file.rs -o output_dir/ -m 500
+//!
+//! # Enable experimental impl block splitting
+//! splitrs -i large_file.rs -o output_dir/ --split-impl-blocks --max-impl-lines 300
+//!
+//! # Dry run to see what would be created
+//! splitrs -i large_file.rs -o output_dir/ -n
+//! ```
+//!
+//! The same split is available in memory through [`split_source`], which
+//! returns the generated files instead of writing them.
+//!
+//! ## Architecture
+//!
+//! SplitRS consists of three main analysis modules, fed by a shared
+//! [`analysis`] model built in a single traversal of the input:
+//!
+//! - [`method_analyzer`]: Detects method boundaries and dependencies in impl blocks
+//! - [`import_analyzer`]: Analyzes type usage and generates appropriate import statements
+//! - [`scope_analyzer`]: Determines correct module placement following Rust's scope rules
+//!
+//! ## Example
+//!
+//! Given a large Rust file with multiple types and impl blocks:
+//!
+//! ```rust,ignore
+//! struct User { name: String, age: u32 }
+//! impl User {
+//!     fn new(name: String, age: u32) -> Self { /* ... */ }
+//!     fn get_name(&self) -> &str { /* ... */ }
+//!     // ... 50+ more methods
+//! }
+//! ```
+//!
+//! SplitRS will:
+//! 1. Analyze the structure and detect large impl blocks
+//! 2. Group related methods by dependency analysis
+//! 3. Generate organized modules with proper imports
+//! 4. Create a `mod.rs` with appropriate re-exports
+
+/// `println!` into a [`Reporter`]
+macro_rules! reportln {
+    ($reporter:expr) => {
+        $reporter.print("\n")
+    };
+    ($reporter:expr, $($arg:tt)*) => {
+        $reporter.print(&format!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+mod analysis;
+mod backup;
+mod batch_summary;
+mod budget;
+mod cargo_target;
+mod cfg_gate;
+mod check;
+mod compat_shim;
+pub mod config;
+mod crate_mode;
+mod crate_root;
+mod dead_code;
+mod dependency_analyzer;
+mod deprecated_items;
+mod diagnostics;
+mod doc_index;
+mod doc_links;
+mod doctests;
+mod existing_module;
+mod export_analyzer;
+mod ffi;
+mod field_usage;
+mod file_names;
+mod file_system;
+mod hierarchy;
+mod import_analyzer;
+mod import_suggester;
+mod in_memory;
+mod in_place;
+mod include_paths;
+mod internal_items;
+mod item_order;
+mod lenient;
+mod merge;
+mod method_analyzer;
+mod migration;
+mod multi_input;
+mod outline;
+mod path_rewriter;
+mod pipeline;
+mod preview;
+mod rebalance;
+mod renames;
+mod report;
+mod reporter;
+mod rerun;
+mod risk;
+mod roundtrip;
+mod scope_analyzer;
+mod state_machine;
+mod stats;
+mod test_helpers;
+mod trait_origin;
+mod verbatim;
+mod verify;
+mod visitor_pattern;
+
+pub use config::Config;
+pub use file_system::{FileSystem, MemoryFileSystem, RealFileSystem};
+pub use in_memory::split_source;
+pub use reporter::{CollectingReporter, Reporter, StdoutReporter};
+
+use analysis::AnalysisModel;
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use config::ShortPaths;
+use diagnostics::{Diagnostic, DiagnosticsFormat};
+use export_analyzer::ReexportPlan;
+use field_usage::MatrixFormat;
+use import_analyzer::{ImportAnalyzer, ReferencedNames};
+use import_suggester::{insert_use_statements, ImportSuggester};
+use method_analyzer::{ImplBlockAnalyzer, MethodGroup};
+use preview::{render_module_tree, render_unresolved, SizeTable};
+use quote::ToTokens;
+use scope_analyzer::ScopeAnalyzer;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
+use syn::{File, Item, ItemImpl};
+
+/// Command-line arguments for the SplitRS refactoring tool
+///
+/// Provides configuration options for controlling how large Rust files are split
+/// into maintainable modules.
+#[derive(Parser, Clone)]
+#[command(name = "splitrs")]
+#[command(author, version, about, long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Args {
+    /// Operation to run instead of splitting
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Input Rust file(s) to split
+    ///
+    /// The source files must be valid Rust code that can be parsed by `syn`.
+    /// When several related files are given (`-i big.rs -i big_helpers.rs`),
+    /// they are analyzed together and split into a single module tree.
+    #[arg(short, long, num_args = 1.., required_unless_present = "crate_dir")]
+    input: Vec<PathBuf>,
+
+    /// Output directory for modules
+    ///
+    /// All generated module files will be placed in this directory.
+    /// The directory will be created if it doesn't exist.
+    #[arg(short, long, required_unless_present_any = ["crate_dir", "in_place"])]
+    output: Option<PathBuf>,
+
+    /// Split every oversized file of the crate at this path
+    ///
+    /// Walks `src/` and splits each `.rs` file over `max_lines` into a
+    /// module directory of its own, adding the `mod` declarations crate
+    /// roots need. Files under `src/bin/` are left out.
+    #[arg(long = "crate", value_name = "PATH", conflicts_with_all = ["input", "output"])]
+    crate_dir: Option<PathBuf>,
+
+    /// Replace the input file with its split
+    ///
+    /// Writes the modules to `<file_stem>/` next to the input, removes the
+    /// input (a copy is in the backup) and declares the new module in the
+    /// parent (`lib.rs`, `main.rs` or the enclosing `mod.rs`) if it is not
+    /// declared yet, so the crate builds without manual fixup.
+    #[arg(long, conflicts_with_all = ["output", "crate_dir", "compat_shim"])]
+    in_place: bool,
+
+    /// Keep the module names of types renamed since the previous run
+    ///
+    /// Types of the previous run's report that are gone are matched with
+    /// new types by signature, and the modules named after them keep their
+    /// names, so the existing files are updated instead of duplicated.
+    #[arg(long)]
+    follow_renames: bool,
+
+    /// Maximum lines per module
+    ///
+    /// Controls the target size for each generated module. SplitRS will attempt
+    /// to keep modules under this line limit while respecting logical boundaries.
+    /// Overrides configuration file if specified.
+    #[arg(short, long)]
+    max_lines: Option<usize>,
+
+    /// Split large impl blocks (experimental)
+    ///
+    /// When enabled, SplitRS will analyze impl blocks and split them into
+    /// multiple modules based on method dependencies and size constraints.
+    /// Overrides configuration file if specified.
+    #[arg(long)]
+    split_impl_blocks: Option<bool>,
+
+    /// Maximum lines per impl block before splitting
+    ///
+    /// Controls when impl blocks should be split. Only applies when
+    /// `--split-impl-blocks` is enabled.
+    /// Overrides configuration file if specified.
+    #[arg(long)]
+    max_impl_lines: Option<usize>,
+
+    /// Extract only the largest modules, at most this many, in this run
+    ///
+    /// Everything else stays in `mod.rs`, and `.splitrs-plan.md` lists the
+    /// remaining steps, so a huge file can be split over several PRs.
+    #[arg(long)]
+    top: Option<usize>,
+
+    /// Extract only the largest modules holding at most this many lines
+    ///
+    /// Like `--top`, the rest stays in `mod.rs` for later runs.
+    #[arg(long)]
+    max_moved_lines: Option<usize>,
+
+    /// Deepest directory level below the output directory
+    ///
+    /// Deeper directories (from `subdirectories`) become one file of inline
+    /// modules with a section comment each. Overrides configuration file if
+    /// specified.
+    #[arg(long)]
+    max_depth: Option<usize>,
+
+    /// Dry run - show what would be done without making changes
+    ///
+    /// Analyzes the input file and prints the proposed module structure
+    /// without creating any files.
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+
+    /// Path to configuration file
+    ///
+    /// If not specified, SplitRS will search for `.splitrs.toml` in the
+    /// current directory and its parents. Subcommands take it after their
+    /// name (`splitrs check -c ci.toml src/lib.rs`) and otherwise search
+    /// from the directory or files they work on.
+    #[arg(short = 'c', long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Interactive mode - prompt for confirmation before creating files
+    #[arg(short = 'I', long)]
+    interactive: bool,
+
+    /// Withhold conflicting public names from the `mod.rs` re-exports
+    ///
+    /// Names exported by more than one generated module are left reachable
+    /// only through module-qualified paths (e.g. `functions::parse`) instead
+    /// of aborting the run.
+    #[arg(long)]
+    qualify_conflicts: bool,
+
+    /// Insert suggested `use` statements for unresolved names
+    ///
+    /// Searches the crate's other source files, the standard library, and
+    /// well-known dependency exports for each name SplitRS cannot import on
+    /// its own, and adds the most likely path to the generated module.
+    #[arg(long)]
+    fix_imports: bool,
+
+    /// Import crate-internal types defined in the crate's other files
+    ///
+    /// Indexes every other source file of the crate into a symbol table so
+    /// moved code that uses e.g. `crate::model::Order` gets the right `use`.
+    #[arg(long)]
+    resolve_crate_types: bool,
+
+    /// Keep going when parts of the input fail to parse
+    ///
+    /// Top-level items that cannot be parsed are copied verbatim into a
+    /// separate module (see `naming.unparsed_module`) and reported; the rest
+    /// of the file is split as usual.
+    #[arg(long)]
+    lenient: bool,
+
+    /// Replace the input file with a compatibility shim
+    ///
+    /// After the split, the original file is rewritten to glob re-export the
+    /// new module (`pub use crate::big_split::*;`), so existing paths keep
+    /// working while callers migrate. The output must be a different module
+    /// of the same crate.
+    #[arg(long)]
+    compat_shim: bool,
+
+    /// Check the written modules against the input
+    ///
+    /// The generated files are parsed again and compared item by item with
+    /// the input (ignoring `use` statements and the visibility, doc and path
+    /// adjustments SplitRS makes). Fails if anything was dropped, duplicated
+    /// or altered.
+    #[arg(long)]
+    verify_roundtrip: bool,
+
+    /// Run `cargo check` on the written modules
+    ///
+    /// Checks the containing crate when the output directory is declared
+    /// as one of its modules, or else a scaffolding crate that includes the
+    /// output, and reports each compile error with the generated module it
+    /// points into. Fails if there are any.
+    #[arg(long)]
+    verify: bool,
+
+    /// Refuse to write output while any semantic risk remains
+    ///
+    /// Macros used outside their defining module, private functions called
+    /// from sibling modules, split impl blocks that lose a `#[cfg]`,
+    /// unresolved names and widened visibility are all reported, and no file
+    /// is written until the split is free of them.
+    #[arg(long)]
+    strict: bool,
+
+    /// Module path of the output directory (e.g. `crate::core::storage`)
+    ///
+    /// By default it is derived from the output directory's place under the
+    /// crate's `src/`. Set it when that does not match the module tree, such
+    /// as for modules declared with `#[path]`; rewritten paths, the crate
+    /// root and compatibility shims then target this path.
+    #[arg(long)]
+    module_prefix: Option<String>,
+
+    /// Also emit findings as newline-delimited JSON on stderr
+    ///
+    /// Each record holds the file, span, code, severity, message and
+    /// suggested fix of one finding, for wrappers, bots and editors.
+    #[arg(long, value_enum, default_value_t = DiagnosticsFormat::Human)]
+    diagnostics_format: DiagnosticsFormat,
+
+    /// Backup directory a `--crate` batch assigns to one of its files
+    ///
+    /// Such a run neither reuses nor prunes backups; the batch prunes once
+    /// it is done.
+    #[arg(skip)]
+    batch_backup_dir: Option<PathBuf>,
+}
+
+/// Operations other than splitting a file
+#[derive(Subcommand, Clone)]
+enum Command {
+    /// Merge a module directory (mod.rs plus submodules) back into one file
+    ///
+    /// Inlines every file-backed submodule, consolidates `use` statements,
+    /// and keeps doc comments. Useful for undoing an over-eager split.
+    Merge {
+        /// Directory containing the `mod.rs` to merge
+        dir: PathBuf,
+
+        /// File to write the merged module to
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Print the merged file instead of writing it
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
+    /// Plan re-splitting a legacy module tree with the current configuration
+    ///
+    /// Flattens the whole subtree, groups it again and reports which items
+    /// would move between files. Nothing is modified.
+    Resplit {
+        /// Directory containing the `mod.rs` of the subtree
+        dir: PathBuf,
+
+        /// Write the migration plan to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Re-balance an already-split module directory that drifted over threshold
+    ///
+    /// Keeps current item assignments where possible and only moves trailing
+    /// items out of oversized modules into siblings with room (or overflow
+    /// modules when none has room).
+    Rebalance {
+        /// Directory containing the `mod.rs` to re-balance
+        dir: PathBuf,
+
+        /// Maximum lines per module (defaults to the configured `max_lines`)
+        #[arg(short, long)]
+        max_lines: Option<usize>,
+
+        /// Print the planned moves without modifying any files
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+    },
+
+    /// Check files against the `[check]` size limits without changing them
+    ///
+    /// Reports oversized files, oversized impl blocks and types with many
+    /// trait impls, each with the severity the configuration assigns.
+    /// Fails if any finding is an error.
+    Check {
+        /// Rust files to check
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Also emit findings as newline-delimited JSON on stderr
+        #[arg(long, value_enum, default_value_t = DiagnosticsFormat::Human)]
+        diagnostics_format: DiagnosticsFormat,
+    },
+
+    /// List or restore the backups taken before splits
+    Backups {
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+
+    /// Report which methods read and write which fields of each struct
+    ///
+    /// One matrix of methods × fields per type, as CSV or an HTML page.
+    /// Methods that share no fields are candidates for separate modules.
+    Fields {
+        /// Rust file to analyze
+        file: PathBuf,
+
+        /// Output format of the matrix
+        #[arg(short, long, value_enum, default_value_t = MatrixFormat::Csv)]
+        format: MatrixFormat,
+
+        /// Write the matrix to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// What to do with the backups
+#[derive(Subcommand, Clone)]
+enum BackupAction {
+    /// List the backups under the backup root, newest first
+    List,
+
+    /// Write the inputs of a backup back to where they came from
+    Restore {
+        /// Backup to restore, as listed, or `latest`
+        #[arg(default_value = "latest")]
+        name: String,
+    },
+}
+
+/// Runs a subcommand
+///
+/// # Arguments
+///
+/// * `command` - The subcommand to run
+/// * `config_path` - The `--config` file, if one was given
+/// * `disk` - Where files are read and written
+/// * `reporter` - Receiver of the output
+fn run_command(
+    command: &Command,
+    config_path: Option<&Path>,
+    disk: &dyn FileSystem,
+    reporter: &mut dyn Reporter,
+) -> Result<()> {
+    match command {
+        Command::Merge {
+            dir,
+            output,
+            dry_run,
+        } => {
+            let outcome = merge::merge_module_dir(disk, dir)?;
+            let merged = outcome.render();
+
+            reportln!(
+                reporter,
+                "Merged {} modules from {:?}: {}",
+                outcome.merged_modules.len(),
+                dir,
+                outcome.merged_modules.join(", ")
+            );
+            if *dry_run {
+                reportln!(reporter, "\n{}", merged);
+                return Ok(());
+            }
+
+            disk.write(output, &merged)?;
+            reportln!(
+                reporter,
+                "Created: {:?} ({} lines from {} files)",
+                output,
+                merged.lines().count(),
+                outcome.source_files.len()
+            );
+            Ok(())
+        }
+        Command::Resplit { dir, output } => {
+            let config = config_near(config_path, dir)?;
+            let outcome = merge::merge_module_dir(disk, dir)?;
+
+            let mut analyzer = FileAnalyzer::from_config(&config);
+            analyzer.analyze(&outcome.file, &AnalysisModel::build(&outcome.file));
+            let proposed: Vec<(String, Vec<String>)> = analyzer
+                .group_by_module(config.splitrs.max_lines)
+                .iter()
+                .map(|m| (m.name.clone(), m.item_labels()))
+                .collect();
+            let current: Vec<(String, Vec<String>)> = outcome
+                .module_items
+                .iter()
+                .map(|(name, items)| {
+                    (
+                        name.clone(),
+                        items.iter().flat_map(migration::item_labels).collect(),
+                    )
+                })
+                .collect();
+
+            let report = migration::MigrationPlan::build(&current, &proposed).render();
+            match output {
+                Some(path) => {
+                    disk.write(path, &report)?;
+                    reportln!(reporter, "Migration plan written to {:?}", path);
+                }
+                None => reporter.print(&report),
+            }
+            Ok(())
+        }
+        Command::Rebalance {
+            dir,
+            max_lines,
+            dry_run,
+        } => {
+            let max_lines = match max_lines {
+                Some(max_lines) => *max_lines,
+                None => config_near(config_path, dir)?.splitrs.max_lines,
+            };
+            let plan = rebalance::plan_rebalance(disk, dir, max_lines)?;
+
+            reportln!(
+                reporter,
+                "Re-balancing {:?} (max {} lines per module)\n",
+                dir,
+                max_lines
+            );
+            reporter.print(&plan.render());
+            if *dry_run || plan.moves.is_empty() {
+                return Ok(());
+            }
+
+            for path in plan.apply(disk)? {
+                reportln!(reporter, "Updated: {:?}", path);
+            }
+            Ok(())
+        }
+        Command::Check {
+            files,
+            diagnostics_format,
+        } => {
+            let mut errors = 0;
+            let mut warnings = 0;
+            for file in files {
+                let policy = config_near(config_path, file)?.check;
+                let source = disk
+                    .read(file)
+                    .context(format!("Failed to read input file: {:?}", file))?;
+                let path = file.display().to_string();
+                for finding in check::check_source(&path, &source, &policy)? {
+                    match finding.severity {
+                        config::Severity::Error => errors += 1,
+                        _ => warnings += 1,
+                    }
+                    reportln!(reporter, "{}", finding);
+                    if *diagnostics_format == DiagnosticsFormat::Json {
+                        diagnostics::emit(reporter, &[finding.to_diagnostic(&path)]);
+                    }
+                }
+            }
+            reportln!(
+                reporter,
+                "Checked {} file(s): {} error(s), {} warning(s)",
+                files.len(),
+                errors,
+                warnings
+            );
+            if errors > 0 {
+                anyhow::bail!("{} finding(s) exceed the error thresholds", errors);
+            }
+            Ok(())
+        }
+        Command::Backups { action } => {
+            let config = config_near(config_path, Path::new("."))?;
+            let root = backup::resolve_root(disk, &config.output.backup_root, Path::new("."));
+            match action {
+                BackupAction::List => {
+                    let backups = backup::list(disk, &root)?;
+                    if backups.is_empty() {
+                        reportln!(reporter, "No backups in {:?}", root);
+                    }
+                    for (name, manifest) in backups {
+                        let files: Vec<String> = manifest
+                            .files
+                            .iter()
+                            .map(|f| f.original.display().to_string())
+                            .collect();
+                        reportln!(
+                            reporter,
+                            "{}  {} -> {}",
+                            name,
+                            files.join(", "),
+                            manifest.output_dir.display()
+                        );
+                    }
+                }
+                BackupAction::Restore { name } => {
+                    for (_, manifest) in backup::restore(disk, &root, name)? {
+                        for file in manifest.files.iter().chain(&manifest.patched) {
+                            reportln!(reporter, "Restored: {:?}", file.original);
+                        }
+                        if manifest.generated.is_empty() {
+                            reportln!(
+                                reporter,
+                                "The generated modules in {:?} were left in place",
+                                manifest.output_dir
+                            );
+                        } else {
+                            reportln!(
+                                reporter,
+                                "Removed the {} generated file(s) in {:?}",
+                                manifest.generated.len(),
+                                manifest.output_dir
+                            );
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+        Command::Fields {
+            file,
+            format,
+            output,
+        } => {
+            let source = disk
+                .read(file)
+                .context(format!("Failed to read input file: {:?}", file))?;
+            let syntax_tree = syn::parse_file(&source).context("Failed to parse Rust file")?;
+            let matrices = field_usage::field_matrices(&syntax_tree);
+            let rendered = match format {
+                MatrixFormat::Csv => field_usage::render_csv(&matrices),
+                MatrixFormat::Html => field_usage::render_html(&matrices),
+            };
+            match output {
+                Some(path) => {
+                    disk.write(path, &rendered)?;
+                    reportln!(
+                        reporter,
+                        "Field usage of {} type(s) written to {:?}",
+                        matrices.len(),
+                        path
+                    );
+                }
+                None => reporter.print(&rendered),
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Information about a Rust type (struct or enum) and its associated impl blocks
+///
+/// This structure tracks all information needed to properly organize a type
+/// when splitting it into modules, including the type definition itself,
+/// its impl blocks, and any large impl blocks that need to be split.
+#[derive(Clone)]
+struct TypeInfo {
+    /// Name of the type (struct or enum name)
+    name: String,
+
+    /// The type definition item (struct or enum)
+    item: Item,
+
+    /// Regular inherent impl blocks for this type (`impl Type { ... }`)
+    impls: Vec<Item>,
+
+    /// Trait implementation blocks (`impl Trait for Type { ... }`)
+    trait_impls: Vec<TraitImplInfo>,
+
+    /// Documentation comments associated with the type
+    doc_comments: Vec<String>,
+
+    /// Large impl blocks that should be split into separate modules
+    ///
+    /// Each tuple contains the original impl block and the groups of methods
+    /// it should be split into, as determined by dependency analysis.
+    large_impls: Vec<(ItemImpl, Vec<MethodGroup>)>,
+}
+
+/// Information about a trait implementation
+#[derive(Clone)]
+struct TraitImplInfo {
+    /// Name of the trait being implemented
+    pub trait_name: String,
+
+    /// The trait impl block
+    impl_item: Item,
+
+    /// Where the trait is defined
+    origin: trait_origin::TraitOrigin,
+
+    /// Whether this is an unsafe impl
+    #[allow(dead_code)]
+    is_unsafe: bool,
+}
+
+impl TraitImplInfo {
+    /// Attributes of the impl block
+    fn attrs(&self) -> &[syn::Attribute] {
+        match &self.impl_item {
+            Item::Impl(impl_block) => &impl_block.attrs,
+            _ => &[],
+        }
+    }
+
+    /// Last segment of the implemented trait's path, without generics
+    fn trait_ident(&self) -> String {
+        match &self.impl_item {
+            Item::Impl(ItemImpl {
+                trait_: Some((_, path, _)),
+                ..
+            }) => path
+                .segments
+                .last()
+                .map(|segment| segment.ident.to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    /// Whether the implemented trait is a `std::fmt` formatting trait
+    fn is_fmt(&self) -> bool {
+        match &self.impl_item {
+            Item::Impl(ItemImpl {
+                trait_: Some((_, path, _)),
+                ..
+            }) => trait_origin::is_fmt_trait(path, self.origin),
+            _ => false,
+        }
+    }
+}
+
+/// Core analyzer that processes a Rust file and determines how to split it
+///
+/// The `FileAnalyzer` is responsible for:
+/// - Identifying types (structs, enums) and their impl blocks
+/// - Determining which impl blocks are large enough to split
+/// - Tracking standalone items (functions, constants, etc.)
+/// - Coordinating with the scope analyzer for proper module placement
+struct FileAnalyzer {
+    /// Map of type names to their information
+    types: HashMap<String, TypeInfo>,
+
+    /// Items that aren't type definitions (functions, constants, etc.)
+    standalone_items: Vec<Item>,
+
+    /// Whether to enable impl block splitting
+    split_impl_blocks: bool,
+
+    /// Maximum lines per impl block before splitting
+    max_impl_lines: usize,
+
+    /// Analyzer for determining proper module scope and placement
+    scope_analyzer: ScopeAnalyzer,
+
+    /// Suffix for extension impl modules; `None` keeps impls of types not
+    /// defined in the input with the standalone items
+    extension_suffix: Option<String>,
+
+    /// Module name overrides for extension impls, by target type name
+    extension_overrides: BTreeMap<String, String>,
+
+    /// Impls of types not defined in the input, by target type name
+    extension_impls: BTreeMap<String, Vec<Item>>,
+
+    /// Module for `#[doc(hidden)]` items and shared private helpers; `None`
+    /// keeps them with the other standalone items
+    internal_module: Option<String>,
+
+    /// Module for `#[deprecated]` items; `None` leaves them in place
+    deprecated_module: Option<String>,
+
+    /// Deprecated types, with all their impls
+    deprecated_types: Vec<TypeInfo>,
+
+    /// Deprecated standalone items and impls holding deprecated methods
+    deprecated_items: Vec<Item>,
+
+    /// Names of modules that already exist next to the output (e.g. the
+    /// siblings of an input `mod.rs`), which generated modules must avoid
+    reserved_module_names: BTreeSet<String>,
+
+    /// Whether each type's trait impls are split by the trait's origin
+    /// (std, external crate, local)
+    group_trait_origins: bool,
+
+    /// Whether each type's formatting-trait impls get a `_fmt` module
+    group_fmt_traits: bool,
+
+    /// Module the extern blocks are moved to
+    ffi_module: String,
+
+    /// Most lines of trait impls a type may have for them to be packed by
+    /// trait (`display_impls`) with those of other small types
+    packed_impl_lines: Option<usize>,
+
+    /// Fewest methods a split impl group needs for a module of its own
+    min_group_methods: usize,
+
+    /// Lowest cohesion a split impl group needs for a module of its own
+    min_group_cohesion: f64,
+
+    /// Whether large enum impls are split by the variants their methods
+    /// handle instead of by calls alone
+    group_enum_variants: bool,
+
+    /// Whether large impls of state-machine types are split by state and
+    /// event family
+    group_state_machines: bool,
+
+    /// `*State` enums of the input, for state-machine grouping
+    state_enums: Vec<state_machine::StateEnum>,
+
+    /// Whether large impls of visitor types are split by the category of
+    /// the visited node
+    group_visitors: bool,
+
+    /// Whether private helpers only test code uses move next to the tests
+    test_helpers_with_tests: bool,
+
+    /// Position of each top-level name in the input
+    source_positions: HashMap<String, usize>,
+
+    /// Names modules of renamed types keep from the previous run, by the
+    /// name they would get now (see `renames`)
+    module_renames: HashMap<String, String>,
+}
+
+impl FileAnalyzer {
+    /// Creates a new FileAnalyzer with the specified configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `split_impl_blocks` - Whether to enable experimental impl block splitting
+    /// * `max_impl_lines` - Maximum lines per impl block before splitting
+    fn new(split_impl_blocks: bool, max_impl_lines: usize) -> Self {
+        Self {
+            types: HashMap::new(),
+            standalone_items: Vec::new(),
+            split_impl_blocks,
+            max_impl_lines,
+            scope_analyzer: ScopeAnalyzer::new(),
+            extension_suffix: None,
+            extension_overrides: BTreeMap::new(),
+            extension_impls: BTreeMap::new(),
+            internal_module: None,
+            deprecated_module: None,
+            deprecated_types: Vec::new(),
+            deprecated_items: Vec::new(),
+            reserved_module_names: BTreeSet::new(),
+            group_trait_origins: false,
+            group_fmt_traits: false,
+            ffi_module: "ffi".to_string(),
+            packed_impl_lines: None,
+            min_group_methods: 1,
+            min_group_cohesion: 0.0,
+            group_enum_variants: false,
+            group_state_machines: false,
+            state_enums: Vec::new(),
+            group_visitors: false,
+            test_helpers_with_tests: false,
+            source_positions: HashMap::new(),
+            module_renames: HashMap::new(),
+        }
+    }
+
+    /// Creates a FileAnalyzer with every grouping option of `config`
+    fn from_config(config: &Config) -> Self {
+        let mut analyzer = Self::new(
+            config.splitrs.split_impl_blocks,
+            config.splitrs.max_impl_lines,
+        );
+        if config.splitrs.extension_impl_modules {
+            analyzer.route_extension_impls(
+                &config.naming.ext_module_suffix,
+                &config.splitrs.extension_modules,
+            );
+        }
+        if config.splitrs.isolate_internal_items {
+            analyzer.isolate_internal_items(&config.naming.internal_module);
+        }
+        analyzer.name_ffi_module(&config.naming.ffi_module);
+        if config.splitrs.segregate_deprecated {
+            analyzer.segregate_deprecated(&config.naming.deprecated_module);
+        }
+        if config.splitrs.group_trait_impls_by_origin {
+            analyzer.group_trait_impls_by_origin();
+        }
+        if config.splitrs.group_fmt_impls {
+            analyzer.group_fmt_impls();
+        }
+        if config.splitrs.pack_trait_impls {
+            analyzer.pack_trait_impls(config.splitrs.max_packed_impl_lines);
+        }
+        analyzer.guard_method_groups(
+            config.splitrs.min_group_methods,
+            config.splitrs.min_group_cohesion,
+        );
+        if config.splitrs.group_enum_methods_by_variants {
+            analyzer.group_enum_methods_by_variants();
+        }
+        if config.splitrs.group_state_machines {
+            analyzer.group_state_machines();
+        }
+        if config.splitrs.group_visitor_methods {
+            analyzer.group_visitor_methods();
+        }
+        if config.splitrs.test_helpers_with_tests {
+            analyzer.keep_test_helpers_with_tests();
+        }
+        analyzer
+    }
+
+    /// Keeps generated modules from taking the given module names
+    fn reserve_module_names(&mut self, names: impl IntoIterator<Item = String>) {
+        self.reserved_module_names.extend(names);
+    }
+
+    /// Keeps the module names the previous run gave types renamed since
+    ///
+    /// # Arguments
+    ///
+    /// * `renames` - Module names this run would use, each paired with the
+    ///   name to use instead
+    fn follow_module_renames(&mut self, renames: impl IntoIterator<Item = (String, String)>) {
+        self.module_renames.extend(renames);
+    }
+
+    /// Every type of the input with its signature; modules are filled in
+    /// once the split is planned
+    fn type_records(&self) -> Vec<report::TypeRecord> {
+        let mut records: Vec<report::TypeRecord> = self
+            .types
+            .values()
+            .map(|type_info| {
+                let impls: Vec<&ItemImpl> = type_info
+                    .impls
+                    .iter()
+                    .filter_map(|item| match item {
+                        Item::Impl(impl_block) => Some(impl_block),
+                        _ => None,
+                    })
+                    .chain(
+                        type_info
+                            .large_impls
+                            .iter()
+                            .map(|(impl_block, _)| impl_block),
+                    )
+                    .collect();
+                let traits: Vec<String> = type_info
+                    .trait_impls
+                    .iter()
+                    .map(TraitImplInfo::trait_ident)
+                    .collect();
+                report::TypeRecord {
+                    name: type_info.name.clone(),
+                    signature: renames::signature(
+                        &type_info.name,
+                        &type_info.item,
+                        &impls,
+                        &traits,
+                    ),
+                    fingerprint: renames::fingerprint(&type_info.name, &type_info.item),
+                    modules: Vec::new(),
+                    module_prefix: None,
+                }
+            })
+            .collect();
+        records.sort_by(|a, b| a.name.cmp(&b.name));
+        records
+    }
+
+    /// `name`, or `name_N` with the smallest `N >= 2` when `name` is reserved
+    ///
+    /// The modules of a renamed type keep their previous names.
+    fn available_name(&self, name: String) -> String {
+        let name = self.module_renames.get(&name).cloned().unwrap_or(name);
+        if !self.reserved_module_names.contains(&name) {
+            return name;
+        }
+        (2..)
+            .map(|n| format!("{}_{}", name, n))
+            .find(|candidate| !self.reserved_module_names.contains(candidate))
+            .unwrap_or(name)
+    }
+
+    /// Names the module extern blocks are moved to
+    fn name_ffi_module(&mut self, module_name: &str) {
+        self.ffi_module = module_name.to_string();
+    }
+
+    /// Moves `#[deprecated]` types, methods and standalone items to a
+    /// dedicated module
+    fn segregate_deprecated(&mut self, module_name: &str) {
+        self.deprecated_module = Some(module_name.to_string());
+    }
+
+    /// Takes the deprecated methods out of an inherent impl of a local type
+    ///
+    /// # Returns
+    ///
+    /// The impl without its deprecated methods, or `None` when the impl is
+    /// left as is.
+    fn take_deprecated_methods(&mut self, item: &Item) -> Option<ItemImpl> {
+        self.deprecated_module.as_ref()?;
+        let Item::Impl(impl_block) = item else {
+            return None;
+        };
+        let type_name = Self::get_impl_type_name(impl_block)?;
+        if !self.types.contains_key(&type_name) {
+            return None;
+        }
+        let (remaining, moved) = deprecated_items::split_deprecated_methods(impl_block)?;
+        self.deprecated_items.push(Item::Impl(moved));
+        Some(remaining)
+    }
+
+    /// Moves deprecated types and standalone items out of the regular layout
+    fn collect_deprecated(&mut self) {
+        if self.deprecated_module.is_none() {
+            return;
+        }
+        let mut names: Vec<String> = self
+            .types
+            .values()
+            .filter(|t| deprecated_items::is_deprecated_item(&t.item))
+            .map(|t| t.name.clone())
+            .collect();
+        names.sort();
+        for name in names {
+            if let Some(mut type_info) = self.types.remove(&name) {
+                // The whole type moves, so its impls are no longer split
+                for (impl_block, _) in type_info.large_impls.drain(..) {
+                    type_info.impls.push(Item::Impl(impl_block));
+                }
+                self.deprecated_types.push(type_info);
+            }
+        }
+
+        let (deprecated, kept): (Vec<Item>, Vec<Item>) = std::mem::take(&mut self.standalone_items)
+            .into_iter()
+            .partition(deprecated_items::is_deprecated_item);
+        self.standalone_items = kept;
+        self.deprecated_items.extend(deprecated);
+    }
+
+    /// Moves `#[doc(hidden)]` items and shared private helpers to a
+    /// module that `mod.rs` does not re-export
+    fn isolate_internal_items(&mut self, module_name: &str) {
+        self.internal_module = Some(module_name.to_string());
+    }
+
+    /// Routes impls of types not defined in the input to per-type modules
+    ///
+    /// # Arguments
+    ///
+    /// * `suffix` - Suffix appended to the lowercased type name (e.g. `_ext`)
+    /// * `overrides` - Explicit module names by type name
+    fn route_extension_impls(&mut self, suffix: &str, overrides: &BTreeMap<String, String>) {
+        self.extension_suffix = Some(suffix.to_string());
+        self.extension_overrides = overrides.clone();
+    }
+
+    /// Splits each type's trait impls into std, external-crate and local
+    /// trait modules
+    fn group_trait_impls_by_origin(&mut self) {
+        self.group_trait_origins = true;
+    }
+
+    /// Keeps small and incoherent method groups in the type's main module
+    ///
+    /// # Arguments
+    ///
+    /// * `min_methods` - Fewest methods a group needs for a module of its own
+    /// * `min_cohesion` - Lowest [`MethodGroup::cohesion`] a group without a
+    ///   family label needs for a module of its own
+    fn guard_method_groups(&mut self, min_methods: usize, min_cohesion: f64) {
+        self.min_group_methods = min_methods;
+        self.min_group_cohesion = min_cohesion;
+    }
+
+    /// Moves each type's `Display`, `Debug`, `LowerHex`, ... impls into a
+    /// `{type}_fmt` module
+    fn group_fmt_impls(&mut self) {
+        self.group_fmt_traits = true;
+    }
+
+    /// Packs the trait impls of small types into one module per trait
+    /// (`display_impls`) instead of a `_traits` module per type
+    ///
+    /// # Arguments
+    ///
+    /// * `max_lines` - Most lines of trait impls a type may have to be packed
+    fn pack_trait_impls(&mut self, max_lines: usize) {
+        self.packed_impl_lines = Some(max_lines);
+    }
+
+    /// Splits large enum impls into clusters of methods handling the same
+    /// variants
+    fn group_enum_methods_by_variants(&mut self) {
+        self.group_enum_variants = true;
+    }
+
+    /// Splits large impls of state-machine types by the state or event
+    /// family each method deals with
+    fn group_state_machines(&mut self) {
+        self.group_state_machines = true;
+    }
+
+    /// Splits large impls of visitor types by the category of node each
+    /// `visit_*` method handles
+    fn group_visitor_methods(&mut self) {
+        self.group_visitors = true;
+    }
+
+    /// Moves private helpers that only `#[cfg(test)]` code uses next to (or
+    /// into) the tests using them
+    fn keep_test_helpers_with_tests(&mut self) {
+        self.test_helpers_with_tests = true;
+    }
+
+    /// Relocates test-only free functions and associated functions
+    fn collect_test_helpers(&mut self) {
+        if !self.test_helpers_with_tests {
+            return;
+        }
+        let mut test_names = BTreeSet::new();
+        for item in &self.standalone_items {
+            if test_helpers::is_test_item(item) {
+                let mut used = test_helpers::UsedNames::default();
+                used.visit_item(item);
+                test_names.extend(used.names);
+            }
+        }
+        let mut other = BTreeSet::new();
+        for type_info in self.types.values() {
+            other.extend(Self::impl_used_names(type_info));
+        }
+        for item in self.extension_impls.values().flatten() {
+            let mut used = test_helpers::UsedNames::default();
+            used.visit_item(item);
+            other.extend(used.names);
+        }
+        for item in &self.standalone_items {
+            if !test_helpers::is_test_item(item) {
+                let mut used = test_helpers::UsedNames::default();
+                used.visit_item(item);
+                other.extend(used.names);
+            }
+        }
+
+        let mut test_impls = Vec::new();
+        for type_info in self.types.values_mut() {
+            for item in &mut type_info.impls {
+                if let Item::Impl(impl_block) = item {
+                    test_impls.extend(test_helpers::take_test_methods(
+                        impl_block,
+                        &test_names,
+                        &other,
+                    ));
+                }
+            }
+            type_info
+                .impls
+                .retain(|item| !matches!(item, Item::Impl(i) if i.items.is_empty()));
+        }
+
+        // Free helpers are judged by what the remaining impls use;
+        // `place_test_helpers` looks at the standalone items itself
+        let mut outside = BTreeSet::new();
+        for type_info in self.types.values() {
+            outside.extend(Self::impl_used_names(type_info));
+        }
+        for item in self.extension_impls.values().flatten() {
+            let mut used = test_helpers::UsedNames::default();
+            used.visit_item(item);
+            outside.extend(used.names);
+        }
+        self.standalone_items = test_helpers::place_test_helpers(
+            std::mem::take(&mut self.standalone_items),
+            test_impls,
+            &outside,
+        );
+    }
+
+    /// Names a type's definition and impls use
+    fn impl_used_names(type_info: &TypeInfo) -> BTreeSet<String> {
+        let mut used = test_helpers::UsedNames::default();
+        used.visit_item(&type_info.item);
+        for item in &type_info.impls {
+            used.visit_item(item);
+        }
+        for trait_impl in &type_info.trait_impls {
+            used.visit_item(&trait_impl.impl_item);
+        }
+        for (impl_block, _) in &type_info.large_impls {
+            used.visit_item_impl(impl_block);
+        }
+        used.names
+    }
+
+    /// Name of the module that extension impls for `type_name` go to
+    fn extension_module_name(&self, type_name: &str) -> Option<String> {
+        let suffix = self.extension_suffix.as_ref()?;
+        Some(
+            self.extension_overrides
+                .get(type_name)
+                .cloned()
+                .unwrap_or_else(|| format!("{}{}", type_name.to_lowercase(), suffix)),
+        )
+    }
+
+    /// Analyzes a parsed Rust file and extracts type information
+    ///
+    /// Deep information (type names, method calls) comes from the shared
+    /// `AnalysisModel`; this method only walks the top-level items:
+    /// 1. Registers every type definition
+    /// 2. Processes each remaining item to attach impls and determine splitting strategy
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - The parsed file
+    /// * `model` - Analysis model built from the same file
+    fn analyze(&mut self, file: &File, model: &AnalysisModel) {
+        self.scope_analyzer.register_types(&model.type_names);
+        let local_traits: BTreeSet<String> = file
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Trait(t) => Some(t.ident.to_string()),
+                _ => None,
+            })
+            .collect();
+        for (position, item) in file.items.iter().enumerate() {
+            if let Some(key) = item_order::position_key(item) {
+                self.source_positions.entry(key).or_insert(position);
+            }
+        }
+        self.state_enums = file
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Enum(e) => state_machine::StateEnum::from_enum(e),
+                _ => None,
+            })
+            .collect();
+
+        // Register type definitions before their impls, since impls may come
+        // first (e.g. when a split module tree has been flattened)
+        for item in &file.items {
+            match item {
+                Item::Struct(s) => {
+                    let name = s.ident.to_string();
+                    self.types.insert(
+                        name.clone(),
+                        TypeInfo {
+                            name,
+                            item: item.clone(),
+                            impls: Vec::new(),
+                            trait_impls: Vec::new(),
+                            doc_comments: Vec::new(),
+                            large_impls: Vec::new(),
+                        },
+                    );
+                }
+                Item::Enum(e) => {
+                    let name = e.ident.to_string();
+                    self.types.insert(
+                        name.clone(),
+                        TypeInfo {
+                            name,
+                            item: item.clone(),
+                            impls: Vec::new(),
+                            trait_impls: Vec::new(),
+                            doc_comments: Vec::new(),
+                            large_impls: Vec::new(),
+                        },
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        for (index, item) in file.items.iter().enumerate() {
+            // Deprecated methods leave their impl before it is analyzed
+            let remaining = self.take_deprecated_methods(item).map(Item::Impl);
+            if matches!(&remaining, Some(Item::Impl(i)) if i.items.is_empty()) {
+                continue;
+            }
+            let kept_methods: Option<HashSet<String>> = match &remaining {
+                Some(Item::Impl(i)) => Some(
+                    i.items
+                        .iter()
+                        .filter_map(|impl_item| match impl_item {
+                            syn::ImplItem::Fn(f) => Some(f.sig.ident.to_string()),
+                            _ => None,
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            };
+            let item = remaining.as_ref().unwrap_or(item);
+
+            match item {
+                Item::Struct(_) | Item::Enum(_) => {}
+                Item::Impl(i) => {
+                    if let Some(type_name) = Self::get_impl_type_name(i) {
+                        if let Some(type_info) = self.types.get_mut(&type_name) {
+                            // Check if this is a trait implementation
+                            if let Some(trait_name) = Self::get_trait_name(i) {
+                                // This is a trait impl: `impl Trait for Type`
+                                let origin = i.trait_.as_ref().map_or(
+                                    trait_origin::TraitOrigin::Local,
+                                    |(_, path, _)| {
+                                        trait_origin::classify(path, &local_traits, &model.imports)
+                                    },
+                                );
+                                type_info.trait_impls.push(TraitImplInfo {
+                                    trait_name,
+                                    impl_item: item.clone(),
+                                    origin,
+                                    is_unsafe: i.unsafety.is_some(),
+                                });
+                                continue;
+                            }
+
+                            // This is an inherent impl: `impl Type`
+                            // Check if impl block is large and should be split
+                            if self.split_impl_blocks {
+                                // Analyze impl block to get accurate line count from methods
+                                let analyzer = ImplBlockAnalyzer::from_methods(
+                                    model
+                                        .method_infos(index, &file.items[index])
+                                        .into_iter()
+                                        .filter(|m| {
+                                            kept_methods
+                                                .as_ref()
+                                                .is_none_or(|kept| kept.contains(&m.name))
+                                        })
+                                        .collect(),
+                                );
+                                let impl_lines = analyzer.get_total_lines();
+
+                                if impl_lines > self.max_impl_lines
+                                    && analyzer.get_total_methods() > 1
+                                {
+                                    // Split this impl block
+                                    let groups = match &type_info.item {
+                                        Item::Enum(e) if self.group_enum_variants => {
+                                            let variants: Vec<String> = e
+                                                .variants
+                                                .iter()
+                                                .map(|v| v.ident.to_string())
+                                                .collect();
+                                            analyzer.group_methods_by_variants(
+                                                &type_name,
+                                                &variants,
+                                                self.max_impl_lines,
+                                            )
+                                        }
+                                        _ => {
+                                            let families = self
+                                                .group_state_machines
+                                                .then(|| {
+                                                    state_machine::method_families(
+                                                        analyzer.methods(),
+                                                        &self.state_enums,
+                                                    )
+                                                })
+                                                .flatten()
+                                                .or_else(|| {
+                                                    self.group_visitors
+                                                        .then(|| {
+                                                            visitor_pattern::method_families(
+                                                                analyzer.methods(),
+                                                            )
+                                                        })
+                                                        .flatten()
+                                                });
+                                            match families {
+                                                Some(families) => analyzer.group_methods_by_family(
+                                                    &families,
+                                                    self.max_impl_lines,
+                                                ),
+                                                None => analyzer.group_methods(self.max_impl_lines),
+                                            }
+                                        }
+                                    };
+
+                                    let (groups, leftovers): (Vec<_>, Vec<_>) =
+                                        groups.into_iter().partition(|group| {
+                                            group.methods.len() >= self.min_group_methods
+                                                && (group.label.is_some()
+                                                    || group.cohesion() >= self.min_group_cohesion)
+                                        });
+                                    if !groups.is_empty() {
+                                        // Associated consts and types only one group uses
+                                        // move with it; the others, and methods of groups
+                                        // too small or too loose for a module of their own,
+                                        // stay with the type
+                                        let mut groups = groups;
+                                        method_analyzer::assign_associated_items(
+                                            i,
+                                            &mut groups,
+                                            &leftovers,
+                                        );
+                                        let mut kept = i.clone();
+                                        kept.items = method_analyzer::associated_items(i, &groups);
+                                        kept.items.extend(
+                                            leftovers
+                                                .iter()
+                                                .flat_map(|group| &group.methods)
+                                                .map(|m| syn::ImplItem::Fn(m.item.clone())),
+                                        );
+                                        if !kept.items.is_empty() {
+                                            type_info.impls.push(Item::Impl(kept));
+                                        }
+                                        // Register each group as an impl block with scope analyzer
+                                        for group in &groups {
+                                            let module_name = format!(
+                                                "{}_{}",
+                                                type_name.to_lowercase(),
+                                                group.suggest_name()
+                                            );
+                                            self.scope_analyzer.register_impl_block(
+                                                type_name.clone(),
+                                                i.clone(),
+                                                module_name,
+                                                group.methods.len(),
+                                            );
+                                        }
+                                        // Mark this type as needing an impl module
+                                        self.scope_analyzer.mark_needs_impl_module(&type_name);
+                                        type_info.large_impls.push((i.clone(), groups));
+                                    } else {
+                                        type_info.impls.push(item.clone());
+                                    }
+                                } else {
+                                    type_info.impls.push(item.clone());
+                                }
+                            } else {
+                                type_info.impls.push(item.clone());
+                            }
+                        } else if self.extension_suffix.is_some() {
+                            // Impl for unknown type - route to its extension module
+                            self.extension_impls
+                                .entry(type_name)
+                                .or_default()
+                                .push(item.clone());
+                        } else {
+                            // Impl for unknown type - keep as standalone
+                            self.standalone_items.push(item.clone());
+                        }
+                    } else {
+                        self.standalone_items.push(item.clone());
+                    }
+                }
+                _ => {
+                    self.standalone_items.push(item.clone());
+                }
+            }
+        }
+
+        self.collect_deprecated();
+        self.collect_test_helpers();
+    }
+
+    /// Extracts the type name from an impl block
+    ///
+    /// # Returns
+    ///
+    /// The name of the type being implemented, or `None` if it cannot be determined.
+    fn get_impl_type_name(impl_item: &syn::ItemImpl) -> Option<String> {
+        if let syn::Type::Path(type_path) = &*impl_item.self_ty {
+            if let Some(segment) = type_path.path.segments.last() {
+                return Some(segment.ident.to_string());
+            }
+        }
+        None
+    }
+
+    /// Extracts the trait name from a trait implementation
+    ///
+    /// Generic arguments are kept, so `impl From<String> for Id` and
+    /// `impl From<u64> for Id` are told apart as `From<String>` and `From<u64>`.
+    ///
+    /// # Returns
+    ///
+    /// The name of the trait being implemented, or `None` if this is an inherent impl.
+    fn get_trait_name(impl_item: &syn::ItemImpl) -> Option<String> {
+        impl_item
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|segment| compact_tokens(&segment.to_token_stream().to_string()))
+    }
+
+    /// Get recommended visibility for a type's fields based on impl organization
+    ///
+    /// When impl blocks are split into separate modules, fields may need to be
+    /// made `pub(super)` to allow access from those modules.
+    fn get_field_visibility(&self, type_name: &str) -> scope_analyzer::FieldVisibility {
+        self.scope_analyzer.infer_field_visibility(type_name)
+    }
+
+    /// Get organization strategy for a type's impl blocks
+    ///
+    /// Determines whether impl blocks should be kept inline, placed in submodules,
+    /// or organized using a wrapper pattern.
+    fn get_organization_strategy(
+        &self,
+        type_name: &str,
+    ) -> scope_analyzer::ImplOrganizationStrategy {
+        self.scope_analyzer.determine_strategy(type_name)
+    }
+
+    /// Types in the order they are defined in the input
+    fn types_in_source_order(&self) -> Vec<&TypeInfo> {
+        let mut types: Vec<&TypeInfo> = self.types.values().collect();
+        types.sort_by_key(|t| (self.source_positions.get(&t.name), t.name.clone()));
+        types
+    }
+
+    /// Groups types and items into modules respecting size constraints
+    ///
+    /// # Arguments
+    ///
+    /// * `max_lines` - Target maximum lines per module
+    ///
+    /// # Returns
+    ///
+    /// A vector of modules, each containing related types and items.
+    fn group_by_module(&self, max_lines: usize) -> Vec<Module> {
+        let mut modules = Vec::new();
+        let mut module_name_counts: HashMap<String, usize> = HashMap::new();
+        let mut packs: Vec<(String, Vec<(String, TraitImplInfo)>)> = Vec::new();
+
+        // Process types with trait implementations
+        for type_info in self.types_in_source_order() {
+            // Modules of a gated type carry the type's `#[cfg]`
+            let type_gate = cfg_gate::cfg_only(internal_items::item_attrs(&type_info.item));
+            let type_key = cfg_gate::cfg_key(&type_gate);
+
+            // Impls under a `#[cfg]` of their own go to one module per gate
+            let mut gated: Vec<(String, Vec<TraitImplInfo>)> = Vec::new();
+            let mut ungated = Vec::new();
+            for trait_impl in &type_info.trait_impls {
+                let key = cfg_gate::cfg_key(trait_impl.attrs());
+                if key.is_empty() || key == type_key {
+                    ungated.push(trait_impl.clone());
+                } else if let Some((_, impls)) = gated.iter_mut().find(|(k, _)| *k == key) {
+                    impls.push(trait_impl.clone());
+                } else {
+                    gated.push((key, vec![trait_impl.clone()]));
+                }
+            }
+
+            // The trait impls of small types are packed by trait instead
+            let packed_lines: usize = ungated
+                .iter()
+                .map(|trait_impl| rebalance::item_lines(&trait_impl.impl_item))
+                .sum();
+            if type_gate.is_empty()
+                && self
+                    .packed_impl_lines
+                    .is_some_and(|max| packed_lines <= max)
+            {
+                for trait_impl in std::mem::take(&mut ungated) {
+                    let entry = (type_info.name.clone(), trait_impl);
+                    let key = entry.1.trait_ident();
+                    match packs.iter_mut().find(|(k, _)| *k == key) {
+                        Some((_, impls)) => impls.push(entry),
+                        None => packs.push((key, vec![entry])),
+                    }
+                }
+            }
+            let (fmt_impls, other_impls): (Vec<TraitImplInfo>, Vec<TraitImplInfo>) = ungated
+                .into_iter()
+                .partition(|trait_impl| self.group_fmt_traits && trait_impl.is_fmt());
+            let mut by_origin: BTreeMap<trait_origin::TraitOrigin, Vec<TraitImplInfo>> =
+                BTreeMap::new();
+            for trait_impl in &other_impls {
+                by_origin
+                    .entry(trait_impl.origin)
+                    .or_default()
+                    .push(trait_impl.clone());
+            }
+            if !self.group_trait_origins || by_origin.len() == 1 {
+                by_origin.clear();
+                if !other_impls.is_empty() {
+                    by_origin.insert(trait_origin::TraitOrigin::Local, other_impls);
+                }
+            }
+            let split = by_origin.len() > 1;
+            for (origin, trait_impls) in by_origin {
+                // Create a module for trait implementations
+                let suffix = if split {
+                    origin.module_suffix()
+                } else {
+                    "_traits"
+                };
+                let mut trait_module = Module::new(self.available_name(format!(
+                    "{}{}",
+                    type_info.name.to_lowercase(),
+                    suffix
+                )));
+                trait_module.type_name_for_traits = Some(type_info.name.clone());
+                trait_module.trait_impls = trait_impls;
+                trait_module.gate = type_gate.clone();
+                modules.push(trait_module);
+            }
+            if !fmt_impls.is_empty() {
+                let mut fmt_module = Module::new(
+                    self.available_name(format!("{}_fmt", type_info.name.to_lowercase())),
+                );
+                fmt_module.type_name_for_traits = Some(type_info.name.clone());
+                fmt_module.trait_impls = fmt_impls;
+                fmt_module.fmt_impls = true;
+                fmt_module.gate = type_gate.clone();
+                modules.push(fmt_module);
+            }
+            for (_, trait_impls) in gated {
+                let gate = cfg_gate::gate_attributes(trait_impls.iter().map(TraitImplInfo::attrs));
+                let mut gated_module = Module::new(self.available_name(format!(
+                    "{}_{}_traits",
+                    type_info.name.to_lowercase(),
+                    cfg_gate::gate_suffix(&gate)
+                )));
+                gated_module.type_name_for_traits = Some(type_info.name.clone());
+                gated_module.trait_impls = trait_impls;
+                gated_module.gate = cfg_gate::merge_gates(&type_gate, &gate);
+                modules.push(gated_module);
+            }
+        }
+        for (trait_name, impls) in packs {
+            let mut packed_module = Module::new(
+                self.available_name(format!("{}_impls", state_machine::snake_case(&trait_name))),
+            );
+            for (type_name, _) in &impls {
+                if !packed_module
+                    .packed_types
+                    .iter()
+                    .any(|(t, _)| t == type_name)
+                {
+                    packed_module.packed_types.push((type_name.clone(), None));
+                }
+            }
+            packed_module.type_name_for_traits = impls.first().map(|(name, _)| name.clone());
+            packed_module.fmt_impls = impls.iter().all(|(_, trait_impl)| trait_impl.is_fmt());
+            packed_module.trait_impls = impls.into_iter().map(|(_, t)| t).collect();
+            modules.push(packed_module);
+        }
+
+        // Process types with large impl blocks separately
+        for type_info in self.types_in_source_order() {
+            if !type_info.large_impls.is_empty() {
+                let type_gate = cfg_gate::cfg_only(internal_items::item_attrs(&type_info.item));
+
+                // Determine organization strategy for this type
+                let _strategy = self.get_organization_strategy(&type_info.name);
+                let _visibility = self.get_field_visibility(&type_info.name);
+                // TODO: Use strategy and visibility in module generation
+
+                // Create a module for this type with split impl blocks
+                for (impl_block, method_groups) in &type_info.large_impls {
+                    for group in method_groups.iter() {
+                        let base_name = if method_groups.len() == 1 {
+                            format!("{}_impl", type_info.name.to_lowercase())
+                        } else {
+                            format!("{}_{}", type_info.name.to_lowercase(), group.suggest_name())
+                        };
+
+                        // Ensure unique module names
+                        let module_name = if let Some(count) = module_name_counts.get(&base_name) {
+                            let unique_name = format!("{}_{}", base_name, count + 1);
+                            module_name_counts.insert(base_name.clone(), count + 1);
+                            unique_name
+                        } else {
+                            module_name_counts.insert(base_name.clone(), 0);
+                            base_name
+                        };
+
+                        let mut module = Module::new(self.available_name(module_name));
+                        module.impl_type_name = Some(type_info.name.clone());
+                        module.impl_self_ty = Some(impl_block.self_ty.clone());
+                        module.impl_generics = impl_block.generics.clone();
+                        module.method_group = Some(group.clone());
+                        module.gate = cfg_gate::merge_gates(
+                            &type_gate,
+                            &cfg_gate::gate_attributes([impl_block.attrs.as_slice()]),
+                        );
+                        modules.push(module);
+                    }
+                }
+
+                // Create main module for the type definition
+                let mut type_module = Module::new(
+                    self.available_name(format!("{}_type", type_info.name.to_lowercase())),
+                );
+                type_module.field_visibility = Some(_visibility.clone());
+                type_module.gate = type_gate;
+                type_module.widened = type_info
+                    .large_impls
+                    .iter()
+                    .flat_map(|(impl_block, groups)| {
+                        method_analyzer::shared_associated_names(impl_block, groups)
+                    })
+                    .map(|name| format!("{}::{}", type_info.name, name))
+                    .collect();
+                type_module.types.push(TypeInfo {
+                    name: type_info.name.clone(),
+                    item: type_info.item.clone(),
+                    impls: type_info.impls.clone(),
+                    trait_impls: vec![], // Trait impls go in separate module
+                    doc_comments: type_info.doc_comments.clone(),
+                    large_impls: vec![],
+                });
+                modules.push(type_module);
+            }
+        }
+
+        // Process regular types
+        let mut current_module = Module::new(self.available_name("types".to_string()));
+        let mut current_lines = 0;
+
+        let regular_types: Vec<_> = self
+            .types_in_source_order()
+            .into_iter()
+            .filter(|t| t.large_impls.is_empty())
+            .collect();
+
+        for type_info in regular_types {
+            let type_lines = type_info.estimate_lines();
+
+            if current_lines + type_lines > max_lines && !current_module.types.is_empty() {
+                modules.push(current_module);
+                current_module =
+                    Module::new(self.available_name(format!("types_{}", modules.len() + 1)));
+                current_lines = 0;
+            }
+
+            current_module.types.push(type_info.clone());
+            current_lines += type_lines;
+        }
+
+        if !current_module.types.is_empty() {
+            modules.push(current_module);
+        }
+
+        // Extension impls get one module per target type
+        for (type_name, impls) in &self.extension_impls {
+            if let Some(module_name) = self.extension_module_name(type_name) {
+                let mut extension_module = Module::new(self.available_name(module_name));
+                extension_module.extension_type = Some(type_name.clone());
+                extension_module.standalone_items = impls.clone();
+                modules.push(extension_module);
+            }
+        }
+
+        // Deprecated items get a module of their own, still re-exported
+        if let Some(deprecated_name) = &self.deprecated_module {
+            if !self.deprecated_types.is_empty() || !self.deprecated_items.is_empty() {
+                let mut module = Module::new(self.available_name(deprecated_name.clone()));
+                module.deprecated = true;
+                module.types = self.deprecated_types.clone();
+                module.standalone_items = self.deprecated_items.clone();
+                for type_info in &self.deprecated_types {
+                    module
+                        .standalone_items
+                        .extend(type_info.trait_impls.iter().map(|t| t.impl_item.clone()));
+                }
+                modules.push(module);
+            }
+        }
+
+        // Extern blocks move whole to the FFI module
+        let (foreign_blocks, mut standalone_items): (Vec<Item>, Vec<Item>) = self
+            .standalone_items
+            .iter()
+            .cloned()
+            .partition(|item| matches!(item, Item::ForeignMod(_)));
+
+        // Internal items leave the standalone items before they are placed
+        let mut internal_module = None;
+        if let Some(internal_name) = &self.internal_module {
+            let usage: Vec<BTreeSet<String>> = modules.iter().map(Module::called_names).collect();
+            let private_names: BTreeSet<String> = standalone_items
+                .iter()
+                .filter(|item| matches!(item, Item::Fn(f) if matches!(f.vis, syn::Visibility::Inherited)))
+                .filter_map(import_analyzer::declared_ident)
+                .collect();
+            let (public, internal) = internal_items::partition_internal(standalone_items, &usage);
+            standalone_items = public;
+            if !internal.is_empty() {
+                let mut module = Module::new(self.available_name(internal_name.clone()));
+                module.widened = internal
+                    .iter()
+                    .filter_map(import_analyzer::declared_ident)
+                    .filter(|name| private_names.contains(name))
+                    .collect();
+                module.internal = true;
+                module.standalone_items = internal;
+                internal_module = Some(module);
+            }
+        }
+
+        // Private helpers of trait impls move along or are imported
+        let helper_imports = Self::place_trait_helpers(&mut modules, &mut standalone_items);
+
+        // Add standalone items to a separate module
+        if !standalone_items.is_empty() {
+            let mut standalone_module = Module::new(self.available_name("functions".to_string()));
+            let imported: BTreeSet<String> = helper_imports.values().flatten().cloned().collect();
+            for item in &mut standalone_items {
+                if import_analyzer::declared_ident(item)
+                    .is_some_and(|name| imported.contains(&name))
+                {
+                    rebalance::widen_private(item);
+                }
+            }
+            standalone_module.widened = imported.into_iter().collect();
+            standalone_module.standalone_items = standalone_items;
+            for (index, names) in &helper_imports {
+                if let Some(statement) =
+                    internal_items::internal_use_statement(&standalone_module.name, names, names)
+                {
+                    modules[*index].extra_uses.push(statement);
+                }
+            }
+            modules.push(standalone_module);
+        }
+
+        // Callers import internal items explicitly, since they are not re-exported
+        if let Some(internal) = internal_module {
+            let internal_names: BTreeSet<String> = internal
+                .standalone_items
+                .iter()
+                .filter_map(import_analyzer::declared_ident)
+                .collect();
+            for module in &mut modules {
+                if let Some(statement) = internal_items::internal_use_statement(
+                    &internal.name,
+                    &internal_names,
+                    &module.called_names(),
+                ) {
+                    module.extra_uses.push(statement);
+                }
+            }
+            modules.push(internal);
+        }
+
+        // Users of foreign items import them from the FFI module
+        if !foreign_blocks.is_empty() {
+            let mut ffi_module = Module::new(self.available_name(self.ffi_module.clone()));
+            let foreign_names: BTreeSet<String> =
+                foreign_blocks.iter().flat_map(ffi::foreign_names).collect();
+            let mut imported = BTreeSet::new();
+            for module in &mut modules {
+                let mut used = module.called_names();
+                used.extend(module.referenced_names().referenced);
+                if let Some(statement) =
+                    internal_items::internal_use_statement(&ffi_module.name, &foreign_names, &used)
+                {
+                    module.extra_uses.push(statement);
+                    imported.extend(foreign_names.intersection(&used).cloned());
+                }
+            }
+            ffi_module.ffi = true;
+            ffi_module.standalone_items = foreign_blocks;
+            for block in &mut ffi_module.standalone_items {
+                let widened = ffi::widen_foreign_items(block, &imported);
+                ffi_module.widened.extend(widened);
+            }
+            modules.push(ffi_module);
+        }
+
+        // The deprecated module and its former neighbours import each other
+        if self.deprecated_module.is_some() {
+            Self::link_deprecated_module(&mut modules);
+        }
+
+        // Trait and split impl modules import their type from where it ended up
+        let type_modules: HashMap<String, String> = modules
+            .iter()
+            .flat_map(|m| m.types.iter().map(|t| (t.name.clone(), m.name.clone())))
+            .collect();
+        for module in &mut modules {
+            let type_name = module
+                .type_name_for_traits
+                .as_ref()
+                .or(module.impl_type_name.as_ref());
+            module.type_module = type_name.and_then(|name| type_modules.get(name).cloned());
+            for (name, type_module) in &mut module.packed_types {
+                *type_module = type_modules.get(name).cloned();
+            }
+        }
+
+        // A private type has to be visible to the modules importing it
+        let imported: BTreeSet<String> = modules
+            .iter()
+            .filter(|m| m.type_module.is_some())
+            .filter_map(|m| m.type_name_for_traits.clone().or(m.impl_type_name.clone()))
+            .chain(
+                modules
+                    .iter()
+                    .flat_map(|m| m.packed_types.iter().map(|(name, _)| name.clone())),
+            )
+            .collect();
+        for module in &mut modules {
+            for type_info in &mut module.types {
+                if imported.contains(&type_info.name)
+                    && rebalance::widen_private(&mut type_info.item)
+                {
+                    module.widened.push(type_info.name.clone());
+                }
+            }
+        }
+
+        modules
+    }
+
+    /// Keeps the private helpers trait impls call in scope
+    ///
+    /// A private function, constant or static that only one trait module
+    /// uses (directly, or through helpers already moved there) moves into
+    /// that module. Helpers that other code uses too stay with the
+    /// standalone items.
+    ///
+    /// # Returns
+    ///
+    /// The helpers each trait module has to import, by module index
+    fn place_trait_helpers(
+        modules: &mut [Module],
+        standalone_items: &mut Vec<Item>,
+    ) -> BTreeMap<usize, BTreeSet<String>> {
+        let trait_modules: Vec<usize> = modules
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.type_name_for_traits.is_some())
+            .map(|(index, _)| index)
+            .collect();
+        loop {
+            let usage: Vec<BTreeSet<String>> = modules.iter().map(Module::called_names).collect();
+            let standalone_usage: Vec<BTreeSet<String>> = standalone_items
+                .iter()
+                .map(internal_items::called_names)
+                .collect();
+            let movable = trait_modules.iter().find_map(|&module| {
+                standalone_items
+                    .iter()
+                    .enumerate()
+                    .find_map(|(index, item)| {
+                        let name = import_analyzer::declared_ident(item)?;
+                        let used_elsewhere = usage
+                            .iter()
+                            .enumerate()
+                            .any(|(other, used)| other != module && used.contains(&name))
+                            || standalone_usage
+                                .iter()
+                                .enumerate()
+                                .any(|(other, used)| other != index && used.contains(&name));
+                        (internal_items::is_private_helper(item)
+                            && usage[module].contains(&name)
+                            && !used_elsewhere)
+                            .then_some((module, index))
+                    })
+            });
+            let Some((module, index)) = movable else {
+                break;
+            };
+            let helper = standalone_items.remove(index);
+            modules[module].standalone_items.push(helper);
+        }
+
+        let helpers: BTreeSet<String> = standalone_items
+            .iter()
+            .filter(|item| internal_items::is_private_helper(item))
+            .filter_map(import_analyzer::declared_ident)
+            .collect();
+        trait_modules
+            .into_iter()
+            .filter_map(|index| {
+                let used: BTreeSet<String> = modules[index]
+                    .called_names()
+                    .intersection(&helpers)
+                    .cloned()
+                    .collect();
+                (!used.is_empty()).then_some((index, used))
+            })
+            .collect()
+    }
+
+    /// Adds the `use` statements between the deprecated module and the
+    /// modules whose code it was taken from
+    fn link_deprecated_module(modules: &mut [Module]) {
+        let Some(deprecated_index) = modules.iter().position(|m| m.deprecated) else {
+            return;
+        };
+        let layout: BTreeMap<String, String> = modules
+            .iter()
+            .flat_map(|module| {
+                module
+                    .reexportable_items()
+                    .iter()
+                    .filter_map(import_analyzer::declared_ident)
+                    .map(|name| (name, module.name.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let deprecated_name = modules[deprecated_index].name.clone();
+
+        for (index, module) in modules.iter_mut().enumerate() {
+            let mut used: BTreeSet<String> = module
+                .referenced_names()
+                .external_names()
+                .into_iter()
+                .collect();
+            used.extend(module.called_names());
+
+            if index == deprecated_index {
+                let statements =
+                    deprecated_items::sibling_use_statements(&layout, &used, &deprecated_name);
+                module.extra_uses.extend(statements);
+            } else {
+                let deprecated_layout: BTreeMap<String, String> = layout
+                    .iter()
+                    .filter(|(_, m)| **m == deprecated_name)
+                    .map(|(name, m)| (name.clone(), m.clone()))
+                    .collect();
+                for statement in deprecated_items::sibling_use_statements(
+                    &deprecated_layout,
+                    &used,
+                    &module.name,
+                ) {
+                    module
+                        .extra_uses
+                        .push(format!("#[allow(deprecated)]\n{}", statement));
+                }
+            }
+        }
+    }
+}
+
+/// Represents a generated module that will be written to a file
+///
+/// A module contains either:
+/// - Type definitions with their impl blocks
+/// - Split impl block methods for a specific type
+/// - Trait implementations for a type
+/// - Standalone items (functions, constants, etc.)
+#[derive(Clone)]
+struct Module {
+    /// Name of the module (used for the filename)
+    name: String,
+
+    /// Types defined in this module
+    types: Vec<TypeInfo>,
+
+    /// Standalone items (functions, constants, etc.)
+    standalone_items: Vec<Item>,
+
+    /// Type name for impl block splitting
+    ///
+    /// When this module contains split impl block methods, this field
+    /// contains the name of the type being implemented.
+    impl_type_name: Option<String>,
+
+    /// Self type for impl block
+    ///
+    /// The actual `Self` type used in the impl block, needed for generating
+    /// the impl statement.
+    impl_self_ty: Option<Box<syn::Type>>,
+
+    /// Generics of the impl block, including its where-clause
+    ///
+    /// Copied from the original impl so split modules keep their type and
+    /// const parameters (`impl<T, const N: usize> Buffer<T, N>`).
+    impl_generics: syn::Generics,
+
+    /// Method group for split impl blocks
+    ///
+    /// When this module contains split impl block methods, this field
+    /// contains the group of methods to include.
+    method_group: Option<MethodGroup>,
+
+    /// Recommended field visibility for types in this module
+    ///
+    /// Determined by the scope analyzer based on how the type's impl blocks
+    /// are organized.
+    field_visibility: Option<scope_analyzer::FieldVisibility>,
+
+    /// Type name for trait implementations module
+    ///
+    /// When this module contains trait implementations, this field
+    /// contains the name of the type.
+    type_name_for_traits: Option<String>,
+
+    /// Trait implementations for this module
+    trait_impls: Vec<TraitImplInfo>,
+
+    /// Target type when this module holds impls of a type defined elsewhere
+    extension_type: Option<String>,
+
+    /// Whether this is the internal module (declared `#[doc(hidden)]`, not re-exported)
+    internal: bool,
+
+    /// Additional `use` statements placed after the module docs
+    extra_uses: Vec<String>,
+
+    /// Whether to list the module's contents in its `//!` docs
+    doc_index: bool,
+
+    /// Whether this is the module collecting `#[deprecated]` items
+    deprecated: bool,
+
+    /// Whether this is the module holding the extern blocks
+    ffi: bool,
+
+    /// Whether this module holds a type's formatting-trait impls, which
+    /// share one `use std::fmt::{...};`
+    fmt_impls: bool,
+
+    /// `#[cfg]` and doc(cfg) attributes of the feature-gated trait impls
+    /// this module holds, repeated on its declaration
+    gate: Vec<syn::Attribute>,
+
+    /// Private items made `pub(super)` so sibling modules can use them
+    widened: Vec<String>,
+
+    /// Module defining the type of a trait or split impl module
+    type_module: Option<String>,
+
+    /// Types whose impls of one trait this packed module holds, with the
+    /// module defining each
+    packed_types: Vec<(String, Option<String>)>,
+
+    /// Stem of the file the module is written to (differs from `name` for
+    /// non-portable names)
+    file_stem: String,
+
+    /// Subdirectory of the output directory the module is written to
+    directory: Option<String>,
+
+    /// Original source text of the input items, for copying unmodified
+    /// items verbatim
+    verbatim: Option<Arc<verbatim::VerbatimSource>>,
+
+    /// Order of the items in the generated file
+    item_order: config::ItemOrder,
+
+    /// Order of the methods of a split impl
+    impl_item_order: config::ItemOrder,
+
+    /// Position of each top-level name in the input, for source order
+    source_positions: Arc<HashMap<String, usize>>,
+}
+
+impl Module {
+    /// Creates a new empty module with the given name
+    fn new(name: String) -> Self {
+        Self {
+            file_stem: name.clone(),
+            directory: None,
+            verbatim: None,
+            name,
+            types: Vec::new(),
+            standalone_items: Vec::new(),
+            impl_type_name: None,
+            impl_self_ty: None,
+            impl_generics: syn::Generics::default(),
+            method_group: None,
+            field_visibility: None,
+            type_name_for_traits: None,
+            trait_impls: Vec::new(),
+            extension_type: None,
+            internal: false,
+            extra_uses: Vec::new(),
+            doc_index: false,
+            deprecated: false,
+            ffi: false,
+            fmt_impls: false,
+            gate: Vec::new(),
+            widened: Vec::new(),
+            type_module: None,
+            packed_types: Vec::new(),
+            item_order: config::ItemOrder::Source,
+            impl_item_order: config::ItemOrder::Source,
+            source_positions: Arc::default(),
+        }
+    }
+
+    /// Path of the module's file, relative to the output directory
+    fn file_name(&self) -> String {
+        match &self.directory {
+            Some(directory) => format!("{}/{}.rs", directory, self.file_stem),
+            None => format!("{}.rs", self.file_stem),
+        }
+    }
+
+    /// Type this module belongs to, if any
+    fn owner_type(&self) -> Option<&str> {
+        if self.internal || self.deprecated || !self.packed_types.is_empty() {
+            return None;
+        }
+        self.impl_type_name
+            .as_deref()
+            .or(self.type_name_for_traits.as_deref())
+            .or(self.extension_type.as_deref())
+            .or(match self.types.as_slice() {
+                [type_info] => Some(type_info.name.as_str()),
+                _ => None,
+            })
+    }
+
+    /// Collects the names referenced by everything this module will contain
+    fn referenced_names(&self) -> ReferencedNames {
+        let mut names = ReferencedNames::new();
+        for type_info in &self.types {
+            names.add_item(&type_info.item);
+            for impl_item in &type_info.impls {
+                names.add_item(impl_item);
+            }
+        }
+        for item in &self.standalone_items {
+            names.add_item(item);
+        }
+        for trait_impl in &self.trait_impls {
+            names.add_item(&trait_impl.impl_item);
+        }
+        if let Some(method_group) = &self.method_group {
+            names.visit_generics(&self.impl_generics);
+            for item in &method_group.associated {
+                names.visit_impl_item(item);
+            }
+            for method in &method_group.methods {
+                names.visit_impl_item_fn(&method.item);
+            }
+        }
+        names
+    }
+
+    /// Names this module's code references that its generated source neither
+    /// defines nor imports
+    fn unresolved_names(&self, imports: &ImportAnalyzer) -> Vec<String> {
+        let content =
+            syn::parse_file(&self.generate_content(imports)).unwrap_or_else(|_| syn::File {
+                shebang: None,
+                attrs: Vec::new(),
+                items: Vec::new(),
+            });
+        imports.unresolved_names(&self.referenced_names(), &content)
+    }
+
+    /// Names in the generic bounds and where-clauses of this module's code
+    ///
+    /// Empty for modules holding the original `use` items, which already
+    /// import them.
+    fn bound_names(&self) -> HashSet<String> {
+        if self
+            .standalone_items
+            .iter()
+            .any(|item| matches!(item, Item::Use(_)))
+        {
+            return HashSet::new();
+        }
+        let mut bounds = BoundNames::default();
+        self.visit_items(&mut bounds);
+        bounds.names
+    }
+
+    /// Names the code of an impl or trait impl module needs imported
+    ///
+    /// Types in signatures and bodies (including the traits and bindings of
+    /// `impl Iterator<Item = Foo>` and `impl Into<String>`), the implemented
+    /// traits, and the names in generic bounds.
+    fn impl_type_names(&self) -> HashSet<String> {
+        let mut visitor = import_analyzer::TypeVisitor::new();
+        self.visit_items(&mut visitor);
+        let mut names = visitor.types_used;
+        for trait_impl in &self.trait_impls {
+            if let Item::Impl(ItemImpl {
+                trait_: Some((_, path, _)),
+                ..
+            }) = &trait_impl.impl_item
+            {
+                if let Some(segment) = path.segments.last() {
+                    names.insert(segment.ident.to_string());
+                }
+            }
+        }
+        names.extend(self.bound_names());
+        names
+    }
+
+    /// The customary `use std::fmt::{self, Display, Formatter};` of a module
+    /// of formatting-trait impls
+    ///
+    /// Imports `fmt` itself when the code uses `fmt::` paths, and each
+    /// formatting trait and `Formatter` the code names unqualified.
+    fn fmt_use_statement(&self) -> Option<String> {
+        let mut roots = existing_module::PathRoots::default();
+        self.visit_items(&mut roots);
+        let referenced = self.referenced_names().referenced;
+        let mut names: Vec<&str> = Vec::new();
+        if roots.roots.contains("fmt") {
+            names.push("self");
+        }
+        names.extend(
+            trait_origin::FMT_TRAITS
+                .iter()
+                .chain(&["Formatter"])
+                .filter(|name| referenced.contains(**name)),
+        );
+        match names.as_slice() {
+            [] => None,
+            ["self"] => Some("use std::fmt;".to_string()),
+            [name] => Some(format!("use std::fmt::{};", name)),
+            _ => Some(format!("use std::fmt::{{{}}};", names.join(", "))),
+        }
+    }
+
+    /// Whether this module holds `type_name` or impls of it
+    fn holds_impls_of(&self, type_name: &str) -> bool {
+        self.type_name_for_traits.as_deref() == Some(type_name)
+            || self.packed_types.iter().any(|(name, _)| name == type_name)
+            || self.impl_type_name.as_deref() == Some(type_name)
+            || self.types.iter().any(|t| t.name == type_name)
+    }
+
+    /// Single-segment value paths (function calls, constants) used by this module
+    fn called_names(&self) -> BTreeSet<String> {
+        let mut called = internal_items::CalledNames::default();
+        self.visit_items(&mut called);
+        called.names
+    }
+
+    /// Labels of everything placed in this module, for migration plans
+    fn item_labels(&self) -> Vec<String> {
+        let mut labels = Vec::new();
+        for type_info in &self.types {
+            labels.extend(migration::item_labels(&type_info.item));
+            for impl_item in &type_info.impls {
+                labels.extend(migration::item_labels(impl_item));
+            }
+        }
+        for trait_impl in &self.trait_impls {
+            labels.extend(migration::item_labels(&trait_impl.impl_item));
+        }
+        if let (Some(self_ty), Some(method_group)) = (&self.impl_self_ty, &self.method_group) {
+            let self_ty = self_ty.to_token_stream().to_string().replace(' ', "");
+            for item in &method_group.associated {
+                let name = match item {
+                    syn::ImplItem::Const(c) => &c.ident,
+                    syn::ImplItem::Type(t) => &t.ident,
+                    _ => continue,
+                };
+                labels.push(migration::method_label(&self_ty, &name.to_string()));
+            }
+            for method in &method_group.methods {
+                labels.push(migration::method_label(&self_ty, &method.name));
+            }
+        }
+        for item in &self.standalone_items {
+            labels.extend(migration::item_labels(item));
+        }
+        labels
+    }
+
+    /// Entries of the contents index in the module docs
+    fn index_entries(&self) -> Vec<doc_index::IndexEntry> {
+        let mut entries = Vec::new();
+        for type_info in &self.types {
+            entries.extend(doc_index::item_entries(std::slice::from_ref(
+                &type_info.item,
+            )));
+            entries.extend(doc_index::item_entries(&type_info.impls));
+        }
+        if let (Some(self_ty), Some(method_group)) = (&self.impl_self_ty, &self.method_group) {
+            let self_ty = self_ty.to_token_stream().to_string().replace(' ', "");
+            let methods: Vec<&syn::ImplItemFn> =
+                method_group.methods.iter().map(|m| &m.item).collect();
+            entries.extend(doc_index::method_entries(&self_ty, &methods));
+        }
+        // Trait modules already list their traits in the header
+        entries.extend(doc_index::item_entries(&self.standalone_items));
+        entries
+    }
+
+    /// Whether this module holds the type `name` or some of its impls
+    fn holds_type(&self, name: &str) -> bool {
+        self.types.iter().any(|t| t.name == name)
+            || self.impl_type_name.as_deref() == Some(name)
+            || self.type_name_for_traits.as_deref() == Some(name)
+            || self.packed_types.iter().any(|(t, _)| t == name)
+    }
+
+    /// Visibility changes SplitRS makes to this module's items
+    fn visibility_changes(&self) -> Vec<report::VisibilityChange> {
+        let mut changes = Vec::new();
+        let field_visibility = match &self.field_visibility {
+            Some(scope_analyzer::FieldVisibility::PubSuper) => Some("pub(super)"),
+            Some(scope_analyzer::FieldVisibility::PubCrate) => Some("pub(crate)"),
+            Some(scope_analyzer::FieldVisibility::Pub) => Some("pub"),
+            _ => None,
+        };
+        if let Some(to) = field_visibility {
+            for type_info in &self.types {
+                let has_private_fields = match &type_info.item {
+                    Item::Struct(s) => s
+                        .fields
+                        .iter()
+                        .any(|f| matches!(f.vis, syn::Visibility::Inherited)),
+                    _ => false,
+                };
+                if has_private_fields {
+                    changes.push(report::VisibilityChange {
+                        module: self.name.clone(),
+                        item: format!("{} fields", type_info.name),
+                        from: "private".to_string(),
+                        to: to.to_string(),
+                    });
+                }
+            }
+        }
+        for name in &self.widened {
+            changes.push(report::VisibilityChange {
+                module: self.name.clone(),
+                item: name.clone(),
+                from: "private".to_string(),
+                to: "pub(super)".to_string(),
+            });
+        }
+        changes
+    }
+
+    /// Runs a visitor over everything this module will contain
+    fn visit_items<'a>(&'a self, visitor: &mut impl Visit<'a>) {
+        for type_info in &self.types {
+            visitor.visit_item(&type_info.item);
+            for impl_item in &type_info.impls {
+                visitor.visit_item(impl_item);
+            }
+        }
+        for item in &self.standalone_items {
+            visitor.visit_item(item);
+        }
+        for trait_impl in &self.trait_impls {
+            visitor.visit_item(&trait_impl.impl_item);
+        }
+        if let Some(method_group) = &self.method_group {
+            visitor.visit_generics(&self.impl_generics);
+            for item in &method_group.associated {
+                visitor.visit_impl_item(item);
+            }
+            for method in &method_group.methods {
+                visitor.visit_impl_item_fn(&method.item);
+            }
+        }
+    }
+
+    /// Runs a mutating visitor over everything this module will contain
+    fn visit_items_mut(&mut self, visitor: &mut impl VisitMut) {
+        for type_info in &mut self.types {
+            visitor.visit_item_mut(&mut type_info.item);
+            for impl_item in &mut type_info.impls {
+                visitor.visit_item_mut(impl_item);
+            }
+        }
+        for item in &mut self.standalone_items {
+            visitor.visit_item_mut(item);
+        }
+        for trait_impl in &mut self.trait_impls {
+            visitor.visit_item_mut(&mut trait_impl.impl_item);
+        }
+        if let Some(method_group) = &mut self.method_group {
+            visitor.visit_generics_mut(&mut self.impl_generics);
+            for item in &mut method_group.associated {
+                visitor.visit_impl_item_mut(item);
+            }
+            for method in &mut method_group.methods {
+                visitor.visit_impl_item_fn_mut(&mut method.item);
+            }
+        }
+    }
+
+    /// Top-level items whose names `mod.rs` may re-export from this module
+    fn reexportable_items(&self) -> Vec<Item> {
+        let mut items: Vec<Item> = self.types.iter().map(|t| t.item.clone()).collect();
+        items.extend(self.standalone_items.iter().cloned());
+        items
+    }
+
+    /// Generates the Rust source code content for this module
+    ///
+    /// # Arguments
+    ///
+    /// * `imports` - Import model of the original file, shared by all modules
+    ///
+    /// # Returns
+    ///
+    /// A formatted Rust source code string ready to be written to a file.
+    fn generate_content(&self, imports: &ImportAnalyzer) -> String {
+        let mut body = self.generate_body(imports);
+        if self.doc_index {
+            body = doc_index::insert_index(&body, &doc_index::render_index(&self.index_entries()));
+        }
+        insert_use_statements(&body, &self.extra_uses)
+    }
+
+    /// Generates the module source without the extra `use` statements
+    fn generate_body(&self, imports: &ImportAnalyzer) -> String {
+        let mut content = String::new();
+
+        // Enhanced module documentation
+        if let (false, Some(trait_impl)) = (self.packed_types.is_empty(), self.trait_impls.first())
+        {
+            let trait_name = trait_impl.trait_ident();
+            content.push_str(&format!("//! # {} Implementations\n//!\n", trait_name));
+            content.push_str(&format!(
+                "//! This module contains `{}` implementations for small types.\n//!\n",
+                trait_name
+            ));
+            content.push_str("//! ## Types\n//!\n");
+            for (type_name, _) in &self.packed_types {
+                content.push_str(&format!("//! - `{}`\n", type_name));
+            }
+            content.push_str("//!\n");
+            content.push_str(
+                "//! 🤖 Generated with [SplitRS](https://github.com/cool-japan/splitrs)\n\n",
+            );
+        } else if let Some(type_name) = &self.type_name_for_traits {
+            content.push_str(&format!(
+                "//! # {} - Trait Implementations\n//!\n",
+                type_name
+            ));
+            content.push_str(&format!(
+                "//! This module contains trait implementations for `{}`.\n//!\n",
+                type_name
+            ));
+            content.push_str("//! ## Implemented Traits\n//!\n");
+            for trait_impl in &self.trait_impls {
+                content.push_str(&format!("//! - `{}`\n", trait_impl.trait_name));
+            }
+            content.push_str("//!\n");
+            content.push_str(
+                "//! 🤖 Generated with [SplitRS](https://github.com/cool-japan/splitrs)\n\n",
+            );
+        } else if let Some(type_name) = &self.impl_type_name {
+            if let Some(method_group) = &self.method_group {
+                content.push_str(&format!(
+                    "//! # {} - {} Methods\n//!\n",
+                    type_name,
+                    method_group.suggest_name()
+                ));
+                content.push_str(&format!(
+                    "//! This module contains method implementations for `{}`.\n//!\n",
+                    type_name
+                ));
+                content.push_str(
+                    "//! 🤖 Generated with [SplitRS](https://github.com/cool-japan/splitrs)\n\n",
+                );
+            } else {
+                content.push_str("//! Auto-generated module\n\n");
+            }
+        } else {
+            content.push_str("//! Auto-generated module\n//!\n");
+            content.push_str(
+                "//! 🤖 Generated with [SplitRS](https://github.com/cool-japan/splitrs)\n\n",
+            );
+        }
+
+        // Extract and preserve module-level attributes and comments from original (simplified)
+
+        // For trait implementations module, generate appropriate imports
+        if let Some(type_name) = &self.type_name_for_traits {
+            // Import the type from the module it was placed in
+            if self.packed_types.is_empty() {
+                let type_module = self.type_module.as_deref().unwrap_or("types");
+                content.push_str(&format!("use super::{}::{};\n", type_module, type_name));
+            }
+            let mut packed: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+            for (name, type_module) in &self.packed_types {
+                packed
+                    .entry(type_module.as_deref().unwrap_or("types"))
+                    .or_default()
+                    .push(name);
+            }
+            for (type_module, names) in packed {
+                match names.as_slice() {
+                    [name] => content.push_str(&format!("use super::{}::{};\n", type_module, name)),
+                    _ => content.push_str(&format!(
+                        "use super::{}::{{{}}};\n",
+                        type_module,
+                        names.join(", ")
+                    )),
+                }
+            }
+            let mut names = self.impl_type_names();
+            if self.fmt_impls {
+                if let Some(statement) = self.fmt_use_statement() {
+                    content.push_str(&statement);
+                    content.push('\n');
+                }
+                names.retain(|name| {
+                    name != "Formatter" && !trait_origin::FMT_TRAITS.contains(&name.as_str())
+                });
+            }
+            for statement in imports.external_use_statements(&names) {
+                content.push_str(&statement);
+                content.push('\n');
+            }
+            content.push('\n');
+
+            // Generate trait implementation blocks
+            for trait_impl in &self.trait_impls {
+                let formatted = self.render_items(std::slice::from_ref(&trait_impl.impl_item));
+                content.push_str(&formatted);
+                content.push('\n');
+            }
+
+            // Private helpers only these impls use
+            if !self.standalone_items.is_empty() {
+                content.push_str(&self.render_items(&self.standalone_items));
+                content.push('\n');
+            }
+            return content;
+        }
+
+        // For impl block modules, generate context-aware imports
+        if let Some(type_name) = &self.impl_type_name {
+            // Import std collections (always useful for impl blocks)
+            content.push_str("use std::collections::{HashMap, HashSet};\n");
+
+            // Import the type from its type module
+            // Type modules are named as {type_name}_type
+            let type_module_name = self
+                .type_module
+                .clone()
+                .unwrap_or_else(|| format!("{}_type", type_name.to_lowercase()));
+            content.push_str(&format!(
+                "use super::{}::{};\n",
+                type_module_name, type_name
+            ));
+            for statement in imports.external_use_statements(&self.impl_type_names()) {
+                content.push_str(&statement);
+                content.push('\n');
+            }
+            content.push('\n');
+        }
+
+        // Generate impl block from method group if this is a split impl module
+        if let Some(method_group) = &self.method_group {
+            if let Some(type_name) = &self.impl_type_name {
+                // Build a complete impl block using syn
+                let mut impl_items = method_group.associated.clone();
+                for method in &method_group.methods {
+                    impl_items.push(syn::ImplItem::Fn(method.item.clone()));
+                }
+                item_order::order_impl_items(&mut impl_items, self.impl_item_order);
+
+                let impl_block = syn::ItemImpl {
+                    attrs: Vec::new(),
+                    defaultness: None,
+                    unsafety: None,
+                    impl_token: Default::default(),
+                    generics: self.impl_generics.clone(),
+                    trait_: None,
+                    self_ty: self.impl_self_ty.clone().unwrap_or_else(|| {
+                        Box::new(syn::parse_str::<syn::Type>(type_name).unwrap())
+                    }),
+                    brace_token: Default::default(),
+                    items: impl_items,
+                };
+
+                // Methods sliced from the input, or prettyplease to format
+                if let Some(preserved) = self
+                    .verbatim
+                    .as_ref()
+                    .and_then(|verbatim| verbatim.render_impl(&impl_block))
+                {
+                    content.push_str(&preserved);
+                    return content;
+                }
+                let formatted = prettyplease::unparse(&syn::File {
+                    shebang: None,
+                    attrs: Vec::new(),
+                    items: vec![syn::Item::Impl(impl_block)],
+                });
+
+                content.push_str(&formatted);
+                return content;
+            }
+        }
+
+        // Generate content for regular type modules
+
+        // First, collect all types used in this module
+        let mut types_used = std::collections::HashSet::new();
+        for type_info in &self.types {
+            // Extract types from struct/enum fields
+            if let Item::Struct(s) = &type_info.item {
+                for field in &s.fields {
+                    extract_type_names(&field.ty, &mut types_used);
+                }
+            } else if let Item::Enum(e) = &type_info.item {
+                for variant in &e.variants {
+                    for field in &variant.fields {
+                        extract_type_names(&field.ty, &mut types_used);
+                    }
+                }
+            }
+        }
+        if self.extension_type.is_some() || self.deprecated {
+            // The extended type and everything the impls use come from elsewhere
+            types_used.extend(self.referenced_names().external_names());
+        }
+        types_used.extend(self.bound_names());
+
+        // Generate imports for types used
+        if !types_used.is_empty() {
+            let needs_collections = types_used.iter().any(|t| {
+                t == "HashMap"
+                    || t == "HashSet"
+                    || t == "BTreeMap"
+                    || t == "BTreeSet"
+                    || t == "VecDeque"
+            });
+
+            if needs_collections {
+                let collection_types: Vec<_> = types_used
+                    .iter()
+                    .filter(|t| {
+                        ["HashMap", "HashSet", "BTreeMap", "BTreeSet", "VecDeque"]
+                            .contains(&t.as_str())
+                    })
+                    .cloned()
+                    .collect();
+                if !collection_types.is_empty() {
+                    content.push_str(&format!(
+                        "use std::collections::{{{}}};\n",
+                        collection_types.join(", ")
+                    ));
+                }
+            }
+            for statement in imports.external_use_statements(&types_used) {
+                content.push_str(&statement);
+                content.push('\n');
+            }
+            content.push('\n');
+        }
+        if self.ffi {
+            // Foreign signatures use lowercase C types (`c_int`, `size_t`)
+            let names: BTreeSet<String> = self.impl_type_names().into_iter().collect();
+            let statements = imports.module_use_statements(&names);
+            for statement in &statements {
+                content.push_str(statement);
+                content.push('\n');
+            }
+            if !statements.is_empty() {
+                content.push('\n');
+            }
+        }
+
+        let mut units = Vec::new();
+
+        for type_info in &self.types {
+            // Apply field visibility based on self.field_visibility
+            let item = if let Some(ref vis) = self.field_visibility {
+                apply_field_visibility(type_info.item.clone(), vis)
+            } else {
+                type_info.item.clone()
+            };
+            let mut unit = vec![item];
+            unit.extend(type_info.impls.clone());
+            units.push(unit);
+        }
+
+        units.extend(self.standalone_items.iter().map(|item| vec![item.clone()]));
+        let items = item_order::order_units(units, self.item_order, &self.source_positions);
+
+        if !items.is_empty() {
+            content.push_str(&self.render_items(&items));
+        }
+
+        content
+    }
+
+    /// Formats items, copying those SplitRS did not modify verbatim when the
+    /// original source is available
+    fn render_items(&self, items: &[Item]) -> String {
+        match &self.verbatim {
+            Some(verbatim) => verbatim.render(items),
+            None => prettyplease::unparse(&syn::File {
+                shebang: None,
+                attrs: Vec::new(),
+                items: items.to_vec(),
+            }),
+        }
+    }
+}
+
+impl TypeInfo {
+    /// Estimates the total number of lines for this type and its impl blocks
+    ///
+    /// This is a rough estimate based on the token stream representation,
+    /// used for determining module size constraints.
+    fn estimate_lines(&self) -> usize {
+        let item_lines = self.item.to_token_stream().to_string().lines().count();
+        let impl_lines: usize = self
+            .impls
+            .iter()
+            .map(|i| i.to_token_stream().to_string().lines().count())
+            .sum();
+        item_lines + impl_lines
+    }
+}
+
+/// Extract type names from a syn::Type for import analysis
+///
+/// Recursively traverses a type expression to find all type names that might
+/// need to be imported. This handles:
+/// - Path types (e.g., `HashMap<K, V>`)
+/// - Generic arguments, including const arguments (`ArrayVec<T, { CAP }>`)
+/// - References, slices, arrays (and constants in their lengths), pointers,
+///   and tuples
+/// - `dyn Trait` and `impl Trait` bounds
+///
+/// # Arguments
+///
+/// * `ty` - The type to analyze
+/// * `types` - Set to collect type names into
+fn extract_type_names(ty: &syn::Type, types: &mut HashSet<String>) {
+    match ty {
+        syn::Type::Path(type_path) => {
+            if let Some(segment) = type_path.path.segments.last() {
+                let type_name = segment.ident.to_string();
+                // Add the main type
+                types.insert(type_name);
+
+                // Check for generic arguments
+                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
+                    for arg in &args.args {
+                        match arg {
+                            syn::GenericArgument::Type(inner_ty) => {
+                                extract_type_names(inner_ty, types)
+                            }
+                            syn::GenericArgument::Const(expr) => extract_const_names(expr, types),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+        syn::Type::Reference(type_ref) => {
+            extract_type_names(&type_ref.elem, types);
+        }
+        syn::Type::Slice(type_slice) => {
+            extract_type_names(&type_slice.elem, types);
+        }
+        syn::Type::Array(type_array) => {
+            extract_type_names(&type_array.elem, types);
+            extract_const_names(&type_array.len, types);
+        }
+        syn::Type::Ptr(type_ptr) => {
+            extract_type_names(&type_ptr.elem, types);
+        }
+        syn::Type::Tuple(type_tuple) => {
+            for elem in &type_tuple.elems {
+                extract_type_names(elem, types);
+            }
+        }
+        syn::Type::TraitObject(trait_object) => {
+            extract_bound_names(&trait_object.bounds, types);
+        }
+        syn::Type::ImplTrait(impl_trait) => {
+            extract_bound_names(&impl_trait.bounds, types);
+        }
+        syn::Type::Paren(type_paren) => {
+            extract_type_names(&type_paren.elem, types);
+        }
+        _ => {}
+    }
+}
+
+/// Printed tokens without the spaces `quote` puts between them
+///
+/// Spaces stay only between two words (`&'static str`, `dyn Fn`) and after
+/// commas, so `From < Vec < u8 > >` becomes `From<Vec<u8>>`.
+pub(crate) fn compact_tokens(tokens: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let chars: Vec<char> = tokens.chars().collect();
+    let mut compact = String::with_capacity(tokens.len());
+    for (index, &c) in chars.iter().enumerate() {
+        if c == ' ' {
+            let before = index.checked_sub(1).map(|i| chars[i]);
+            let after = chars.get(index + 1).copied();
+            let between_words = before.is_some_and(is_word) && after.is_some_and(is_word);
+            if !(between_words || before == Some(',')) {
+                continue;
+            }
+        }
+        compact.push(c);
+    }
+    compact
+}
+
+/// Extract the constants a const expression refers to
+///
+/// Array lengths and const generic arguments name constants (`[u8; BUF_SIZE]`,
+/// `{ LIMIT * 2 }`) that need importing like types do. `Self::` constants
+/// are skipped.
+///
+/// # Arguments
+///
+/// * `expr` - An array length or const generic argument
+/// * `types` - Set to collect the names into
+fn extract_const_names(expr: &syn::Expr, types: &mut HashSet<String>) {
+    match expr {
+        syn::Expr::Path(path) => {
+            let first = path.path.segments.first().map(|s| s.ident.to_string());
+            if first.as_deref() != Some("Self") {
+                if let Some(segment) = path.path.segments.last() {
+                    types.insert(segment.ident.to_string());
+                }
+            }
+        }
+        syn::Expr::Block(block) => {
+            for stmt in &block.block.stmts {
+                if let syn::Stmt::Expr(inner, _) = stmt {
+                    extract_const_names(inner, types);
+                }
+            }
+        }
+        syn::Expr::Binary(binary) => {
+            extract_const_names(&binary.left, types);
+            extract_const_names(&binary.right, types);
+        }
+        syn::Expr::Paren(paren) => extract_const_names(&paren.expr, types),
+        syn::Expr::Unary(unary) => extract_const_names(&unary.expr, types),
+        syn::Expr::Cast(cast) => extract_const_names(&cast.expr, types),
+        _ => {}
+    }
+}
+
+/// Extract the trait names of `dyn Trait` / `impl Trait` bounds
+///
+/// Collects the last segment of each trait bound (`Handler` in
+/// `Box<dyn Handler + Send>`) and the types in its generic arguments,
+/// including associated type bindings (`Item = Record`) and `Fn` sugar.
+///
+/// # Arguments
+///
+/// * `bounds` - The bounds of a trait object or `impl Trait` type
+/// * `types` - Set to collect type names into
+fn extract_bound_names(
+    bounds: &syn::punctuated::Punctuated<syn::TypeParamBound, syn::Token![+]>,
+    types: &mut HashSet<String>,
+) {
+    for bound in bounds {
+        let syn::TypeParamBound::Trait(trait_bound) = bound else {
+            continue;
+        };
+        let Some(segment) = trait_bound.path.segments.last() else {
+            continue;
+        };
+        types.insert(segment.ident.to_string());
+        match &segment.arguments {
+            syn::PathArguments::AngleBracketed(args) => {
+                for arg in &args.args {
+                    match arg {
+                        syn::GenericArgument::Type(inner_ty) => extract_type_names(inner_ty, types),
+                        syn::GenericArgument::AssocType(assoc) => {
+                            extract_type_names(&assoc.ty, types)
+                        }
+                        syn::GenericArgument::Const(expr) => extract_const_names(expr, types),
+                        _ => {}
+                    }
+                }
+            }
+            syn::PathArguments::Parenthesized(args) => {
+                for input in &args.inputs {
+                    extract_type_names(input, types);
+                }
+                if let syn::ReturnType::Type(_, output) = &args.output {
+                    extract_type_names(output, types);
+                }
+            }
+            syn::PathArguments::None => {}
+        }
+    }
+}
+
+/// Visitor collecting the names in generic bounds and where-clauses
+///
+/// `T: Serialize + Hash` and `where K: Into<Key>` name traits (and types)
+/// that appear nowhere else in a signature, so they need imports of their
+/// own.
+#[derive(Default)]
+struct BoundNames {
+    names: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for BoundNames {
+    fn visit_generics(&mut self, generics: &'ast syn::Generics) {
+        for param in &generics.params {
+            match param {
+                syn::GenericParam::Type(type_param) => {
+                    extract_bound_names(&type_param.bounds, &mut self.names);
+                    if let Some(default) = &type_param.default {
+                        extract_type_names(default, &mut self.names);
+                    }
+                }
+                syn::GenericParam::Const(const_param) => {
+                    extract_type_names(&const_param.ty, &mut self.names);
+                }
+                syn::GenericParam::Lifetime(_) => {}
+            }
+        }
+        for predicate in generics.where_clause.iter().flat_map(|w| &w.predicates) {
+            if let syn::WherePredicate::Type(predicate) = predicate {
+                extract_type_names(&predicate.bounded_ty, &mut self.names);
+                extract_bound_names(&predicate.bounds, &mut self.names);
+            }
+        }
+        syn::visit::visit_generics(self, generics);
+    }
+}
+
+/// Apply field visibility modifications to a struct or enum
+///
+/// When impl blocks are split into separate modules, struct fields may need
+/// to have their visibility adjusted to `pub(super)` or `pub(crate)` to allow
+/// access from those modules while maintaining encapsulation.
+///
+/// # Arguments
+///
+/// * `item` - The item to modify (should be a struct or enum)
+/// * `visibility` - The target visibility level
+///
+/// # Returns
+///
+/// The modified item with updated field visibility
+fn apply_field_visibility(item: Item, visibility: &scope_analyzer::FieldVisibility) -> Item {
+    match item {
+        Item::Struct(mut s) => {
+            // Only modify if we need pub(super) or other non-default visibility
+            match visibility {
+                scope_analyzer::FieldVisibility::PubSuper => {
+                    // Modify each field to have pub(super) visibility
+                    for field in &mut s.fields {
+                        if matches!(field.vis, syn::Visibility::Inherited) {
+                            // Field is currently private, make it pub(super)
+                            field.vis = syn::parse_quote!(pub(super));
+                        }
+                    }
+                }
+                scope_analyzer::FieldVisibility::PubCrate => {
+                    for field in &mut s.fields {
+                        if matches!(field.vis, syn::Visibility::Inherited) {
+                            field.vis = syn::parse_quote!(pub(crate));
+                        }
+                    }
+                }
+                scope_analyzer::FieldVisibility::Pub => {
+                    for field in &mut s.fields {
+                        if matches!(field.vis, syn::Visibility::Inherited) {
+                            field.vis = syn::parse_quote!(pub);
+                        }
+                    }
+                }
+                scope_analyzer::FieldVisibility::Private => {
+                    // Keep fields private (no change)
+                }
+            }
+            Item::Struct(s)
+        }
+        Item::Enum(mut e) => {
+            // Apply visibility to enum variant fields
+            match visibility {
+                scope_analyzer::FieldVisibility::PubSuper => {
+                    for variant in &mut e.variants {
+                        for field in &mut variant.fields {
+                            if matches!(field.vis, syn::Visibility::Inherited) {
+                                field.vis = syn::parse_quote!(pub(super));
+                            }
+                        }
+                    }
+                }
+                scope_analyzer::FieldVisibility::PubCrate => {
+                    for variant in &mut e.variants {
+                        for field in &mut variant.fields {
+                            if matches!(field.vis, syn::Visibility::Inherited) {
+                                field.vis = syn::parse_quote!(pub(crate));
+                            }
+                        }
+                    }
+                }
+                scope_analyzer::FieldVisibility::Pub => {
+                    for variant in &mut e.variants {
+                        for field in &mut variant.fields {
+                            if matches!(field.vis, syn::Visibility::Inherited) {
+                                field.vis = syn::parse_quote!(pub);
+                            }
+                        }
+                    }
+                }
+                scope_analyzer::FieldVisibility::Private => {
+                    // Keep fields private
+                }
+            }
+            Item::Enum(e)
+        }
+        other => other, // Return unchanged for non-struct/enum items
+    }
+}
+
+/// Generated module each top-level name was placed in
+fn module_layout(modules: &[Module]) -> HashMap<String, String> {
+    modules
+        .iter()
+        .flat_map(|module| {
+            module
+                .reexportable_items()
+                .iter()
+                .filter_map(import_analyzer::declared_ident)
+                .map(|name| (name, module.name.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Rewrites paths in the modules' items that the move would break
+///
+/// # Arguments
+///
+/// * `modules` - The generated modules
+/// * `hoisted` - Re-exports of the input moved into `mod.rs`
+/// * `reexports` - What `mod.rs` re-exports
+/// * `original_module` - Module path of the input file, if known
+/// * `new_module` - Module path of the output directory, if known
+///
+/// # Returns
+///
+/// The number of rewritten paths in each module that has any (`mod` for
+/// the hoisted re-exports)
+fn rewrite_paths(
+    modules: &mut [Module],
+    hoisted: &mut [Item],
+    reexports: &ReexportPlan,
+    original_module: Option<&str>,
+    new_module: Option<&str>,
+) -> Vec<(String, usize)> {
+    let layout = module_layout(modules);
+    let mut hidden: HashSet<String> = modules
+        .iter()
+        .filter(|m| m.internal)
+        .flat_map(|m| m.reexportable_items())
+        .filter_map(|item| import_analyzer::declared_ident(&item))
+        .collect();
+    hidden.extend(reexports.qualified.iter().map(|(_, name)| name.clone()));
+
+    let context = path_rewriter::PathContext {
+        original_module,
+        new_module,
+        layout: &layout,
+        hidden: &hidden,
+    };
+    let mut counts = Vec::new();
+    for module in modules.iter_mut() {
+        let name = module.name.clone();
+        let mut rewriter = path_rewriter::PathRewriter::new(&context, &name);
+        module.visit_items_mut(&mut rewriter);
+        if rewriter.rewritten > 0 {
+            counts.push((module.name.clone(), rewriter.rewritten));
+        }
+    }
+    let mut hoisted_count = 0;
+    for item in hoisted {
+        if let Item::Use(u) = item {
+            if context.rewrite_use_tree(&mut u.tree) {
+                hoisted_count += 1;
+            }
+        }
+    }
+    if hoisted_count > 0 {
+        counts.push(("mod".to_string(), hoisted_count));
+    }
+    counts
+}
+
+/// Rewrites relative `include_str!`, `include_bytes!`, `include!` and
+/// `#[path]` paths for the directories the modules are written to
+///
+/// # Arguments
+///
+/// * `modules` - The generated modules
+/// * `inputs` - The input files; paths are only rewritten when they all
+///   share one directory
+/// * `output_dir` - Directory the modules are written to
+/// * `counts` - Rewritten paths per module, added to
+///
+/// # Returns
+///
+/// The paths that could not be rewritten, with their module
+fn rewrite_include_paths(
+    modules: &mut [Module],
+    inputs: &[PathBuf],
+    output_dir: &Path,
+    counts: &mut Vec<(String, usize)>,
+) -> Vec<(String, String)> {
+    let mut directories = inputs
+        .iter()
+        .map(|input| existing_module::absolute(input.parent().unwrap_or(Path::new("."))));
+    let Some(original_dir) = directories.next() else {
+        return Vec::new();
+    };
+    if directories.any(|dir| dir != original_dir) {
+        return Vec::new();
+    }
+
+    let mut unresolved = Vec::new();
+    for module in modules.iter_mut() {
+        let module_dir = existing_module::absolute(
+            &output_dir.join(module.directory.as_deref().unwrap_or_default()),
+        );
+        let prefix = existing_module::relative_path(&original_dir, &module_dir);
+        let mut rewriter = include_paths::IncludePathRewriter::new(&prefix);
+        module.visit_items_mut(&mut rewriter);
+        if rewriter.rewritten > 0 {
+            match counts.iter_mut().find(|(name, _)| *name == module.name) {
+                Some((_, count)) => *count += rewriter.rewritten,
+                None => counts.push((module.name.clone(), rewriter.rewritten)),
+            }
+        }
+        unresolved.extend(
+            rewriter
+                .unresolved
+                .into_iter()
+                .map(|include| (module.name.clone(), include)),
+        );
+    }
+    unresolved
+}
+
+/// Rewrites intra-doc links in the modules' items for the new layout
+///
+/// # Arguments
+///
+/// * `modules` - The generated modules
+/// * `imports` - Import model of the original file
+fn rewrite_doc_links(modules: &mut [Module], imports: &ImportAnalyzer) {
+    let layout = module_layout(modules);
+
+    for module in modules.iter_mut() {
+        let name = module.name.clone();
+        let context = doc_links::LinkContext {
+            layout: &layout,
+            module: &name,
+            imports,
+        };
+        module.visit_items_mut(&mut context.rewriter());
+    }
+}
+
+/// Move clusters of related modules into subdirectories once there are
+/// more than `max_per_directory` of them
+fn place_in_subdirectories(modules: &mut [Module], max_per_directory: usize) {
+    let summaries: Vec<hierarchy::ModuleSummary> = modules
+        .iter()
+        .map(|module| {
+            let names = module.referenced_names();
+            hierarchy::ModuleSummary {
+                name: module.name.clone(),
+                owner: module.owner_type().map(str::to_string),
+                referenced: names
+                    .referenced
+                    .difference(&names.declared)
+                    .cloned()
+                    .collect(),
+                declared: names.declared,
+            }
+        })
+        .collect();
+    let directories = hierarchy::plan_directories(&summaries, max_per_directory);
+    for (module, directory) in modules.iter_mut().zip(directories) {
+        module.directory = directory;
+    }
+}
+
+/// Add the imports each generated module needs: names kept in `mod.rs`,
+/// the modules its paths start with and the traits of its method calls
+///
+/// # Arguments
+///
+/// * `modules` - The generated modules
+/// * `imports` - Imports of the original file
+/// * `short_paths` - How paths through an imported module are handled
+/// * `declared_children` - Child modules declared in `mod.rs`
+/// * `hoisted_names` - Names re-exported or anchored in `mod.rs`
+fn import_module_dependencies(
+    modules: &mut [Module],
+    imports: &ImportAnalyzer,
+    short_paths: ShortPaths,
+    declared_children: &BTreeSet<String>,
+    hoisted_names: &BTreeSet<String>,
+) {
+    for module in modules {
+        let mut roots = existing_module::PathRoots::default();
+        module.visit_items(&mut roots);
+        let uses = existing_module::mod_rs_uses(declared_children, &roots.roots);
+        module.extra_uses.extend(uses);
+        let uses = existing_module::mod_rs_uses(hoisted_names, &roots.names);
+        module.extra_uses.extend(uses);
+
+        // Modules holding the original `use` items already import the
+        // modules and traits their code relies on
+        if !module
+            .standalone_items
+            .iter()
+            .any(|item| matches!(item, Item::Use(_)))
+        {
+            match short_paths {
+                ShortPaths::Import => {
+                    // The fmt module imports `fmt` with its formatting traits
+                    if module.fmt_impls {
+                        roots.roots.remove("fmt");
+                    }
+                    let uses = imports.module_use_statements(&roots.roots);
+                    module.extra_uses.extend(uses);
+                }
+                ShortPaths::Qualify => {
+                    let mut qualifier = import_analyzer::PathQualifier::new(imports);
+                    module.visit_items_mut(&mut qualifier);
+                }
+            }
+            let mut calls = import_analyzer::CalledMethods::default();
+            module.visit_items(&mut calls);
+            let uses = imports.trait_use_statements(&calls.names);
+            module.extra_uses.extend(uses);
+        }
+    }
+}
+
+/// Generates the `mod.rs` file content for the output directory
+///
+/// Creates a module file that:
+/// - Declares all generated modules
+/// - Re-exports all public items from those modules
+///
+/// # Arguments
+///
+/// * `modules` - The list of modules to include
+/// * `reexports` - The planned re-export line for each module
+/// * `_output_dir` - The output directory (currently unused but reserved for future use)
+///
+/// # Returns
+///
+/// The content of `mod.rs` as a string
+/// Converts the newlines of generated `content` to `newline`
+fn convert_newlines(content: &str, newline: &str) -> String {
+    let content = content.replace("\r\n", "\n");
+    if newline == "\n" {
+        content
+    } else {
+        content.replace('\n', newline)
+    }
+}
+
+fn generate_mod_rs(
+    modules: &[Module],
+    reexports: &ReexportPlan,
+    _output_dir: &Path,
+) -> Result<String> {
+    let mut content = format!("{}\n\n", rerun::GENERATED_HEADER);
+
+    let mut directories = BTreeSet::new();
+    for module in modules {
+        match &module.directory {
+            Some(directory) => {
+                if directories.insert(directory) {
+                    content.push_str(&format!("pub mod {};\n", directory));
+                }
+            }
+            None => content.push_str(&module_declaration(module)),
+        }
+    }
+
+    content.push_str("\n// Re-export all types\n");
+    for entry in &reexports.entries {
+        let reexport_module = entry.module.clone();
+        let mut entry = entry.clone();
+        if let Some(module) = modules.iter().find(|m| m.name == entry.module) {
+            if let Some(directory) = &module.directory {
+                entry.module = format!("{}::{}", directory, entry.module);
+            }
+        }
+        let gate = modules
+            .iter()
+            .find(|m| m.name == reexport_module)
+            .map(|m| cfg_gate::cfg_only(&m.gate))
+            .unwrap_or_default();
+        for line in entry.render_lines() {
+            if let Some(attribute) = &reexports.doc_attribute {
+                content.push_str(attribute);
+                content.push('\n');
+            }
+            content.push_str(&cfg_gate::with_attributes(&line, &gate));
+        }
+    }
+
+    Ok(content)
+}
+
+/// Generate the `mod.rs` of a subdirectory holding some of the modules
+fn generate_directory_mod_rs(modules: &[Module], directory: &str) -> String {
+    let mut content = format!("{}\n\n", rerun::GENERATED_HEADER);
+    for module in modules {
+        if module.directory.as_deref() == Some(directory) {
+            content.push_str(&module_declaration(module));
+        }
+    }
+    content
+}
+
+/// `pub mod` declaration of a generated module, with its attributes
+fn module_declaration(module: &Module) -> String {
+    let mut declaration = String::new();
+    if module.internal {
+        declaration.push_str("#[doc(hidden)]\n");
+    }
+    if module.file_stem != module.name {
+        declaration.push_str(&format!("#[path = \"{}.rs\"]\n", module.file_stem));
+    }
+    declaration.push_str(&cfg_gate::with_attributes(
+        &format!("pub mod {};", module.name),
+        &module.gate,
+    ));
+    declaration
+}
+
+/// Configuration of a run: the `--config` file or the `.splitrs.toml`
+/// nearest to the input (or the crate in `--crate` mode), with the
+/// command-line overrides applied
+fn load_config(args: &Args) -> Result<Config> {
+    let start = args
+        .input
+        .first()
+        .or(args.crate_dir.as_ref())
+        .map_or(Path::new("."), PathBuf::as_path);
+    let mut config = config_near(args.config.as_deref(), start)?;
+
+    // Merge command-line arguments with configuration
+    config.merge_with_args(args.max_lines, args.max_impl_lines, args.split_impl_blocks);
+    if args.max_depth.is_some() {
+        config.output.max_depth = args.max_depth;
+    }
+    Ok(config)
+}
+
+/// The `--config` file, or else the `.splitrs.toml` nearest to `path`
+///
+/// # Arguments
+///
+/// * `config_path` - The `--config` file, if one was given
+/// * `path` - File or directory the run works on; the search for
+///   `.splitrs.toml` starts there and walks up its ancestors
+fn config_near(config_path: Option<&Path>, path: &Path) -> Result<Config> {
+    if let Some(config_path) = config_path {
+        return Config::from_file(config_path).context(format!(
+            "Failed to load configuration from {:?}",
+            config_path
+        ));
+    }
+    let path = existing_module::absolute(path);
+    let start = match path.parent() {
+        Some(parent) if !path.is_dir() => parent,
+        _ => &path,
+    };
+    Ok(Config::find_and_load(start).unwrap_or_default())
+}
+
+/// Seconds since the Unix epoch
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Split every oversized file of the crate at `crate_dir`
+///
+/// Each file is a separate run with the options of this one, deepest files
+/// first (see `crate_mode`). A file that fails to split does not stop the
+/// others; the summary at the end lists it (see `batch_summary`).
+fn run_crate(
+    args: &Args,
+    crate_dir: &Path,
+    disk: &dyn FileSystem,
+    reporter: &mut dyn Reporter,
+) -> Result<()> {
+    let config = load_config(args)?;
+    let plan = crate_mode::plan_targets(crate_dir, config.splitrs.max_lines)?;
+    let targets = plan.targets;
+    if targets.is_empty() {
+        reportln!(
+            reporter,
+            "No file in {:?} exceeds {} lines; nothing to split",
+            crate_dir.join("src"),
+            config.splitrs.max_lines
+        );
+        return Ok(());
+    }
+
+    reportln!(
+        reporter,
+        "Splitting {} file(s) of {:?} over {} lines:",
+        targets.len(),
+        crate_dir,
+        config.splitrs.max_lines
+    );
+    for target in &targets {
+        reportln!(
+            reporter,
+            "  {:?} ({} lines) -> {:?}",
+            target.input,
+            target.lines,
+            target.output
+        );
+    }
+    let mut summary = batch_summary::BatchSummary::new(
+        crate_dir,
+        config.splitrs.max_lines,
+        args.dry_run,
+        &plan.skipped,
+    );
+    // One backup run for the whole batch, with a numbered backup per file
+    let backup_root = backup::resolve_root(disk, &config.output.backup_root, crate_dir);
+    let batch_backups = match &config.output.backup_dir {
+        Some(dir) => dir.clone(),
+        None => backup_root.join(backup::run_name(unix_time())),
+    };
+    for (index, target) in targets.iter().enumerate() {
+        reportln!(reporter, "\n=== {:?} ===", target.input);
+        let file_args = Args {
+            input: vec![target.input.clone()],
+            output: Some(target.output.clone()),
+            crate_dir: None,
+            verify: false,
+            batch_backup_dir: Some(batch_backups.join(format!("{:03}", index + 1))),
+            ..args.clone()
+        };
+        let result = run_split(&file_args, disk, reporter).and_then(|outcome| {
+            if outcome == SplitOutcome::Cancelled {
+                anyhow::bail!("cancelled by user");
+            }
+            if target.replaces_input() && disk.exists(&target.input) {
+                if !outcome.record_replacement(disk, &target.output, &[])? {
+                    if matches!(outcome, SplitOutcome::Applied { .. }) {
+                        anyhow::bail!(
+                            "{:?} or the backup of the input is missing; kept the input",
+                            target.output.join("mod.rs")
+                        );
+                    }
+                    return Ok(());
+                }
+                disk.remove(&target.input)?;
+                reportln!(
+                    reporter,
+                    "Removed {:?}; {:?} replaces it (a copy is in the backup)",
+                    target.input,
+                    target.output
+                );
+            }
+            Ok(())
+        });
+        if let Err(error) = &result {
+            reportln!(
+                reporter,
+                "❌ Failed to split {:?}: {:#}",
+                target.input,
+                error
+            );
+        }
+        summary.record(batch_summary::FileOutcome {
+            input: target.input.clone(),
+            output: target.output.clone(),
+            lines: target.lines,
+            modules: report::RefactorReport::load(disk, &target.output)
+                .ok()
+                .filter(|_| result.is_ok() && !args.dry_run)
+                .map(|report| report.modules.len()),
+            error: result.err().map(|error| format!("{:#}", error)),
+        });
+    }
+
+    reportln!(reporter, "\n{}", summary.render().trim_end());
+    if !args.dry_run {
+        let path = summary.write(disk)?;
+        reportln!(reporter, "Summary written to {:?}", path);
+        if config.output.backup_dir.is_none() {
+            for removed in backup::prune(disk, &backup_root, config.output.backup_retention)? {
+                reportln!(reporter, "Removed old backup {:?}", removed);
+            }
+        }
+    }
+    if summary.failed > 0 {
+        anyhow::bail!(
+            "{} of {} file(s) of {:?} failed to split",
+            summary.failed,
+            targets.len(),
+            crate_dir
+        );
+    }
+
+    if args.verify && !args.dry_run {
+        reportln!(reporter, "\n🔎 Running cargo check in {:?}", crate_dir);
+        let output_dirs: Vec<PathBuf> = targets.iter().map(|t| t.output.clone()).collect();
+        let errors = verify::check_crate(crate_dir, &[], &output_dirs)?;
+        report_compile_errors(&errors, None, reporter)?;
+    }
+    Ok(())
+}
+
+/// Print the errors of `--verify` and fail if there are any
+fn report_compile_errors(
+    errors: &[verify::CompileError],
+    backup_dir: Option<&Path>,
+    reporter: &mut dyn Reporter,
+) -> Result<()> {
+    if errors.is_empty() {
+        reportln!(reporter, "✅ cargo check passed");
+        return Ok(());
+    }
+    reportln!(reporter, "❌ cargo check found {} error(s):", errors.len());
+    for error in errors {
+        reportln!(reporter, "  {}", error);
+    }
+    match backup_dir {
+        Some(backup_dir) => anyhow::bail!(
+            "The generated modules do not compile; the original is backed up at {:?}",
+            backup_dir
+        ),
+        None => anyhow::bail!("The generated modules do not compile"),
+    }
+}
+
+/// Run the command line of the `splitrs` binary
+pub fn run() -> Result<()> {
+    run_args(&Args::parse(), &RealFileSystem, &mut StdoutReporter)
+}
+
+/// Run a `splitrs` command line without printing anything
+///
+/// For build scripts and `cargo xtask` automation: the arguments are those
+/// of the binary, the program name first, files are read and written
+/// through `file_system`, and everything the run would print goes to
+/// `reporter`.
+///
+/// # Arguments
+///
+/// * `args` - The command line, e.g. `["splitrs", "-i", "src/big.rs", "-o", "src/big"]`
+/// * `file_system` - Where inputs are read and output is written
+///   ([`RealFileSystem`] for the disk, [`MemoryFileSystem`] for a tree in memory)
+/// * `reporter` - Receiver of the output
+pub fn run_from<I, T>(
+    args: I,
+    file_system: &dyn FileSystem,
+    reporter: &mut dyn Reporter,
+) -> Result<()>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    run_args(&Args::try_parse_from(args)?, file_system, reporter)
+}
+
+/// Run the command of parsed arguments
+fn run_args(args: &Args, disk: &dyn FileSystem, reporter: &mut dyn Reporter) -> Result<()> {
+    if let Some(command) = &args.command {
+        return run_command(command, args.config.as_deref(), disk, reporter);
+    }
+    if let Some(crate_dir) = &args.crate_dir {
+        return run_crate(args, crate_dir, disk, reporter);
+    }
+    if args.in_place {
+        return run_in_place(args, disk, reporter);
+    }
+    run_split(args, disk, reporter).map(|_| ())
+}
+
+/// Split the input of `args` into a module directory that replaces it
+///
+/// See `in_place`.
+fn run_in_place(args: &Args, disk: &dyn FileSystem, reporter: &mut dyn Reporter) -> Result<()> {
+    let [input] = args.input.as_slice() else {
+        anyhow::bail!("--in-place takes exactly one input file");
+    };
+    let target = in_place::target(input);
+    let file_args = Args {
+        output: Some(target.output.clone()),
+        verify: false,
+        ..args.clone()
+    };
+    let outcome = run_split(&file_args, disk, reporter)?;
+    // Target files are rewritten with inline modules instead
+    if cargo_target::detect(input).is_some() {
+        return Ok(());
+    }
+    match outcome {
+        SplitOutcome::Applied { .. } => {}
+        SplitOutcome::UpToDate if disk.exists(input) => {
+            reportln!(reporter, "Kept {:?}; nothing was split", input);
+            return Ok(());
+        }
+        _ => return Ok(()),
+    }
+
+    if target.replaces_input() && disk.exists(input) {
+        let name = input.file_stem().unwrap_or_default().to_string_lossy();
+        let file_name = input.file_name().unwrap_or_default().to_string_lossy();
+        let parent = in_place::parent_module(disk, input);
+        let patched = match &parent {
+            Some(parent) => in_place::declare_module(&disk.read(parent)?, &name, &file_name),
+            None => None,
+        };
+        let backed_up: Vec<&Path> = parent
+            .iter()
+            .filter(|_| patched.is_some())
+            .map(PathBuf::as_path)
+            .collect();
+        if !outcome.record_replacement(disk, &target.output, &backed_up)? {
+            anyhow::bail!(
+                "Kept {:?}: {:?} or the backup of the input is missing",
+                input,
+                target.output.join("mod.rs")
+            );
+        }
+        disk.remove(input)?;
+        reportln!(
+            reporter,
+            "Removed {:?}; {:?} replaces it (a copy is in the backup)",
+            input,
+            target.output
+        );
+        match (parent, patched) {
+            (Some(parent), Some(patched)) => {
+                disk.write(&parent, &patched)
+                    .context(format!("Failed to patch {:?}", parent))?;
+                reportln!(reporter, "Patched {:?} to declare `mod {}`", parent, name);
+            }
+            (Some(_), None) => {}
+            (None, _) => reportln!(
+                reporter,
+                "⚠️  No parent module found for {:?}; declare `mod {};` by hand",
+                input,
+                name
+            ),
+        }
+    }
+
+    if args.verify {
+        reportln!(reporter, "\n🔎 Running cargo check for {:?}", target.output);
+        let output_dirs = std::slice::from_ref(&target.output);
+        let errors = match verify::containing_crate(&target.output) {
+            Some(crate_dir) => verify::check_crate(&crate_dir, &[], output_dirs)?,
+            None => verify::check_scaffold(&target.output)?,
+        };
+        report_compile_errors(&errors, None, reporter)?;
+    }
+    Ok(())
+}
+
+/// What a split run did
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SplitOutcome {
+    /// The modules were written
+    Applied {
+        /// Backup of the inputs, if there were inputs to back up
+        backup_dir: Option<PathBuf>,
+
+        /// The files of the output directory
+        files: Vec<PathBuf>,
+    },
+
+    /// `--dry-run`: nothing was written
+    Previewed,
+
+    /// The output already held the modules; nothing was written
+    UpToDate,
+
+    /// The `--interactive` question was declined; nothing was written
+    Cancelled,
+}
+
+impl SplitOutcome {
+    /// Record that the output replaces the input, if it can: the modules
+    /// and the backup of the input must both be on disk
+    ///
+    /// # Arguments
+    ///
+    /// * `disk` - Where the split was written
+    /// * `output_dir` - The output directory of the split
+    /// * `patched` - Files about to be changed besides the input, backed up
+    ///   with it
+    ///
+    /// # Returns
+    ///
+    /// Whether the input can be removed
+    fn record_replacement(
+        &self,
+        disk: &dyn FileSystem,
+        output_dir: &Path,
+        patched: &[&Path],
+    ) -> Result<bool> {
+        let SplitOutcome::Applied {
+            backup_dir: Some(backup_dir),
+            files,
+        } = self
+        else {
+            return Ok(false);
+        };
+        if !disk.exists(&backup_dir.join(backup::MANIFEST_FILE))
+            || !disk.exists(&output_dir.join("mod.rs"))
+        {
+            return Ok(false);
+        }
+        backup::record_replacement(disk, backup_dir, files, patched)?;
+        Ok(true)
+    }
+}
+
+/// Split the input file(s) of `args` into the output directory
+fn run_split(
+    args: &Args,
+    disk: &dyn FileSystem,
+    reporter: &mut dyn Reporter,
+) -> Result<SplitOutcome> {
+    let output_dir = args.output.clone().context("--output is required")?;
+
+    // Load configuration
+    let mut config = load_config(args)?;
+
+    reportln!(reporter, "Configuration loaded:");
+    reportln!(
+        reporter,
+        "  Max lines per module: {}",
+        config.splitrs.max_lines
+    );
+    reportln!(
+        reporter,
+        "  Max lines per impl: {}",
+        config.splitrs.max_impl_lines
+    );
+    reportln!(
+        reporter,
+        "  Split impl blocks: {}",
+        config.splitrs.split_impl_blocks
+    );
+
+    // Test, bench and example targets cannot become module directories:
+    // with --in-place the file is rewritten with inline modules, otherwise
+    // that file is written into the output directory
+    let target_kind = match args.input.as_slice() {
+        [input] => cargo_target::detect(input),
+        _ => None,
+    };
+    let inline_target = target_kind.map(|_| {
+        let input = &args.input[0];
+        if args.in_place {
+            input.clone()
+        } else {
+            output_dir.join(input.file_name().unwrap_or_default())
+        }
+    });
+    if let (Some(kind), Some(inline_target)) = (target_kind, &inline_target) {
+        let input = &args.input[0];
+        match config.output.target_files {
+            config::TargetFiles::Refuse => anyhow::bail!(
+                "{:?} is a target of its own: Cargo compiles every file in {}/ as a separate \
+                 crate, so it cannot become a module directory.\n\
+                 Split it into inline modules (target_files = \"inline\"), or move the shared \
+                 code into {}/common/mod.rs and split that file instead.",
+                input,
+                kind.directory(),
+                kind.directory()
+            ),
+            config::TargetFiles::Inline => {
+                reportln!(
+                    reporter,
+                    "{:?} is a target in {}/; its modules are written inline into {:?}",
+                    input,
+                    kind.directory(),
+                    inline_target
+                );
+                config.output.subdirectories = false;
+            }
+        }
+    }
+
+    // Read the input files, and find out what their place in the crate means
+    let mut files = Vec::new();
+    for input in &args.input {
+        let source = disk
+            .read(input)
+            .context(format!("Failed to read input file: {:?}", input))?;
+        files.push((input.clone(), source));
+    }
+    let single_input = match args.input.as_slice() {
+        [input] => Some(input),
+        _ => None,
+    };
+    let previous_types = if args.follow_renames {
+        match report::RefactorReport::load(disk, &output_dir) {
+            Ok(previous) => Some(previous.types),
+            Err(_) => {
+                reportln!(
+                    reporter,
+                    "No report of a previous run in {:?}; there are no renames to follow",
+                    output_dir
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let in_place_mod_rs =
+        single_input.is_some_and(|input| existing_module::is_mod_rs_of(input, &output_dir));
+    let inputs = pipeline::SplitInputs {
+        files,
+        output_dir: output_dir.clone(),
+        lenient: args.lenient,
+        keep_original: args.verify_roundtrip,
+        crate_root: single_input
+            .filter(|input| crate_root::is_crate_root(input))
+            .map(|input| crate_root::root_kind(input)),
+        existing_children: in_place_mod_rs
+            .then(|| existing_module::child_module_names(&output_dir)),
+        relocate_declarations: target_kind.is_none(),
+        crate_symbols: (args.resolve_crate_types || config.splitrs.resolve_crate_types)
+            .then(|| ImportSuggester::for_inputs(&args.input).crate_symbols()),
+        previous_types,
+        budget: budget::Budget {
+            max_moved_lines: args.max_moved_lines,
+            top: args.top,
+        },
+        qualify_conflicts: args.qualify_conflicts,
+        original_module: single_input.and_then(|input| compat_shim::module_path(input)),
+        new_module: args
+            .module_prefix
+            .as_ref()
+            .or(config.output.module_prefix.as_ref())
+            .map(|prefix| compat_shim::prefixed_module_path(prefix))
+            .or_else(|| compat_shim::module_path(&output_dir)),
+        doctest_crate: args
+            .input
+            .first()
+            .and_then(|input| doctests::crate_name(input)),
+    };
+    let plan = pipeline::plan(&inputs, &config, reporter)?;
+    let modules = &plan.modules;
+    let unparsed_module = &config.naming.unparsed_module;
+
+    let suggester = if plan.unresolved.iter().any(|(_, names)| !names.is_empty()) {
+        ImportSuggester::for_inputs(&args.input)
+    } else {
+        ImportSuggester::new()
+    };
+    if args.diagnostics_format == DiagnosticsFormat::Json {
+        emit_split_diagnostics(args, &config, &inputs, &plan, &suggester, reporter);
+    }
+
+    // A target file is rewritten in place; its modules are only staged
+    let staged = MemoryFileSystem::new();
+    let file_system: &dyn FileSystem = if target_kind.is_some() { &staged } else { disk };
+    if args.dry_run {
+        report_dry_run(args, &config, &inputs, &plan, &suggester, disk, reporter)?;
+        return Ok(SplitOutcome::Previewed);
+    }
+
+    plan.check_writable(&config, args.strict)?;
+
+    // Interactive mode confirmation
+    if args.interactive {
+        reportln!(reporter, "\n{}", "=".repeat(60));
+        reportln!(reporter, "⚠️  INTERACTIVE MODE");
+        reportln!(reporter, "{}", "=".repeat(60));
+        reportln!(
+            reporter,
+            "\nThis will create {} module files in: {}",
+            modules.len(),
+            output_dir.display()
+        );
+        reportln!(reporter, "\n🛡️  Safety report:");
+        reporter.print(&risk::render_safety_report(&plan.risks));
+        if !reporter.confirm("\nProceed with file generation? [y/N]: ")? {
+            reportln!(reporter, "\n❌ Operation cancelled by user");
+            return Ok(SplitOutcome::Cancelled);
+        }
+        reportln!(reporter);
+    }
+
+    // Render the files, with the suggested imports when asked to
+    let mut fixes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    if args.fix_imports {
+        for (module, names) in &plan.unresolved {
+            let statements: Vec<String> = names
+                .iter()
+                .filter_map(|name| suggester.best(name))
+                .map(|suggestion| format!("use {};", suggestion.path))
+                .collect();
+            if !statements.is_empty() {
+                fixes.insert(module.clone(), statements);
+            }
+        }
+    }
+    let pipeline::RenderedSplit {
+        planned,
+        tree_files,
+        module_reports,
+        directories,
+        mod_path,
+    } = plan.render(&inputs, &config, &fixes, reporter)?;
+    let newline = config.output.line_ending.newline(&plan.source_code);
+
+    // On a re-run over earlier output, only write what changed
+    let rerun = rerun::is_generated_output(file_system, &output_dir);
+    let changes = rerun::classify(file_system, &planned, rerun);
+    let stale = if rerun {
+        rerun::stale_modules(file_system, &output_dir, &planned)
+    } else {
+        Vec::new()
+    };
+    for path in &stale {
+        reportln!(
+            reporter,
+            "⚠️  {:?} is no longer generated; remove it if nothing else uses it",
+            path
+        );
+    }
+    if changes.iter().all(|c| *c == rerun::FileChange::Unchanged) {
+        reportln!(
+            reporter,
+            "\n✅ {:?} is up to date; nothing was written",
+            output_dir
+        );
+        return Ok(SplitOutcome::UpToDate);
+    }
+
+    // Create backup for rollback support, unless the newest one already
+    // holds the inputs as they are
+    let backup_root = backup::resolve_root(disk, &config.output.backup_root, &args.input[0]);
+    let fixed_backup_dir = args
+        .batch_backup_dir
+        .as_ref()
+        .or(config.output.backup_dir.as_ref());
+    let mut backup_dir = match fixed_backup_dir {
+        Some(dir) => dir.clone(),
+        None => backup_root.join(backup::run_name(unix_time())),
+    };
+    let existing_inputs: Vec<&PathBuf> = args.input.iter().filter(|p| disk.exists(p)).collect();
+    let identical_backup = match fixed_backup_dir {
+        Some(_) => None,
+        None => backup::find_identical(disk, &backup_root, &existing_inputs, &output_dir)?,
+    };
+    if let Some(dir) = identical_backup {
+        reportln!(reporter, "📦 Inputs unchanged since backup {:?}", dir);
+        backup_dir = dir;
+    } else if !existing_inputs.is_empty() {
+        backup::create(disk, &backup_dir, &existing_inputs, &output_dir)?;
+        reportln!(reporter, "📦 Backup created at: {:?}", backup_dir);
+        if fixed_backup_dir.is_none() {
+            for removed in backup::prune(disk, &backup_root, config.output.backup_retention)? {
+                reportln!(reporter, "Removed old backup {:?}", removed);
+            }
+        }
+    }
+
+    file_system.create_dir(&output_dir)?;
+    for ((path, content), change) in planned.iter().zip(&changes) {
+        if *change != rerun::FileChange::Unchanged {
+            if let Some(parent) = path.parent() {
+                file_system.create_dir(parent)?;
+            }
+            file_system
+                .write(path, content)
+                .context(format!("Failed to write module: {:?}", path))?;
+        }
+        if inline_target.is_none() {
+            reportln!(reporter, "{}: {:?}", change.label(), path);
+        }
+    }
+    if inputs.budget.is_limited() {
+        reportln!(
+            reporter,
+            "{} module(s) left for later runs; see {:?}",
+            plan.deferred_modules.len(),
+            output_dir.join(budget::PLAN_FILE)
+        );
+    }
+
+    // Rewrite the crate root around the generated module
+    if let Some(root_kind) = inputs.crate_root {
+        let input = &args.input[0];
+        match inputs
+            .new_module
+            .as_deref()
+            .and_then(|path| path.strip_prefix("crate::"))
+            .filter(|name| !name.contains("::"))
+        {
+            Some(module) => {
+                let root = crate_root::render_root(
+                    &plan.syntax_tree.attrs,
+                    &plan.root_items,
+                    module,
+                    root_kind.reexports(),
+                );
+                file_system
+                    .write(input, &convert_newlines(&root, newline))
+                    .context(format!("Failed to rewrite crate root: {:?}", input))?;
+                reportln!(reporter, "Rewrote crate root {:?} to declare `mod {}`", input, module);
+            }
+            None => reportln!(
+                reporter,
+                "⚠️  {:?} is not a direct child of the crate root; keep the root-only items of {:?} and declare the module by hand",
+                output_dir,
+                input
+            ),
+        }
+    }
+
+    // Leave a shim at the original path so callers can migrate gradually
+    if (args.compat_shim || config.output.compat_shim) && inputs.crate_root.is_none() {
+        let target = inputs.new_module.clone().context(format!(
+            "Cannot determine the module path of {:?} (is it inside a crate's src/? \
+             Otherwise pass --module-prefix)",
+            output_dir
+        ))?;
+        for input in &args.input {
+            if compat_shim::module_path(input).as_deref() == Some(target.as_str()) {
+                anyhow::bail!(
+                    "{:?} and the output are the same module; remove the file instead of shimming it",
+                    input
+                );
+            }
+            file_system
+                .write(
+                    input,
+                    &convert_newlines(&compat_shim::render_shim(&target), newline),
+                )
+                .context(format!("Failed to write shim: {:?}", input))?;
+            reportln!(
+                reporter,
+                "Replaced {:?} with a compatibility shim for {}",
+                input,
+                target
+            );
+        }
+        reportln!(
+            reporter,
+            "Declare `mod {};` next to the original module to finish the move",
+            target.rsplit("::").next().unwrap_or_default()
+        );
+    }
+
+    // Check that the written modules hold exactly the input's items
+    let mut roundtrip_failure = None;
+    if let Some(original_tree) = &plan.original_tree {
+        let mut generated = vec![File {
+            shebang: None,
+            attrs: Vec::new(),
+            items: plan.root_items.clone(),
+        }];
+        let paths = modules
+            .iter()
+            .map(|m| output_dir.join(m.file_name()))
+            .chain(
+                directories
+                    .iter()
+                    .map(|d| output_dir.join(d).join("mod.rs")),
+            )
+            .chain([mod_path.clone()]);
+        for path in paths {
+            let source = file_system.read(&path)?;
+            generated.push(
+                syn::parse_file(&source)
+                    .context(format!("Generated module does not parse: {:?}", path))?,
+            );
+        }
+        let mut module_names: BTreeSet<String> = modules.iter().map(|m| m.name.clone()).collect();
+        module_names.extend(directories.iter().cloned());
+        for path in [&inputs.original_module, &inputs.new_module]
+            .into_iter()
+            .flatten()
+        {
+            module_names.extend(path.split("::").skip(1).map(str::to_string));
+        }
+
+        let roundtrip = roundtrip::verify(original_tree, &generated, &module_names);
+        if roundtrip.is_clean() {
+            reportln!(
+                reporter,
+                "✅ Round-trip verified: the modules hold exactly the input's items"
+            );
+        } else {
+            reportln!(
+                reporter,
+                "❌ Round-trip verification failed:\n{}",
+                roundtrip.render()
+            );
+            roundtrip_failure = Some(roundtrip.render());
+        }
+    }
+
+    // Record what happened for follow-up tooling
+    let mut warnings: Vec<String> = plan
+        .skipped_spans
+        .iter()
+        .map(|(input, span)| {
+            format!(
+                "{}:{}-{}: copied verbatim to {}.rs: {}",
+                input, span.start_line, span.end_line, unparsed_module, span.error
+            )
+        })
+        .collect();
+    for (module, names) in &plan.unresolved {
+        for name in names {
+            warnings.push(format!("{}.rs: unresolved name `{}`", module, name));
+        }
+    }
+    for risk in plan
+        .risks
+        .iter()
+        .filter(|r| !r.kind.is_reported_elsewhere() && r.kind.blocks_strict())
+    {
+        warnings.push(risk.to_string());
+    }
+    if let Some(differences) = &roundtrip_failure {
+        warnings.push(format!("round-trip verification failed:\n{}", differences));
+    }
+    for (module, path) in &plan.reexports.opaque_globs {
+        warnings.push(format!(
+            "{}.rs: glob re-export `{}::*` may overlap with sibling modules",
+            module, path
+        ));
+    }
+    let refactor_report = report::RefactorReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        generated_at: unix_time(),
+        inputs: args.input.clone(),
+        output_dir: output_dir.clone(),
+        backup_dir: (!existing_inputs.is_empty()).then(|| backup_dir.clone()),
+        original_lines: plan.source_code.lines().count(),
+        modules: module_reports,
+        visibility_changes: modules
+            .iter()
+            .flat_map(Module::visibility_changes)
+            .collect(),
+        warnings,
+        risks: plan
+            .risks
+            .iter()
+            .map(|r| report::RiskFinding {
+                kind: r.kind.label().to_string(),
+                module: r.module.clone(),
+                detail: r.detail.clone(),
+            })
+            .collect(),
+        outline: plan.placements.clone(),
+        types: plan.type_records.clone(),
+    };
+    let report_path = refactor_report.write(file_system, &output_dir)?;
+    if inline_target.is_none() {
+        reportln!(
+            reporter,
+            "{}: {:?}",
+            if rerun { "Updated" } else { "Created" },
+            report_path
+        );
+    }
+    if let Some(stats_file) = &config.output.stats_file {
+        let module_lines: Vec<usize> = refactor_report.modules.iter().map(|m| m.lines).collect();
+        stats::RunStats::new(
+            "split",
+            &args.input,
+            refactor_report.original_lines,
+            &module_lines,
+            refactor_report.warnings.len(),
+        )
+        .append(disk, stats_file)?;
+        reportln!(reporter, "Recorded run statistics in {:?}", stats_file);
+    }
+    if let Some(differences) = roundtrip_failure {
+        anyhow::bail!(
+            "Round-trip verification failed; the original is backed up at {:?}\n{}",
+            backup_dir,
+            differences
+        );
+    }
+
+    // Inline the staged modules into the target file
+    if let Some(inline_target) = &inline_target {
+        let read = |path: &Path| -> Result<String> { Ok(staged.read(path)?.replace("\r\n", "\n")) };
+        let mut contents = Vec::new();
+        for module in modules {
+            contents.push((
+                module.name.clone(),
+                read(&output_dir.join(module.file_name()))?,
+            ));
+        }
+        let unparsed_path = output_dir.join(format!("{}.rs", unparsed_module));
+        if staged.exists(&unparsed_path) {
+            contents.push((unparsed_module.clone(), read(&unparsed_path)?));
+        }
+        let inline = cargo_target::render_inline(&read(&mod_path)?, &contents);
+        let existed = disk.exists(inline_target);
+        if let Some(parent) = inline_target.parent() {
+            disk.create_dir(parent)?;
+        }
+        disk.write(inline_target, &convert_newlines(&inline, newline))
+            .context(format!("Failed to write target file: {:?}", inline_target))?;
+        reportln!(
+            reporter,
+            "{}: {:?} with {} inline module(s)",
+            if existed { "Updated" } else { "Created" },
+            inline_target,
+            contents.len()
+        );
+    }
+
+    reportln!(reporter, "\nRefactoring complete!");
+    reportln!(
+        reporter,
+        "Original file: {} lines",
+        plan.source_code.lines().count()
+    );
+    // A target's module files were only staged, so there is no tree to show
+    if inline_target.is_none() {
+        reportln!(reporter, "Generated {} module files", modules.len());
+        reportln!(reporter, "\n🌳 Module tree:");
+        reporter.print(&render_module_tree(
+            &output_dir.display().to_string(),
+            &tree_files,
+        ));
+    }
+
+    // Compile the result
+    if args.verify {
+        let errors = match verify::containing_crate(&output_dir) {
+            Some(crate_dir) => {
+                reportln!(reporter, "\n🔎 Running cargo check in {:?}", crate_dir);
+                let shadowing: Vec<PathBuf> = args
+                    .input
+                    .iter()
+                    .filter(|input| {
+                        existing_module::absolute(&input.with_extension(""))
+                            == existing_module::absolute(&output_dir)
+                    })
+                    .cloned()
+                    .collect();
+                verify::check_crate(&crate_dir, &shadowing, std::slice::from_ref(&output_dir))?
+            }
+            None => {
+                reportln!(
+                    reporter,
+                    "\n🔎 {:?} is not declared as a module; running cargo check on a scaffolding crate",
+                    output_dir
+                );
+                verify::check_scaffold(&output_dir)?
+            }
+        };
+        report_compile_errors(
+            &errors,
+            (!existing_inputs.is_empty()).then_some(backup_dir.as_path()),
+            reporter,
+        )?;
+    }
+
+    Ok(SplitOutcome::Applied {
+        backup_dir: (!existing_inputs.is_empty()).then_some(backup_dir),
+        files: match inline_target {
+            Some(inline_target) => vec![inline_target],
+            None => planned
+                .into_iter()
+                .map(|(path, _)| path)
+                .chain([report_path])
+                .collect(),
+        },
+    })
+}
+
+/// Emit the findings of a split as JSON diagnostics
+fn emit_split_diagnostics(
+    args: &Args,
+    config: &Config,
+    inputs: &pipeline::SplitInputs,
+    plan: &pipeline::SplitPlan,
+    suggester: &ImportSuggester,
+    reporter: &mut dyn Reporter,
+) {
+    let module_file = |name: &str| {
+        let file = plan
+            .modules
+            .iter()
+            .find(|m| m.name == name)
+            .map_or_else(|| "mod.rs".to_string(), |m| m.file_name());
+        inputs.output_dir.join(file).display().to_string()
+    };
+    let mut import_fixes = HashMap::new();
+    for (module, names) in &plan.unresolved {
+        for name in names {
+            if let Some(suggestion) = suggester.best(name) {
+                import_fixes.insert(
+                    (module.clone(), format!("unresolved name `{}`", name)),
+                    format!("use {};", suggestion.path),
+                );
+            }
+        }
+    }
+    let mut records = Vec::new();
+    for (input, span) in &plan.skipped_spans {
+        records.push(Diagnostic {
+            file: input.clone(),
+            span: Some(diagnostics::Span {
+                start_line: span.start_line,
+                end_line: span.end_line,
+            }),
+            code: "unparsed_item".to_string(),
+            severity: "warning".to_string(),
+            message: span.error.clone(),
+            suggested_fix: Some(format!(
+                "fix the syntax error; the item is copied verbatim to {}.rs",
+                config.naming.unparsed_module
+            )),
+        });
+    }
+    for risk in &plan.risks {
+        let error = risk.kind.policy(&config.risks) == config::RiskPolicy::Error
+            || (args.strict && risk.kind.blocks_strict());
+        let mut record = Diagnostic::from_risk(risk, module_file(&risk.module), error);
+        if let Some(fix) = import_fixes.get(&(risk.module.clone(), risk.detail.clone())) {
+            record.suggested_fix = Some(fix.clone());
+        }
+        records.push(record);
+    }
+    for collision in plan.reexports.unresolved_ambiguities() {
+        records.push(Diagnostic {
+            file: module_file("mod"),
+            span: None,
+            code: "export_conflict".to_string(),
+            severity: "error".to_string(),
+            message: collision.to_string(),
+            suggested_fix: Some(
+                "rename or make one of the items private, or pass --qualify-conflicts".to_string(),
+            ),
+        });
+    }
+    for (module, path) in &plan.reexports.opaque_globs {
+        records.push(Diagnostic {
+            file: module_file(module),
+            span: None,
+            code: "opaque_glob".to_string(),
+            severity: "warning".to_string(),
+            message: format!("`pub use {}::*;` may overlap with sibling modules", path),
+            suggested_fix: None,
+        });
+    }
+    diagnostics::emit(reporter, &records);
+}
+
+/// Report what a `--dry-run` split would create
+fn report_dry_run(
+    args: &Args,
+    config: &Config,
+    inputs: &pipeline::SplitInputs,
+    plan: &pipeline::SplitPlan,
+    suggester: &ImportSuggester,
+    disk: &dyn FileSystem,
+    reporter: &mut dyn Reporter,
+) -> Result<()> {
+    let output_dir = &inputs.output_dir;
+    let modules = &plan.modules;
+    let model = &plan.model;
+    let reexports = &plan.reexports;
+    let collisions = reexports.unresolved_ambiguities();
+    let unparsed_module = &config.naming.unparsed_module;
+
+    reportln!(reporter, "\n{}", "=".repeat(60));
+    reportln!(reporter, "DRY RUN - Preview Mode");
+    reportln!(reporter, "{}", "=".repeat(60));
+
+    reportln!(reporter, "\n📊 Statistics:");
+    reportln!(
+        reporter,
+        "  Original file: {} lines",
+        plan.source_code.lines().count()
+    );
+    reportln!(reporter, "  Total modules to create: {}", modules.len());
+
+    reportln!(reporter, "\n📁 Module Structure:");
+    for module in modules {
+        let module_types = module.types.len();
+        let module_items = module.standalone_items.len();
+        let trait_impls = module.trait_impls.len();
+
+        reporter.print(&format!("  📄 {}", module.file_name()));
+
+        if module_types > 0 {
+            reporter.print(&format!(" ({} types", module_types));
+        }
+        if module_items > 0 {
+            if module_types > 0 {
+                reporter.print(&format!(", {} items", module_items));
+            } else {
+                reporter.print(&format!(" ({} items", module_items));
+            }
+        }
+        if trait_impls > 0 {
+            if module_types > 0 || module_items > 0 {
+                reporter.print(&format!(", {} trait impls", trait_impls));
+            } else {
+                reporter.print(&format!(" ({} trait impls", trait_impls));
+            }
+        }
+
+        if module_types > 0 || module_items > 0 || trait_impls > 0 {
+            reporter.print(")");
+        }
+        reportln!(reporter);
+    }
+
+    reportln!(reporter, "\n📏 Estimated Sizes:");
+    let mut size_table = SizeTable::new(plan.source_code.lines().count());
+    let mut module_lines = Vec::new();
+    for module in modules {
+        let lines = module.generate_content(&model.imports).lines().count();
+        size_table.add_module(
+            module.file_name().trim_end_matches(".rs").to_string(),
+            lines,
+        );
+        module_lines.push(lines);
+    }
+    reporter.print(&size_table.render());
+
+    reportln!(reporter, "\n🌳 Module tree:");
+    let mut tree_files: Vec<(String, usize)> = vec![(
+        "mod.rs".to_string(),
+        generate_mod_rs(modules, reexports, output_dir)?
+            .lines()
+            .count(),
+    )];
+    let mut tree_directories = BTreeSet::new();
+    for (module, lines) in modules.iter().zip(&module_lines) {
+        tree_files.push((module.file_name(), *lines));
+        if let Some(directory) = &module.directory {
+            tree_directories.insert(directory);
+        }
+    }
+    for directory in tree_directories {
+        tree_files.push((
+            format!("{}/mod.rs", directory),
+            generate_directory_mod_rs(modules, directory)
+                .lines()
+                .count(),
+        ));
+    }
+    // Subdirectories are one level deep; `max_depth = 0` flattens them
+    if config.output.max_depth == Some(0) {
+        let mut flat: Vec<(String, usize)> = Vec::new();
+        for (file, lines) in tree_files {
+            let file = match file.split_once('/') {
+                Some((directory, _)) => format!("{}.rs", directory),
+                None => file,
+            };
+            match flat.iter_mut().find(|(name, _)| *name == file) {
+                Some((_, total)) => *total += lines,
+                None => flat.push((file, lines)),
+            }
+        }
+        tree_files = flat;
+    }
+    if !plan.skipped_spans.is_empty() {
+        tree_files.push((
+            format!("{}.rs", unparsed_module),
+            lenient::render_skipped_module(&plan.skipped_spans)
+                .lines()
+                .count(),
+        ));
+    }
+    reporter.print(&render_module_tree(
+        &output_dir.display().to_string(),
+        &tree_files,
+    ));
+
+    reportln!(reporter, "\n🔀 Before → after:");
+    reporter.print(&outline::render_comparison(&plan.placements));
+
+    let annotated: Vec<(String, Vec<String>)> = plan
+        .unresolved
+        .iter()
+        .map(|(module, names)| {
+            let names = names
+                .iter()
+                .map(|name| match suggester.best(name) {
+                    Some(suggestion) => format!("{} (use {})", name, suggestion.path),
+                    None => name.clone(),
+                })
+                .collect();
+            (module.clone(), names)
+        })
+        .collect();
+    let unresolved_report = render_unresolved(&annotated);
+    if !unresolved_report.is_empty() {
+        if args.fix_imports {
+            reportln!(
+                reporter,
+                "\n⚠️  Unresolved names (suggested imports will be inserted):"
+            );
+        } else {
+            reportln!(
+                reporter,
+                "\n⚠️  Unresolved names (manual `use` fixes likely needed):"
+            );
+        }
+        reporter.print(&unresolved_report);
+    }
+
+    if !collisions.is_empty() {
+        reportln!(
+            reporter,
+            "\n❌ Conflicting public names (mod.rs re-exports would clash):"
+        );
+        for collision in &collisions {
+            reportln!(reporter, "  {}", collision);
+        }
+    }
+    if !reexports.qualified.is_empty() {
+        reportln!(
+            reporter,
+            "\n🔒 Names only reachable through their module path:"
+        );
+        for (module, name) in &reexports.qualified {
+            reportln!(reporter, "  {}::{}", module, name);
+        }
+    }
+    if !reexports.opaque_globs.is_empty() {
+        reportln!(
+            reporter,
+            "\n⚠️  Glob re-exports that may overlap with sibling modules:"
+        );
+        for (module, path) in &reexports.opaque_globs {
+            reportln!(reporter, "  📄 {}.rs: pub use {}::*;", module, path);
+        }
+    }
+    let new_risks: Vec<risk::Risk> = plan
+        .risks
+        .iter()
+        .filter(|r| args.strict || !r.kind.is_reported_elsewhere())
+        .cloned()
+        .collect();
+    if !new_risks.is_empty() {
+        reportln!(reporter, "\n⚠️  Semantic risks:");
+        reporter.print(&risk::render_risks(&new_risks));
+    }
+
+    reportln!(reporter, "\n💾 Files that would be created:");
+    if let [input] = args.input.as_slice() {
+        if cargo_target::detect(input).is_some() {
+            reportln!(
+                reporter,
+                "  📄 {} (rewritten with inline modules)",
+                input.display()
+            );
+        }
+    }
+    reportln!(reporter, "  📁 {}/", output_dir.display());
+    let mut directories = BTreeSet::new();
+    for module in modules {
+        reportln!(reporter, "    📄 {}", module.file_name());
+        if let Some(directory) = &module.directory {
+            directories.insert(directory);
+        }
+    }
+    for directory in directories {
+        reportln!(reporter, "    📄 {}/mod.rs", directory);
+    }
+    if !plan.skipped_spans.is_empty() {
+        reportln!(reporter, "    📄 {}.rs (verbatim)", unparsed_module);
+    }
+    reportln!(reporter, "    📄 mod.rs");
+
+    reportln!(reporter, "\n{}", "=".repeat(60));
+    reportln!(reporter, "✓ Preview complete - no files were created");
+    reportln!(reporter, "{}", "=".repeat(60));
+
+    if let Some(stats_file) = &config.output.stats_file {
+        let warnings = plan.skipped_spans.len()
+            + plan
+                .unresolved
+                .iter()
+                .map(|(_, names)| names.len())
+                .sum::<usize>()
+            + reexports.opaque_globs.len()
+            + collisions.len();
+        stats::RunStats::new(
+            "dry_run",
+            &args.input,
+            plan.source_code.lines().count(),
+            &module_lines,
+            warnings,
+        )
+        .append(disk, stats_file)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_impl_type_extraction() {
+        let code = r#"
+            struct Foo;
+            impl Foo {
+                fn bar() {}
+            }
+        "#;
+
+        let file = syn::parse_file(code).unwrap();
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.analyze(&file, &AnalysisModel::build(&file));
+
+        assert_eq!(analyzer.types.len(), 1);
+        assert_eq!(analyzer.types.get("Foo").unwrap().impls.len(), 1);
+    }
+
+    #[test]
+    fn test_type_names_include_trait_bounds() {
+        let mut types = HashSet::new();
+        let boxed: syn::Type = syn::parse_quote!(Box<dyn Handler<Event = Click> + Send>);
+        let callback: syn::Type = syn::parse_quote!(&(impl Fn(Request) -> Response));
+        extract_type_names(&boxed, &mut types);
+        extract_type_names(&callback, &mut types);
+
+        for name in [
+            "Box", "Handler", "Click", "Send", "Fn", "Request", "Response",
+        ] {
+            assert!(types.contains(name), "missing {}", name);
+        }
+    }
+
+    #[test]
+    fn test_split_methods_import_bound_traits() {
+        let file: File = syn::parse_quote! {
+            use serde::Serialize;
+            use std::hash::Hash;
+            use std::fmt::Debug;
+            pub struct Store { len: usize }
+            impl Store {
+                pub fn dump<K>(&self, key: K) -> usize where K: Serialize + Hash { self.len }
+                pub fn show<D: Debug>(&self, d: D) -> usize { self.len + 1 }
+            }
+        };
+        let model = AnalysisModel::build(&file);
+        let mut analyzer = FileAnalyzer::new(true, 1);
+        analyzer.analyze(&file, &model);
+
+        let modules = analyzer.group_by_module(1000);
+        let contents: Vec<String> = modules
+            .iter()
+            .filter(|m| m.method_group.is_some())
+            .map(|m| m.generate_content(&model.imports))
+            .collect();
+        let dump = contents.iter().find(|c| c.contains("fn dump")).unwrap();
+        assert!(dump.contains("use serde::Serialize;"));
+        assert!(dump.contains("use std::hash::Hash;"));
+        assert!(!dump.contains("use std::fmt::Debug;"));
+        let show = contents.iter().find(|c| c.contains("fn show")).unwrap();
+        assert!(show.contains("use std::fmt::Debug;"));
+    }
+
+    #[test]
+    fn test_small_and_loose_groups_stay_with_type() {
+        let file: File = syn::parse_quote! {
+            pub struct Store { items: Vec<u32>, hits: u64 }
+            impl Store {
+                pub fn add(&mut self, item: u32) { self.items.push(item); self.count(); }
+                pub fn count(&mut self) { self.hits += 1; }
+                pub fn clear(&mut self) { self.items.clear(); }
+                pub fn version() -> u32 { 1 }
+            }
+        };
+        let model = AnalysisModel::build(&file);
+        let mut analyzer = FileAnalyzer::new(true, 40);
+        analyzer.guard_method_groups(2, 0.5);
+        analyzer.analyze(&file, &model);
+
+        let modules = analyzer.group_by_module(1000);
+        let split: Vec<Vec<&str>> = modules
+            .iter()
+            .filter_map(|m| m.method_group.as_ref())
+            .map(|g| g.methods.iter().map(|m| m.name.as_str()).collect())
+            .collect();
+        assert_eq!(split, vec![vec!["add", "count"]]);
+        let type_module = modules.iter().find(|m| m.name == "store_type").unwrap();
+        let Item::Impl(kept) = &type_module.types[0].impls[0] else {
+            panic!("expected impl");
+        };
+        assert_eq!(kept.items.len(), 2);
+    }
+
+    #[test]
+    fn test_split_methods_import_impl_trait_types() {
+        let file: File = syn::parse_quote! {
+            use crate::model::{Record, Label};
+            pub struct Store { len: usize }
+            impl Store {
+                pub fn records(&self) -> impl Iterator<Item = Record> + '_ { std::iter::empty() }
+                pub fn rename(&mut self, name: impl Into<Label>) { self.len = 0; }
+            }
+        };
+        let model = AnalysisModel::build(&file);
+        let mut analyzer = FileAnalyzer::new(true, 1);
+        analyzer.analyze(&file, &model);
+
+        let modules = analyzer.group_by_module(1000);
+        let contents: Vec<String> = modules
+            .iter()
+            .filter(|m| m.method_group.is_some())
+            .map(|m| m.generate_content(&model.imports))
+            .collect();
+        let records = contents.iter().find(|c| c.contains("fn records")).unwrap();
+        assert!(records.contains("use crate::model::Record;"));
+        let rename = contents.iter().find(|c| c.contains("fn rename")).unwrap();
+        assert!(rename.contains("use crate::model::Label;"));
+        assert!(!rename.contains("use crate::model::Record;"));
+    }
+
+    #[test]
+    fn test_split_impl_keeps_associated_consts() {
+        let file: File = syn::parse_quote! {
+            pub struct Pool { size: usize }
+            impl Pool {
+                const MAX: usize = 10;
+                pub const MIN: usize = 1;
+                pub fn grow(&mut self) { self.size = Self::MAX; }
+                pub fn shrink(&mut self) { self.size = Self::MIN.max(Self::MAX / 2); }
+            }
+        };
+        let model = AnalysisModel::build(&file);
+        let mut analyzer = FileAnalyzer::new(true, 1);
+        analyzer.analyze(&file, &model);
+
+        let modules = analyzer.group_by_module(1000);
+        let type_module = modules.iter().find(|m| m.name == "pool_type").unwrap();
+        let content = type_module.generate_content(&model.imports);
+        assert!(content.contains("pub(super) const MAX: usize = 10;"));
+        assert!(content.contains("pub const MIN: usize = 1;"));
+        assert_eq!(type_module.widened, vec!["Pool::MAX"]);
+        assert!(modules
+            .iter()
+            .filter(|m| m.method_group.is_some())
+            .all(|m| !m.generate_content(&model.imports).contains("const")));
+    }
+
+    #[test]
+    fn test_associated_const_moves_with_its_only_group() {
+        let file: File = syn::parse_quote! {
+            pub struct Pool { size: usize }
+            impl Pool {
+                const STEP: usize = 2;
+                const LIMIT: usize = Self::STEP * 8;
+                pub fn grow(&mut self) { self.size = (self.size + Self::STEP).min(Self::LIMIT); }
+                pub fn clear(&mut self) { self.size = 0; }
+            }
+        };
+        let model = AnalysisModel::build(&file);
+        let mut analyzer = FileAnalyzer::new(true, 1);
+        analyzer.analyze(&file, &model);
+
+        let modules = analyzer.group_by_module(1000);
+        let type_module = modules.iter().find(|m| m.name == "pool_type").unwrap();
+        assert!(type_module.widened.is_empty());
+        assert!(!type_module
+            .generate_content(&model.imports)
+            .contains("const"));
+        let grow = modules
+            .iter()
+            .find(|m| {
+                m.method_group
+                    .as_ref()
+                    .is_some_and(|g| g.methods.iter().any(|m| m.name == "grow"))
+            })
+            .unwrap();
+        let content = grow.generate_content(&model.imports);
+        assert!(content.contains("    const STEP: usize = 2;"));
+        assert!(content.contains("    const LIMIT: usize = Self::STEP * 8;"));
+    }
+
+    #[test]
+    fn test_private_types_are_widened_for_importers() {
+        let file: File = syn::parse_quote! {
+            struct Meters(f64);
+            impl Clone for Meters {
+                fn clone(&self) -> Self { Meters(self.0) }
+            }
+            struct Local;
+        };
+        let model = AnalysisModel::build(&file);
+        let mut analyzer = FileAnalyzer::new(false, 1000);
+        analyzer.analyze(&file, &model);
+
+        let modules = analyzer.group_by_module(1000);
+        let holder = modules
+            .iter()
+            .find(|m| m.types.iter().any(|t| t.name == "Meters"))
+            .unwrap();
+        assert_eq!(holder.widened, vec!["Meters"]);
+        let content = holder.generate_content(&model.imports);
+        assert!(content.contains("pub(super) struct Meters"));
+        assert!(content.contains("\nstruct Local;"));
+        let traits = modules
+            .iter()
+            .find(|m| m.type_name_for_traits.is_some())
+            .unwrap();
+        assert!(traits
+            .generate_content(&model.imports)
+            .contains(&format!("use super::{}::Meters;", holder.name)));
+    }
+
+    #[test]
+    fn test_const_generics_are_kept() {
+        let mut types = HashSet::new();
+        let array: syn::Type = syn::parse_quote!([Frame; MAX_FRAMES]);
+        let vec: syn::Type = syn::parse_quote!(ArrayVec<Slot, { CAPACITY * 2 }>);
+        extract_type_names(&array, &mut types);
+        extract_type_names(&vec, &mut types);
+        for name in ["Frame", "MAX_FRAMES", "ArrayVec", "Slot", "CAPACITY"] {
+            assert!(types.contains(name), "missing {}", name);
+        }
+
+        let file: File = syn::parse_quote! {
+            pub struct Ring<T, const N: usize> { items: [T; N] }
+            impl<T: Copy, const N: usize> Ring<T, N> where T: Default {
+                pub fn first(&self) -> T { self.items[0] }
+                pub fn capacity(&self) -> usize { N }
+            }
+        };
+        let model = AnalysisModel::build(&file);
+        let mut analyzer = FileAnalyzer::new(true, 1);
+        analyzer.analyze(&file, &model);
+        let modules = analyzer.group_by_module(1000);
+        let split = modules.iter().find(|m| m.method_group.is_some()).unwrap();
+        let content = split.generate_content(&model.imports);
+        assert!(
+            content.contains("impl<T: Copy, const N: usize> Ring<T, N>\nwhere\n    T: Default,")
+        );
+    }
+
+    #[test]
+    fn test_shared_helpers_move_to_internal_module() {
+        let file: File = syn::parse_quote! {
+            pub struct A;
+            impl A { pub fn a(&self) -> u32 { helper() } }
+            pub struct B;
+            impl B { pub fn b(&self) -> u32 { helper() + 1 } }
+            fn helper() -> u32 { 41 }
+            pub fn api() {}
+        };
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.isolate_internal_items("internal");
+        analyzer.analyze(&file, &AnalysisModel::build(&file));
+
+        let modules = analyzer.group_by_module(1);
+        let internal = modules.iter().find(|m| m.internal).unwrap();
+        assert_eq!(internal.standalone_items.len(), 1);
+        let users = modules
+            .iter()
+            .filter(|m| m.extra_uses == vec!["use super::internal::helper;".to_string()])
+            .count();
+        assert_eq!(users, 2);
+    }
+
+    #[test]
+    fn test_extension_impls_get_own_module() {
+        let file: File = syn::parse_quote! {
+            use crate::model::Order;
+            struct Foo;
+            impl Order { fn total(&self) -> u32 { 0 } }
+            impl std::fmt::Display for Order {
+                fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { Ok(()) }
+            }
+        };
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.route_extension_impls("_ext", &BTreeMap::new());
+        analyzer.analyze(&file, &AnalysisModel::build(&file));
+
+        let modules = analyzer.group_by_module(1000);
+        let extension = modules.iter().find(|m| m.name == "order_ext").unwrap();
+        assert_eq!(extension.standalone_items.len(), 2);
+
+        let content = extension.generate_content(&AnalysisModel::build(&file).imports);
+        assert!(content.contains("use crate::model::Order;"));
+    }
+
+    #[test]
+    fn test_trait_impls_grouped_by_origin() {
+        let file: File = syn::parse_quote! {
+            use serde::Serialize;
+            pub trait Shape { fn area(&self) -> f64; }
+            pub struct Square;
+            impl Clone for Square { fn clone(&self) -> Self { Square } }
+            impl Serialize for Square { fn serialize(&self) {} }
+            impl Shape for Square { fn area(&self) -> f64 { 1.0 } }
+            pub struct Circle;
+            impl Clone for Circle { fn clone(&self) -> Self { Circle } }
+        };
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.group_trait_impls_by_origin();
+        analyzer.analyze(&file, &AnalysisModel::build(&file));
+
+        let modules = analyzer.group_by_module(1000);
+        let mut names: Vec<&str> = modules
+            .iter()
+            .filter(|m| !m.trait_impls.is_empty())
+            .map(|m| m.name.as_str())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "circle_traits",
+                "square_external_traits",
+                "square_local_traits",
+                "square_std_traits"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generic_trait_impls_are_listed_apart() {
+        let file: File = syn::parse_quote! {
+            pub struct Id(u64);
+            impl From<String> for Id { fn from(s: String) -> Self { Id(s.len() as u64) } }
+            impl From<u64> for Id { fn from(n: u64) -> Self { Id(n) } }
+            impl<'a> From<&'a str> for Id { fn from(s: &'a str) -> Self { Id(s.len() as u64) } }
+        };
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.analyze(&file, &AnalysisModel::build(&file));
+
+        let modules = analyzer.group_by_module(1000);
+        let traits = modules.iter().find(|m| m.name == "id_traits").unwrap();
+        let names: Vec<&str> = traits
+            .trait_impls
+            .iter()
+            .map(|t| t.trait_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["From<String>", "From<u64>", "From<&'a str>"]);
+        assert_eq!(
+            compact_tokens("HashMap < K , Vec < u8 > >"),
+            "HashMap<K, Vec<u8>>"
+        );
+    }
+
+    #[test]
+    fn test_fmt_impls_get_own_module() {
+        let file: File = syn::parse_quote! {
+            use std::fmt;
+            use std::fmt::{Display, Formatter};
+            pub struct Id(u64);
+            impl fmt::Debug for Id {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { Ok(()) }
+            }
+            impl Display for Id {
+                fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { Ok(()) }
+            }
+            impl Clone for Id { fn clone(&self) -> Self { Id(self.0) } }
+        };
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.group_fmt_impls();
+        analyzer.analyze(&file, &AnalysisModel::build(&file));
+
+        let modules = analyzer.group_by_module(1000);
+        let traits = modules.iter().find(|m| m.name == "id_traits").unwrap();
+        assert_eq!(traits.trait_impls[0].trait_name, "Clone");
+        let fmt = modules.iter().find(|m| m.name == "id_fmt").unwrap();
+        let names: Vec<&str> = fmt
+            .trait_impls
+            .iter()
+            .map(|t| t.trait_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Debug", "Display"]);
+        assert_eq!(
+            fmt.fmt_use_statement().as_deref(),
+            Some("use std::fmt::{self, Display, Formatter};")
+        );
+    }
+
+    #[test]
+    fn test_small_trait_impls_are_packed_by_trait() {
+        let file: File = syn::parse_quote! {
+            use std::fmt;
+            pub struct Meters(f64);
+            pub struct Grams(f64);
+            impl fmt::Display for Meters {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}m", self.0) }
+            }
+            impl fmt::Display for Grams {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}g", self.0) }
+            }
+            impl From<f64> for Grams { fn from(value: f64) -> Self { Grams(value) } }
+        };
+        let model = AnalysisModel::build(&file);
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.pack_trait_impls(40);
+        analyzer.analyze(&file, &model);
+
+        let modules = analyzer.group_by_module(1000);
+        let names: Vec<&str> = modules.iter().map(|m| m.name.as_str()).collect();
+        assert!(names.contains(&"display_impls") && names.contains(&"from_impls"));
+        assert!(!names.iter().any(|name| name.ends_with("_traits")));
+        let display = modules.iter().find(|m| m.name == "display_impls").unwrap();
+        assert_eq!(display.trait_impls.len(), 2);
+        let content = display.generate_content(&model.imports);
+        assert!(content.contains("use super::types::{Meters, Grams};"));
+        assert!(content.contains("use std::fmt;"));
+    }
+
+    #[test]
+    fn test_extern_blocks_move_whole_to_ffi_module() {
+        let file: File = syn::parse_quote! {
+            #[link(name = "ssl")]
+            extern "C" {
+                fn SSL_library_init() -> i32;
+                pub fn SSL_free(ssl: *mut u8);
+            }
+            pub struct Ctx { raw: *mut u8 }
+            impl Ctx {
+                pub fn new() -> Self {
+                    unsafe { SSL_library_init() };
+                    Ctx { raw: std::ptr::null_mut() }
+                }
+            }
+            pub fn free(ctx: Ctx) { unsafe { SSL_free(ctx.raw) } }
+        };
+        let model = AnalysisModel::build(&file);
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.analyze(&file, &model);
+
+        let modules = analyzer.group_by_module(1000);
+        let ffi = modules.iter().find(|m| m.name == "ffi").unwrap();
+        assert_eq!(ffi.standalone_items.len(), 1);
+        assert_eq!(ffi.widened, vec!["SSL_library_init"]);
+        assert!(ffi
+            .generate_content(&model.imports)
+            .contains("#[link(name = \"ssl\")]\nextern \"C\" {\n    pub(super) fn SSL_library_init() -> i32;\n    pub fn SSL_free(ssl: *mut u8);\n}"));
+        let types = modules.iter().find(|m| m.name == "types").unwrap();
+        assert_eq!(types.extra_uses, vec!["use super::ffi::SSL_library_init;"]);
+        let functions = modules.iter().find(|m| m.name == "functions").unwrap();
+        assert_eq!(functions.extra_uses, vec!["use super::ffi::SSL_free;"]);
+    }
+
+    #[test]
+    fn test_gated_trait_impls_get_gated_module() {
+        let file: File = syn::parse_quote! {
+            pub struct Id(u64);
+            impl Clone for Id { fn clone(&self) -> Self { Id(self.0) } }
+            #[cfg(feature = "serde")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+            impl serde::Serialize for Id {}
+            #[cfg(feature = "serde")]
+            impl<'de> serde::Deserialize<'de> for Id {}
+        };
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.analyze(&file, &AnalysisModel::build(&file));
+
+        let modules = analyzer.group_by_module(1000);
+        let traits = modules.iter().find(|m| m.name == "id_traits").unwrap();
+        assert_eq!(traits.trait_impls.len(), 1);
+        assert!(traits.gate.is_empty());
+        let gated = modules
+            .iter()
+            .find(|m| m.name == "id_serde_traits")
+            .unwrap();
+        assert_eq!(gated.trait_impls.len(), 2);
+        assert_eq!(
+            module_declaration(gated),
+            "#[cfg(feature = \"serde\")]\n#[cfg_attr(docsrs, doc(cfg(feature = \"serde\")))]\n\
+             pub mod id_serde_traits;\n"
+        );
+    }
+
+    #[test]
+    fn test_gated_type_modules_share_its_cfg() {
+        let file: File = syn::parse_quote! {
+            #[cfg(feature = "wire")]
+            pub struct Wire(u8);
+            #[cfg(feature = "wire")]
+            impl Clone for Wire { fn clone(&self) -> Self { Wire(self.0) } }
+            #[cfg(unix)]
+            impl Default for Wire { fn default() -> Self { Wire(0) } }
+        };
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.analyze(&file, &AnalysisModel::build(&file));
+
+        let modules = analyzer.group_by_module(1000);
+        let traits = modules.iter().find(|m| m.name == "wire_traits").unwrap();
+        assert_eq!(traits.trait_impls.len(), 1);
+        assert_eq!(
+            module_declaration(traits),
+            "#[cfg(feature = \"wire\")]\npub mod wire_traits;\n"
+        );
+        let unix = modules
+            .iter()
+            .find(|m| m.name == "wire_unix_traits")
+            .unwrap();
+        assert_eq!(
+            module_declaration(unix),
+            "#[cfg(feature = \"wire\")]\n#[cfg(unix)]\npub mod wire_unix_traits;\n"
+        );
+    }
+
+    #[test]
+    fn test_trait_impl_helpers_stay_in_scope() {
+        let file: File = syn::parse_quote! {
+            const CURRENCY: &str = "EUR";
+            fn fmt_money(cents: u64) -> String { format!("{} {}", cents, CURRENCY) }
+            fn label() -> &'static str { "price" }
+            pub struct Price(u64);
+            pub struct Fee(u64);
+            impl Display for Price {
+                fn fmt(&self, f: &mut Formatter) -> Result { write!(f, "{}", fmt_money(self.0)) }
+            }
+            impl Display for Fee {
+                fn fmt(&self, f: &mut Formatter) -> Result {
+                    write!(f, "{} {}", label(), fmt_money(self.0))
+                }
+            }
+        };
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.analyze(&file, &AnalysisModel::build(&file));
+
+        let modules = analyzer.group_by_module(1000);
+        let fee = modules.iter().find(|m| m.name == "fee_traits").unwrap();
+        let moved: Vec<String> = fee
+            .standalone_items
+            .iter()
+            .filter_map(import_analyzer::declared_ident)
+            .collect();
+        assert_eq!(moved, vec!["label"]);
+        assert_eq!(fee.extra_uses, vec!["use super::functions::fmt_money;"]);
+        let price = modules.iter().find(|m| m.name == "price_traits").unwrap();
+        assert_eq!(price.extra_uses, vec!["use super::functions::fmt_money;"]);
+        let functions = modules.iter().find(|m| m.name == "functions").unwrap();
+        assert_eq!(functions.widened, vec!["fmt_money"]);
+    }
+
+    #[test]
+    fn test_deprecated_items_get_own_module() {
+        let file: File = syn::parse_quote! {
+            pub struct Store;
+            #[deprecated]
+            pub struct OldStore { inner: Store }
+            impl Store {
+                pub fn get(&self) {}
+                #[deprecated]
+                pub fn fetch(&self) {}
+            }
+            pub fn modern(s: &OldStore) {}
+        };
+        let mut analyzer = FileAnalyzer::new(false, 500);
+        analyzer.segregate_deprecated("deprecated");
+        analyzer.analyze(&file, &AnalysisModel::build(&file));
+
+        let modules = analyzer.group_by_module(1000);
+        let deprecated = modules.iter().find(|m| m.deprecated).unwrap();
+        assert_eq!(deprecated.types[0].name, "OldStore");
+        assert_eq!(deprecated.standalone_items.len(), 1);
+        assert_eq!(deprecated.extra_uses, vec!["use super::types::Store;"]);
+
+        let types = modules.iter().find(|m| m.name == "types").unwrap();
+        let Item::Impl(store_impl) = &types.types[0].impls[0] else {
+            panic!("expected impl");
+        };
+        assert_eq!(store_impl.items.len(), 1);
+
+        let functions = modules.iter().find(|m| m.name == "functions").unwrap();
+        assert_eq!(
+            functions.extra_uses,
+            vec!["#[allow(deprecated)]\nuse super::deprecated::OldStore;"]
+        );
+    }
+
+    #[test]
+    fn test_subcommands_load_the_configuration_of_their_input() {
+        let dir =
+            std::env::temp_dir().join(format!("splitrs_command_config_{}", std::process::id()));
+        let project = dir.join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(
+            project.join(".splitrs.toml"),
+            "[check]\nwarn_lines = 1\nerror_lines = 2\n",
+        )
+        .unwrap();
+        let input = project.join("src").join("big.rs");
+        fs::write(&input, "pub fn a() {}\npub fn b() {}\npub fn c() {}\n").unwrap();
+        let lenient = dir.join("lenient.toml");
+        fs::write(&lenient, "[check]\nerror_lines = 100\n").unwrap();
+
+        let check = |extra: &[&std::ffi::OsStr]| {
+            let mut args: Vec<&std::ffi::OsStr> = vec!["splitrs".as_ref(), "check".as_ref()];
+            args.extend_from_slice(extra);
+            args.push(input.as_os_str());
+            run_from(args, &RealFileSystem, &mut CollectingReporter::default())
+        };
+        let error = check(&[]).unwrap_err();
+        assert!(error.to_string().contains("exceed the error thresholds"));
+        check(&["--config".as_ref(), lenient.as_os_str()]).unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_splits_load_the_configuration_of_their_input() {
+        let dir = std::env::temp_dir().join(format!("splitrs_split_config_{}", std::process::id()));
+        let project = dir.join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(project.join(".splitrs.toml"), "[splitrs]\nmax_lines = 77\n").unwrap();
+        let input = project.join("src").join("big.rs");
+        fs::write(&input, "pub struct A;\npub fn f() {}\n").unwrap();
+        let output = project.join("src").join("big");
+
+        let mut reporter = CollectingReporter::default();
+        run_from(
+            [
+                "splitrs".as_ref(),
+                "-i".as_ref(),
+                input.as_os_str(),
+                "-o".as_ref(),
+                output.as_os_str(),
+                "--dry-run".as_ref(),
+            ],
+            &RealFileSystem,
+            &mut reporter,
+        )
+        .unwrap();
+        assert!(reporter.output.contains("Max lines per module: 77"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_from_splits_in_a_memory_file_system() {
+        let input = Path::new("/splitrs-memory/src/big.rs");
+        let output = Path::new("/splitrs-memory/src/big");
+        let file_system = MemoryFileSystem::new().with_file(
+            input,
+            "pub struct A;\nimpl A { pub fn a(&self) {} }\npub fn f() {}\n",
+        );
+        let mut reporter = CollectingReporter::default();
+        run_from(
+            [
+                "splitrs".as_ref(),
+                "-i".as_ref(),
+                input.as_os_str(),
+                "-o".as_ref(),
+                output.as_os_str(),
+            ],
+            &file_system,
+            &mut reporter,
+        )
+        .unwrap();
+
+        let files = file_system.files();
+        assert!(files[&output.join("mod.rs")].contains("pub mod "));
+        assert!(files
+            .keys()
+            .any(|path| path.starts_with("/splitrs-memory/src/.splitrs/backups")));
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn test_strict_refuses_names_the_module_does_not_import() {
+        let input = Path::new("/splitrs-strict/src/big.rs");
+        let output = Path::new("/splitrs-strict/src/big");
+        let file_system = MemoryFileSystem::new().with_file(
+            input,
+            "pub enum Kind { A, B }\n\n\
+             pub fn classify(n: u32) -> Kind {\n    if n > 1 { Kind::A } else { Kind::B }\n}\n",
+        );
+        let mut reporter = CollectingReporter::default();
+        let error = run_from(
+            [
+                "splitrs".as_ref(),
+                "-i".as_ref(),
+                input.as_os_str(),
+                "-o".as_ref(),
+                output.as_os_str(),
+                "--strict".as_ref(),
+            ],
+            &file_system,
+            &mut reporter,
+        )
+        .unwrap_err();
+
+        // The original file defines `Kind`, but functions.rs does not import it
+        assert!(error
+            .to_string()
+            .contains("functions.rs: unresolved name `Kind`"));
+        assert!(!file_system.exists(&output.join("mod.rs")));
+    }
+
+    #[test]
+    fn test_in_place_split_keeps_or_restores_the_input() {
+        let input = Path::new("/splitrs-in-place/src/big.rs");
+        let lib = Path::new("/splitrs-in-place/src/lib.rs");
+        let source = "pub struct A;\nimpl A { pub fn a(&self) {} }\npub fn f() {}\n";
+        let file_system = MemoryFileSystem::new()
+            .with_file(input, source)
+            .with_file(lib, "pub fn run() {}\n");
+        let mut reporter = CollectingReporter::default();
+        run_from(
+            [
+                "splitrs".as_ref(),
+                "-i".as_ref(),
+                input.as_os_str(),
+                "--in-place".as_ref(),
+                "--interactive".as_ref(),
+            ],
+            &file_system,
+            &mut reporter,
+        )
+        .unwrap();
+        assert!(reporter.output.contains("Operation cancelled by user"));
+        assert_eq!(file_system.files().len(), 2);
+        assert_eq!(file_system.read(input).unwrap(), source);
+
+        run_from(
+            [
+                "splitrs".as_ref(),
+                "-i".as_ref(),
+                input.as_os_str(),
+                "--in-place".as_ref(),
+            ],
+            &file_system,
+            &mut reporter,
+        )
+        .unwrap();
+        assert!(!file_system.exists(input));
+        assert!(file_system.exists(Path::new("/splitrs-in-place/src/big/mod.rs")));
+        assert!(file_system.read(lib).unwrap().contains("mod big;"));
+        let root = Path::new("/splitrs-in-place/src/.splitrs/backups");
+        assert_eq!(backup::list(&file_system, root).unwrap().len(), 1);
+
+        // Restoring undoes the split: the directory and the parent's edit go
+        backup::restore(&file_system, root, "latest").unwrap();
+        assert_eq!(file_system.read(input).unwrap(), source);
+        assert_eq!(file_system.read(lib).unwrap(), "pub fn run() {}\n");
+        assert!(!file_system.exists(Path::new("/splitrs-in-place/src/big")));
+    }
+
+    #[test]
+    fn test_target_file_is_written_into_the_output_directory() {
+        let root = std::env::temp_dir().join(format!("splitrs_target_out_{}", std::process::id()));
+        let input = root.join("tests/api.rs");
+        let output = root.join("split");
+        fs::create_dir_all(root.join("tests")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+        let source = "pub struct A;\nimpl A { pub fn a(&self) {} }\npub fn f() {}\n";
+        fs::write(&input, source).unwrap();
+
+        let mut reporter = CollectingReporter::default();
+        run_from(
+            [
+                "splitrs".as_ref(),
+                "-i".as_ref(),
+                input.as_os_str(),
+                "-o".as_ref(),
+                output.as_os_str(),
+            ],
+            &RealFileSystem,
+            &mut reporter,
+        )
+        .unwrap();
+
+        // The source stays untouched and only the inline file is reported
+        assert_eq!(fs::read_to_string(&input).unwrap(), source);
+        let inline = fs::read_to_string(output.join("api.rs")).unwrap();
+        assert!(inline.contains("pub mod "));
+        assert!(!output.join("mod.rs").exists());
+        assert!(!reporter.output.contains("mod.rs"));
+        assert!(reporter.output.contains("Created: "));
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_crate_run_backs_up_every_file_in_one_run() {
+        let dir =
+            std::env::temp_dir().join(format!("splitrs_crate_backups_{}", std::process::id()));
+        let src = dir.join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        fs::write(src.join("lib.rs"), "mod alpha;\nmod beta;\n").unwrap();
+        let big = |name: &str| {
+            format!(
+                "pub struct {0};\nimpl {0} {{\n    pub fn get(&self) -> u32 {{\n        1\n    }}\n}}\npub fn make() -> {0} {{\n    {0}\n}}\n",
+                name
+            )
+        };
+        fs::write(src.join("alpha.rs"), big("Alpha")).unwrap();
+        fs::write(src.join("beta.rs"), big("Beta")).unwrap();
+        let run = |extra: &[&str]| {
+            let mut args = vec!["splitrs", "--crate", dir.to_str().unwrap(), "-m", "4"];
+            args.extend_from_slice(extra);
+            let mut reporter = CollectingReporter::default();
+            let result = run_from(args, &RealFileSystem, &mut reporter);
+            (result, reporter.output)
+        };
+
+        // Declining the question keeps every file
+        let (result, output) = run(&["--interactive"]);
+        assert!(result.is_err());
+        assert!(output.contains("Operation cancelled by user"));
+        assert!(src.join("alpha.rs").exists() && src.join("beta.rs").exists());
+
+        run(&[]).0.unwrap();
+        assert!(!src.join("alpha.rs").exists() && !src.join("beta.rs").exists());
+        let root = dir.join(".splitrs/backups");
+        let backups = backup::list(&RealFileSystem, &root).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(backups[0].0.ends_with("/002") && backups[1].0.ends_with("/001"));
+
+        backup::restore(&RealFileSystem, &root, "latest").unwrap();
+        assert_eq!(
+            fs::read_to_string(src.join("alpha.rs")).unwrap(),
+            big("Alpha")
+        );
+        assert_eq!(
+            fs::read_to_string(src.join("beta.rs")).unwrap(),
+            big("Beta")
+        );
+        assert!(!src.join("alpha").exists() && !src.join("beta").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,526 @@
+//! Merging a module directory back into a single file
+//!
+//! This is the inverse of splitting: given a directory with a `mod.rs` and
+//! the submodules it declares, every file-backed submodule is inlined into
+//! one flat file. Imports are consolidated (duplicates and imports between
+//! the merged modules are dropped), `pub(super)` visibility that only
+//! existed to cross the module boundary is removed, and both item docs and
+//! hand-written module docs are preserved. `super::` paths of the inlined
+//! items are rewritten for their new place: a path into the merged
+//! directory starts at the merged file, one leaving it keeps going up, and
+//! `use super::*` of a submodule is dropped as the merged file holds it all.
+
+use crate::file_system::FileSystem;
+use crate::multi_input::{use_targets_sibling, SiblingPathRewriter, UseDeduplicator};
+use anyhow::{Context, Result};
+use quote::ToTokens;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use syn::visit_mut::VisitMut;
+use syn::{Attribute, File, Item, ItemMod, UseTree, Visibility};
+
+/// Result of merging a module directory
+pub struct MergeOutcome {
+    /// The merged syntax tree
+    pub file: File,
+
+    /// Names of the submodules that were inlined, in declaration order
+    pub merged_modules: Vec<String>,
+
+    /// Files that were read, in declaration order (starting with `mod.rs`)
+    pub source_files: Vec<PathBuf>,
+
+    /// Non-`use` items of each module before merging, keyed by module path
+    /// (`a::b` for nested modules, empty for `mod.rs` itself)
+    pub module_items: Vec<(String, Vec<Item>)>,
+}
+
+impl MergeOutcome {
+    /// Render the merged file with prettyplease
+    pub fn render(&self) -> String {
+        prettyplease::unparse(&self.file)
+    }
+}
+
+/// A file-backed module collected from the directory
+struct CollectedModule {
+    path: String,
+    depth: usize,
+    items: Vec<Item>,
+    docs: Vec<Attribute>,
+    from_submodule: bool,
+}
+
+/// Merge `dir/mod.rs` and all file-backed submodules it declares
+pub fn merge_module_dir(file_system: &dyn FileSystem, dir: &Path) -> Result<MergeOutcome> {
+    let root_path = dir.join("mod.rs");
+    let root_source = file_system.read(&root_path)?;
+    let root: File =
+        syn::parse_file(&root_source).context(format!("Failed to parse {:?}", root_path))?;
+
+    let mut merged_modules = Vec::new();
+    let mut source_files = vec![root_path];
+    let mut collected = Vec::new();
+    let root_items = collect_modules(
+        file_system,
+        dir,
+        "",
+        root.items,
+        &mut merged_modules,
+        &mut source_files,
+        &mut collected,
+    )?;
+    collected.insert(
+        0,
+        CollectedModule {
+            path: String::new(),
+            depth: 0,
+            items: root_items,
+            docs: Vec::new(),
+            from_submodule: false,
+        },
+    );
+
+    let sibling_names: HashSet<String> = merged_modules.iter().cloned().collect();
+    let mut rewriter = SiblingPathRewriter::new(&sibling_names);
+    let mut seen_uses = UseDeduplicator::default();
+    let mut uses = Vec::new();
+    let mut items = Vec::new();
+    let mut attrs = strip_generated_docs(root.attrs);
+    let mut module_items = Vec::new();
+
+    for module in collected {
+        module_items.push((
+            module.path.clone(),
+            module
+                .items
+                .iter()
+                .filter(|i| !matches!(i, Item::Use(_)))
+                .cloned()
+                .collect(),
+        ));
+
+        if !module.docs.iter().any(is_generated_doc) {
+            attrs.extend(module.docs);
+        }
+
+        let mut super_paths = SuperPathRewriter {
+            depth: module.depth,
+            nesting: 0,
+        };
+        for mut item in module.items {
+            if let Item::Use(u) = &item {
+                if use_targets_sibling(&u.tree, &sibling_names)
+                    || use_targets_merged_file(&u.tree, module.depth)
+                {
+                    continue;
+                }
+            }
+
+            if module.from_submodule {
+                SuperVisibilityRemover.visit_item_mut(&mut item);
+                super_paths.visit_item_mut(&mut item);
+            }
+            rewriter.visit_item_mut(&mut item);
+
+            match item {
+                Item::Use(u) => uses.extend(seen_uses.dedup(u).0.map(Item::Use)),
+                item => items.push(item),
+            }
+        }
+    }
+
+    uses.sort_by_key(|u| u.to_token_stream().to_string());
+    uses.extend(items);
+
+    Ok(MergeOutcome {
+        file: File {
+            shebang: root.shebang,
+            attrs,
+            items: uses,
+        },
+        merged_modules,
+        source_files,
+        module_items,
+    })
+}
+
+/// Split items into file-backed modules (collected recursively) and the rest
+fn collect_modules(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+    parent_path: &str,
+    items: Vec<Item>,
+    merged_modules: &mut Vec<String>,
+    source_files: &mut Vec<PathBuf>,
+    collected: &mut Vec<CollectedModule>,
+) -> Result<Vec<Item>> {
+    let mut remaining = Vec::new();
+
+    for item in items {
+        let Item::Mod(m) = &item else {
+            remaining.push(item);
+            continue;
+        };
+        if m.content.is_some() {
+            remaining.push(item);
+            continue;
+        }
+
+        let name = m.ident.to_string();
+        let Some(path) = module_file(file_system, dir, &name, &m.attrs) else {
+            // Declared but not present in the directory: keep the declaration
+            remaining.push(item);
+            continue;
+        };
+
+        let source = file_system.read(&path)?;
+        let file: File =
+            syn::parse_file(&source).context(format!("Failed to parse module {:?}", path))?;
+
+        let module_path = if parent_path.is_empty() {
+            name.clone()
+        } else {
+            format!("{}::{}", parent_path, name)
+        };
+        merged_modules.push(name);
+        source_files.push(path.clone());
+
+        let inner_docs: Vec<Attribute> = file
+            .attrs
+            .into_iter()
+            .filter(|a| a.path().is_ident("doc"))
+            .collect();
+        let nested_dir = if path.file_name().is_some_and(|f| f == "mod.rs") {
+            path.parent().map(Path::to_path_buf).unwrap_or_default()
+        } else {
+            dir.join(m.ident.to_string())
+        };
+        let module_items = collect_modules(
+            file_system,
+            &nested_dir,
+            &module_path,
+            file.items,
+            merged_modules,
+            source_files,
+            collected,
+        )?;
+
+        collected.push(CollectedModule {
+            depth: module_path.split("::").count(),
+            path: module_path,
+            items: module_items,
+            docs: inner_docs,
+            from_submodule: true,
+        });
+    }
+
+    Ok(remaining)
+}
+
+/// Locate the file backing `mod name;` inside `dir`
+pub fn module_file(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+    name: &str,
+    attrs: &[Attribute],
+) -> Option<PathBuf> {
+    for attr in attrs {
+        if attr.path().is_ident("path") {
+            if let syn::Meta::NameValue(nv) = &attr.meta {
+                if let syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(s),
+                    ..
+                }) = &nv.value
+                {
+                    let path = dir.join(s.value());
+                    return file_system.exists(&path).then_some(path);
+                }
+            }
+        }
+    }
+
+    let flat = dir.join(format!("{}.rs", name));
+    if file_system.exists(&flat) {
+        return Some(flat);
+    }
+    let nested = dir.join(name).join("mod.rs");
+    file_system.exists(&nested).then_some(nested)
+}
+
+/// Whether a module doc line was produced by SplitRS rather than a person
+///
+/// A module whose docs contain any generated line is treated as entirely
+/// generated, since SplitRS writes the whole header block.
+fn is_generated_doc(attr: &Attribute) -> bool {
+    let text = attr.to_token_stream().to_string();
+    text.contains("Auto-generated module") || text.contains("Generated with [SplitRS]")
+}
+
+/// Drop the root module's doc block if SplitRS generated it
+fn strip_generated_docs(attrs: Vec<Attribute>) -> Vec<Attribute> {
+    let generated = attrs.iter().any(is_generated_doc);
+    attrs
+        .into_iter()
+        .filter(|a| !(generated && a.path().is_ident("doc")))
+        .collect()
+}
+
+/// Turns `pub(super)` into private visibility for items lifted one level up
+///
+/// Inline modules inside the items keep theirs: their parent did not move.
+struct SuperVisibilityRemover;
+
+impl VisitMut for SuperVisibilityRemover {
+    fn visit_visibility_mut(&mut self, vis: &mut Visibility) {
+        if let Visibility::Restricted(r) = vis {
+            if r.in_token.is_none() && r.path.is_ident("super") {
+                *vis = Visibility::Inherited;
+            }
+        }
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut ItemMod) {
+        self.visit_visibility_mut(&mut module.vis);
+    }
+}
+
+/// Whether a `use` of a submodule `depth` levels down only imports names
+/// of modules that are merged (`use super::*`, `use super::{Config, run}`)
+fn use_targets_merged_file(tree: &UseTree, depth: usize) -> bool {
+    fn only_names(tree: &UseTree) -> bool {
+        match tree {
+            UseTree::Name(name) => name.ident != "self",
+            UseTree::Glob(_) => true,
+            UseTree::Group(group) => group.items.iter().all(only_names),
+            UseTree::Path(_) | UseTree::Rename(_) => false,
+        }
+    }
+    let (ups, rest) = leading_supers(tree);
+    ups > 0 && ups <= depth && only_names(rest)
+}
+
+/// Number of leading `super` segments of a use tree, and the tree after them
+fn leading_supers(tree: &UseTree) -> (usize, &UseTree) {
+    let mut ups = 0;
+    let mut rest = tree;
+    while let UseTree::Path(path) = rest {
+        if path.ident != "super" {
+            break;
+        }
+        ups += 1;
+        rest = &path.tree;
+    }
+    (ups, rest)
+}
+
+/// Rewrites the `super::` paths of items lifted from a submodule `depth`
+/// levels below the merged file
+///
+/// Inside `nesting` inline modules of an item, a path going up `k` levels
+/// with `k <= nesting` stays inside the item. Any other path reaches a
+/// module that is now the merged file, or one above it, so it goes up
+/// `max(nesting, k - depth)` levels instead (`self::` for none).
+struct SuperPathRewriter {
+    depth: usize,
+    nesting: usize,
+}
+
+impl SuperPathRewriter {
+    /// Levels to go up instead of `ups`, if the path leaves the item
+    fn rewritten(&self, ups: usize) -> Option<usize> {
+        (ups > self.nesting).then(|| self.nesting.max(ups.saturating_sub(self.depth)))
+    }
+
+    fn rewrite_use_tree(&self, tree: &mut UseTree) {
+        if let UseTree::Group(group) = tree {
+            for item in &mut group.items {
+                self.rewrite_use_tree(item);
+            }
+            return;
+        }
+        let (ups, rest) = leading_supers(tree);
+        let Some(levels) = self.rewritten(ups) else {
+            return;
+        };
+        let mut rewritten = rest.clone();
+        if levels == 0 {
+            rewritten = syn::parse_quote!(self::#rewritten);
+        }
+        for _ in 0..levels {
+            rewritten = syn::parse_quote!(super::#rewritten);
+        }
+        *tree = rewritten;
+    }
+}
+
+impl VisitMut for SuperPathRewriter {
+    fn visit_item_mod_mut(&mut self, module: &mut ItemMod) {
+        self.visit_visibility_mut(&mut module.vis);
+        if let Some((_, items)) = &mut module.content {
+            self.nesting += 1;
+            for item in items {
+                self.visit_item_mut(item);
+            }
+            self.nesting -= 1;
+        }
+    }
+
+    fn visit_item_use_mut(&mut self, item: &mut syn::ItemUse) {
+        self.rewrite_use_tree(&mut item.tree);
+    }
+
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        let ups = path
+            .segments
+            .iter()
+            .take_while(|segment| segment.ident == "super")
+            .count();
+        if let Some(levels) = self.rewritten(ups) {
+            let rest: Vec<syn::PathSegment> = path.segments.iter().skip(ups).cloned().collect();
+            let up: syn::PathSegment = if levels == 0 {
+                syn::parse_quote!(self)
+            } else {
+                syn::parse_quote!(super)
+            };
+            path.segments = std::iter::repeat_n(up, levels.max(1)).collect();
+            path.segments.extend(rest);
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::RealFileSystem;
+    use std::fs;
+
+    fn write_fixture(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("splitrs_merge_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("mod.rs"),
+            "//! Auto-generated module structure\n\
+             pub mod user_type;\npub mod user_traits;\n\
+             pub use user_type::*;\npub use user_traits::*;\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("user_type.rs"),
+            "//! Users of the system\nuse std::collections::HashMap;\n\
+             /// A user\npub struct User { pub(super) tags: HashMap<String, u32> }\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("user_traits.rs"),
+            "use std::collections::HashMap;\nuse super::user_type::User;\n\
+             impl Default for User { fn default() -> Self { User { tags: HashMap::new() } } }\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_merge_inlines_and_consolidates() {
+        let dir = write_fixture("inline");
+        let outcome = merge_module_dir(&RealFileSystem, &dir).unwrap();
+
+        assert_eq!(outcome.merged_modules, vec!["user_type", "user_traits"]);
+        let code = outcome.render();
+
+        assert_eq!(code.matches("use std::collections::HashMap;").count(), 1);
+        assert!(!code.contains("user_type"));
+        assert!(!code.contains("pub(super)"));
+        assert!(code.contains("/// A user"));
+        assert!(code.contains("//! Users of the system"));
+        assert!(!code.contains("Auto-generated"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_super_paths_follow_the_merged_items() {
+        let dir = std::env::temp_dir().join("splitrs_merge_super");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("engine")).unwrap();
+        fs::write(
+            dir.join("mod.rs"),
+            "pub mod engine;\npub use engine::*;\n\nfn helper() -> u32 {\n    1\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("engine/mod.rs"),
+            "use super::helper;\npub mod parts;\n\n\
+             pub fn run() -> u32 {\n    helper() + super::helper() + parts::part()\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("engine/parts.rs"),
+            "use super::super::*;\n\n\
+             pub(super) fn part() -> u32 {\n    super::super::helper() + inner::deep()\n}\n\n\
+             mod inner {\n    pub(super) fn deep() -> u32 {\n        \
+             super::super::super::helper()\n    }\n}\n",
+        )
+        .unwrap();
+
+        let code = merge_module_dir(&RealFileSystem, &dir).unwrap().render();
+        assert!(!code.contains("use super"));
+        assert!(code.contains("helper() + self::helper() + part()"));
+        assert!(code.contains("self::helper() + inner::deep()"));
+        assert!(code.contains("pub(super) fn deep() -> u32 {\n        super::helper()"));
+
+        // The merged file compiles as a module of its own
+        let merged = dir.join("merged");
+        fs::create_dir_all(&merged).unwrap();
+        fs::write(merged.join("mod.rs"), &code).unwrap();
+        let errors = crate::verify::check_scaffold(&merged).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn test_merging_split_output_compiles() {
+        let source = "use std::fmt::{self, Display};\n\n\
+             pub struct Point {\n    pub x: u32,\n}\n\n\
+             impl Point {\n    pub fn show(&self) -> String {\n        format!(\"{}\", self)\n    }\n}\n\n\
+             impl Display for Point {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {\n        \
+             write!(f, \"{}\", self.x)\n    }\n}\n\n\
+             pub fn describe(value: &dyn Display) -> String {\n    value.to_string()\n}\n";
+        let dir = std::env::temp_dir().join("splitrs_merge_split");
+        let _ = fs::remove_dir_all(&dir);
+        let mut grouped = false;
+        for (path, mut content) in crate::split_source(source, &crate::Config::default()).unwrap() {
+            // One module imports through a group, as after a hand edit
+            if !grouped && content.contains("use std::fmt::Display;\n") {
+                content = content.replace(
+                    "use std::fmt::Display;\n",
+                    "use std::fmt::{self, Display};\n",
+                );
+                grouped = true;
+            }
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        assert!(grouped);
+
+        let code = merge_module_dir(&RealFileSystem, &dir).unwrap().render();
+        assert!(syn::parse_file(&code).is_ok());
+
+        let merged = dir.join("merged");
+        fs::create_dir_all(&merged).unwrap();
+        fs::write(merged.join("mod.rs"), &code).unwrap();
+        let errors = crate::verify::check_scaffold(&merged).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert!(errors.is_empty(), "{:?}\n{}", errors, code);
+    }
+
+    #[test]
+    fn test_missing_mod_rs_is_an_error() {
+        let dir = std::env::temp_dir().join("splitrs_merge_missing");
+        let _ = fs::create_dir_all(&dir);
+        assert!(merge_module_dir(&RealFileSystem, &dir).is_err());
+    }
+}
@@ -1,7 +1,11 @@
 //! Method boundary detection and analysis for splitting large impl blocks
 
-use std::collections::{HashMap, HashSet};
-use syn::{visit::Visit, Expr, ExprCall, ExprMethodCall, ImplItem, ImplItemFn, ItemImpl};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{
+    visit::Visit, Expr, ExprCall, ExprMethodCall, ExprPath, ImplItem, ImplItemFn, ItemImpl, Token,
+};
 
 /// Information about a method within an impl block
 #[derive(Clone)]
@@ -9,53 +13,226 @@ pub struct MethodInfo {
     pub name: String,
     pub item: ImplItemFn,
     pub calls_methods: HashSet<String>,
+    pub fields: BTreeSet<String>,
     pub line_count: usize,
 }
 
+impl MethodInfo {
+    /// Method info from the summary of `method`
+    pub fn from_summary(summary: &MethodSummary, method: &ImplItemFn) -> Self {
+        Self {
+            name: summary.name.clone(),
+            item: method.clone(),
+            calls_methods: summary.calls_methods.clone(),
+            fields: summary.fields.clone(),
+            line_count: summary.line_count,
+        }
+    }
+}
+
+/// What the analysis learns about a method, without its syntax tree
+///
+/// Syntax trees are neither `Send` nor `Sync`, summaries are, so the
+/// analysis model built from them can be shared across threads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodSummary {
+    pub name: String,
+    pub calls_methods: HashSet<String>,
+    /// Fields of `self` the method reads or writes
+    pub fields: BTreeSet<String>,
+    pub line_count: usize,
+}
+
+impl MethodSummary {
+    /// Summarize a method of `type_name`: its calls, fields and length
+    pub fn new(method: &ImplItemFn, type_name: &str) -> Self {
+        Self {
+            name: method.sig.ident.to_string(),
+            calls_methods: called_methods(method),
+            fields: crate::field_usage::self_fields(method, type_name),
+            line_count: measure_lines(method),
+        }
+    }
+}
+
+/// Impl blocks with at least this many methods are analyzed on several threads
+pub const PARALLEL_METHOD_THRESHOLD: usize = 256;
+
+/// Fewest methods worth handing to a worker thread
+const MIN_METHODS_PER_THREAD: usize = 64;
+
+/// Lines a method spans in the source, its docs and attributes included
+///
+/// Methods without source positions (built by macros rather than parsed
+/// from a file) fall back to an estimate from their tokens.
+fn measure_lines(method: &ImplItemFn) -> usize {
+    let span = method.span();
+    if span.byte_range().is_empty() {
+        return estimate_lines(&quote::ToTokens::to_token_stream(method).to_string());
+    }
+    span.end().line - span.start().line + 1
+}
+
+/// Line estimate for a method from its token stream text
+fn estimate_lines(tokens: &str) -> usize {
+    // Use heuristic for line count since token stream loses formatting
+    // Average Rust method is 25-35 lines; use token stream as base and multiply
+    let token_lines = tokens.lines().count();
+
+    // Heuristic: multiply by 15 to approximate real formatting
+    // A 2-line token stream method is typically ~30 lines in real code
+    token_lines.max(1) * 15
+}
+
+/// Summarize the methods of an impl block, in source order
+///
+/// Blocks of at least [`PARALLEL_METHOD_THRESHOLD`] methods are analyzed on
+/// several threads; the result is the same as summarizing each method with
+/// [`MethodSummary::new`].
+pub fn analyze_impl_methods(impl_block: &ItemImpl) -> Vec<MethodSummary> {
+    let type_name = self_type_name(impl_block);
+    let methods: Vec<&ImplItemFn> = impl_block
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            ImplItem::Fn(method) => Some(method),
+            _ => None,
+        })
+        .collect();
+    if methods.len() < PARALLEL_METHOD_THRESHOLD {
+        return methods
+            .iter()
+            .map(|method| MethodSummary::new(method, &type_name))
+            .collect();
+    }
+    analyze_methods_parallel(&methods, &type_name)
+}
+
+/// Calls and fields of one method, as found by a worker thread
+type MethodUses = (HashSet<String>, BTreeSet<String>);
+
+/// Analyze the methods of one huge impl block across threads
+///
+/// Syntax trees cannot cross threads (their spans are thread-bound), so the
+/// methods are split into one run per worker and each worker parses its run
+/// once, from the run's source text, to read calls and field accesses. Line
+/// counts come from the spans on the calling thread. Runs a worker fails to
+/// parse are analyzed sequentially.
+fn analyze_methods_parallel(methods: &[&ImplItemFn], type_name: &str) -> Vec<MethodSummary> {
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(methods.len() / MIN_METHODS_PER_THREAD)
+        .max(1);
+    let chunk_size = methods.len().div_ceil(threads).max(1);
+    let chunks: Vec<&[&ImplItemFn]> = methods.chunks(chunk_size).collect();
+    let texts: Vec<String> = chunks.iter().map(|chunk| run_text(chunk)).collect();
+
+    let results: Vec<Option<Vec<MethodUses>>> = std::thread::scope(|scope| {
+        let workers: Vec<_> = texts
+            .iter()
+            .zip(&chunks)
+            .map(|(text, chunk)| {
+                let expected = chunk.len();
+                scope.spawn(move || analyze_run(text, type_name, expected))
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("method analysis thread panicked"))
+            .collect()
+    });
+
+    chunks
+        .iter()
+        .zip(results)
+        .flat_map(|(chunk, uses)| match uses {
+            Some(uses) => chunk
+                .iter()
+                .zip(uses)
+                .map(|(method, (calls_methods, fields))| MethodSummary {
+                    name: method.sig.ident.to_string(),
+                    calls_methods,
+                    fields,
+                    line_count: measure_lines(method),
+                })
+                .collect::<Vec<_>>(),
+            None => chunk
+                .iter()
+                .map(|method| MethodSummary::new(method, type_name))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Source text of a run of consecutive methods
+///
+/// The slice of the input file they were parsed from, or their tokens when
+/// they carry no source positions.
+fn run_text(run: &[&ImplItemFn]) -> String {
+    let (Some(first), Some(last)) = (run.first(), run.last()) else {
+        return String::new();
+    };
+    first
+        .span()
+        .join(last.span())
+        .filter(|span| !span.byte_range().is_empty())
+        .and_then(|span| span.source_text())
+        .unwrap_or_else(|| quote::quote!(#(#run)*).to_string())
+}
+
+/// Parse a run of methods once and read the calls and fields of each
+///
+/// # Returns
+///
+/// `None` when the text does not parse back into `expected` methods.
+fn analyze_run(text: &str, type_name: &str, expected: usize) -> Option<Vec<MethodUses>> {
+    let block: ItemImpl = syn::parse_str(&format!("impl Run {{ {} }}", text)).ok()?;
+    let uses: Vec<MethodUses> = block
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            ImplItem::Fn(method) => Some((
+                called_methods(method),
+                crate::field_usage::self_fields(method, type_name),
+            )),
+            _ => None,
+        })
+        .collect();
+    (uses.len() == expected).then_some(uses)
+}
+
 /// Analyzer for impl blocks to detect method boundaries and dependencies
 pub struct ImplBlockAnalyzer {
     methods: Vec<MethodInfo>,
 }
 
 impl ImplBlockAnalyzer {
+    #[allow(dead_code)]
     pub fn new() -> Self {
         Self {
             methods: Vec::new(),
         }
     }
 
-    /// Analyze an impl block and extract method information
-    pub fn analyze(&mut self, impl_item: &ItemImpl) {
-        for item in &impl_item.items {
-            if let ImplItem::Fn(method) = item {
-                let method_info = self.analyze_method(method);
-                self.methods.push(method_info);
-            }
-        }
+    /// Create an analyzer from methods collected by a previous traversal
+    pub fn from_methods(methods: Vec<MethodInfo>) -> Self {
+        Self { methods }
     }
 
-    fn analyze_method(&self, method: &ImplItemFn) -> MethodInfo {
-        let name = method.sig.ident.to_string();
-        let mut visitor = MethodCallVisitor::new();
-        visitor.visit_impl_item_fn(method);
-
-        // Use heuristic for line count since token stream loses formatting
-        // Average Rust method is 25-35 lines; use token stream as base and multiply
-        let token_lines = quote::ToTokens::to_token_stream(method)
-            .to_string()
-            .lines()
-            .count();
-
-        // Heuristic: multiply by 15 to approximate real formatting
-        // A 2-line token stream method is typically ~30 lines in real code
-        let line_count = token_lines.max(1) * 15;
-
-        MethodInfo {
-            name,
-            item: method.clone(),
-            calls_methods: visitor.called_methods,
-            line_count,
-        }
+    /// Analyze an impl block and extract method information
+    #[allow(dead_code)]
+    pub fn analyze(&mut self, impl_item: &ItemImpl) {
+        let methods = impl_item.items.iter().filter_map(|item| match item {
+            ImplItem::Fn(method) => Some(method),
+            _ => None,
+        });
+        let summaries = analyze_impl_methods(impl_item);
+        self.methods.extend(
+            summaries
+                .iter()
+                .zip(methods)
+                .map(|(summary, method)| MethodInfo::from_summary(summary, method)),
+        );
     }
 
     /// Group methods into clusters based on dependencies
@@ -63,83 +240,277 @@ impl ImplBlockAnalyzer {
         // Build dependency graph
         let dep_graph = self.build_dependency_graph();
 
-        // Find strongly connected components (method clusters)
+        // Find connected components (method clusters)
         let clusters = self.find_clusters(&dep_graph);
 
         // Group clusters into modules respecting size limits
         self.create_groups(clusters, max_lines_per_group)
     }
 
-    fn build_dependency_graph(&self) -> HashMap<String, HashSet<String>> {
-        let mut graph = HashMap::new();
-
-        for method in &self.methods {
-            graph.insert(method.name.clone(), method.calls_methods.clone());
-        }
+    /// Adjacency lists by method index: method `i` calls every method in `graph[i]`
+    ///
+    /// Calls to names that are not methods of this impl block are dropped.
+    fn build_dependency_graph(&self) -> Vec<Vec<usize>> {
+        let index: HashMap<&str, usize> = self
+            .methods
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (m.name.as_str(), i))
+            .collect();
 
-        graph
+        self.methods
+            .iter()
+            .map(|method| {
+                method
+                    .calls_methods
+                    .iter()
+                    .filter_map(|callee| index.get(callee.as_str()).copied())
+                    .collect()
+            })
+            .collect()
     }
 
-    fn find_clusters(&self, _graph: &HashMap<String, HashSet<String>>) -> Vec<Vec<String>> {
-        // Simple clustering: group methods that call each other
-        let mut clusters: Vec<Vec<String>> = Vec::new();
-        let mut assigned: HashSet<String> = HashSet::new();
+    /// Group the methods of an enum's impl by the variants they handle
+    ///
+    /// Methods that name only some of the variants (`Self::Add`,
+    /// `Op::Sub`) are clustered with every method sharing one of those
+    /// variants, so e.g. all methods dealing with arithmetic instructions
+    /// end up together. Methods that handle no variant, or more than half of
+    /// them (exhaustive `match self` dispatchers), are clustered by their
+    /// calls among themselves as in `group_methods`.
+    ///
+    /// # Arguments
+    ///
+    /// * `type_name` - Name of the enum
+    /// * `variants` - Its variants, in declaration order
+    /// * `max_lines_per_group` - Line budget per group
+    pub fn group_methods_by_variants(
+        &self,
+        type_name: &str,
+        variants: &[String],
+        max_lines_per_group: usize,
+    ) -> Vec<MethodGroup> {
+        let handled: Vec<BTreeSet<String>> = self
+            .methods
+            .iter()
+            .map(|method| handled_variants(&method.item, type_name, variants))
+            .collect();
+        let narrow: Vec<bool> = handled
+            .iter()
+            .map(|set| !set.is_empty() && set.len() * 2 <= variants.len())
+            .collect();
 
-        for method in &self.methods {
-            if assigned.contains(&method.name) {
-                continue;
+        let mut sets = UnionFind::new(self.methods.len());
+        let mut first_handler: HashMap<&str, usize> = HashMap::new();
+        for (method, set) in handled.iter().enumerate().filter(|(i, _)| narrow[*i]) {
+            for variant in set {
+                match first_handler.get(variant.as_str()) {
+                    Some(&other) => sets.union(method, other),
+                    None => {
+                        first_handler.insert(variant, method);
+                    }
+                }
             }
+        }
+        for (caller, callees) in self.build_dependency_graph().iter().enumerate() {
+            for &callee in callees {
+                if !narrow[caller] && !narrow[callee] {
+                    sets.union(caller, callee);
+                }
+            }
+        }
 
-            let mut cluster = vec![method.name.clone()];
-            assigned.insert(method.name.clone());
+        let mut groups = self.create_groups(self.collect_clusters(&mut sets), max_lines_per_group);
+        for group in &mut groups {
+            // Name variant clusters after their first variant in declaration order
+            let group_variants: BTreeSet<&String> = group
+                .methods
+                .iter()
+                .map(|m| &handled[self.index_of(&m.name)])
+                .filter(|set| !set.is_empty() && set.len() * 2 <= variants.len())
+                .flatten()
+                .collect();
+            group.label = variants
+                .iter()
+                .find(|v| group_variants.contains(v))
+                .map(|v| format!("{}_variants", v.to_lowercase()));
+        }
+        groups
+    }
 
-            // Find methods that this method calls or that call this method
-            for other_method in &self.methods {
-                if assigned.contains(&other_method.name) {
-                    continue;
+    /// Group methods by a family assigned to each of them
+    ///
+    /// Methods of the same family form one cluster named after it; methods
+    /// without a family are clustered by their calls among themselves.
+    ///
+    /// # Arguments
+    ///
+    /// * `families` - Family of each method, in method order
+    /// * `max_lines_per_group` - Line budget per group
+    pub fn group_methods_by_family(
+        &self,
+        families: &[Option<String>],
+        max_lines_per_group: usize,
+    ) -> Vec<MethodGroup> {
+        let mut sets = UnionFind::new(self.methods.len());
+        let mut first_member: HashMap<&str, usize> = HashMap::new();
+        for (method, family) in families.iter().enumerate() {
+            if let Some(family) = family {
+                match first_member.get(family.as_str()) {
+                    Some(&other) => sets.union(method, other),
+                    None => {
+                        first_member.insert(family, method);
+                    }
                 }
+            }
+        }
+        for (caller, callees) in self.build_dependency_graph().iter().enumerate() {
+            for &callee in callees {
+                if families[caller].is_none() && families[callee].is_none() {
+                    sets.union(caller, callee);
+                }
+            }
+        }
 
-                let calls_other = method.calls_methods.contains(&other_method.name);
-                let called_by_other = other_method.calls_methods.contains(&method.name);
+        let mut groups = self.create_groups(self.collect_clusters(&mut sets), max_lines_per_group);
+        for group in &mut groups {
+            group.label = group
+                .methods
+                .first()
+                .and_then(|m| families[self.index_of(&m.name)].clone());
+        }
+        groups
+    }
 
-                if calls_other || called_by_other {
-                    cluster.push(other_method.name.clone());
-                    assigned.insert(other_method.name.clone());
-                }
+    /// Methods analyzed so far
+    pub fn methods(&self) -> &[MethodInfo] {
+        &self.methods
+    }
+
+    /// Position of a method by name
+    fn index_of(&self, name: &str) -> usize {
+        self.methods
+            .iter()
+            .position(|m| m.name == name)
+            .unwrap_or_default()
+    }
+
+    /// Connected components of the call graph, ordered by first method
+    ///
+    /// Uses union-find so clustering stays near-linear in the number of
+    /// methods and calls, even for generated impl blocks with thousands of
+    /// methods.
+    fn find_clusters(&self, graph: &[Vec<usize>]) -> Vec<Vec<usize>> {
+        let mut sets = UnionFind::new(self.methods.len());
+        for (caller, callees) in graph.iter().enumerate() {
+            for &callee in callees {
+                sets.union(caller, callee);
             }
+        }
+        self.collect_clusters(&mut sets)
+    }
+
+    /// The methods of each set, ordered by first method
+    ///
+    /// Accessor pairs are joined first, so no grouping separates them.
+    fn collect_clusters(&self, sets: &mut UnionFind) -> Vec<Vec<usize>> {
+        for (method, partner) in self.accessor_pairs() {
+            sets.union(method, partner);
+        }
 
-            clusters.push(cluster);
+        let mut cluster_of_root: HashMap<usize, usize> = HashMap::new();
+        let mut clusters: Vec<Vec<usize>> = Vec::new();
+        for method in 0..self.methods.len() {
+            let root = sets.find(method);
+            let cluster = *cluster_of_root.entry(root).or_insert_with(|| {
+                clusters.push(Vec::new());
+                clusters.len() - 1
+            });
+            clusters[cluster].push(method);
         }
 
         clusters
     }
 
-    fn create_groups(&self, clusters: Vec<Vec<String>>, max_lines: usize) -> Vec<MethodGroup> {
+    /// Accessor pairs by method index: `get_x`/`set_x`, `x`/`set_x` and
+    /// `x`/`x_mut`
+    fn accessor_pairs(&self) -> Vec<(usize, usize)> {
+        let index: HashMap<&str, usize> = self
+            .methods
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (m.name.as_str(), i))
+            .collect();
+
+        let mut pairs = Vec::new();
+        for (i, method) in self.methods.iter().enumerate() {
+            let partners = if let Some(field) = method.name.strip_prefix("set_") {
+                vec![format!("get_{}", field), field.to_string()]
+            } else if let Some(base) = method.name.strip_suffix("_mut") {
+                vec![base.to_string()]
+            } else {
+                continue;
+            };
+            for partner in partners {
+                if let Some(&j) = index.get(partner.as_str()) {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// Split clusters into groups within the line budget
+    ///
+    /// A method and its accessor partners are placed as one unit, so a
+    /// cluster is never cut between them. Each group lists its methods in
+    /// source order, so the moved block diffs cleanly against the original.
+    fn create_groups(&self, clusters: Vec<Vec<usize>>, max_lines: usize) -> Vec<MethodGroup> {
+        let mut partners: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (a, b) in self.accessor_pairs() {
+            partners.entry(a).or_default().push(b);
+            partners.entry(b).or_default().push(a);
+        }
+
         let mut groups = Vec::new();
-        let method_map: HashMap<String, &MethodInfo> =
-            self.methods.iter().map(|m| (m.name.clone(), m)).collect();
+        let mut placed = HashSet::new();
+        let to_group = |mut indices: Vec<usize>| {
+            indices.sort_unstable();
+            let mut group = MethodGroup::new();
+            group.methods = indices.iter().map(|&i| self.methods[i].clone()).collect();
+            group
+        };
 
         for cluster in clusters {
-            let mut current_group = MethodGroup::new();
+            let mut current_group: Vec<usize> = Vec::new();
             let mut current_lines = 0;
 
-            for method_name in &cluster {
-                if let Some(method) = method_map.get(method_name) {
-                    if current_lines + method.line_count > max_lines
-                        && !current_group.methods.is_empty()
-                    {
-                        groups.push(current_group);
-                        current_group = MethodGroup::new();
-                        current_lines = 0;
+            for method_index in cluster {
+                // The method and, transitively, its not yet placed partners
+                let mut unit = Vec::new();
+                let mut pending = vec![method_index];
+                while let Some(index) = pending.pop() {
+                    if placed.insert(index) {
+                        unit.push(index);
+                        pending.extend(partners.get(&index).into_iter().flatten());
                     }
+                }
+                if unit.is_empty() {
+                    continue;
+                }
 
-                    current_group.methods.push((*method).clone());
-                    current_lines += method.line_count;
+                let unit_lines: usize = unit.iter().map(|&i| self.methods[i].line_count).sum();
+                if current_lines + unit_lines > max_lines && !current_group.is_empty() {
+                    groups.push(to_group(std::mem::take(&mut current_group)));
+                    current_lines = 0;
                 }
+
+                current_group.extend(unit);
+                current_lines += unit_lines;
             }
 
-            if !current_group.methods.is_empty() {
-                groups.push(current_group);
+            if !current_group.is_empty() {
+                groups.push(to_group(current_group));
             }
         }
 
@@ -159,12 +530,22 @@ impl ImplBlockAnalyzer {
 #[derive(Clone)]
 pub struct MethodGroup {
     pub methods: Vec<MethodInfo>,
+
+    /// Name chosen by the grouping (e.g. `add_variants`), preferred over the
+    /// guess from method names
+    pub label: Option<String>,
+
+    /// Private associated consts and types only this group uses, which move
+    /// along with its methods (see [`assign_associated_items`])
+    pub associated: Vec<ImplItem>,
 }
 
 impl MethodGroup {
     fn new() -> Self {
         Self {
             methods: Vec::new(),
+            label: None,
+            associated: Vec::new(),
         }
     }
 
@@ -173,11 +554,47 @@ impl MethodGroup {
         self.methods.iter().map(|m| m.line_count).sum()
     }
 
+    /// Share of the group's methods related to another method of the group
+    ///
+    /// Two methods are related when one calls the other or both use the
+    /// same field of `self`. A group of one method is fully cohesive.
+    pub fn cohesion(&self) -> f64 {
+        if self.methods.len() < 2 {
+            return 1.0;
+        }
+        let related = self
+            .methods
+            .iter()
+            .enumerate()
+            .filter(|(i, method)| {
+                self.methods.iter().enumerate().any(|(j, other)| {
+                    *i != j
+                        && (method.calls_methods.contains(&other.name)
+                            || other.calls_methods.contains(&method.name)
+                            || !method.fields.is_disjoint(&other.fields))
+                })
+            })
+            .count();
+        related as f64 / self.methods.len() as f64
+    }
+
     pub fn suggest_name(&self) -> String {
+        if let Some(label) = &self.label {
+            return label.clone();
+        }
         if self.methods.is_empty() {
             return "methods".to_string();
         }
 
+        // Name the group after what its methods share (`cache_get`,
+        // `cache_put` -> `cache`)
+        if self.methods.len() > 1 {
+            let names: Vec<&str> = self.methods.iter().map(|m| m.name.as_str()).collect();
+            if let Some(stem) = common_stem(&names) {
+                return stem;
+            }
+        }
+
         // Try to find a common prefix or theme
         let first_method = &self.methods[0].name;
 
@@ -204,6 +621,306 @@ impl MethodGroup {
     }
 }
 
+/// Names of associated items a piece of code reaches through `Self::` or
+/// `Type::` (`Self::MAX_RETRIES`, `Self::Output`)
+#[derive(Default)]
+struct AssociatedUses {
+    type_name: String,
+    names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for AssociatedUses {
+    fn visit_path(&mut self, node: &'ast syn::Path) {
+        let mut segments = node.segments.iter().map(|s| s.ident.to_string());
+        if let (Some(owner), Some(name)) = (segments.next(), segments.next()) {
+            if owner == "Self" || owner == self.type_name {
+                self.names.insert(name);
+            }
+        }
+        syn::visit::visit_path(self, node);
+    }
+}
+
+/// Associated item names an impl member uses
+fn associated_uses(type_name: &str, item: &ImplItem) -> BTreeSet<String> {
+    let mut uses = AssociatedUses {
+        type_name: type_name.to_string(),
+        ..AssociatedUses::default()
+    };
+    uses.visit_impl_item(item);
+    uses.names
+}
+
+/// Name of a private associated const or type
+fn private_associated_name(item: &ImplItem) -> Option<String> {
+    match item {
+        ImplItem::Const(c) if matches!(c.vis, syn::Visibility::Inherited) => {
+            Some(c.ident.to_string())
+        }
+        ImplItem::Type(t) if matches!(t.vis, syn::Visibility::Inherited) => {
+            Some(t.ident.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Name of the type an impl is for (`Pool` for `impl<T> Pool<T>`)
+fn self_type_name(impl_block: &ItemImpl) -> String {
+    match &*impl_block.self_ty {
+        syn::Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// Private associated names the groups took along
+fn moved_associated_names(groups: &[MethodGroup]) -> BTreeSet<String> {
+    groups
+        .iter()
+        .flat_map(|g| g.associated.iter().filter_map(private_associated_name))
+        .collect()
+}
+
+/// Move the private associated consts and types only one group uses into
+/// that group
+///
+/// A const that another private const uses goes where that const goes; one
+/// used by several groups, by methods staying with the type, or by public
+/// associated items stays in the type's own impl.
+///
+/// # Arguments
+///
+/// * `impl_block` - The impl being split
+/// * `groups` - Groups getting a module of their own
+/// * `leftovers` - Groups whose methods stay with the type
+pub fn assign_associated_items(
+    impl_block: &ItemImpl,
+    groups: &mut [MethodGroup],
+    leftovers: &[MethodGroup],
+) {
+    let type_name = self_type_name(impl_block);
+    let private: Vec<(String, &ImplItem)> = impl_block
+        .items
+        .iter()
+        .filter_map(|item| private_associated_name(item).map(|name| (name, item)))
+        .collect();
+    let method_uses = |methods: &[MethodInfo]| -> BTreeSet<String> {
+        methods
+            .iter()
+            .flat_map(|m| associated_uses(&type_name, &ImplItem::Fn(m.item.clone())))
+            .collect()
+    };
+    let group_uses: Vec<BTreeSet<String>> =
+        groups.iter().map(|g| method_uses(&g.methods)).collect();
+    let mut kept_uses: BTreeSet<String> = leftovers
+        .iter()
+        .flat_map(|g| method_uses(&g.methods))
+        .collect();
+    for item in &impl_block.items {
+        if !matches!(item, ImplItem::Fn(_)) && private_associated_name(item).is_none() {
+            kept_uses.extend(associated_uses(&type_name, item));
+        }
+    }
+
+    // Users of each private item: a group, or `None` for the type's impl
+    let mut users: Vec<BTreeSet<Option<usize>>> = private
+        .iter()
+        .map(|(name, _)| {
+            let mut users: BTreeSet<Option<usize>> = group_uses
+                .iter()
+                .enumerate()
+                .filter(|(_, uses)| uses.contains(name))
+                .map(|(group, _)| Some(group))
+                .collect();
+            if kept_uses.contains(name) {
+                users.insert(None);
+            }
+            users
+        })
+        .collect();
+    let place = |users: &BTreeSet<Option<usize>>| match users.iter().collect::<Vec<_>>()[..] {
+        [Some(group)] => Some(*group),
+        _ => None,
+    };
+
+    // Private items go where the private items using them go
+    let item_uses: Vec<BTreeSet<String>> = private
+        .iter()
+        .map(|(_, item)| associated_uses(&type_name, item))
+        .collect();
+    loop {
+        let mut changed = false;
+        for user in 0..private.len() {
+            let placement = place(&users[user]);
+            for (used, (name, _)) in private.iter().enumerate() {
+                if used != user && item_uses[user].contains(name) {
+                    changed |= users[used].insert(placement);
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    for (index, (_, item)) in private.iter().enumerate() {
+        if let Some(group) = place(&users[index]) {
+            groups[group].associated.push((*item).clone());
+        }
+    }
+}
+
+/// Private associated consts and types that stay with the type although
+/// split groups use them, and so become `pub(super)`
+pub fn shared_associated_names(impl_block: &ItemImpl, groups: &[MethodGroup]) -> Vec<String> {
+    let type_name = self_type_name(impl_block);
+    let moved = moved_associated_names(groups);
+    let used: BTreeSet<String> = groups
+        .iter()
+        .flat_map(|g| {
+            g.methods
+                .iter()
+                .map(|m| ImplItem::Fn(m.item.clone()))
+                .chain(g.associated.iter().cloned())
+        })
+        .flat_map(|item| associated_uses(&type_name, &item))
+        .collect();
+    impl_block
+        .items
+        .iter()
+        .filter_map(private_associated_name)
+        .filter(|name| !moved.contains(name) && used.contains(name))
+        .collect()
+}
+
+/// Associated consts, types and macro calls of an impl that stay with the
+/// type when its methods are split into `groups`
+///
+/// Those the groups took along are left out, and private ones the groups
+/// still use become `pub(super)`.
+pub fn associated_items(impl_block: &ItemImpl, groups: &[MethodGroup]) -> Vec<ImplItem> {
+    let shared = shared_associated_names(impl_block, groups);
+    let moved = moved_associated_names(groups);
+    let mut items = Vec::new();
+    for item in impl_block
+        .items
+        .iter()
+        .filter(|item| !matches!(item, ImplItem::Fn(_)))
+    {
+        let name = private_associated_name(item);
+        if name.as_ref().is_some_and(|name| moved.contains(name)) {
+            continue;
+        }
+        let mut item = item.clone();
+        if name.is_some_and(|name| shared.contains(&name)) {
+            match &mut item {
+                ImplItem::Const(c) => c.vis = syn::parse_quote!(pub(super)),
+                ImplItem::Type(t) => t.vis = syn::parse_quote!(pub(super)),
+                _ => {}
+            }
+        }
+        items.push(item);
+    }
+    items
+}
+
+/// Name tokens that say what a method does rather than what it works on
+const GENERIC_TOKENS: &[&str] = &[
+    "as", "check", "do", "from", "get", "handle", "has", "into", "is", "new", "on", "process",
+    "set", "test", "to", "try", "with",
+];
+
+/// Longest run of `_`-separated tokens found in every name
+///
+/// Ties go to the run found first in the first name. Runs made only of
+/// generic verbs (`get`, `is`, ...) and runs that are not identifiers
+/// (keywords, leading digits) do not count.
+///
+/// # Arguments
+///
+/// * `names` - Method names of a group
+///
+/// # Returns
+///
+/// The run joined with `_`, or `None` when the names share no usable run
+fn common_stem(names: &[&str]) -> Option<String> {
+    let tokens: Vec<Vec<&str>> = names
+        .iter()
+        .map(|name| name.split('_').filter(|t| !t.is_empty()).collect())
+        .collect();
+    let (first, rest) = tokens.split_first()?;
+    for len in (1..=first.len()).rev() {
+        for run in first.windows(len) {
+            if run.iter().all(|token| GENERIC_TOKENS.contains(token))
+                || !rest
+                    .iter()
+                    .all(|other| other.windows(len).any(|window| window == run))
+            {
+                continue;
+            }
+            let stem = run.join("_");
+            if syn::parse_str::<syn::Ident>(&stem).is_ok() {
+                return Some(stem);
+            }
+        }
+    }
+    None
+}
+
+/// Disjoint-set forest with path compression and union by rank
+struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        Self {
+            parent: (0..size).collect(),
+            rank: vec![0; size],
+        }
+    }
+
+    fn find(&mut self, mut node: usize) -> usize {
+        let mut root = node;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        while self.parent[node] != root {
+            let next = self.parent[node];
+            self.parent[node] = root;
+            node = next;
+        }
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        match self.rank[a].cmp(&self.rank[b]) {
+            std::cmp::Ordering::Less => self.parent[a] = b,
+            std::cmp::Ordering::Greater => self.parent[b] = a,
+            std::cmp::Ordering::Equal => {
+                self.parent[b] = a;
+                self.rank[a] += 1;
+            }
+        }
+    }
+}
+
+/// Names of the methods and functions a method calls or refers to
+pub fn called_methods(method: &ImplItemFn) -> HashSet<String> {
+    let mut visitor = MethodCallVisitor::new();
+    visitor.visit_impl_item_fn(method);
+    visitor.called_methods
+}
+
 /// Visitor to find method calls within a method body
 struct MethodCallVisitor {
     called_methods: HashSet<String>,
@@ -232,6 +949,92 @@ impl<'ast> Visit<'ast> for MethodCallVisitor {
         }
         syn::visit::visit_expr_call(self, node);
     }
+
+    fn visit_expr_path(&mut self, node: &'ast ExprPath) {
+        if let Some(name) = associated_path_name(node) {
+            self.called_methods.insert(name);
+        }
+        syn::visit::visit_expr_path(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        self.called_methods.extend(macro_calls(node));
+        syn::visit::visit_macro(self, node);
+    }
+}
+
+/// Method an associated path refers to without calling it
+///
+/// `Self::convert` passed to `map`, and `<Self as Trait>::convert` or
+/// `<DataStore>::convert` in UFCS form, link the method like a call does.
+fn associated_path_name(path: &ExprPath) -> Option<String> {
+    let segments = &path.path.segments;
+    let associated = path.qself.is_some()
+        || (segments.len() == 2 && segments.first().is_some_and(|s| s.ident == "Self"));
+    associated.then(|| segments.last().map(|s| s.ident.to_string()))?
+}
+
+/// Calls made in the arguments of a macro (`format!("{}", self.name())`)
+///
+/// Arguments are only seen when they parse as comma-separated expressions.
+fn macro_calls(mac: &syn::Macro) -> HashSet<String> {
+    let mut visitor = MethodCallVisitor::new();
+    if let Ok(args) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+        for arg in &args {
+            visitor.visit_expr(arg);
+        }
+    }
+    visitor.called_methods
+}
+
+/// Variants of `type_name` a method names as `Self::Variant` or
+/// `Type::Variant`, in patterns, expressions or types
+pub fn handled_variants(
+    method: &ImplItemFn,
+    type_name: &str,
+    variants: &[String],
+) -> BTreeSet<String> {
+    struct VariantVisitor<'a> {
+        type_name: &'a str,
+        variants: &'a [String],
+        handled: BTreeSet<String>,
+    }
+
+    impl<'ast> Visit<'ast> for VariantVisitor<'_> {
+        fn visit_path(&mut self, path: &'ast syn::Path) {
+            let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+            if let [owner, variant] = segments.as_slice() {
+                if (owner == "Self" || owner == self.type_name) && self.variants.contains(variant) {
+                    self.handled.insert(variant.clone());
+                }
+            }
+            syn::visit::visit_path(self, path);
+        }
+
+        fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+            // Macro arguments (`matches!(self, Self::Add | Self::Sub)`) are not
+            // parsed, so look for `Owner::Variant` in their text
+            let text = mac.tokens.to_string().replace(" :: ", "::");
+            for path in text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':')) {
+                if let Some((owner, variant)) = path.split_once("::") {
+                    if (owner == "Self" || owner == self.type_name)
+                        && self.variants.iter().any(|v| v == variant)
+                    {
+                        self.handled.insert(variant.to_string());
+                    }
+                }
+            }
+            syn::visit::visit_macro(self, mac);
+        }
+    }
+
+    let mut visitor = VariantVisitor {
+        type_name,
+        variants,
+        handled: BTreeSet::new(),
+    };
+    visitor.visit_impl_item_fn(method);
+    visitor.handled
 }
 
 #[cfg(test)]
@@ -290,4 +1093,219 @@ mod tests {
         let groups = analyzer.group_methods(1000);
         assert!(!groups.is_empty());
     }
+
+    #[test]
+    fn test_associated_references_link_methods() {
+        let impl_block: ItemImpl = parse_quote! {
+            impl DataStore {
+                fn totals(&self) -> Vec<u32> {
+                    self.items.iter().map(Self::weigh).collect()
+                }
+                fn weigh(item: &u32) -> u32 { *item }
+                fn describe(&self) -> String {
+                    format!("{}", <Self as Summary>::summary(self))
+                }
+                fn summary(&self) -> String { String::new() }
+            }
+        };
+
+        let mut analyzer = ImplBlockAnalyzer::new();
+        analyzer.analyze(&impl_block);
+
+        let groups = analyzer.group_methods(1000);
+        let group_of = |name: &str| {
+            groups
+                .iter()
+                .position(|g| g.methods.iter().any(|m| m.name == name))
+        };
+        assert_eq!(group_of("totals"), group_of("weigh"));
+        assert_eq!(group_of("describe"), group_of("summary"));
+        assert_ne!(group_of("totals"), group_of("describe"));
+    }
+
+    #[test]
+    fn test_clusters_are_transitive_and_scale() {
+        // A call chain m0 -> m1 -> ... plus unrelated methods every 100th index
+        let methods: Vec<MethodInfo> = (0..5000)
+            .map(|i| {
+                let method: ImplItemFn = syn::parse_str(&format!("fn m{}(&self) {{}}", i)).unwrap();
+                let calls = if i % 100 == 99 {
+                    HashSet::new()
+                } else {
+                    [format!("m{}", i + 1)].into_iter().collect()
+                };
+                MethodInfo {
+                    name: method.sig.ident.to_string(),
+                    item: method,
+                    calls_methods: calls,
+                    fields: BTreeSet::new(),
+                    line_count: 1,
+                }
+            })
+            .collect();
+
+        let analyzer = ImplBlockAnalyzer::from_methods(methods);
+        let clusters = analyzer.find_clusters(&analyzer.build_dependency_graph());
+
+        assert_eq!(clusters.len(), 50);
+        assert!(clusters.iter().all(|c| c.len() == 100));
+        let groups = analyzer.group_methods(usize::MAX);
+        assert_eq!(groups.iter().map(|g| g.methods.len()).sum::<usize>(), 5000);
+    }
+
+    #[test]
+    fn test_methods_grouped_by_variant_clusters() {
+        let impl_block: ItemImpl = parse_quote! {
+            impl Op {
+                fn is_arith(&self) -> bool { matches!(self, Self::Add | Self::Sub) }
+                fn eval_arith(&self, a: i64, b: i64) -> i64 {
+                    match self { Op::Add => a + b, Op::Sub => a - b, _ => 0 }
+                }
+                fn is_memory(&self) -> bool { matches!(self, Self::Load | Self::Store) }
+                fn name(&self) -> &str {
+                    match self { Op::Add => "add", Op::Sub => "sub", Op::Load => "load", Op::Store => "store" }
+                }
+            }
+        };
+        let mut analyzer = ImplBlockAnalyzer::new();
+        analyzer.analyze(&impl_block);
+        let variants: Vec<String> = ["Add", "Sub", "Load", "Store"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+
+        let groups = analyzer.group_methods_by_variants("Op", &variants, 1000);
+        let layout: Vec<(String, Vec<&str>)> = groups
+            .iter()
+            .map(|g| {
+                (
+                    g.suggest_name(),
+                    g.methods.iter().map(|m| m.name.as_str()).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            layout,
+            vec![
+                ("add_variants".to_string(), vec!["is_arith", "eval_arith"]),
+                ("load_variants".to_string(), vec!["is_memory"]),
+                ("name_group".to_string(), vec!["name"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_accessor_pairs_stay_together() {
+        let impl_block: ItemImpl = parse_quote! {
+            impl Config {
+                fn name(&self) -> &str { &self.name }
+                fn get_port(&self) -> u16 { self.port }
+                fn unrelated(&self) {}
+                fn set_name(&mut self, name: String) { self.name = name; }
+                fn set_port(&mut self, port: u16) { self.port = port; }
+                fn name_mut(&mut self) -> &mut String { &mut self.name }
+            }
+        };
+        let mut analyzer = ImplBlockAnalyzer::new();
+        analyzer.analyze(&impl_block);
+
+        // A budget of one method per group still keeps each pair whole
+        let groups = analyzer.group_methods(15);
+        let layout: Vec<Vec<&str>> = groups
+            .iter()
+            .map(|g| g.methods.iter().map(|m| m.name.as_str()).collect())
+            .collect();
+        assert_eq!(
+            layout,
+            vec![
+                vec!["name", "set_name", "name_mut"],
+                vec!["get_port", "set_port"],
+                vec!["unrelated"],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_groups_keep_source_order() {
+        let impl_block: ItemImpl = parse_quote! {
+            impl Counter {
+                fn reset(&mut self) { self.set_value(0); }
+                fn value(&self) -> u32 { self.value }
+                fn bump(&mut self) { self.reset(); }
+                fn set_value(&mut self, value: u32) { self.value = value; }
+            }
+        };
+        let mut analyzer = ImplBlockAnalyzer::new();
+        analyzer.analyze(&impl_block);
+
+        let groups = analyzer.group_methods(1000);
+        assert_eq!(groups.len(), 1);
+        let names: Vec<&str> = groups[0].methods.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["reset", "value", "bump", "set_value"]);
+    }
+
+    #[test]
+    fn test_cohesion_counts_calls_and_shared_fields() {
+        let impl_block: ItemImpl = parse_quote! {
+            impl Cache {
+                fn load(&mut self) { self.entries.clear(); }
+                fn size(&self) -> usize { self.entries.len() }
+                fn name(&self) -> &str { &self.label }
+                fn log(&self) { println!("cache"); }
+            }
+        };
+        let mut analyzer = ImplBlockAnalyzer::new();
+        analyzer.analyze(&impl_block);
+        let group = MethodGroup {
+            methods: analyzer.methods().to_vec(),
+            label: None,
+            associated: Vec::new(),
+        };
+        assert_eq!(group.cohesion(), 0.5);
+
+        let single = MethodGroup {
+            methods: group.methods[3..].to_vec(),
+            label: None,
+            associated: Vec::new(),
+        };
+        assert_eq!(single.cohesion(), 1.0);
+    }
+
+    #[test]
+    fn test_group_named_after_common_stem() {
+        let impl_block: ItemImpl = parse_quote! {
+            impl Store {
+                fn cache_get(&self) {}
+                fn cache_put(&mut self) {}
+                fn evict_from_cache(&mut self) {}
+                fn get_port(&self) {}
+                fn get_host(&self) {}
+                fn type_of(&self) {}
+                fn type_name(&self) {}
+            }
+        };
+        let mut analyzer = ImplBlockAnalyzer::new();
+        analyzer.analyze(&impl_block);
+        let group = |names: &[&str]| MethodGroup {
+            methods: analyzer
+                .methods()
+                .iter()
+                .filter(|m| names.contains(&m.name.as_str()))
+                .cloned()
+                .collect(),
+            label: None,
+            associated: Vec::new(),
+        };
+
+        assert_eq!(
+            group(&["cache_get", "cache_put", "evict_from_cache"]).suggest_name(),
+            "cache"
+        );
+        // Shared verbs and keywords are no names; the old patterns apply
+        assert_eq!(group(&["get_port", "get_host"]).suggest_name(), "accessors");
+        assert_eq!(
+            group(&["type_of", "type_name"]).suggest_name(),
+            "type_of_group"
+        );
+    }
 }
@@ -0,0 +1,244 @@
+//! Migration plans for re-splitting legacy module trees
+//!
+//! A module tree that was split by hand (or by an older configuration) can be
+//! flattened and grouped again with the current settings. Rather than
+//! rewriting the tree immediately, this module compares where every item
+//! lives today with where the new grouping would put it and reports the
+//! difference as a list of moves.
+//!
+//! Items are compared by label: type definitions by name, trait impls as
+//! `Trait for Type`, inherent methods as `Type::method` (so split impl
+//! blocks can be tracked method by method) and other items by name.
+
+use quote::ToTokens;
+use std::collections::{BTreeMap, BTreeSet};
+use syn::{ImplItem, Item};
+
+/// A single item that changes files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemMigration {
+    /// Label of the item (see module docs)
+    pub item: String,
+
+    /// Module the item lives in today
+    pub from: String,
+
+    /// Module the new grouping places it in
+    pub to: String,
+}
+
+/// Differences between the current module tree and a fresh grouping
+#[derive(Debug, Default)]
+pub struct MigrationPlan {
+    /// Items that would move, sorted by source then target module
+    pub moves: Vec<ItemMigration>,
+
+    /// Number of items that stay in a module of the same name
+    pub unchanged: usize,
+
+    /// Current modules that would no longer exist
+    pub removed_modules: Vec<String>,
+
+    /// Modules the new grouping would create
+    pub new_modules: Vec<String>,
+
+    /// Items only found on one side (e.g. macro invocations)
+    pub unmatched: Vec<String>,
+}
+
+impl MigrationPlan {
+    /// Compare the current placement of items with a proposed one
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - Module name and item labels of the existing tree
+    /// * `proposed` - Module name and item labels of the new grouping
+    pub fn build(current: &[(String, Vec<String>)], proposed: &[(String, Vec<String>)]) -> Self {
+        let mut placement: BTreeMap<&str, &str> = BTreeMap::new();
+        for (module, labels) in proposed {
+            for label in labels {
+                placement.insert(label, module);
+            }
+        }
+
+        let mut plan = MigrationPlan::default();
+        let mut seen = BTreeSet::new();
+        for (module, labels) in current {
+            for label in labels {
+                seen.insert(label.as_str());
+                match placement.get(label.as_str()) {
+                    Some(target) if *target == module => plan.unchanged += 1,
+                    Some(target) => plan.moves.push(ItemMigration {
+                        item: label.clone(),
+                        from: display_module(module),
+                        to: target.to_string(),
+                    }),
+                    None => plan.unmatched.push(label.clone()),
+                }
+            }
+        }
+        plan.unmatched.extend(
+            placement
+                .keys()
+                .filter(|label| !seen.contains(*label))
+                .map(|label| label.to_string()),
+        );
+        plan.moves
+            .sort_by(|a, b| (&a.from, &a.to, &a.item).cmp(&(&b.from, &b.to, &b.item)));
+
+        let current_names: BTreeSet<&str> = current
+            .iter()
+            .filter(|(_, labels)| !labels.is_empty())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let proposed_names: BTreeSet<&str> = proposed
+            .iter()
+            .filter(|(_, labels)| !labels.is_empty())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        plan.removed_modules = current_names
+            .difference(&proposed_names)
+            .map(|name| display_module(name))
+            .collect();
+        plan.new_modules = proposed_names
+            .difference(&current_names)
+            .map(|name| name.to_string())
+            .collect();
+        plan
+    }
+
+    /// Render the plan as a human-readable report
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!(
+            "Migration plan: {} item(s) move, {} stay in place\n",
+            self.moves.len(),
+            self.unchanged
+        ));
+
+        if !self.new_modules.is_empty() {
+            out.push_str(&format!("\nNew modules: {}\n", self.new_modules.join(", ")));
+        }
+        if !self.removed_modules.is_empty() {
+            out.push_str(&format!(
+                "Emptied modules: {}\n",
+                self.removed_modules.join(", ")
+            ));
+        }
+
+        let mut last_route: Option<(&str, &str)> = None;
+        for m in &self.moves {
+            if last_route != Some((&m.from, &m.to)) {
+                out.push_str(&format!("\n{} -> {}:\n", m.from, m.to));
+                last_route = Some((&m.from, &m.to));
+            }
+            out.push_str(&format!("  {}\n", m.item));
+        }
+
+        if !self.unmatched.is_empty() {
+            out.push_str(&format!(
+                "\nNot tracked ({}): {}\n",
+                self.unmatched.len(),
+                self.unmatched.join(", ")
+            ));
+        }
+        out
+    }
+}
+
+/// Name used for the root `mod.rs` in reports
+fn display_module(name: &str) -> String {
+    if name.is_empty() {
+        "mod.rs".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Labels identifying what a top-level item contributes to a module
+pub fn item_labels(item: &Item) -> Vec<String> {
+    match item {
+        Item::Impl(impl_item) => {
+            let self_ty = impl_item
+                .self_ty
+                .to_token_stream()
+                .to_string()
+                .replace(' ', "");
+            if let Some((_, path, _)) = &impl_item.trait_ {
+                let trait_name = path.to_token_stream().to_string().replace(' ', "");
+                return vec![format!("{} for {}", trait_name, self_ty)];
+            }
+            impl_item
+                .items
+                .iter()
+                .filter_map(|i| match i {
+                    ImplItem::Fn(f) => Some(method_label(&self_ty, &f.sig.ident.to_string())),
+                    ImplItem::Const(c) => Some(method_label(&self_ty, &c.ident.to_string())),
+                    ImplItem::Type(t) => Some(method_label(&self_ty, &t.ident.to_string())),
+                    _ => None,
+                })
+                .collect()
+        }
+        Item::Use(_) | Item::Mod(_) => Vec::new(),
+        _ => crate::import_analyzer::declared_ident(item)
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Label of an associated item of an inherent impl
+pub fn method_label(self_ty: &str, name: &str) -> String {
+    format!("{}::{}", self_ty, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_labels() {
+        let file: syn::File = syn::parse_quote! {
+            pub struct User;
+            impl User { fn new() -> Self { User } fn name(&self) {} }
+            impl std::fmt::Debug for User {
+                fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { Ok(()) }
+            }
+            use std::fmt;
+        };
+        let labels: Vec<String> = file.items.iter().flat_map(item_labels).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "User",
+                "User::new",
+                "User::name",
+                "std::fmt::Debug for User"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_reports_moves_and_module_changes() {
+        let current = vec![
+            (
+                "user".to_string(),
+                vec!["User".to_string(), "User::new".to_string()],
+            ),
+            ("helpers".to_string(), vec!["format_name".to_string()]),
+        ];
+        let proposed = vec![
+            (
+                "types".to_string(),
+                vec!["User".to_string(), "User::new".to_string()],
+            ),
+            ("helpers".to_string(), vec!["format_name".to_string()]),
+        ];
+
+        let plan = MigrationPlan::build(&current, &proposed);
+        assert_eq!(plan.moves.len(), 2);
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.new_modules, vec!["types"]);
+        assert_eq!(plan.removed_modules, vec!["user"]);
+        assert!(plan.render().contains("user -> types:"));
+    }
+}
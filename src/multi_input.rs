@@ -0,0 +1,356 @@
+//! Merging several related input files into one analysis unit
+//!
+//! Large modules are often already spread over a couple of files (e.g.
+//! `big.rs` and `big_helpers.rs`). Splitting them independently produces two
+//! unrelated output trees that still reference each other through the old
+//! module paths. This module merges the parsed inputs into a single
+//! `syn::File`: imports shared between the inputs are deduplicated, `mod` declarations
+//! and imports that point at a sibling input are dropped, and paths that go
+//! through a sibling input's module name are shortened so they resolve
+//! inside the unified output.
+
+use quote::ToTokens;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use syn::visit_mut::VisitMut;
+use syn::{File, Ident, Item, ItemUse, UseTree};
+
+/// Result of merging several input files
+pub struct MergedInput {
+    /// The merged syntax tree
+    pub file: File,
+
+    /// Duplicate imports removed during the merge
+    pub deduplicated_imports: usize,
+
+    /// Imports and `mod` declarations dropped because they referenced a sibling input
+    pub resolved_cross_references: usize,
+}
+
+/// Module name an input file is known by (its file stem)
+pub fn input_module_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Merge parsed input files into a single syntax tree
+///
+/// Items keep their relative order: all items of the first input, then the
+/// second, and so on. Inner attributes are taken from the first input.
+pub fn merge_inputs(inputs: Vec<(PathBuf, File)>) -> MergedInput {
+    let sibling_names: HashSet<String> = inputs
+        .iter()
+        .map(|(path, _)| input_module_name(path))
+        .collect();
+
+    let mut merged = File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: Vec::new(),
+    };
+    let mut seen_uses = UseDeduplicator::default();
+    let mut deduplicated_imports = 0;
+    let mut resolved_cross_references = 0;
+    let mut rewriter = SiblingPathRewriter::new(&sibling_names);
+
+    for (index, (_, file)) in inputs.into_iter().enumerate() {
+        if index == 0 {
+            merged.shebang = file.shebang;
+            merged.attrs = file.attrs;
+        }
+
+        for item in file.items {
+            let mut item = match item {
+                Item::Mod(m)
+                    if m.content.is_none() && sibling_names.contains(&m.ident.to_string()) =>
+                {
+                    resolved_cross_references += 1;
+                    continue;
+                }
+                Item::Use(u) if use_targets_sibling(&u.tree, &sibling_names) => {
+                    resolved_cross_references += 1;
+                    continue;
+                }
+                Item::Use(u) => {
+                    let (fresh, dropped) = seen_uses.dedup(u);
+                    deduplicated_imports += dropped;
+                    match fresh {
+                        Some(u) => Item::Use(u),
+                        None => continue,
+                    }
+                }
+                item => item,
+            };
+
+            rewriter.visit_item_mut(&mut item);
+            merged.items.push(item);
+        }
+    }
+
+    MergedInput {
+        file: merged,
+        deduplicated_imports,
+        resolved_cross_references,
+    }
+}
+
+/// Imports already kept, tracked leaf by leaf
+///
+/// Comparing whole statements misses that `use std::fmt::{self, Display};`
+/// and `use std::fmt::Display;` both import `Display`, and keeping both is
+/// E0252. Each statement is flattened into its leaves (a path and the name,
+/// alias or glob it ends in), and only the leaves not seen before are
+/// emitted again. Leaves are keyed together with the statement's attributes
+/// and visibility, as a `pub use` or a `#[cfg]` import is not a duplicate
+/// of a plain one.
+#[derive(Default)]
+pub struct UseDeduplicator {
+    seen: HashSet<String>,
+}
+
+/// One import of a `use` tree: the path leading to it and how it ends
+struct UseLeaf {
+    path: Vec<Ident>,
+    end: UseTree,
+}
+
+impl UseLeaf {
+    /// What the leaf imports; `a::{self}` imports the same as `a`
+    fn key(&self) -> String {
+        let (path, end) = match (&self.end, self.path.split_last()) {
+            (UseTree::Name(name), Some((module, parent))) if name.ident == "self" => {
+                (parent, module.to_string())
+            }
+            (UseTree::Rename(rename), Some((module, parent))) if rename.ident == "self" => {
+                (parent, format!("{} as {}", module, rename.rename))
+            }
+            _ => (self.path.as_slice(), self.end.to_token_stream().to_string()),
+        };
+        let mut key: Vec<String> = path.iter().map(Ident::to_string).collect();
+        key.push(end);
+        key.join("::")
+    }
+}
+
+impl UseDeduplicator {
+    /// Drop the imports of `item` that an earlier statement already made
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The next `use` statement
+    ///
+    /// # Returns
+    ///
+    /// The statement holding only its new imports (`None` when none are
+    /// left), and the number of imports dropped
+    pub fn dedup(&mut self, mut item: ItemUse) -> (Option<ItemUse>, usize) {
+        let scope = format!(
+            "{} {} {}",
+            item.attrs
+                .iter()
+                .map(|attr| attr.to_token_stream().to_string())
+                .collect::<String>(),
+            item.vis.to_token_stream(),
+            item.leading_colon.is_some()
+        );
+        let mut leaves = Vec::new();
+        collect_use_leaves(&item.tree, &mut Vec::new(), &mut leaves);
+        let total = leaves.len();
+        leaves.retain(|leaf| self.seen.insert(format!("{} {}", scope, leaf.key())));
+
+        let dropped = total - leaves.len();
+        if leaves.is_empty() {
+            return (None, dropped);
+        }
+        if dropped > 0 {
+            item.tree = build_use_tree(&leaves.iter().collect::<Vec<_>>(), 0);
+        }
+        (Some(item), dropped)
+    }
+}
+
+/// Flatten a use tree into its leaves
+fn collect_use_leaves(tree: &UseTree, path: &mut Vec<Ident>, leaves: &mut Vec<UseLeaf>) {
+    match tree {
+        UseTree::Path(segment) => {
+            path.push(segment.ident.clone());
+            collect_use_leaves(&segment.tree, path, leaves);
+            path.pop();
+        }
+        UseTree::Group(group) => {
+            for item in &group.items {
+                collect_use_leaves(item, path, leaves);
+            }
+        }
+        UseTree::Name(_) | UseTree::Rename(_) | UseTree::Glob(_) => leaves.push(UseLeaf {
+            path: path.clone(),
+            end: tree.clone(),
+        }),
+    }
+}
+
+/// Rebuild a use tree from leaves, sharing the path segments they have in
+/// common below `depth`
+fn build_use_tree(leaves: &[&UseLeaf], depth: usize) -> UseTree {
+    let mut items = Vec::new();
+    let mut branches: Vec<(&Ident, Vec<&UseLeaf>)> = Vec::new();
+    for leaf in leaves {
+        match leaf.path.get(depth) {
+            None => items.push(leaf.end.clone()),
+            Some(ident) => match branches.iter_mut().find(|(name, _)| *name == ident) {
+                Some((_, branch)) => branch.push(leaf),
+                None => branches.push((ident, vec![leaf])),
+            },
+        }
+    }
+    for (ident, branch) in branches {
+        items.push(UseTree::Path(syn::UsePath {
+            ident: ident.clone(),
+            colon2_token: Default::default(),
+            tree: Box::new(build_use_tree(&branch, depth + 1)),
+        }));
+    }
+
+    // `self` can only be imported from inside braces
+    let imports_self = |tree: &UseTree| match tree {
+        UseTree::Name(name) => name.ident == "self",
+        UseTree::Rename(rename) => rename.ident == "self",
+        _ => false,
+    };
+    match items.as_slice() {
+        [single] if !imports_self(single) => items.remove(0),
+        _ => UseTree::Group(syn::UseGroup {
+            brace_token: Default::default(),
+            items: items.into_iter().collect(),
+        }),
+    }
+}
+
+/// Strip `crate::`/`super::`/`self::` prefixes and return the first real segment
+fn first_module_segment(segments: &[String]) -> Option<&String> {
+    segments
+        .iter()
+        .find(|s| !matches!(s.as_str(), "crate" | "super" | "self"))
+}
+
+/// Whether a `use` tree imports from one of the merged sibling inputs
+pub fn use_targets_sibling(tree: &UseTree, siblings: &HashSet<String>) -> bool {
+    let mut segments = Vec::new();
+    let mut current = tree;
+    while let UseTree::Path(p) = current {
+        segments.push(p.ident.to_string());
+        current = &p.tree;
+    }
+    first_module_segment(&segments).is_some_and(|s| siblings.contains(s))
+}
+
+/// Rewrites `sibling::Item` (optionally rooted at `crate`/`super`/`self`) to `Item`
+pub struct SiblingPathRewriter<'a> {
+    siblings: &'a HashSet<String>,
+}
+
+impl<'a> SiblingPathRewriter<'a> {
+    pub fn new(siblings: &'a HashSet<String>) -> Self {
+        Self { siblings }
+    }
+}
+
+impl VisitMut for SiblingPathRewriter<'_> {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        let names: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        let prefix_len = names
+            .iter()
+            .take_while(|s| matches!(s.as_str(), "crate" | "super" | "self"))
+            .count();
+
+        let targets_sibling = names
+            .get(prefix_len)
+            .is_some_and(|s| self.siblings.contains(s));
+        if targets_sibling && names.len() > prefix_len + 1 {
+            let remaining: Vec<_> = path.segments.iter().skip(prefix_len + 1).cloned().collect();
+            path.leading_colon = None;
+            path.segments = remaining.into_iter().collect();
+        }
+
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_deduplicates_and_resolves_siblings() {
+        let big: File = syn::parse_quote! {
+            use std::sync::Arc;
+            use super::big_helpers::Helper;
+            mod big_helpers;
+
+            pub struct Engine { helper: Arc<Helper> }
+
+            impl Engine {
+                fn run(&self) { crate::big_helpers::assist(); }
+            }
+        };
+        let helpers: File = syn::parse_quote! {
+            use std::sync::Arc;
+
+            pub struct Helper;
+            pub fn assist() {}
+        };
+
+        let merged = merge_inputs(vec![
+            (PathBuf::from("src/big.rs"), big),
+            (PathBuf::from("src/big_helpers.rs"), helpers),
+        ]);
+
+        assert_eq!(merged.deduplicated_imports, 1);
+        assert_eq!(merged.resolved_cross_references, 2);
+        assert_eq!(merged.file.items.len(), 5);
+
+        let code = merged.file.to_token_stream().to_string();
+        assert!(code.contains("assist ()"));
+        assert!(!code.contains("big_helpers"));
+    }
+
+    #[test]
+    fn test_duplicate_imports_are_dropped_leaf_by_leaf() {
+        let big: File = syn::parse_quote! {
+            use std::fmt::{self, Display};
+            use std::sync::Arc;
+
+            pub struct Engine(Arc<u32>);
+        };
+        let helpers: File = syn::parse_quote! {
+            use std::fmt;
+            use std::fmt::{Debug, Display};
+            use std::sync::{Arc, Mutex};
+            pub use std::sync::Arc as Shared;
+
+            pub fn show(value: &dyn Display, _: &dyn Debug, _: Mutex<()>) -> fmt::Result { Ok(()) }
+        };
+
+        let merged = merge_inputs(vec![
+            (PathBuf::from("src/big.rs"), big),
+            (PathBuf::from("src/big_helpers.rs"), helpers),
+        ]);
+
+        assert_eq!(merged.deduplicated_imports, 3);
+        let code = prettyplease::unparse(&merged.file);
+        assert!(code.contains("use std::fmt::{self, Display};\nuse std::sync::Arc;\n"));
+        assert!(!code.contains("use std::fmt;"));
+        assert!(code.contains("use std::fmt::Debug;\nuse std::sync::Mutex;\n"));
+        assert!(code.contains("pub use std::sync::Arc as Shared;"));
+        assert!(syn::parse_file(&code).is_ok());
+    }
+
+    #[test]
+    fn test_input_module_name() {
+        assert_eq!(
+            input_module_name(Path::new("src/big_helpers.rs")),
+            "big_helpers"
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Before/after comparison of a split
+//!
+//! Lists every top-level item of the inputs in source order next to the
+//! file it lands in, so reviewers can confirm at a glance that nothing
+//! important ends up somewhere surprising. Items are matched to modules by
+//! the labels of [`crate::migration`].
+
+use crate::import_analyzer::declared_ident;
+use crate::lenient::item_spans;
+use crate::migration::item_labels;
+use crate::report::ItemPlacement;
+use quote::ToTokens;
+use syn::Item;
+
+/// Labels to match an item by; module declarations go by their name
+pub fn placement_labels(item: &Item) -> Vec<String> {
+    match item {
+        Item::Mod(_) => declared_ident(item).into_iter().collect(),
+        _ => item_labels(item),
+    }
+}
+
+/// Short description of a top-level item (`struct User`, `impl Display for User`)
+fn describe(item: &Item) -> String {
+    let tokens = |t: &dyn ToTokens| t.to_token_stream().to_string().replace(' ', "");
+    match item {
+        Item::Struct(s) => format!("struct {}", s.ident),
+        Item::Enum(e) => format!("enum {}", e.ident),
+        Item::Union(u) => format!("union {}", u.ident),
+        Item::Trait(t) => format!("trait {}", t.ident),
+        Item::TraitAlias(t) => format!("trait {}", t.ident),
+        Item::Type(t) => format!("type {}", t.ident),
+        Item::Const(c) => format!("const {}", c.ident),
+        Item::Static(s) => format!("static {}", s.ident),
+        Item::Fn(f) => format!("fn {}", f.sig.ident),
+        Item::Mod(m) => format!("mod {}", m.ident),
+        Item::ExternCrate(e) => format!("extern crate {}", e.ident),
+        Item::ForeignMod(_) => "extern block".to_string(),
+        Item::Macro(m) => match &m.ident {
+            Some(ident) => format!("macro_rules! {}", ident),
+            None => format!("{}!", tokens(&m.mac.path)),
+        },
+        Item::Impl(i) => match &i.trait_ {
+            Some((_, path, _)) => format!("impl {} for {}", tokens(path), tokens(&i.self_ty)),
+            None => format!("impl {}", tokens(&i.self_ty)),
+        },
+        _ => "item".to_string(),
+    }
+}
+
+/// Where each item of an input went
+///
+/// `use` items are left out: the generated modules get imports of their
+/// own. Items whose labels are found nowhere get no destination.
+///
+/// # Arguments
+///
+/// * `file` - The input, as displayed
+/// * `source` - Its content
+/// * `destinations` - Each output file with the labels of its items
+pub fn place_items(
+    file: &str,
+    source: &str,
+    destinations: &[(String, Vec<String>)],
+) -> Vec<ItemPlacement> {
+    let mut placements = Vec::new();
+    for (index, (start, end)) in item_spans(source).into_iter().enumerate() {
+        let text = &source[start..end];
+        let Ok(item) = syn::parse_str::<Item>(text.trim()) else {
+            continue;
+        };
+        if matches!(item, Item::Use(_)) {
+            continue;
+        }
+        let offset = start + (text.len() - text.trim_start().len());
+        let labels = placement_labels(&item);
+        let mut files: Vec<String> = Vec::new();
+        for (destination, destination_labels) in destinations {
+            if labels
+                .iter()
+                .any(|label| destination_labels.contains(label))
+                && !files.contains(destination)
+            {
+                files.push(destination.clone());
+            }
+        }
+        placements.push(ItemPlacement {
+            file: file.to_string(),
+            line: source[..offset].matches('\n').count() + 1,
+            index,
+            item: describe(&item),
+            destinations: files,
+        });
+    }
+    placements
+}
+
+/// Render the original items next to their destinations
+pub fn render_comparison(placements: &[ItemPlacement]) -> String {
+    let locations: Vec<String> = placements
+        .iter()
+        .map(|p| format!("{}:{}", p.file, p.line))
+        .collect();
+    let location_width = locations.iter().map(String::len).max().unwrap_or(0);
+    let item_width = placements
+        .iter()
+        .map(|p| p.item.chars().count())
+        .chain(std::iter::once("Original item".len()))
+        .max()
+        .unwrap_or(0);
+
+    let mut out = format!(
+        "  {:<lw$}  {:<iw$}     {}\n",
+        "",
+        "Original item",
+        "Destination",
+        lw = location_width,
+        iw = item_width
+    );
+    for (placement, location) in placements.iter().zip(&locations) {
+        let destination = if placement.destinations.is_empty() {
+            "(not tracked)".to_string()
+        } else {
+            placement.destinations.join(", ")
+        };
+        out.push_str(&format!(
+            "  {:<lw$}  {:<iw$}  →  {}\n",
+            location,
+            placement.item,
+            destination,
+            lw = location_width,
+            iw = item_width
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_place_items() {
+        let source = "use std::fmt;\n\n/// A user\npub struct User;\n\nimpl User {\n    fn a(&self) {}\n    fn b(&self) {}\n}\n\nimpl fmt::Display for User {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { Ok(()) }\n}\n\nmod tests;\n";
+        let destinations = vec![
+            (
+                "types.rs".to_string(),
+                vec!["User".to_string(), "User::a".to_string()],
+            ),
+            ("user_impl.rs".to_string(), vec!["User::b".to_string()]),
+            (
+                "user_traits.rs".to_string(),
+                vec!["fmt::Display for User".to_string()],
+            ),
+        ];
+        let placements = place_items("big.rs", source, &destinations);
+        let summary: Vec<(usize, usize, &str, Vec<&str>)> = placements
+            .iter()
+            .map(|p| {
+                (
+                    p.index,
+                    p.line,
+                    p.item.as_str(),
+                    p.destinations.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, 3, "struct User", vec!["types.rs"]),
+                (2, 6, "impl User", vec!["types.rs", "user_impl.rs"]),
+                (3, 11, "impl fmt::Display for User", vec!["user_traits.rs"]),
+                (4, 15, "mod tests", vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_comparison() {
+        let placements = vec![
+            ItemPlacement {
+                file: "big.rs".to_string(),
+                line: 3,
+                index: 1,
+                item: "struct User".to_string(),
+                destinations: vec!["types.rs".to_string()],
+            },
+            ItemPlacement {
+                file: "big.rs".to_string(),
+                line: 15,
+                index: 5,
+                item: "lazy_static!".to_string(),
+                destinations: Vec::new(),
+            },
+        ];
+        let rendered = render_comparison(&placements);
+        assert!(rendered.contains("big.rs:3   struct User    →  types.rs\n"));
+        assert!(rendered.contains("big.rs:15  lazy_static!   →  (not tracked)\n"));
+    }
+}
@@ -0,0 +1,442 @@
+//! Rewriting paths in moved code for the new module layout
+//!
+//! Absolute paths into the original module (`crate::big::helper()`) go stale
+//! when the split output lives at a different module path, or when the
+//! target item is not re-exported from the new `mod.rs`. Relative
+//! `self::` and `super::` paths go stale as soon as the item moves into a
+//! generated submodule, one level deeper than the original file. This pass
+//! points them at the item's new location.
+
+use std::collections::{HashMap, HashSet};
+use syn::punctuated::Punctuated;
+use syn::visit_mut::VisitMut;
+use syn::{Ident, Path, PathSegment, UseTree};
+
+/// Where the items of the original file ended up
+pub struct PathContext<'a> {
+    /// Module path of the original file (`crate::big`), if known
+    pub original_module: Option<&'a str>,
+
+    /// Module path of the output directory (`crate::big_split`), if known
+    pub new_module: Option<&'a str>,
+
+    /// Generated module each top-level name was placed in
+    pub layout: &'a HashMap<String, String>,
+
+    /// Names that the new `mod.rs` does not re-export
+    pub hidden: &'a HashSet<String>,
+}
+
+impl PathContext<'_> {
+    /// Rewritten segments for an absolute `crate::` path, or `None` if it
+    /// still resolves
+    pub fn rewrite_crate_path(&self, segments: &[String]) -> Option<Vec<String>> {
+        let original: Vec<&str> = self.original_module?.split("::").collect();
+        let new: Vec<&str> = self
+            .new_module
+            .unwrap_or(self.original_module?)
+            .split("::")
+            .collect();
+        if segments.len() <= original.len() || !segments.iter().zip(&original).all(|(a, b)| a == b)
+        {
+            return None;
+        }
+
+        let rest = &segments[original.len()..];
+        let mut rewritten: Vec<String> = new.iter().map(|s| s.to_string()).collect();
+        if let Some(module) = self.layout.get(&rest[0]) {
+            if self.hidden.contains(&rest[0]) {
+                rewritten.push(module.clone());
+            }
+        }
+        rewritten.extend(rest.iter().cloned());
+        (rewritten != segments).then_some(rewritten)
+    }
+
+    /// Rewritten segments for a `self::` path written in the original file
+    /// and now living in the generated module `module`, or `None` if it
+    /// still resolves
+    pub fn rewrite_self_path(&self, segments: &[String], module: &str) -> Option<Vec<String>> {
+        let name = segments.get(1)?;
+        let target = self.layout.get(name);
+        if target.is_some_and(|target| target == module) {
+            return None;
+        }
+
+        // The original module is now the parent of the generated one
+        let mut rewritten = vec!["super".to_string()];
+        if let Some(target) = target {
+            if self.hidden.contains(name) {
+                rewritten.push(target.clone());
+            }
+        }
+        rewritten.extend(segments[1..].iter().cloned());
+        Some(rewritten)
+    }
+
+    /// Rewritten segments for a `super::` path written in the original file
+    /// and now living in the generated module `module` (or in the new
+    /// `mod.rs` itself when `module` is `None`)
+    ///
+    /// With both module paths known, the target is resolved against the
+    /// original module and re-expressed relative to the new home, falling
+    /// back to a `crate::` path when the homes are further apart. Otherwise
+    /// the generated module is assumed to sit one level below the original.
+    pub fn rewrite_super_path(
+        &self,
+        segments: &[String],
+        module: Option<&str>,
+    ) -> Option<Vec<String>> {
+        let supers = segments.iter().take_while(|s| *s == "super").count();
+        if supers == segments.len() {
+            return None;
+        }
+        let (Some(original), Some(new)) = (
+            self.original_module,
+            self.new_module.or(self.original_module),
+        ) else {
+            module?;
+            let mut rewritten = vec!["super".to_string()];
+            rewritten.extend(segments.iter().cloned());
+            return Some(rewritten);
+        };
+
+        let original: Vec<&str> = original.split("::").collect();
+        if supers >= original.len() {
+            return None;
+        }
+        let mut target: Vec<String> = original[..original.len() - supers]
+            .iter()
+            .map(|s| s.to_string())
+            .chain(segments[supers..].iter().cloned())
+            .collect();
+        if let Some(moved) = self.rewrite_crate_path(&target) {
+            target = moved;
+        }
+
+        let home: Vec<&str> = new.split("::").chain(module).collect();
+        let common = target
+            .iter()
+            .zip(&home)
+            .take_while(|(a, b)| a == *b)
+            .count();
+        // Keep the relative form while it needs at most the one extra level
+        let rewritten = if common == home.len() || home.len() - common > supers + 1 {
+            target
+        } else {
+            let mut relative = vec!["super".to_string(); home.len() - common];
+            relative.extend(target[common..].iter().cloned());
+            relative
+        };
+        (rewritten != segments).then_some(rewritten)
+    }
+
+    /// Rewrite the path of a `use` item hoisted from the original file into
+    /// the new `mod.rs`
+    ///
+    /// # Returns
+    ///
+    /// Whether the path changed
+    pub fn rewrite_use_tree(&self, tree: &mut UseTree) -> bool {
+        let span = match &*tree {
+            UseTree::Path(p) => p.ident.span(),
+            UseTree::Name(n) => n.ident.span(),
+            UseTree::Rename(r) => r.ident.span(),
+            _ => return false,
+        };
+        let mut segments = Vec::new();
+        let mut leaf = &*tree;
+        while let UseTree::Path(p) = leaf {
+            segments.push(p.ident.to_string());
+            leaf = &p.tree;
+        }
+        let leaf_name = match leaf {
+            UseTree::Name(n) => Some(n.ident.to_string()),
+            UseTree::Rename(r) => Some(r.ident.to_string()),
+            _ => None,
+        };
+        segments.extend(leaf_name.clone());
+
+        let rewritten = match segments.first().map(String::as_str) {
+            Some("crate") => self.rewrite_crate_path(&segments),
+            Some("super") => self.rewrite_super_path(&segments, None),
+            Some("self") if segments.len() > 1 => {
+                let name = &segments[1];
+                self.layout
+                    .get(name)
+                    .filter(|_| self.hidden.contains(name))
+                    .map(|module| {
+                        let mut rewritten = vec!["self".to_string(), module.clone()];
+                        rewritten.extend(segments[1..].iter().cloned());
+                        rewritten
+                    })
+            }
+            _ => None,
+        };
+        let Some(mut rewritten) = rewritten else {
+            return false;
+        };
+
+        let mut new_tree = leaf.clone();
+        if leaf_name.is_some() {
+            let name = rewritten.pop().unwrap_or_default();
+            match &mut new_tree {
+                UseTree::Name(n) => n.ident = Ident::new(&name, n.ident.span()),
+                UseTree::Rename(r) => r.ident = Ident::new(&name, r.ident.span()),
+                _ => {}
+            }
+        }
+        for name in rewritten.iter().rev() {
+            new_tree = UseTree::Path(syn::UsePath {
+                ident: Ident::new(name, span),
+                colon2_token: Default::default(),
+                tree: Box::new(new_tree),
+            });
+        }
+        *tree = new_tree;
+        true
+    }
+}
+
+/// Visitor applying a `PathContext` to every path it visits
+pub struct PathRewriter<'c, 'a> {
+    pub context: &'c PathContext<'a>,
+
+    /// Generated module the visited items were placed in
+    pub module: &'c str,
+
+    /// Number of paths rewritten
+    pub rewritten: usize,
+
+    /// Depth of inline `mod` blocks, where relative paths are left alone
+    nested: usize,
+}
+
+impl<'c, 'a> PathRewriter<'c, 'a> {
+    pub fn new(context: &'c PathContext<'a>, module: &'c str) -> Self {
+        Self {
+            context,
+            module,
+            rewritten: 0,
+            nested: 0,
+        }
+    }
+}
+
+impl VisitMut for PathRewriter<'_, '_> {
+    fn visit_path_mut(&mut self, path: &mut Path) {
+        let first = path.segments.first().map(|s| s.ident.to_string());
+        if path.leading_colon.is_none() && path.segments.len() > 1 {
+            let names: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+            let new_names = match first.as_deref() {
+                Some("crate") => self.context.rewrite_crate_path(&names),
+                Some("self") if self.nested == 0 => {
+                    self.context.rewrite_self_path(&names, self.module)
+                }
+                Some("super") if self.nested == 0 => {
+                    self.context.rewrite_super_path(&names, Some(self.module))
+                }
+                _ => None,
+            };
+            if let Some(new_names) = new_names {
+                replace_prefix(path, names.len(), &new_names);
+                self.rewritten += 1;
+            }
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        self.nested += 1;
+        syn::visit_mut::visit_item_mod_mut(self, module);
+        self.nested -= 1;
+    }
+}
+
+/// Replace the identifiers of `path` with `new_names`, keeping the generic
+/// arguments of the trailing segments that survive the rewrite
+fn replace_prefix(path: &mut Path, old_len: usize, new_names: &[String]) {
+    let old: Vec<PathSegment> = path.segments.iter().cloned().collect();
+    let kept_tail = old_len.min(new_names.len());
+    let span = old[0].ident.span();
+
+    let mut segments = Punctuated::new();
+    for (index, name) in new_names.iter().enumerate() {
+        let from_end = new_names.len() - index;
+        if from_end <= kept_tail {
+            let mut segment = old[old_len - from_end].clone();
+            segment.ident = Ident::new(name, segment.ident.span());
+            segments.push(segment);
+        } else {
+            segments.push(PathSegment::from(Ident::new(name, span)));
+        }
+    }
+    path.segments = segments;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::ToTokens;
+
+    fn rewrite(context: &PathContext, code: syn::Expr) -> String {
+        let mut expr = code;
+        PathRewriter::new(context, "functions").visit_expr_mut(&mut expr);
+        expr.to_token_stream().to_string().replace(' ', "")
+    }
+
+    #[test]
+    fn test_crate_paths_follow_the_move() {
+        let layout: HashMap<String, String> = [
+            ("helper".to_string(), "functions".to_string()),
+            ("secret".to_string(), "internal".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let hidden: HashSet<String> = ["secret".to_string()].into_iter().collect();
+        let context = PathContext {
+            original_module: Some("crate::big"),
+            new_module: Some("crate::big_split"),
+            layout: &layout,
+            hidden: &hidden,
+        };
+
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(crate::big::helper::<u32>(1))),
+            "crate::big_split::helper::<u32>(1)"
+        );
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(crate::big::secret())),
+            "crate::big_split::internal::secret()"
+        );
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(crate::other::helper())),
+            "crate::other::helper()"
+        );
+    }
+
+    #[test]
+    fn test_same_location_keeps_reexported_paths() {
+        let layout: HashMap<String, String> = [("helper".to_string(), "functions".to_string())]
+            .into_iter()
+            .collect();
+        let hidden = HashSet::new();
+        let context = PathContext {
+            original_module: Some("crate::big"),
+            new_module: Some("crate::big"),
+            layout: &layout,
+            hidden: &hidden,
+        };
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(crate::big::helper())),
+            "crate::big::helper()"
+        );
+    }
+
+    #[test]
+    fn test_self_paths_point_at_the_parent() {
+        let layout: HashMap<String, String> = [
+            ("helper".to_string(), "functions".to_string()),
+            ("LIMIT".to_string(), "constants".to_string()),
+            ("secret".to_string(), "internal".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        let hidden: HashSet<String> = ["secret".to_string()].into_iter().collect();
+        let context = PathContext {
+            original_module: None,
+            new_module: None,
+            layout: &layout,
+            hidden: &hidden,
+        };
+
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(self::helper(self::LIMIT))),
+            "self::helper(super::LIMIT)"
+        );
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(self::secret())),
+            "super::internal::secret()"
+        );
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(self::child::Thing::new())),
+            "super::child::Thing::new()"
+        );
+    }
+
+    #[test]
+    fn test_super_paths_account_for_the_extra_level() {
+        let layout: HashMap<String, String> = [("helper".to_string(), "functions".to_string())]
+            .into_iter()
+            .collect();
+        let hidden = HashSet::new();
+        let mut context = PathContext {
+            original_module: Some("crate::net::big"),
+            new_module: Some("crate::net::big"),
+            layout: &layout,
+            hidden: &hidden,
+        };
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(super::util::parse())),
+            "super::super::util::parse()"
+        );
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(super::big::helper())),
+            "super::helper()"
+        );
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(super::super::Config::new())),
+            "super::super::super::Config::new()"
+        );
+
+        context.new_module = Some("crate::split::big");
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(super::util::parse())),
+            "crate::net::util::parse()"
+        );
+
+        context.original_module = None;
+        assert_eq!(
+            rewrite(&context, syn::parse_quote!(super::util::parse())),
+            "super::super::util::parse()"
+        );
+    }
+
+    #[test]
+    fn test_hoisted_reexports_point_at_the_new_layout() {
+        let layout: HashMap<String, String> = [("secret".to_string(), "internal".to_string())]
+            .into_iter()
+            .collect();
+        let hidden: HashSet<String> = ["secret".to_string()].into_iter().collect();
+        let context = PathContext {
+            original_module: Some("crate::net::big"),
+            new_module: Some("crate::split::big"),
+            layout: &layout,
+            hidden: &hidden,
+        };
+        let rewrite_use = |item: syn::ItemUse| {
+            let mut item = item;
+            context.rewrite_use_tree(&mut item.tree);
+            item.to_token_stream().to_string().replace(' ', "")
+        };
+
+        assert_eq!(
+            rewrite_use(syn::parse_quote!(
+                pub use super::util::{parse, Parser};
+            )),
+            "pubusesuper::super::net::util::{parse,Parser};"
+        );
+        assert_eq!(
+            rewrite_use(syn::parse_quote!(
+                pub use self::secret as open;
+            )),
+            "pubuseself::internal::secretasopen;"
+        );
+        assert_eq!(
+            rewrite_use(syn::parse_quote!(
+                pub use self::detail::Thing;
+            )),
+            "pubuseself::detail::Thing;"
+        );
+    }
+}
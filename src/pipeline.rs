@@ -0,0 +1,984 @@
+//! The split itself, apart from where its inputs come from
+//!
+//! [`plan`] parses the inputs, groups their items into modules and finds
+//! what the move would break; [`SplitPlan::render`] turns the modules into
+//! the files of the output directory. Neither touches the disk: whatever a
+//! split needs to know about the crate around its inputs is looked up by
+//! the caller and passed in [`SplitInputs`]. The command line and
+//! [`crate::split_source`] both split through here.
+
+use crate::analysis::AnalysisModel;
+use crate::config::{self, Config, OutputFormat};
+use crate::export_analyzer::{self, ReexportPlan};
+use crate::import_suggester::insert_use_statements;
+use crate::reporter::Reporter;
+use crate::{
+    budget, cfg_gate, compact_tokens, convert_newlines, crate_root, dead_code, doctests,
+    existing_module, file_names, generate_directory_mod_rs, generate_mod_rs, hierarchy,
+    import_analyzer, import_module_dependencies, lenient, multi_input, outline,
+    place_in_subdirectories, renames, report, rewrite_doc_links, rewrite_include_paths,
+    rewrite_paths, risk, scope_analyzer, verbatim, FileAnalyzer, Module,
+};
+use anyhow::{Context, Result};
+use quote::ToTokens;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use syn::{File, Item};
+
+/// The inputs of a split, with what their place on disk tells about them
+pub struct SplitInputs {
+    /// Each input file with its content
+    pub files: Vec<(PathBuf, String)>,
+
+    /// Directory the modules are written to
+    pub output_dir: PathBuf,
+
+    /// Parse with recovery, copying unparseable items verbatim
+    pub lenient: bool,
+
+    /// Keep the parsed inputs for round-trip verification
+    pub keep_original: bool,
+
+    /// Kind of crate root the single input is, if it is one
+    pub crate_root: Option<crate_root::RootKind>,
+
+    /// Child modules already in the output directory, when the input is
+    /// its `mod.rs`
+    pub existing_children: Option<BTreeSet<String>>,
+
+    /// Point the input's `mod child;` declarations at their files from the
+    /// output directory
+    pub relocate_declarations: bool,
+
+    /// Crate-internal names and their paths, when crate types are resolved
+    pub crate_symbols: Option<BTreeMap<String, String>>,
+
+    /// Types of the previous run, when renames are followed
+    pub previous_types: Option<Vec<report::TypeRecord>>,
+
+    /// How much this run may move
+    pub budget: budget::Budget,
+
+    /// Leave names exported twice reachable only through their module
+    pub qualify_conflicts: bool,
+
+    /// Module path of the input file, if known
+    pub original_module: Option<String>,
+
+    /// Module path of the output directory, if known
+    pub new_module: Option<String>,
+
+    /// Name doctests use for the crate of the inputs, if they are in one
+    pub doctest_crate: Option<String>,
+}
+
+impl SplitInputs {
+    /// Inputs with nothing known about their crate
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - Each input file with its content
+    /// * `output_dir` - Directory the modules are written to
+    pub fn new(files: Vec<(PathBuf, String)>, output_dir: PathBuf) -> Self {
+        Self {
+            files,
+            output_dir,
+            lenient: false,
+            keep_original: false,
+            crate_root: None,
+            existing_children: None,
+            relocate_declarations: false,
+            crate_symbols: None,
+            previous_types: None,
+            budget: budget::Budget::default(),
+            qualify_conflicts: false,
+            original_module: None,
+            new_module: None,
+            doctest_crate: None,
+        }
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        self.files.iter().map(|(path, _)| path.clone()).collect()
+    }
+}
+
+/// The modules a split produces and everything found out on the way
+pub struct SplitPlan {
+    /// The inputs, after the items that stay behind were taken out
+    pub syntax_tree: File,
+
+    /// The inputs as parsed, when kept for round-trip verification
+    pub original_tree: Option<File>,
+
+    /// Content of all inputs, one after the other
+    pub source_code: String,
+    pub model: AnalysisModel,
+    pub modules: Vec<Module>,
+
+    /// Items that stay in the crate root, when the input is one
+    pub root_items: Vec<Item>,
+    pub child_declarations: Vec<Item>,
+    pub hoisted_reexports: Vec<Item>,
+    pub anchored_items: Vec<Item>,
+
+    /// Items a budgeted run leaves in `mod.rs`
+    pub remainder_items: Vec<Item>,
+    pub skipped_spans: Vec<(String, lenient::SkippedSpan)>,
+    pub type_records: Vec<report::TypeRecord>,
+    pub extracted_sizes: Vec<(String, usize)>,
+    pub deferred_modules: Vec<budget::DeferredModule>,
+
+    /// Names each module needs that nothing imports
+    pub unresolved: Vec<(String, Vec<String>)>,
+    pub reexports: ReexportPlan,
+    pub risks: Vec<risk::Risk>,
+    pub placements: Vec<report::ItemPlacement>,
+}
+
+/// The files of a split, rendered
+pub struct RenderedSplit {
+    /// Every file to write, in order, with its content
+    pub planned: Vec<(PathBuf, String)>,
+
+    /// Each file relative to the output directory, with its line count
+    pub tree_files: Vec<(String, usize)>,
+    pub module_reports: Vec<report::ModuleReport>,
+
+    /// Directories the modules were placed in
+    pub directories: BTreeSet<String>,
+    pub mod_path: PathBuf,
+}
+
+/// Parse the inputs, group their items into modules and check the result
+///
+/// # Arguments
+///
+/// * `inputs` - What to split
+/// * `config` - Settings of the split
+/// * `reporter` - Receives the progress of the analysis
+pub fn plan(
+    inputs: &SplitInputs,
+    config: &Config,
+    reporter: &mut dyn Reporter,
+) -> Result<SplitPlan> {
+    let output_dir = &inputs.output_dir;
+    let single_input = inputs.files.len() == 1;
+
+    // Parse the input files
+    let mut source_code = String::new();
+    let mut parsed_inputs = Vec::new();
+    let mut skipped_spans = Vec::new();
+    let preserve = config.output.format == OutputFormat::Preserve;
+    let mut verbatim_source = if preserve {
+        verbatim::VerbatimSource::preserving()
+    } else {
+        verbatim::VerbatimSource::default()
+    };
+    for (input, source) in &inputs.files {
+        let file: File = if inputs.lenient {
+            let parsed = lenient::parse_lenient(source);
+            skipped_spans.extend(
+                parsed
+                    .skipped
+                    .into_iter()
+                    .map(|span| (input.display().to_string(), span)),
+            );
+            parsed.file
+        } else {
+            syn::parse_file(source)
+                .context(format!("Failed to parse Rust source code in {:?}", input))?
+        };
+        if config.output.verbatim_items || preserve {
+            verbatim_source.add(source);
+        }
+        source_code.push_str(source);
+        parsed_inputs.push((input.clone(), file));
+    }
+
+    let mut syntax_tree: File = if single_input {
+        parsed_inputs.remove(0).1
+    } else {
+        let merged = multi_input::merge_inputs(parsed_inputs);
+        reportln!(
+            reporter,
+            "\nMerged {} input files ({} duplicate imports removed, {} cross-references resolved)",
+            inputs.files.len(),
+            merged.deduplicated_imports,
+            merged.resolved_cross_references
+        );
+        merged.file
+    };
+
+    for (input, _) in &inputs.files {
+        reportln!(reporter, "\nAnalyzing file: {:?}", input);
+    }
+    let original_tree = inputs.keep_original.then(|| syntax_tree.clone());
+
+    // Crate-root-only items and inner attributes stay in the rewritten root
+    let root_items = match inputs.crate_root {
+        Some(root_kind) => {
+            let root_items = crate_root::take_root_items(
+                &mut syntax_tree,
+                root_kind,
+                &config.splitrs.anchor_attributes,
+            );
+            reportln!(
+                reporter,
+                "Input is a crate root; {} root-only item(s) and its inner attributes stay in it",
+                root_items.len()
+            );
+            let widened =
+                crate_root::expose_to_entry_points(&mut syntax_tree, &root_items, root_kind);
+            if widened > 0 {
+                reportln!(
+                    reporter,
+                    "Made {} item(s) used by the crate's entry points pub(crate)",
+                    widened
+                );
+            }
+            root_items
+        }
+        None => Vec::new(),
+    };
+
+    // `mod child;` declarations stay in mod.rs, pointing at the same files
+    let child_declarations = if single_input {
+        let input = &inputs.files[0].0;
+        let mut declarations = existing_module::take_child_declarations(&mut syntax_tree);
+        if inputs.relocate_declarations {
+            for declaration in &mut declarations {
+                existing_module::relocate_declaration(declaration, input, output_dir);
+            }
+        }
+        if inputs.existing_children.is_some() {
+            reportln!(
+                reporter,
+                "Input is the mod.rs of the output directory; its child modules are kept"
+            );
+        }
+        if !declarations.is_empty() {
+            reportln!(
+                reporter,
+                "Keeping {} module declaration(s) in mod.rs",
+                declarations.len()
+            );
+        }
+        declarations
+    } else {
+        Vec::new()
+    };
+
+    // Re-exports are the module's surface rather than code; they move too
+    let mut hoisted_reexports = if single_input {
+        existing_module::take_reexports(&mut syntax_tree)
+    } else {
+        Vec::new()
+    };
+    let mut hoisted_names = BTreeSet::new();
+    for item in &hoisted_reexports {
+        if let Item::Use(u) = item {
+            let mut names = Vec::new();
+            export_analyzer::collect_use_leaves(&u.tree, &mut names);
+            hoisted_names.extend(names);
+        }
+    }
+    if !hoisted_reexports.is_empty() {
+        reportln!(
+            reporter,
+            "Hoisting {} re-export(s) into mod.rs",
+            hoisted_reexports.len()
+        );
+    }
+
+    // Items with an anchor attribute are never relocated
+    let anchored_items = if inputs.crate_root.is_some() {
+        Vec::new()
+    } else {
+        existing_module::take_anchored_items(&mut syntax_tree, &config.splitrs.anchor_attributes)
+    };
+    hoisted_names.extend(
+        anchored_items
+            .iter()
+            .filter_map(import_analyzer::declared_ident),
+    );
+    if !anchored_items.is_empty() {
+        reportln!(
+            reporter,
+            "Keeping {} anchored item(s) in mod.rs",
+            anchored_items.len()
+        );
+    }
+    if !skipped_spans.is_empty() {
+        reportln!(
+            reporter,
+            "⚠️  Skipped {} unparseable item(s), copied verbatim to {}.rs:",
+            skipped_spans.len(),
+            config.naming.unparsed_module
+        );
+        for (input, span) in &skipped_spans {
+            reportln!(
+                reporter,
+                "  {}:{}-{}: {}",
+                input,
+                span.start_line,
+                span.end_line,
+                span.error
+            );
+        }
+    }
+    reportln!(reporter, "Total items: {}", syntax_tree.items.len());
+    if config.splitrs.split_impl_blocks {
+        reportln!(
+            reporter,
+            "Impl block splitting enabled (max {} lines per impl)",
+            config.splitrs.max_impl_lines
+        );
+    }
+
+    // Analyze the file
+    let mut model = AnalysisModel::build(&syntax_tree);
+    if let Some(symbols) = &inputs.crate_symbols {
+        reportln!(reporter, "Indexed {} crate-internal symbols", symbols.len());
+        model.imports.add_crate_symbols(symbols.clone());
+    }
+    let mut analyzer = FileAnalyzer::from_config(config);
+    if let Some(existing_children) = &inputs.existing_children {
+        analyzer.reserve_module_names(existing_children.clone());
+    }
+    let declared_children = existing_module::declared_names(&child_declarations);
+    analyzer.reserve_module_names(declared_children.clone());
+    analyzer.analyze(&syntax_tree, &model);
+
+    reportln!(reporter, "Found {} types", analyzer.types.len());
+    reportln!(
+        reporter,
+        "Found {} standalone items",
+        analyzer.standalone_items.len()
+    );
+
+    // Private items nothing uses may be better deleted than moved
+    let unreferenced = dead_code::unreferenced_items(&syntax_tree);
+    if !unreferenced.is_empty() {
+        reportln!(
+            reporter,
+            "Found {} unreferenced private items (consider deleting instead of moving):",
+            unreferenced.len()
+        );
+        for label in &unreferenced {
+            reportln!(reporter, "  - {}", label);
+        }
+    }
+
+    // Show trait implementation counts
+    let total_trait_impls: usize = analyzer.types.values().map(|t| t.trait_impls.len()).sum();
+    if total_trait_impls > 0 {
+        reportln!(
+            reporter,
+            "Found {} trait implementations",
+            total_trait_impls
+        );
+    }
+
+    // Keep the module names of types renamed since the previous run
+    let mut type_records = analyzer.type_records();
+    if let Some(previous_types) = &inputs.previous_types {
+        let (renames, kept) = renames::follow_renames(previous_types, &mut type_records);
+        for rename in &renames {
+            reportln!(
+                reporter,
+                "Following rename {} -> {} ({:.0}% similar)",
+                rename.from,
+                rename.to,
+                rename.similarity * 100.0
+            );
+        }
+        if !renames.is_empty() {
+            for (name, old) in &kept {
+                reportln!(reporter, "  keeping module {} instead of {}", old, name);
+            }
+        }
+        analyzer.follow_module_renames(kept);
+    }
+
+    // Group into modules
+    let mut modules = analyzer.group_by_module(config.splitrs.max_lines);
+    for record in &mut type_records {
+        record.modules = modules
+            .iter()
+            .filter(|m| m.holds_type(&record.name))
+            .map(|m| m.name.clone())
+            .collect();
+    }
+    let source_positions = Arc::new(analyzer.source_positions.clone());
+    for module in &mut modules {
+        module.doc_index = config.output.module_index;
+        module.item_order = config.output.item_order;
+        module.impl_item_order = config.output.impl_item_order;
+        module.source_positions = Arc::clone(&source_positions);
+    }
+
+    // A budgeted run extracts the largest modules; the rest stays in mod.rs
+    let budget = inputs.budget;
+    let mut deferred_modules = Vec::new();
+    let mut extracted_sizes = Vec::new();
+    let mut remainder_items = Vec::new();
+    if budget.is_limited() {
+        let sizes: Vec<(String, usize)> = modules
+            .iter()
+            .map(|m| {
+                let lines = m.generate_content(&model.imports).lines().count();
+                (m.name.clone(), lines)
+            })
+            .collect();
+        let selected = budget.select(&sizes);
+        if selected.is_empty() {
+            anyhow::bail!("--top 0 extracts nothing");
+        }
+        let (extracted, deferred): (Vec<Module>, Vec<Module>) = modules
+            .into_iter()
+            .partition(|m| selected.contains(&m.name));
+        modules = extracted;
+
+        let mut moved_labels = BTreeSet::new();
+        let mut moved_items = BTreeSet::new();
+        for module in &modules {
+            moved_labels.extend(module.item_labels());
+            moved_items.extend(
+                module
+                    .standalone_items
+                    .iter()
+                    .map(|item| item.to_token_stream().to_string()),
+            );
+        }
+        remainder_items = budget::remainder(&syntax_tree.items, &moved_labels, &moved_items);
+        if !deferred.is_empty() {
+            for module in &mut modules {
+                module.extra_uses.push("use super::*;".to_string());
+            }
+        }
+        extracted_sizes = sizes
+            .iter()
+            .filter(|(name, _)| selected.contains(name))
+            .cloned()
+            .collect();
+        deferred_modules = deferred
+            .iter()
+            .map(|m| budget::DeferredModule {
+                name: m.name.clone(),
+                lines: sizes
+                    .iter()
+                    .find(|(name, _)| *name == m.name)
+                    .map_or(0, |(_, lines)| *lines),
+                labels: m.item_labels(),
+            })
+            .collect();
+        reportln!(
+            reporter,
+            "Extracting {} of {} modules this run; the rest stays in mod.rs",
+            modules.len(),
+            modules.len() + deferred_modules.len()
+        );
+    }
+    rewrite_doc_links(&mut modules, &model.imports);
+    let names: Vec<String> = modules.iter().map(|m| m.name.clone()).collect();
+    let stems = file_names::assign_file_stems(&names, &analyzer.reserved_module_names);
+    let verbatim_source = Arc::new(verbatim_source);
+    for (module, stem) in modules.iter_mut().zip(stems) {
+        module.file_stem = stem;
+        if config.output.verbatim_items || preserve {
+            module.verbatim = Some(Arc::clone(&verbatim_source));
+        }
+    }
+    if config.output.subdirectories {
+        place_in_subdirectories(&mut modules, config.output.max_modules_per_directory);
+    }
+    import_module_dependencies(
+        &mut modules,
+        &model.imports,
+        config.output.short_paths,
+        &declared_children,
+        &hoisted_names,
+    );
+    reportln!(reporter, "Generated {} modules", modules.len());
+
+    // Find names that moved code needs but SplitRS cannot import
+    let mut unresolved: Vec<(String, Vec<String>)> = modules
+        .iter()
+        .map(|m| {
+            (
+                m.name.clone(),
+                m.unresolved_names(&model.imports)
+                    .into_iter()
+                    .filter(|name| !hoisted_names.contains(name))
+                    .collect(),
+            )
+        })
+        .collect();
+
+    // Names a glob import of the original may provide: carry the globs along
+    let glob_uses = model.imports.glob_use_statements();
+    if !glob_uses.is_empty() {
+        let mut carried = 0;
+        for (module, (_, names)) in modules.iter_mut().zip(unresolved.iter_mut()) {
+            if names.is_empty() {
+                continue;
+            }
+            names.clear();
+            // Modules holding the original `use` items already have the globs
+            if !module
+                .standalone_items
+                .iter()
+                .any(|item| matches!(item, Item::Use(_)))
+            {
+                module.extra_uses.extend(glob_uses.iter().cloned());
+                carried += 1;
+            }
+        }
+        if carried > 0 {
+            reportln!(reporter, "Carried glob imports into {} module(s)", carried);
+        }
+    }
+
+    // Compute what mod.rs re-exports and detect names exported twice
+    let reexport_items: Vec<(String, Vec<Item>)> = modules
+        .iter()
+        .filter(|m| !m.internal)
+        .map(|m| (m.name.clone(), m.reexportable_items()))
+        .collect();
+    let mut reexports = ReexportPlan::build(
+        &reexport_items,
+        config.output.explicit_reexports,
+        inputs.qualify_conflicts || config.output.qualify_conflicting_exports,
+    );
+    reexports.doc_attribute = config.output.reexport_doc.attribute().map(str::to_string);
+
+    // Point paths into the original module at the new layout
+    let mut rewritten_paths = rewrite_paths(
+        &mut modules,
+        &mut hoisted_reexports,
+        &reexports,
+        inputs.original_module.as_deref(),
+        inputs.new_module.as_deref(),
+    );
+    let unresolved_includes = rewrite_include_paths(
+        &mut modules,
+        &inputs.paths(),
+        output_dir,
+        &mut rewritten_paths,
+    );
+    let rewritten_total: usize = rewritten_paths.iter().map(|(_, count)| count).sum();
+    if rewritten_total > 0 {
+        reportln!(
+            reporter,
+            "Rewrote {} path(s) for the new layout",
+            rewritten_total
+        );
+    }
+
+    // Doc examples name moved items by the public path of the split module
+    let mut doctests = 0;
+    let mut stale_doctests = Vec::new();
+    if let (Some(crate_name), Some(original), Some(new)) = (
+        &inputs.doctest_crate,
+        &inputs.original_module,
+        &inputs.new_module,
+    ) {
+        let mut reachable: BTreeSet<String> = modules.iter().map(|m| m.name.clone()).collect();
+        for (entry, (_, items)) in reexports.entries.iter().zip(&reexport_items) {
+            match &entry.style {
+                export_analyzer::ReexportStyle::Glob => reachable.extend(
+                    export_analyzer::exported_symbols(items)
+                        .into_iter()
+                        .map(|symbol| symbol.name),
+                ),
+                export_analyzer::ReexportStyle::Explicit(names) => {
+                    reachable.extend(names.iter().cloned())
+                }
+            }
+        }
+        for items in [
+            &child_declarations,
+            &hoisted_reexports,
+            &anchored_items,
+            &remainder_items,
+        ] {
+            reachable.extend(
+                export_analyzer::exported_symbols(items)
+                    .into_iter()
+                    .map(|symbol| symbol.name),
+            );
+        }
+        for module in &modules {
+            let mut scan = doctests::DoctestScan::new(module.impl_type_name.clone());
+            module.visit_items(&mut scan);
+            doctests += scan.doctests;
+            for (_, detail) in
+                doctests::stale_paths(&scan.paths, crate_name, original, new, &reachable)
+            {
+                stale_doctests.push((module.name.clone(), detail));
+            }
+        }
+    }
+    if doctests > 0 {
+        reportln!(
+            reporter,
+            "Doc examples: {} doctest(s) in moved items, {} path(s) to update by hand",
+            doctests,
+            stale_doctests.len()
+        );
+        for (module, detail) in &stale_doctests {
+            reportln!(reporter, "  {}: {}", module, detail);
+        }
+    }
+    // Gather everything that could change the meaning of the split code
+    let scans: Vec<(String, risk::RiskScan)> = modules
+        .iter()
+        .map(|m| {
+            let mut scan = risk::RiskScan::default();
+            m.visit_items(&mut scan);
+            // Fields a type module widens are visible to its siblings
+            if m.field_visibility
+                .as_ref()
+                .is_some_and(|vis| *vis != scope_analyzer::FieldVisibility::Private)
+            {
+                scan.private_fields.clear();
+            }
+            (m.name.clone(), scan)
+        })
+        .collect();
+    let mut risks = risk::cross_module_risks(&scans);
+    let cfg_gated = risk::cfg_gated_types(&syntax_tree);
+    for (type_name, detail) in cfg_gate::mismatched_impls(&syntax_tree) {
+        if let Some(module) = modules.iter().find(|m| m.holds_impls_of(&type_name)) {
+            risks.push(risk::Risk {
+                kind: risk::RiskKind::CfgSplit,
+                module: module.name.clone(),
+                detail,
+            });
+        }
+    }
+    for module in &modules {
+        if let (Some(type_name), Some(_)) = (&module.impl_type_name, &module.method_group) {
+            if cfg_gated.contains(type_name) && module.gate.is_empty() {
+                risks.push(risk::Risk {
+                    kind: risk::RiskKind::CfgSplit,
+                    module: module.name.clone(),
+                    detail: format!("split impl of `{}` drops its `#[cfg]`", type_name),
+                });
+            }
+        }
+        for change in module.visibility_changes() {
+            risks.push(risk::Risk {
+                kind: risk::RiskKind::VisibilityEscalation,
+                module: module.name.clone(),
+                detail: format!(
+                    "{} widened from {} to {}",
+                    change.item, change.from, change.to
+                ),
+            });
+        }
+    }
+    for (module, names) in &unresolved {
+        for name in names {
+            risks.push(risk::Risk {
+                kind: risk::RiskKind::UnresolvedImport,
+                module: module.clone(),
+                detail: format!("unresolved name `{}`", name),
+            });
+        }
+    }
+    for (module, include) in &unresolved_includes {
+        risks.push(risk::Risk {
+            kind: risk::RiskKind::PathRewrite,
+            module: module.clone(),
+            detail: format!(
+                "`{}` depends on the location of the file and was not rewritten",
+                compact_tokens(include)
+            ),
+        });
+    }
+    for (module, detail) in &stale_doctests {
+        risks.push(risk::Risk {
+            kind: risk::RiskKind::PathRewrite,
+            module: module.clone(),
+            detail: detail.clone(),
+        });
+    }
+    for (module, count) in &rewritten_paths {
+        risks.push(risk::Risk {
+            kind: risk::RiskKind::PathRewrite,
+            module: module.clone(),
+            detail: format!("{} path(s) rewritten for the new layout", count),
+        });
+    }
+    risks.retain(|r| r.kind.policy(&config.risks) != config::RiskPolicy::Ignore);
+    risks.sort();
+
+    // Where every item of the inputs ends up
+    let mut destinations: Vec<(String, Vec<String>)> = modules
+        .iter()
+        .map(|m| {
+            let mut labels = m.item_labels();
+            labels.extend(
+                m.standalone_items
+                    .iter()
+                    .filter(|item| matches!(item, Item::Mod(_)))
+                    .flat_map(outline::placement_labels),
+            );
+            (m.file_name(), labels)
+        })
+        .collect();
+    destinations.push((
+        "mod.rs".to_string(),
+        child_declarations
+            .iter()
+            .chain(&anchored_items)
+            .flat_map(outline::placement_labels)
+            .collect(),
+    ));
+    if inputs.crate_root.is_some() {
+        destinations.push((
+            format!("{} (crate root)", inputs.files[0].0.display()),
+            root_items
+                .iter()
+                .flat_map(outline::placement_labels)
+                .collect(),
+        ));
+    }
+    let placements: Vec<report::ItemPlacement> = inputs
+        .files
+        .iter()
+        .flat_map(|(input, source)| {
+            outline::place_items(&input.display().to_string(), source, &destinations)
+        })
+        .collect();
+
+    Ok(SplitPlan {
+        syntax_tree,
+        original_tree,
+        source_code,
+        model,
+        modules,
+        root_items,
+        child_declarations,
+        hoisted_reexports,
+        anchored_items,
+        remainder_items,
+        skipped_spans,
+        type_records,
+        extracted_sizes,
+        deferred_modules,
+        unresolved,
+        reexports,
+        risks,
+        placements,
+    })
+}
+
+impl SplitPlan {
+    /// Refuse a split whose risks or export conflicts forbid writing it
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Settings of the split; its `[risks]` policy decides
+    ///   which risks are errors
+    /// * `strict` - Also refuse risks `--strict` blocks
+    pub fn check_writable(&self, config: &Config, strict: bool) -> Result<()> {
+        let blocking: Vec<risk::Risk> = self
+            .risks
+            .iter()
+            .filter(|r| r.kind.blocks_strict())
+            .cloned()
+            .collect();
+        if strict && !blocking.is_empty() {
+            anyhow::bail!(
+                "Refusing to write output in --strict mode; {} semantic risk(s) remain:\n{}",
+                blocking.len(),
+                risk::render_risks(&blocking).trim_end()
+            );
+        }
+        let policy_errors: Vec<risk::Risk> = self
+            .risks
+            .iter()
+            .filter(|r| r.kind.policy(&config.risks) == config::RiskPolicy::Error)
+            .cloned()
+            .collect();
+        if !policy_errors.is_empty() {
+            anyhow::bail!(
+                "Refusing to write output; {} risk(s) are errors under the [risks] policy:\n{}",
+                policy_errors.len(),
+                risk::render_risks(&policy_errors).trim_end()
+            );
+        }
+
+        let collisions = self.reexports.unresolved_ambiguities();
+        if !collisions.is_empty() {
+            let details: Vec<String> = collisions.iter().map(|c| format!("  {}", c)).collect();
+            anyhow::bail!(
+                "Generated modules would export conflicting public names:\n{}\n\
+                 Rename the items, make one of them private, or pass --qualify-conflicts.",
+                details.join("\n")
+            );
+        }
+        Ok(())
+    }
+
+    /// Render the files of the output directory
+    ///
+    /// # Arguments
+    ///
+    /// * `inputs` - What was split
+    /// * `config` - Settings of the split
+    /// * `fixes` - `use` statements to add to each module, by module name
+    /// * `reporter` - Receives the directories flattened into files
+    pub fn render(
+        &self,
+        inputs: &SplitInputs,
+        config: &Config,
+        fixes: &BTreeMap<String, Vec<String>>,
+        reporter: &mut dyn Reporter,
+    ) -> Result<RenderedSplit> {
+        let output_dir = &inputs.output_dir;
+        let modules = &self.modules;
+        let newline = config.output.line_ending.newline(&self.source_code);
+        let unparsed_module = &config.naming.unparsed_module;
+        let deferred_names: BTreeSet<String> = self
+            .deferred_modules
+            .iter()
+            .map(|m| m.name.clone())
+            .collect();
+
+        // Render module files, in the order they are written
+        let mut planned: Vec<(PathBuf, String)> = Vec::new();
+        let moved: BTreeMap<String, String> = modules
+            .iter()
+            .filter_map(|m| Some((m.name.clone(), m.directory.clone()?)))
+            .collect();
+        let directories: BTreeSet<String> = moved.values().cloned().collect();
+        let mut tree_files = Vec::new();
+        for directory in &directories {
+            let content = generate_directory_mod_rs(modules, directory);
+            planned.push((
+                output_dir.join(directory).join("mod.rs"),
+                convert_newlines(&content, newline),
+            ));
+            tree_files.push((format!("{}/mod.rs", directory), content.lines().count()));
+        }
+        let mut module_reports = Vec::new();
+        for module in modules {
+            let module_path = output_dir.join(module.file_name());
+            let mut content = module.generate_content(&self.model.imports);
+            content = insert_use_statements(
+                &content,
+                &self
+                    .model
+                    .imports
+                    .crate_use_statements(&module.referenced_names()),
+            );
+            if let Some(statements) = fixes.get(&module.name) {
+                content = insert_use_statements(&content, statements);
+            }
+            if !moved.is_empty() {
+                content = hierarchy::relocate_paths(&content, module.directory.as_deref(), &moved);
+            }
+            if !deferred_names.is_empty() {
+                content = budget::redirect_to_parent(&content, &deferred_names);
+            }
+            let lines = content.lines().count();
+            planned.push((module_path.clone(), convert_newlines(&content, newline)));
+            tree_files.push((module.file_name(), lines));
+
+            module_reports.push(report::ModuleReport {
+                name: module.name.clone(),
+                file: module_path,
+                lines,
+                items: module.item_labels(),
+                reexport: self
+                    .reexports
+                    .entries
+                    .iter()
+                    .find(|entry| entry.module == module.name)
+                    .and_then(|entry| entry.render()),
+            });
+        }
+
+        // Write items that could not be parsed, untouched
+        let mut mod_content = generate_mod_rs(modules, &self.reexports, output_dir)?;
+        if !self.skipped_spans.is_empty() {
+            let unparsed_path = output_dir.join(format!("{}.rs", unparsed_module));
+            let content = lenient::render_skipped_module(&self.skipped_spans);
+            planned.push((unparsed_path, convert_newlines(&content, newline)));
+            tree_files.push((format!("{}.rs", unparsed_module), content.lines().count()));
+            mod_content.push_str(&format!(
+                "\npub mod {};\npub use {}::*;\n",
+                unparsed_module, unparsed_module
+            ));
+        }
+
+        // Render mod.rs
+        let mut kept_items = self.child_declarations.clone();
+        kept_items.extend(self.hoisted_reexports.iter().cloned());
+        kept_items.extend(self.anchored_items.iter().cloned());
+        kept_items.extend(self.remainder_items.iter().cloned());
+        if inputs.existing_children.is_some() || inputs.budget.is_limited() {
+            mod_content =
+                existing_module::render_mod_rs(&self.syntax_tree.attrs, &kept_items, &mod_content);
+        } else if !kept_items.is_empty() {
+            mod_content = existing_module::render_mod_rs(&[], &kept_items, &mod_content);
+        }
+        let mod_path = output_dir.join("mod.rs");
+        planned.push((mod_path.clone(), convert_newlines(&mod_content, newline)));
+        tree_files.push(("mod.rs".to_string(), mod_content.lines().count()));
+
+        // Directories nested too deep become files of inline modules
+        if let Some(max_depth) = config.output.max_depth {
+            let flattened =
+                hierarchy::flatten_deep_directories(output_dir, &mut planned, max_depth);
+            for (dir, file) in &flattened {
+                reportln!(reporter, "Flattened {:?} into {:?}", dir, file);
+                for module in &mut module_reports {
+                    if module.file.starts_with(dir) {
+                        module.file = file.clone();
+                    }
+                }
+            }
+            if !flattened.is_empty() {
+                for (_, content) in &mut planned {
+                    *content = convert_newlines(content, newline);
+                }
+                tree_files = planned
+                    .iter()
+                    .filter_map(|(path, content)| {
+                        let relative = path.strip_prefix(output_dir).ok()?;
+                        Some((relative.display().to_string(), content.lines().count()))
+                    })
+                    .collect();
+            }
+        }
+
+        // Tell the next runs of a staged split what is left
+        if inputs.budget.is_limited() {
+            let plan = budget::render_plan(
+                &inputs.files[0].0,
+                output_dir,
+                &inputs.budget,
+                &self.extracted_sizes,
+                &self.deferred_modules,
+            );
+            planned.push((
+                output_dir.join(budget::PLAN_FILE),
+                convert_newlines(&plan, newline),
+            ));
+        }
+
+        Ok(RenderedSplit {
+            planned,
+            tree_files,
+            module_reports,
+            directories,
+            mod_path,
+        })
+    }
+}
@@ -0,0 +1,287 @@
+//! Dry-run preview rendering
+//!
+//! Builds the human-readable tables shown by `--dry-run` so users can judge
+//! whether their thresholds are sensible before any files are written, and
+//! the module tree shown after a run.
+
+/// Estimated size of a single proposed module
+#[derive(Debug, Clone)]
+pub struct ModuleSize {
+    /// Module name (without the `.rs` extension)
+    pub name: String,
+
+    /// Estimated line count of the generated file
+    pub lines: usize,
+}
+
+/// Before/after size comparison for a planned split
+#[derive(Debug, Clone)]
+pub struct SizeTable {
+    /// Line count of the original input file
+    pub original_lines: usize,
+
+    /// Estimated sizes of all proposed modules, in plan order
+    pub modules: Vec<ModuleSize>,
+}
+
+impl SizeTable {
+    pub fn new(original_lines: usize) -> Self {
+        Self {
+            original_lines,
+            modules: Vec::new(),
+        }
+    }
+
+    /// Record the estimated size of a proposed module
+    pub fn add_module(&mut self, name: impl Into<String>, lines: usize) {
+        self.modules.push(ModuleSize {
+            name: name.into(),
+            lines,
+        });
+    }
+
+    /// The largest proposed module, if any
+    pub fn largest(&self) -> Option<&ModuleSize> {
+        self.modules.iter().max_by_key(|m| m.lines)
+    }
+
+    /// Total estimated lines across all proposed modules
+    pub fn total_lines(&self) -> usize {
+        self.modules.iter().map(|m| m.lines).sum()
+    }
+
+    /// Percentage by which the largest file shrinks compared to the original
+    ///
+    /// Returns `0.0` when the original is empty or the largest module is not
+    /// smaller than the original.
+    pub fn reduction_percent(&self) -> f64 {
+        let largest = self.largest().map(|m| m.lines).unwrap_or(0);
+        if self.original_lines == 0 || largest >= self.original_lines {
+            return 0.0;
+        }
+        (self.original_lines - largest) as f64 * 100.0 / self.original_lines as f64
+    }
+
+    /// Render the table as indented text suitable for the dry-run output
+    pub fn render(&self) -> String {
+        let name_width = self
+            .modules
+            .iter()
+            .map(|m| m.name.len() + 3)
+            .chain(std::iter::once("Module".len()))
+            .max()
+            .unwrap_or(6);
+
+        let mut out = String::new();
+        out.push_str(&format!(
+            "  {:<width$}  {:>8}  {:>8}\n",
+            "Module",
+            "Lines",
+            "Share",
+            width = name_width
+        ));
+        out.push_str(&format!("  {}\n", "-".repeat(name_width + 20)));
+
+        let total = self.total_lines().max(1);
+        for module in &self.modules {
+            out.push_str(&format!(
+                "  {:<width$}  {:>8}  {:>7.1}%\n",
+                format!("{}.rs", module.name),
+                module.lines,
+                module.lines as f64 * 100.0 / total as f64,
+                width = name_width
+            ));
+        }
+
+        out.push_str(&format!("  {}\n", "-".repeat(name_width + 20)));
+        out.push_str(&format!(
+            "  {:<width$}  {:>8}\n",
+            "Total",
+            self.total_lines(),
+            width = name_width
+        ));
+        out.push_str(&format!(
+            "  {:<width$}  {:>8}\n",
+            "Original",
+            self.original_lines,
+            width = name_width
+        ));
+
+        if let Some(largest) = self.largest() {
+            out.push_str(&format!(
+                "\n  Largest module: {}.rs ({} lines)\n",
+                largest.name, largest.lines
+            ));
+            out.push_str(&format!(
+                "  Largest file reduced by {:.1}% ({} -> {} lines)\n",
+                self.reduction_percent(),
+                self.original_lines,
+                largest.lines
+            ));
+        }
+
+        out
+    }
+}
+
+/// Render the names each module references but SplitRS cannot import
+///
+/// Each entry pairs a module name with its unresolved names. Modules without
+/// unresolved names are skipped; an empty string is returned when every
+/// module resolves cleanly.
+pub fn render_unresolved(entries: &[(String, Vec<String>)]) -> String {
+    let mut out = String::new();
+    for (module, names) in entries {
+        if names.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("  📄 {}.rs: {}\n", module, names.join(", ")));
+    }
+    out
+}
+
+/// A file or directory of the module tree
+struct TreeNode {
+    name: String,
+    lines: usize,
+    children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    fn insert(&mut self, path: &str, lines: usize) {
+        self.lines += lines;
+        let Some((directory, rest)) = path.split_once('/') else {
+            self.children.push(TreeNode {
+                name: path.to_string(),
+                lines,
+                children: Vec::new(),
+            });
+            return;
+        };
+        let name = format!("{}/", directory);
+        let index = match self.children.iter().position(|c| c.name == name) {
+            Some(index) => index,
+            None => {
+                self.children.push(TreeNode {
+                    name,
+                    lines: 0,
+                    children: Vec::new(),
+                });
+                self.children.len() - 1
+            }
+        };
+        self.children[index].insert(rest, lines);
+    }
+
+    fn render(&self, prefix: &str, out: &mut String) {
+        let mut children: Vec<&TreeNode> = self.children.iter().collect();
+        children.sort_by_key(|child| child.name != "mod.rs");
+        for (index, child) in children.iter().enumerate() {
+            let last = index + 1 == children.len();
+            out.push_str(&format!(
+                "{}{} {} ({} lines)\n",
+                prefix,
+                if last { "└──" } else { "├──" },
+                child.name,
+                child.lines
+            ));
+            let nested = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            child.render(&nested, out);
+        }
+    }
+}
+
+/// Render the generated module hierarchy as a tree with line counts
+///
+/// Directories show the total of the files below them; `mod.rs` comes
+/// first in each directory, the other files keep their order.
+///
+/// # Arguments
+///
+/// * `root` - The output directory, as displayed at the top
+/// * `files` - Path relative to the output directory and line count of
+///   every generated file
+pub fn render_module_tree(root: &str, files: &[(String, usize)]) -> String {
+    let mut tree = TreeNode {
+        name: format!("{}/", root.trim_end_matches('/')),
+        lines: 0,
+        children: Vec::new(),
+    };
+    for (path, lines) in files {
+        tree.insert(path, *lines);
+    }
+    let mut out = format!("  {} ({} lines)\n", tree.name, tree.lines);
+    tree.render("  ", &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_largest_and_reduction() {
+        let mut table = SizeTable::new(1000);
+        table.add_module("types", 300);
+        table.add_module("functions", 200);
+
+        assert_eq!(table.largest().unwrap().name, "types");
+        assert_eq!(table.total_lines(), 500);
+        assert!((table.reduction_percent() - 70.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_no_reduction_when_module_not_smaller() {
+        let mut table = SizeTable::new(100);
+        table.add_module("types", 120);
+        assert_eq!(table.reduction_percent(), 0.0);
+    }
+
+    #[test]
+    fn test_render_lists_every_module() {
+        let mut table = SizeTable::new(400);
+        table.add_module("types", 150);
+        table.add_module("user_traits", 50);
+
+        let rendered = table.render();
+        assert!(rendered.contains("types.rs"));
+        assert!(rendered.contains("user_traits.rs"));
+        assert!(rendered.contains("Largest module: types.rs (150 lines)"));
+    }
+
+    #[test]
+    fn test_render_module_tree() {
+        let files = vec![
+            ("functions.rs".to_string(), 40),
+            ("user/user_type.rs".to_string(), 30),
+            ("user/user_impl.rs".to_string(), 20),
+            ("user/mod.rs".to_string(), 5),
+            ("mod.rs".to_string(), 10),
+        ];
+        assert_eq!(
+            render_module_tree("src/big/", &files),
+            "  src/big/ (105 lines)\n\
+             \x20 ├── mod.rs (10 lines)\n\
+             \x20 ├── functions.rs (40 lines)\n\
+             \x20 └── user/ (55 lines)\n\
+             \x20     ├── mod.rs (5 lines)\n\
+             \x20     ├── user_type.rs (30 lines)\n\
+             \x20     └── user_impl.rs (20 lines)\n"
+        );
+    }
+
+    #[test]
+    fn test_render_unresolved_skips_clean_modules() {
+        let entries = vec![
+            ("types".to_string(), vec![]),
+            (
+                "functions".to_string(),
+                vec!["Missing".to_string(), "Other".to_string()],
+            ),
+        ];
+
+        let rendered = render_unresolved(&entries);
+        assert!(!rendered.contains("types.rs"));
+        assert!(rendered.contains("functions.rs: Missing, Other"));
+    }
+}
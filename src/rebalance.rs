@@ -0,0 +1,758 @@
+//! Re-balancing an existing module directory
+//!
+//! Directories produced by an earlier split tend to drift: one module keeps
+//! growing while its siblings stay small. Instead of re-splitting every file
+//! from scratch, re-balancing keeps the current assignment of items to
+//! modules and only moves trailing items out of modules that exceed the
+//! line threshold, preferring the smallest sibling with room and creating an
+//! overflow module only when no sibling can take the item.
+//!
+//! Types move together with the impl blocks that live next to them. Moved
+//! items keep working for the code they leave behind: private items are
+//! widened to `pub(super)`, and the source module imports them from their
+//! new home when its remaining code uses them (or re-exports them with the
+//! same visibility when sibling modules import them through it), while the
+//! target module receives a copy of the source's imports. Items the moved
+//! code uses that stay behind are imported from the source module, and
+//! widened to `pub(super)` when private.
+//!
+//! Modules are edited as text: items are cut out of the source module and
+//! appended to the target byte for byte, with the comments around them, so
+//! only the moved items and the added imports show up in the diff.
+
+use crate::existing_module::PathRoots;
+use crate::file_system::FileSystem;
+use crate::import_analyzer::declared_ident;
+use crate::lenient::{item_spans, item_start};
+use crate::merge::module_file;
+use crate::verbatim::without_inner_attributes;
+use anyhow::{Context, Result};
+use quote::ToTokens;
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use syn::visit::Visit;
+use syn::{File, Item, Visibility};
+
+/// An existing file-backed module of the directory
+struct ExistingModule {
+    name: String,
+    path: PathBuf,
+    source: String,
+    lines: usize,
+    changed: bool,
+}
+
+/// Items that must stay together (a type and its impls, or a single item)
+struct ItemUnit {
+    name: String,
+    items: Vec<Item>,
+    lines: usize,
+}
+
+/// A planned move of one unit between modules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemMove {
+    /// Name of the moved item (the type name for type units)
+    pub item: String,
+
+    /// Module the item currently lives in
+    pub from: String,
+
+    /// Module the item moves to
+    pub to: String,
+
+    /// Estimated lines moved
+    pub lines: usize,
+}
+
+/// The outcome of planning a re-balance
+pub struct RebalancePlan {
+    /// Moves to perform, in order
+    pub moves: Vec<ItemMove>,
+
+    /// Overflow modules that need to be created
+    pub new_modules: Vec<String>,
+
+    /// Module sizes (name, before, after) in declaration order
+    pub sizes: Vec<(String, usize, usize)>,
+
+    dir: PathBuf,
+    modules: Vec<ExistingModule>,
+}
+
+impl RebalancePlan {
+    /// Render a human-readable summary of the plan
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        if self.moves.is_empty() {
+            out.push_str("All modules are within the threshold; nothing to move.\n");
+            return out;
+        }
+
+        out.push_str("Planned moves:\n");
+        for m in &self.moves {
+            out.push_str(&format!(
+                "  {} ({} lines): {}.rs -> {}.rs\n",
+                m.item, m.lines, m.from, m.to
+            ));
+        }
+        out.push_str("\nModule sizes:\n");
+        for (name, before, after) in &self.sizes {
+            let marker = if self.new_modules.contains(name) {
+                " (new)"
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "  {}.rs: {} -> {} lines{}\n",
+                name, before, after, marker
+            ));
+        }
+        out
+    }
+
+    /// Write the changed modules and register new ones in `mod.rs`
+    ///
+    /// # Returns
+    ///
+    /// The paths of all files that were written.
+    pub fn apply(&self, file_system: &dyn FileSystem) -> Result<Vec<PathBuf>> {
+        let mut written = Vec::new();
+        for module in self.modules.iter().filter(|m| m.changed) {
+            file_system.write(&module.path, &module.source)?;
+            written.push(module.path.clone());
+        }
+
+        if !self.new_modules.is_empty() {
+            let mod_path = self.dir.join("mod.rs");
+            let mut content = file_system.read(&mod_path)?;
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            for name in &self.new_modules {
+                content.push_str(&format!("pub mod {};\npub use {}::*;\n", name, name));
+            }
+            file_system.write(&mod_path, &content)?;
+            written.push(mod_path);
+        }
+        Ok(written)
+    }
+}
+
+/// Plan a re-balance of the module directory `dir`
+///
+/// # Arguments
+///
+/// * `file_system` - Where the module directory lives
+/// * `dir` - Directory containing `mod.rs` and its file-backed submodules
+/// * `max_lines` - Threshold each module should stay under
+pub fn plan_rebalance(
+    file_system: &dyn FileSystem,
+    dir: &Path,
+    max_lines: usize,
+) -> Result<RebalancePlan> {
+    let mod_path = dir.join("mod.rs");
+    let mod_source = file_system.read(&mod_path)?;
+    let root: File =
+        syn::parse_file(&mod_source).context(format!("Failed to parse {:?}", mod_path))?;
+
+    let mut modules = Vec::new();
+    for item in &root.items {
+        let Item::Mod(m) = item else { continue };
+        if m.content.is_some() {
+            continue;
+        }
+        let name = m.ident.to_string();
+        let Some(path) = module_file(file_system, dir, &name, &m.attrs) else {
+            continue;
+        };
+        let source = file_system.read(&path)?;
+        syn::parse_file(&source).context(format!("Failed to parse module {:?}", path))?;
+        modules.push(ExistingModule {
+            name,
+            path,
+            lines: source.lines().count(),
+            source,
+            changed: false,
+        });
+    }
+
+    let before: Vec<(String, usize)> = modules.iter().map(|m| (m.name.clone(), m.lines)).collect();
+    let mut taken: HashSet<String> = modules.iter().map(|m| m.name.clone()).collect();
+    let mut moves = Vec::new();
+    let mut new_modules = Vec::new();
+
+    for src in 0..modules.len() {
+        if modules[src].lines <= max_lines {
+            continue;
+        }
+
+        let mut units = split_units(&modules[src].source);
+        let mut outgoing = Vec::new();
+        while modules[src].lines > max_lines && units.len() > 1 {
+            let unit = units.pop().expect("more than one unit");
+            modules[src].lines = modules[src].lines.saturating_sub(unit.lines);
+            outgoing.push(unit);
+        }
+        outgoing.reverse();
+
+        for unit in outgoing {
+            let target = (0..modules.len())
+                .filter(|&i| i != src && modules[i].lines + unit.lines <= max_lines)
+                .min_by_key(|&i| modules[i].lines);
+
+            let target = match target {
+                Some(index) => index,
+                None => {
+                    let name = overflow_name(&modules[src].name, &taken);
+                    taken.insert(name.clone());
+                    new_modules.push(name.clone());
+                    let path = dir.join(format!("{}.rs", name));
+                    let source = format!(
+                        "//! Items moved out of `{}.rs` to keep it under {} lines\n",
+                        modules[src].name, max_lines
+                    );
+                    modules.push(ExistingModule {
+                        name,
+                        path,
+                        source,
+                        lines: 0,
+                        changed: true,
+                    });
+                    modules.len() - 1
+                }
+            };
+
+            moves.push(ItemMove {
+                item: unit.name.clone(),
+                from: modules[src].name.clone(),
+                to: modules[target].name.clone(),
+                lines: unit.lines,
+            });
+            move_unit(&mut modules, src, target, unit);
+        }
+    }
+
+    let sizes = modules
+        .iter()
+        .map(|m| {
+            let before = before
+                .iter()
+                .find(|(name, _)| *name == m.name)
+                .map(|(_, lines)| *lines)
+                .unwrap_or(0);
+            (m.name.clone(), before, m.lines)
+        })
+        .collect();
+
+    Ok(RebalancePlan {
+        moves,
+        new_modules,
+        sizes,
+        dir: dir.to_path_buf(),
+        modules,
+    })
+}
+
+/// The top-level items of a module's source, each with its byte range
+///
+/// A range starts at the comments and attributes of its item; the inner
+/// docs and attributes of the file are left out of the first one.
+fn spanned_items(source: &str) -> Vec<((usize, usize), Item)> {
+    item_spans(source)
+        .into_iter()
+        .filter_map(|(start, end)| {
+            let span = &source[start..end];
+            let text = without_inner_attributes(span.trim());
+            let end = start + span.trim_end().len();
+            let mut file = syn::parse_file(text).ok()?;
+            let item = file.items.pop().filter(|_| file.items.is_empty())?;
+            Some(((end - text.len(), end), item))
+        })
+        .collect()
+}
+
+/// Tokens identifying an item
+fn item_key(item: &Item) -> String {
+    item.to_token_stream().to_string()
+}
+
+/// Group a module's items into units that must move together
+fn split_units(source: &str) -> Vec<ItemUnit> {
+    let mut units: Vec<ItemUnit> = Vec::new();
+
+    for ((start, end), item) in &spanned_items(source) {
+        let lines = source[*start..*end].lines().count();
+        // Extern blocks stay where callers import their foreign items from
+        if matches!(item, Item::Use(_) | Item::Mod(_) | Item::ForeignMod(_)) {
+            continue;
+        }
+        if let Item::Impl(i) = item {
+            let self_name = impl_self_name(i);
+            if let Some(unit) = units
+                .iter_mut()
+                .find(|u| Some(&u.name) == self_name.as_ref())
+            {
+                unit.lines += lines;
+                unit.items.push(item.clone());
+                continue;
+            }
+        }
+
+        let name = declared_ident(item)
+            .or_else(|| match item {
+                Item::Impl(i) => impl_self_name(i),
+                _ => None,
+            })
+            .unwrap_or_else(|| "item".to_string());
+        units.push(ItemUnit {
+            name,
+            items: vec![item.clone()],
+            lines,
+        });
+    }
+
+    units
+}
+
+fn impl_self_name(impl_item: &syn::ItemImpl) -> Option<String> {
+    if let syn::Type::Path(type_path) = &*impl_item.self_ty {
+        return type_path.path.segments.last().map(|s| s.ident.to_string());
+    }
+    None
+}
+
+/// Lines of an item as prettyplease formats it
+pub fn item_lines(item: &Item) -> usize {
+    prettyplease::unparse(&File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![item.clone()],
+    })
+    .lines()
+    .count()
+}
+
+/// Pick an unused overflow module name derived from `base`
+fn overflow_name(base: &str, taken: &HashSet<String>) -> String {
+    let mut index = 2;
+    loop {
+        let candidate = format!("{}_{}", base, index);
+        if !taken.contains(&candidate) {
+            return candidate;
+        }
+        index += 1;
+    }
+}
+
+/// Move a unit from `src` to `dst`, keeping references in `src` valid
+fn move_unit(modules: &mut [ExistingModule], src: usize, dst: usize, unit: ItemUnit) {
+    // Cut the moved items out of the source module, comments included
+    let moved_keys: HashSet<String> = unit.items.iter().map(item_key).collect();
+    let mut texts = Vec::new();
+    for ((start, end), item) in spanned_items(&modules[src].source).into_iter().rev() {
+        if moved_keys.contains(&item_key(&item)) {
+            texts.push(modules[src].source[start..end].to_string());
+            modules[src].source = cut(&modules[src].source, start, end);
+        }
+    }
+    texts.reverse();
+    let remaining = spanned_items(&modules[src].source);
+
+    // Imports of the source module travel with the moved code, except
+    // those of items the target already holds
+    let mut imports: Vec<String> = remaining
+        .iter()
+        .filter(
+            |(_, item)| matches!(item, Item::Use(u) if !imports_from(&u.tree, &modules[dst].name)),
+        )
+        .map(|((start, end), _)| modules[src].source[*start..*end].to_string())
+        .collect();
+
+    // Items the moved code uses that stay behind are imported from there
+    let mut roots = PathRoots::default();
+    for item in &unit.items {
+        roots.visit_item(item);
+    }
+    let mut staying = BTreeSet::new();
+    for ((start, end), item) in remaining.iter().rev() {
+        let Some(name) = declared_ident(item).filter(|name| roots.names.contains(name)) else {
+            continue;
+        };
+        match item_visibility(item) {
+            None => continue,
+            Some(Visibility::Inherited) => {
+                let widened = widen_text(&modules[src].source[*start..*end]);
+                modules[src].source.replace_range(*start..*end, &widened);
+            }
+            Some(_) => {}
+        }
+        staying.insert(name);
+    }
+    let names: Vec<String> = staying.into_iter().collect();
+    imports.extend(render_use(
+        &Visibility::Inherited,
+        &modules[src].name,
+        &names,
+    ));
+    let mut dst_keys: HashSet<String> = spanned_items(&modules[dst].source)
+        .iter()
+        .map(|(_, item)| item_key(item))
+        .collect();
+    imports.retain(|import| {
+        syn::parse_str::<Item>(import).is_ok_and(|item| dst_keys.insert(item_key(&item)))
+    });
+    modules[dst].source = insert_imports(&modules[dst].source, &imports);
+
+    // Keep the moved name reachable where it is still used: siblings
+    // importing it through the source get a re-export, the source's own
+    // code an import
+    let dst_name = modules[dst].name.clone();
+    let mut first = unit.items.first().cloned();
+    if let (Some(item), Some(text)) = (first.as_mut(), texts.first_mut()) {
+        if widen_private(item) {
+            *text = widen_text(text);
+        }
+    }
+    let vis = first
+        .as_ref()
+        .and_then(item_visibility)
+        .filter(|_| unit.name != "item");
+    if let Some(vis) = vis {
+        let src_name = &modules[src].name;
+        let through_source = modules.iter().enumerate().any(|(index, module)| {
+            index != src
+                && syn::parse_file(&module.source).is_ok_and(|file| {
+                    let mut paths = PathsThrough::new(src_name, &unit.name);
+                    paths.visit_file(&file);
+                    paths.found
+                })
+        });
+        let mut used_here = PathRoots::default();
+        for (_, item) in spanned_items(&modules[src].source) {
+            if !matches!(item, Item::Use(_)) {
+                used_here.visit_item(&item);
+            }
+        }
+        let import_vis = if through_source {
+            Some(vis)
+        } else {
+            used_here
+                .names
+                .contains(&unit.name)
+                .then_some(Visibility::Inherited)
+        };
+        if let Some(import_vis) = import_vis {
+            let import = render_use(&import_vis, &dst_name, std::slice::from_ref(&unit.name));
+            modules[src].source = insert_imports(&modules[src].source, &Vec::from_iter(import));
+        }
+    }
+
+    let dst_source = &mut modules[dst].source;
+    for text in texts {
+        let kept = dst_source.trim_end().len();
+        dst_source.truncate(kept);
+        if !dst_source.is_empty() {
+            dst_source.push_str("\n\n");
+        }
+        dst_source.push_str(&text);
+        dst_source.push('\n');
+    }
+    modules[dst].lines += unit.lines;
+    modules[src].changed = true;
+    modules[dst].changed = true;
+}
+
+/// `vis use super::module::{names};` as prettyplease formats it
+fn render_use(vis: &Visibility, module: &str, names: &[String]) -> Option<String> {
+    let module: syn::Ident = syn::parse_str(module).ok()?;
+    let names: Vec<syn::Ident> = names
+        .iter()
+        .map(|name| syn::parse_str(name))
+        .collect::<syn::Result<_>>()
+        .ok()?;
+    let item: syn::ItemUse = match names.as_slice() {
+        [] => return None,
+        [name] => syn::parse_quote!(#vis use super::#module::#name;),
+        _ => syn::parse_quote!(#vis use super::#module::{#(#names),*};),
+    };
+    let rendered = prettyplease::unparse(&File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![Item::Use(item)],
+    });
+    Some(rendered.trim_end().to_string())
+}
+
+/// Finds paths and imports that reach `name` through the sibling `module`
+/// (`super::module::name`, `use super::module::{name, ..}`)
+struct PathsThrough<'a> {
+    module: &'a str,
+    name: &'a str,
+    found: bool,
+}
+
+impl<'a> PathsThrough<'a> {
+    fn new(module: &'a str, name: &'a str) -> Self {
+        Self {
+            module,
+            name,
+            found: false,
+        }
+    }
+
+    fn use_tree_reaches(&self, tree: &syn::UseTree, after_module: bool) -> bool {
+        match tree {
+            syn::UseTree::Path(path) => {
+                (after_module && path.ident == self.name)
+                    || self.use_tree_reaches(&path.tree, path.ident == self.module)
+            }
+            syn::UseTree::Group(group) => group
+                .items
+                .iter()
+                .any(|item| self.use_tree_reaches(item, after_module)),
+            syn::UseTree::Name(name) => after_module && name.ident == self.name,
+            syn::UseTree::Rename(rename) => after_module && rename.ident == self.name,
+            syn::UseTree::Glob(_) => after_module,
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for PathsThrough<'_> {
+    fn visit_item_use(&mut self, node: &'ast syn::ItemUse) {
+        self.found |= self.use_tree_reaches(&node.tree, false);
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        self.found |= path
+            .segments
+            .iter()
+            .zip(path.segments.iter().skip(1))
+            .any(|(module, name)| module.ident == self.module && name.ident == self.name);
+        syn::visit::visit_path(self, path);
+    }
+}
+
+/// Whether a use tree imports from the sibling module `name`
+fn imports_from(tree: &syn::UseTree, name: &str) -> bool {
+    match tree {
+        syn::UseTree::Path(path) if path.ident == "super" => {
+            matches!(&*path.tree, syn::UseTree::Path(module) if module.ident == name)
+        }
+        _ => false,
+    }
+}
+
+/// `source` without the item at `start..end` and the blank lines before it
+fn cut(source: &str, start: usize, end: usize) -> String {
+    let before = source[..start].trim_end();
+    let after = &source[end..];
+    if before.is_empty() {
+        after.trim_start().to_string()
+    } else {
+        format!("{}{}", before, after)
+    }
+}
+
+/// `source` with `imports` added after its leading `use` items
+///
+/// Without leading imports they go before the first item, after the inner
+/// docs and attributes of the file.
+fn insert_imports(source: &str, imports: &[String]) -> String {
+    if imports.is_empty() {
+        return source.to_string();
+    }
+    let items = spanned_items(source);
+    let block = imports.join("\n");
+    let mut result = source.to_string();
+    match items
+        .iter()
+        .take_while(|(_, item)| matches!(item, Item::Use(_)))
+        .last()
+    {
+        Some(((_, end), _)) => result.insert_str(*end, &format!("\n{}", block)),
+        None => match items.first() {
+            Some(((start, _), _)) => result.insert_str(*start, &format!("{}\n\n", block)),
+            None => {
+                let kept = result.trim_end().len();
+                result.truncate(kept);
+                if !result.is_empty() {
+                    result.push_str("\n\n");
+                }
+                result.push_str(&block);
+                result.push('\n');
+            }
+        },
+    }
+    result
+}
+
+/// Source text of a private item made `pub(super)`
+///
+/// The visibility goes after the item's comments and attributes.
+fn widen_text(text: &str) -> String {
+    let at = item_start(text);
+    format!("{}pub(super) {}", &text[..at], &text[at..])
+}
+
+fn item_visibility(item: &Item) -> Option<Visibility> {
+    match item {
+        Item::Struct(s) => Some(s.vis.clone()),
+        Item::Enum(e) => Some(e.vis.clone()),
+        Item::Union(u) => Some(u.vis.clone()),
+        Item::Trait(t) => Some(t.vis.clone()),
+        Item::Type(t) => Some(t.vis.clone()),
+        Item::Const(c) => Some(c.vis.clone()),
+        Item::Static(s) => Some(s.vis.clone()),
+        Item::Fn(f) => Some(f.vis.clone()),
+        _ => None,
+    }
+}
+
+/// Widen a private item to `pub(super)` so its old siblings can import it
+///
+/// # Returns
+///
+/// Whether the item was private
+pub fn widen_private(item: &mut Item) -> bool {
+    let vis = match item {
+        Item::Struct(s) => &mut s.vis,
+        Item::Enum(e) => &mut e.vis,
+        Item::Union(u) => &mut u.vis,
+        Item::Trait(t) => &mut t.vis,
+        Item::Type(t) => &mut t.vis,
+        Item::Const(c) => &mut c.vis,
+        Item::Static(s) => &mut s.vis,
+        Item::Fn(f) => &mut f.vis,
+        _ => return false,
+    };
+    if matches!(vis, Visibility::Inherited) {
+        *vis = syn::parse_quote!(pub(super));
+        return true;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::{MemoryFileSystem, RealFileSystem};
+    use std::fs;
+
+    fn fixture(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("splitrs_rebalance_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("mod.rs"),
+            "pub mod big;\npub mod small;\npub use big::*;\npub use small::*;\n",
+        )
+        .unwrap();
+
+        let mut big = String::from(
+            "//! Big module\nuse std::fmt;\n\n// Shared by every type\nfn helper() -> u32 {\n    1\n}\n",
+        );
+        for name in ["Alpha", "Beta", "Gamma"] {
+            big.push_str(&format!(
+                "pub struct {name} {{\n    a: u32,\n    b: u32,\n}}\n\
+                 impl {name} {{\n    fn get(&self) -> u32 {{\n        // {name} only\n        \
+                 self.a + helper()\n    }}\n}}\n"
+            ));
+        }
+        fs::write(dir.join("big.rs"), big).unwrap();
+        fs::write(dir.join("small.rs"), "pub fn tiny() {}\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_moves_trailing_units_to_smallest_sibling() {
+        let dir = fixture("moves");
+        let plan = plan_rebalance(&RealFileSystem, &dir, 30).unwrap();
+
+        assert_eq!(plan.moves.len(), 1);
+        assert_eq!(plan.moves[0].item, "Gamma");
+        assert_eq!(plan.moves[0].to, "small");
+        assert!(plan.new_modules.is_empty());
+
+        plan.apply(&RealFileSystem).unwrap();
+        let small = fs::read_to_string(dir.join("small.rs")).unwrap();
+        assert!(small.contains("use std::fmt;"));
+        assert!(small.contains("use super::big::helper;"));
+        assert!(small.contains("impl Gamma"));
+        assert!(small.contains("        // Gamma only\n"));
+        let big = fs::read_to_string(dir.join("big.rs")).unwrap();
+        assert!(!big.contains("super::small"));
+        assert!(big.contains("// Shared by every type\npub(super) fn helper() -> u32 {"));
+        assert!(big.contains("        // Beta only\n"));
+        assert!(!big.contains("Gamma only"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_creates_overflow_module_when_no_room() {
+        let dir = fixture("overflow");
+        let plan = plan_rebalance(&RealFileSystem, &dir, 10).unwrap();
+
+        assert!(plan.new_modules.contains(&"big_2".to_string()));
+        plan.apply(&RealFileSystem).unwrap();
+        let mod_rs = fs::read_to_string(dir.join("mod.rs")).unwrap();
+        assert!(mod_rs.contains("pub mod big_2;"));
+        let overflow = fs::read_to_string(dir.join("big_2.rs")).unwrap();
+        assert!(overflow.starts_with("//! Items moved out of `big.rs` to keep it under 10 lines\n"));
+        assert!(!overflow.contains("Big module"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_moved_items_stay_reachable_where_used() {
+        let dir = Path::new("/splitrs-rebalance/src/parts");
+        let file_system = MemoryFileSystem::new()
+            .with_file(dir.join("mod.rs"), "pub mod big;\npub mod user;\n")
+            .with_file(
+                dir.join("big.rs"),
+                "pub fn first() -> u32 {\n    second() + 1\n}\n\
+                 #[doc = \"]\"]\nfn second() -> u32 {\n    2\n}\n\
+                 pub struct Gamma {\n    a: u32,\n}\n",
+            )
+            .with_file(
+                dir.join("user.rs"),
+                "use super::big::Gamma;\n\npub fn tiny(gamma: Gamma) -> u32 {\n    \
+                 let _ = gamma;\n    1\n}\n",
+            );
+        let plan = plan_rebalance(&file_system, dir, 4).unwrap();
+        assert_eq!(plan.new_modules, vec!["big_2", "big_3"]);
+        plan.apply(&file_system).unwrap();
+
+        // The remaining code imports `second`; `user` reaches `Gamma` through
+        // a re-export with the original visibility
+        let big = file_system.read(&dir.join("big.rs")).unwrap();
+        assert!(big.contains("use super::big_2::second;\n"));
+        assert!(big.contains("pub use super::big_3::Gamma;\n"));
+        assert!(!big.contains("allow(unused_imports)"));
+        let big_2 = file_system.read(&dir.join("big_2.rs")).unwrap();
+        assert!(big_2.contains("#[doc = \"]\"]\npub(super) fn second() -> u32 {"));
+    }
+
+    #[test]
+    fn test_imports_and_widening_respect_tokens() {
+        let vis: Visibility = syn::parse_quote!(pub(super));
+        assert_eq!(
+            render_use(&vis, "big_2", &["Gamma".to_string()]).unwrap(),
+            "pub(super) use super::big_2::Gamma;"
+        );
+        assert_eq!(
+            widen_text("/* [ */\n#[cfg(feature = \"a]\")]\n#[doc = r\"]\"]\nfn x() {}"),
+            "/* [ */\n#[cfg(feature = \"a]\")]\n#[doc = r\"]\"]\npub(super) fn x() {}"
+        );
+    }
+
+    #[test]
+    fn test_nothing_to_do_within_threshold() {
+        let dir = fixture("noop");
+        let plan = plan_rebalance(&RealFileSystem, &dir, 1000).unwrap();
+        assert!(plan.moves.is_empty());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,349 @@
+//! Following renamed types from one run to the next
+//!
+//! The report of every run records each type of the input with a signature
+//! (kind, fields, variants, methods and traits), a fingerprint of its
+//! definition with its own name blanked out, and the modules it went to.
+//! With `--follow-renames`, a type of the previous run that is gone is
+//! matched with a new type whose fingerprint is the same or whose signature
+//! is similar enough, as long as the two share a field, variant or method
+//! (all unit structs look alike), and the modules the new type would get
+//! keep the old type's names (`widget_type` stays `widget_type` when
+//! `Widget` becomes `Gadget`), so the re-run updates those files instead of
+//! writing duplicates next to them.
+
+use crate::report::TypeRecord;
+use quote::ToTokens;
+use std::collections::BTreeSet;
+use syn::{Fields, ImplItem, Item, ItemImpl};
+
+/// Lowest signature similarity for a gone type and a new one to count as a
+/// rename
+pub const RENAME_THRESHOLD: f64 = 0.6;
+
+/// A type of the previous run matched with a type of this one
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rename {
+    /// Name in the previous run
+    pub from: String,
+
+    /// Name in this run
+    pub to: String,
+
+    /// How similar the two signatures are, from 0 to 1
+    pub similarity: f64,
+}
+
+/// Kind, fields, variants, methods and traits of a type, without its name
+///
+/// # Arguments
+///
+/// * `name` - Name of the type, replaced by `Self` in field types
+/// * `item` - The type definition
+/// * `impls` - Inherent impl blocks of the type
+/// * `traits` - Names of the traits the type implements
+pub fn signature(name: &str, item: &Item, impls: &[&ItemImpl], traits: &[String]) -> Vec<String> {
+    let type_text = |ty: &syn::Type| without_name(&ty.to_token_stream().to_string(), name);
+    let mut signature = BTreeSet::new();
+    let fields = match item {
+        Item::Struct(s) => {
+            signature.insert("struct".to_string());
+            Some(s.fields.clone())
+        }
+        Item::Enum(e) => {
+            signature.insert("enum".to_string());
+            for variant in &e.variants {
+                signature.insert(format!("variant {}", variant.ident));
+            }
+            None
+        }
+        Item::Union(u) => {
+            signature.insert("union".to_string());
+            Some(Fields::Named(u.fields.clone()))
+        }
+        _ => None,
+    };
+    for (index, field) in fields.iter().flat_map(Fields::iter).enumerate() {
+        let ident = field
+            .ident
+            .as_ref()
+            .map_or_else(|| index.to_string(), |ident| ident.to_string());
+        signature.insert(format!("field {}: {}", ident, type_text(&field.ty)));
+    }
+    for impl_block in impls {
+        for impl_item in &impl_block.items {
+            if let ImplItem::Fn(method) = impl_item {
+                signature.insert(format!("fn {}", method.sig.ident));
+            }
+        }
+    }
+    for trait_name in traits {
+        signature.insert(format!("impl {}", trait_name));
+    }
+    signature.into_iter().collect()
+}
+
+/// Hash of a type definition with its own name blanked out
+///
+/// FNV-1a over the item's tokens, so the value is the same on every
+/// platform and toolchain.
+pub fn fingerprint(name: &str, item: &Item) -> String {
+    let text = without_name(&item.to_token_stream().to_string(), name);
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Token text with every `name` token replaced by `Self`
+fn without_name(text: &str, name: &str) -> String {
+    text.split(' ')
+        .map(|token| if token == name { "Self" } else { token })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// How similar two recorded types are, from 0 to 1
+///
+/// 0 unless the two share a field, variant or method; then 1 for the same
+/// fingerprint, otherwise the share of signature entries the two have in
+/// common.
+pub fn similarity(a: &TypeRecord, b: &TypeRecord) -> f64 {
+    let shares_member = a.signature.iter().any(|entry| {
+        ["field ", "variant ", "fn "]
+            .iter()
+            .any(|kind| entry.starts_with(kind))
+            && b.signature.contains(entry)
+    });
+    if !shares_member {
+        return 0.0;
+    }
+    if a.fingerprint == b.fingerprint {
+        return 1.0;
+    }
+    let a: BTreeSet<&String> = a.signature.iter().collect();
+    let b: BTreeSet<&String> = b.signature.iter().collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// Types of the previous run that this run has under another name
+///
+/// Only types missing from the other run are matched, each at most once,
+/// the most similar pairs first.
+pub fn detect_renames(previous: &[TypeRecord], current: &[TypeRecord]) -> Vec<Rename> {
+    let gone: Vec<&TypeRecord> = previous
+        .iter()
+        .filter(|old| current.iter().all(|new| new.name != old.name))
+        .collect();
+    let added: Vec<&TypeRecord> = current
+        .iter()
+        .filter(|new| previous.iter().all(|old| old.name != new.name))
+        .collect();
+
+    let mut candidates: Vec<Rename> = gone
+        .iter()
+        .flat_map(|old| {
+            added.iter().map(move |new| Rename {
+                from: old.name.clone(),
+                to: new.name.clone(),
+                similarity: similarity(old, new),
+            })
+        })
+        .filter(|rename| rename.similarity >= RENAME_THRESHOLD)
+        .collect();
+    candidates.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.from.cmp(&b.from))
+            .then_with(|| a.to.cmp(&b.to))
+    });
+
+    let mut renames: Vec<Rename> = Vec::new();
+    for candidate in candidates {
+        if renames
+            .iter()
+            .all(|r| r.from != candidate.from && r.to != candidate.to)
+        {
+            renames.push(candidate);
+        }
+    }
+    renames
+}
+
+/// Module names this run would give the renamed type, each paired with the
+/// name the previous run used
+///
+/// Only modules named after the type (`widget_type`, `widget_traits`,
+/// `widget_parse_group`) are followed; shared ones such as `types` keep
+/// their names anyway.
+pub fn module_renames(rename: &Rename, previous: &TypeRecord) -> Vec<(String, String)> {
+    let from = module_prefix(previous);
+    let to = rename.to.to_lowercase();
+    previous
+        .modules
+        .iter()
+        .filter_map(|module| {
+            let rest = module.strip_prefix(&from)?;
+            (rest.is_empty() || rest.starts_with('_'))
+                .then(|| (format!("{}{}", to, rest), module.clone()))
+        })
+        .collect()
+}
+
+/// Module names to keep from the previous run
+///
+/// Renamed types keep the names of their modules, and so do types whose
+/// rename an earlier run followed. The kept prefixes are recorded in
+/// `current` for the next run.
+///
+/// # Returns
+///
+/// The renames found, and each module name this run would use paired with
+/// the name to keep
+pub fn follow_renames(
+    previous: &[TypeRecord],
+    current: &mut [TypeRecord],
+) -> (Vec<Rename>, Vec<(String, String)>) {
+    let renames = detect_renames(previous, current);
+    let followed: Vec<Rename> = previous
+        .iter()
+        .filter(|record| record.module_prefix.is_some())
+        .filter(|record| current.iter().any(|t| t.name == record.name))
+        .map(|record| Rename {
+            from: record.name.clone(),
+            to: record.name.clone(),
+            similarity: 1.0,
+        })
+        .collect();
+    let mut kept = Vec::new();
+    for rename in renames.iter().cloned().chain(followed) {
+        let Some(record) = previous.iter().find(|t| t.name == rename.from) else {
+            continue;
+        };
+        let modules = module_renames(&rename, record);
+        if modules.is_empty() {
+            continue;
+        }
+        if let Some(type_record) = current.iter_mut().find(|t| t.name == rename.to) {
+            type_record.module_prefix = Some(module_prefix(record));
+        }
+        kept.extend(modules);
+    }
+    (renames, kept)
+}
+
+/// Prefix of the modules named after a recorded type
+pub fn module_prefix(record: &TypeRecord) -> String {
+    record
+        .module_prefix
+        .clone()
+        .unwrap_or_else(|| record.name.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str, item: Item, impls: &[ItemImpl], modules: &[&str]) -> TypeRecord {
+        let impls: Vec<&ItemImpl> = impls.iter().collect();
+        TypeRecord {
+            name: name.to_string(),
+            signature: signature(name, &item, &impls, &["Clone".to_string()]),
+            fingerprint: fingerprint(name, &item),
+            modules: modules.iter().map(|m| m.to_string()).collect(),
+            module_prefix: None,
+        }
+    }
+
+    #[test]
+    fn test_renamed_type_keeps_its_modules() {
+        let previous = vec![
+            record(
+                "Widget",
+                syn::parse_quote! { pub struct Widget { size: u32, parent: Option<Box<Widget>> } },
+                &[syn::parse_quote! { impl Widget { fn grow(&mut self) {} fn draw(&self) {} } }],
+                &["widget_type", "widget_grow_group", "widget_traits", "types"],
+            ),
+            record(
+                "Color",
+                syn::parse_quote! { pub enum Color { Red, Green } },
+                &[],
+                &["types"],
+            ),
+        ];
+        let current = vec![
+            record(
+                "Gadget",
+                syn::parse_quote! { pub struct Gadget { size: u32, parent: Option<Box<Gadget>> } },
+                &[syn::parse_quote! {
+                    impl Gadget { fn grow(&mut self) {} fn draw(&self) {} fn hide(&self) {} }
+                }],
+                &[],
+            ),
+            record(
+                "Shade",
+                syn::parse_quote! { pub enum Shade { Light, Dark } },
+                &[],
+                &[],
+            ),
+        ];
+        assert_eq!(previous[0].fingerprint, current[0].fingerprint);
+
+        let renames = detect_renames(&previous, &current);
+        assert_eq!(renames.len(), 1);
+        assert_eq!(
+            (renames[0].from.as_str(), renames[0].to.as_str()),
+            ("Widget", "Gadget")
+        );
+        assert_eq!(
+            module_renames(&renames[0], &previous[0]),
+            vec![
+                ("gadget_type".to_string(), "widget_type".to_string()),
+                (
+                    "gadget_grow_group".to_string(),
+                    "widget_grow_group".to_string()
+                ),
+                ("gadget_traits".to_string(), "widget_traits".to_string()),
+            ]
+        );
+
+        // Later runs keep the names, through further renames too
+        let mut current = current;
+        let (_, kept) = follow_renames(&previous, &mut current);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(current[0].module_prefix.as_deref(), Some("widget"));
+        current[0].modules = previous[0].modules.clone();
+        let mut unchanged = current.clone();
+        let (renames, kept) = follow_renames(&current, &mut unchanged);
+        assert!(renames.is_empty());
+        assert!(kept.contains(&("gadget_type".to_string(), "widget_type".to_string())));
+        let mut renamed = current.clone();
+        renamed[0].name = "Gizmo".to_string();
+        let (_, kept) = follow_renames(&current, &mut renamed);
+        assert!(kept.contains(&("gizmo_type".to_string(), "widget_type".to_string())));
+    }
+
+    #[test]
+    fn test_unit_structs_are_not_renames_of_each_other() {
+        let previous = vec![record(
+            "Marker",
+            syn::parse_quote! { pub struct Marker; },
+            &[],
+            &["marker_type"],
+        )];
+        let current = vec![record(
+            "Token",
+            syn::parse_quote! { pub struct Token; },
+            &[],
+            &[],
+        )];
+        assert_eq!(previous[0].fingerprint, current[0].fingerprint);
+        assert_eq!(similarity(&previous[0], &current[0]), 0.0);
+        assert!(detect_renames(&previous, &current).is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+//! Machine-readable record of a refactoring run
+//!
+//! After a successful split, `.splitrs-report.json` is written into the
+//! output directory. It records the module plan, per-module statistics,
+//! every visibility change SplitRS made and the warnings it printed, so
+//! follow-up tooling has a durable record of what happened.
+
+use crate::file_system::FileSystem;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name of the report file inside the output directory
+pub const REPORT_FILE: &str = ".splitrs-report.json";
+
+/// Summary of one refactoring run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefactorReport {
+    /// SplitRS version that produced the output
+    pub version: String,
+
+    /// Seconds since the Unix epoch when the report was written
+    pub generated_at: u64,
+
+    /// Input files that were split
+    pub inputs: Vec<PathBuf>,
+
+    /// Directory the modules were written to
+    pub output_dir: PathBuf,
+
+    /// Copies of the inputs taken before writing, if any
+    pub backup_dir: Option<PathBuf>,
+
+    /// Total line count of the inputs
+    pub original_lines: usize,
+
+    /// Generated modules, in `mod.rs` order
+    pub modules: Vec<ModuleReport>,
+
+    /// Items whose visibility was widened so sibling modules can reach them
+    pub visibility_changes: Vec<VisibilityChange>,
+
+    /// Warnings printed during the run
+    pub warnings: Vec<String>,
+
+    /// Every semantic risk of the split, in the order of the safety report
+    #[serde(default)]
+    pub risks: Vec<RiskFinding>,
+
+    /// Every item of the inputs, in source order, with where it went
+    #[serde(default)]
+    pub outline: Vec<ItemPlacement>,
+
+    /// Every type of the inputs and the modules it went to, for following
+    /// renames on the next run
+    #[serde(default)]
+    pub types: Vec<TypeRecord>,
+}
+
+/// Statistics for one generated module
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleReport {
+    /// Module name
+    pub name: String,
+
+    /// File the module was written to
+    pub file: PathBuf,
+
+    /// Line count of the written file
+    pub lines: usize,
+
+    /// Items placed in the module (`Type`, `Type::method`, `Trait for Type`, ...)
+    pub items: Vec<String>,
+
+    /// Re-export line in `mod.rs`, if any
+    pub reexport: Option<String>,
+}
+
+/// A visibility SplitRS changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VisibilityChange {
+    /// Module the item was written to
+    pub module: String,
+
+    /// The item (`Type` fields, or a function name)
+    pub item: String,
+
+    /// Visibility in the input
+    pub from: String,
+
+    /// Visibility in the output
+    pub to: String,
+}
+
+/// A semantic risk found while planning the split
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RiskFinding {
+    /// Risk class (`macro scoping`, `cfg split`, ...)
+    pub kind: String,
+
+    /// Module the risk was found in
+    pub module: String,
+
+    /// What was found
+    pub detail: String,
+}
+
+/// Where an item of the input ended up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemPlacement {
+    /// Input file holding the item
+    pub file: String,
+
+    /// Line the item starts on (1-based)
+    pub line: usize,
+
+    /// Position of the item among the top-level items of its file
+    /// (0-based, `use` items included), which decides its place in the
+    /// module it went to
+    #[serde(default)]
+    pub index: usize,
+
+    /// The item (`struct User`, `impl Display for User`, ...)
+    pub item: String,
+
+    /// Files the item was written to, relative to the output directory;
+    /// split impl blocks have several
+    pub destinations: Vec<String>,
+}
+
+/// A type of the inputs and where it went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeRecord {
+    /// Name of the type
+    pub name: String,
+
+    /// Kind, fields, variants, methods and traits of the type, without its
+    /// name (see `renames::signature`)
+    pub signature: Vec<String>,
+
+    /// Hash of the definition with the type's name blanked out
+    pub fingerprint: String,
+
+    /// Generated modules holding the type or its impls
+    pub modules: Vec<String>,
+
+    /// Prefix of the modules named after the type, when a followed rename
+    /// kept an earlier name's (`pool` for `Reservoir`, formerly `Pool`)
+    #[serde(default)]
+    pub module_prefix: Option<String>,
+}
+
+impl RefactorReport {
+    /// Write the report into `output_dir`
+    ///
+    /// # Returns
+    ///
+    /// The path of the written report
+    pub fn write(&self, file_system: &dyn FileSystem, output_dir: &Path) -> Result<PathBuf> {
+        let path = output_dir.join(REPORT_FILE);
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize report")?;
+        file_system
+            .write(&path, &(json + "\n"))
+            .context(format!("Failed to write report: {:?}", path))?;
+        Ok(path)
+    }
+
+    /// Load a report from an output directory
+    pub fn load(file_system: &dyn FileSystem, output_dir: &Path) -> Result<Self> {
+        let path = output_dir.join(REPORT_FILE);
+        let json = file_system
+            .read(&path)
+            .context(format!("Failed to read report: {:?}", path))?;
+        serde_json::from_str(&json).context(format!("Failed to parse report: {:?}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::RealFileSystem;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_report_round_trip() {
+        let dir = env::temp_dir().join(format!("splitrs_report_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let report = RefactorReport {
+            version: "0.2.0".to_string(),
+            generated_at: 0,
+            inputs: vec![PathBuf::from("src/big.rs")],
+            output_dir: dir.clone(),
+            backup_dir: None,
+            original_lines: 10,
+            modules: vec![ModuleReport {
+                name: "types".to_string(),
+                file: dir.join("types.rs"),
+                lines: 8,
+                items: vec!["User".to_string()],
+                reexport: Some("pub use types::*;".to_string()),
+            }],
+            visibility_changes: vec![VisibilityChange {
+                module: "internal".to_string(),
+                item: "helper".to_string(),
+                from: "private".to_string(),
+                to: "pub(super)".to_string(),
+            }],
+            warnings: vec!["unresolved name `Foo` in types".to_string()],
+            risks: vec![RiskFinding {
+                kind: "cfg split".to_string(),
+                module: "user_impl".to_string(),
+                detail: "split impl of `User` drops its `#[cfg]`".to_string(),
+            }],
+            outline: vec![ItemPlacement {
+                file: "src/big.rs".to_string(),
+                line: 1,
+                index: 0,
+                item: "struct User".to_string(),
+                destinations: vec!["types.rs".to_string()],
+            }],
+            types: vec![TypeRecord {
+                name: "User".to_string(),
+                signature: vec!["field name: String".to_string(), "struct".to_string()],
+                fingerprint: "00000000deadbeef".to_string(),
+                modules: vec!["types".to_string()],
+                module_prefix: None,
+            }],
+        };
+        let path = report.write(&RealFileSystem, &dir).unwrap();
+        assert!(path.ends_with(REPORT_FILE));
+
+        let loaded = RefactorReport::load(&RealFileSystem, &dir).unwrap();
+        assert_eq!(loaded.modules[0].items, vec!["User"]);
+        assert_eq!(loaded.visibility_changes, report.visibility_changes);
+        assert_eq!(loaded.risks, report.risks);
+        assert_eq!(loaded.outline, report.outline);
+        assert_eq!(loaded.types, report.types);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,131 @@
+//! Where a run reports what it does
+//!
+//! The splitting path never prints or reads from the terminal itself: its
+//! progress, previews and summaries go to a [`Reporter`], as do the JSON
+//! diagnostics and the confirmation of `--interactive`. The `splitrs`
+//! binary reports to the terminal; build scripts and `cargo xtask` tasks
+//! calling [`crate::run_from`] can collect the output instead.
+
+use crate::diagnostics::Diagnostic;
+use anyhow::Result;
+use std::io::{self, Write};
+
+/// Receiver of the output of a run
+pub trait Reporter {
+    /// Human-oriented output, newlines included
+    fn print(&mut self, text: &str);
+
+    /// One finding of `--diagnostics-format json`
+    fn diagnostic(&mut self, diagnostic: &Diagnostic);
+
+    /// Ask whether to go on, for `--interactive`
+    fn confirm(&mut self, question: &str) -> Result<bool>;
+}
+
+/// Reports to the terminal: output on stdout, diagnostics on stderr and
+/// answers from stdin
+#[derive(Debug, Default)]
+pub struct StdoutReporter;
+
+impl Reporter for StdoutReporter {
+    fn print(&mut self, text: &str) {
+        print!("{}", text);
+    }
+
+    fn diagnostic(&mut self, diagnostic: &Diagnostic) {
+        eprintln!("{}", diagnostic.to_json_line());
+    }
+
+    fn confirm(&mut self, question: &str) -> Result<bool> {
+        print!("{}", question);
+        io::stdout().flush()?;
+        let mut response = String::new();
+        io::stdin().read_line(&mut response)?;
+        Ok(response.trim().eq_ignore_ascii_case("y"))
+    }
+}
+
+/// Keeps everything a run reports; questions are declined
+#[derive(Debug, Default)]
+pub struct CollectingReporter {
+    /// The human-oriented output
+    pub output: String,
+
+    /// The findings, in the order they were reported
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Reporter for CollectingReporter {
+    fn print(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn diagnostic(&mut self, diagnostic: &Diagnostic) {
+        self.diagnostics.push(diagnostic.clone());
+    }
+
+    fn confirm(&mut self, question: &str) -> Result<bool> {
+        self.output.push_str(question);
+        self.output.push('\n');
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_library_run_reports_to_the_reporter() {
+        let dir = std::env::temp_dir().join(format!("splitrs_reporter_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("big.rs");
+        fs::write(
+            &input,
+            "pub struct A;\nimpl A { pub fn a(&self) {} }\npub fn f() {}\n",
+        )
+        .unwrap();
+
+        let mut reporter = CollectingReporter::default();
+        crate::run_from(
+            [
+                "splitrs".as_ref(),
+                "-i".as_ref(),
+                input.as_os_str(),
+                "-o".as_ref(),
+                dir.join("big").as_os_str(),
+                "--dry-run".as_ref(),
+            ],
+            &crate::RealFileSystem,
+            &mut reporter,
+        )
+        .unwrap();
+        assert!(reporter.output.contains("DRY RUN - Preview Mode"));
+        assert!(reporter.output.contains("Preview complete"));
+        assert!(!dir.join("big").exists());
+
+        // Questions are declined, so nothing is written
+        let mut reporter = CollectingReporter::default();
+        crate::run_from(
+            [
+                "splitrs".as_ref(),
+                "-i".as_ref(),
+                input.as_os_str(),
+                "-o".as_ref(),
+                dir.join("big").as_os_str(),
+                "--interactive".as_ref(),
+            ],
+            &crate::RealFileSystem,
+            &mut reporter,
+        )
+        .unwrap();
+        assert!(reporter
+            .output
+            .contains("Proceed with file generation? [y/N]: "));
+        assert!(reporter.output.contains("Operation cancelled by user"));
+        assert!(!dir.join("big").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
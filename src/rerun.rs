@@ -0,0 +1,142 @@
+//! Re-running a split over its own output
+//!
+//! When the output directory already holds a split (its `mod.rs` starts
+//! with the generated header, or the previous run left its report there),
+//! the files of the new plan are compared with the ones on disk before
+//! anything is written. Files with the same content are left alone, so
+//! their modification times do not change; when nothing changed at all, no
+//! backup is taken and nothing is written. Modules the previous run wrote
+//! that the new plan no longer has are reported rather than deleted.
+
+use crate::file_system::FileSystem;
+use crate::report::{RefactorReport, REPORT_FILE};
+use std::path::{Path, PathBuf};
+
+/// First line of a generated `mod.rs`
+pub const GENERATED_HEADER: &str = "//! Auto-generated module structure";
+
+/// What writing a planned file does to the output directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChange {
+    /// The file does not exist yet
+    Created,
+
+    /// The file exists with other content
+    Updated,
+
+    /// The file exists with the same content and is not written
+    Unchanged,
+}
+
+impl FileChange {
+    /// Word printed in front of the file's path
+    pub fn label(self) -> &'static str {
+        match self {
+            FileChange::Created => "Created",
+            FileChange::Updated => "Updated",
+            FileChange::Unchanged => "Unchanged",
+        }
+    }
+}
+
+/// Whether `output_dir` holds the output of an earlier split
+pub fn is_generated_output(file_system: &dyn FileSystem, output_dir: &Path) -> bool {
+    file_system.exists(&output_dir.join(REPORT_FILE))
+        || file_system
+            .read(&output_dir.join("mod.rs"))
+            .is_ok_and(|content| content.starts_with(GENERATED_HEADER))
+}
+
+/// Compare the planned files with the output directory
+///
+/// # Arguments
+///
+/// * `file_system` - Where the previous output lives
+/// * `planned` - Paths and contents about to be written
+/// * `rerun` - Whether the output directory holds an earlier split; if
+///   not, every file counts as created
+pub fn classify(
+    file_system: &dyn FileSystem,
+    planned: &[(PathBuf, String)],
+    rerun: bool,
+) -> Vec<FileChange> {
+    planned
+        .iter()
+        .map(|(path, content)| {
+            if !rerun {
+                return FileChange::Created;
+            }
+            match file_system.read(path) {
+                Ok(existing) if existing == *content => FileChange::Unchanged,
+                Ok(_) => FileChange::Updated,
+                Err(_) => FileChange::Created,
+            }
+        })
+        .collect()
+}
+
+/// Files the previous run wrote that are not part of the new plan
+pub fn stale_modules(
+    file_system: &dyn FileSystem,
+    output_dir: &Path,
+    planned: &[(PathBuf, String)],
+) -> Vec<PathBuf> {
+    let Ok(previous) = RefactorReport::load(file_system, output_dir) else {
+        return Vec::new();
+    };
+    previous
+        .modules
+        .into_iter()
+        .map(|module| module.file)
+        .filter(|file| {
+            file_system.read(file).is_ok() && planned.iter().all(|(path, _)| path != file)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::MemoryFileSystem;
+
+    #[test]
+    fn test_rerun_leaves_unchanged_files_alone() {
+        let output_dir = PathBuf::from("/crate/src/big");
+        let fresh = MemoryFileSystem::new();
+        assert!(!is_generated_output(&fresh, &output_dir));
+
+        let previous = MemoryFileSystem::new()
+            .with_file(
+                output_dir.join("mod.rs"),
+                &format!("{}\n\npub mod types;\n", GENERATED_HEADER),
+            )
+            .with_file(output_dir.join("types.rs"), "pub struct A;\n");
+        assert!(is_generated_output(&previous, &output_dir));
+
+        let planned = vec![
+            (output_dir.join("types.rs"), "pub struct A;\n".to_string()),
+            (
+                output_dir.join("mod.rs"),
+                format!(
+                    "{}\n\npub mod types;\npub mod functions;\n",
+                    GENERATED_HEADER
+                ),
+            ),
+            (
+                output_dir.join("functions.rs"),
+                "pub fn f() {}\n".to_string(),
+            ),
+        ];
+        assert_eq!(
+            classify(&previous, &planned, true),
+            vec![
+                FileChange::Unchanged,
+                FileChange::Updated,
+                FileChange::Created
+            ]
+        );
+        assert!(classify(&previous, &planned, false)
+            .iter()
+            .all(|change| *change == FileChange::Created));
+    }
+}
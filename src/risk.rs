@@ -0,0 +1,506 @@
+//! Semantic risks of a split
+//!
+//! Some splits parse fine but change what the code means or stop it from
+//! compiling: a `macro_rules!` macro is only in scope textually, so callers
+//! in a sibling module lose it; a private function, method or field is
+//! invisible to sibling modules; a split impl block loses the `#[cfg]` of the
+//! original;
+//! names SplitRS cannot import stay unresolved; widened visibility changes
+//! the API surface; rewritten paths are worth a look. These detections are
+//! gathered here into one safety report, and `--strict` can refuse to write
+//! anything while a blocking one remains.
+
+use crate::config::{RiskPolicies, RiskPolicy};
+use crate::internal_items::item_attrs;
+use std::collections::BTreeSet;
+use std::fmt;
+use syn::visit::Visit;
+use syn::{Attribute, File, Item};
+
+/// Kind of semantic risk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RiskKind {
+    /// A `macro_rules!` macro used outside the module that defines it
+    MacroScoping,
+
+    /// An item whose visibility SplitRS widens
+    VisibilityEscalation,
+
+    /// A name the generated module cannot import
+    UnresolvedImport,
+
+    /// A split impl block that loses the `#[cfg]` of its original
+    CfgSplit,
+
+    /// A private function or method called from a sibling module
+    PrivateCall,
+
+    /// A private field used from a sibling module (E0616, E0451)
+    PrivateField,
+
+    /// Paths SplitRS rewrote for the new layout
+    PathRewrite,
+}
+
+impl RiskKind {
+    /// Short label used in messages
+    pub fn label(self) -> &'static str {
+        match self {
+            RiskKind::MacroScoping => "macro scoping",
+            RiskKind::VisibilityEscalation => "visibility escalation",
+            RiskKind::UnresolvedImport => "unresolved import",
+            RiskKind::CfgSplit => "cfg split",
+            RiskKind::PrivateCall => "private call",
+            RiskKind::PrivateField => "private field",
+            RiskKind::PathRewrite => "path rewrite",
+        }
+    }
+
+    /// Policy for this kind under the `[risks]` configuration
+    pub fn policy(self, policies: &RiskPolicies) -> RiskPolicy {
+        match self {
+            RiskKind::MacroScoping => policies.macro_scoping,
+            RiskKind::VisibilityEscalation => policies.visibility_widening,
+            RiskKind::UnresolvedImport => policies.unresolved_import,
+            RiskKind::CfgSplit => policies.cfg_split,
+            RiskKind::PrivateCall => policies.private_call,
+            RiskKind::PrivateField => policies.private_field,
+            RiskKind::PathRewrite => policies.path_rewrite,
+        }
+    }
+
+    /// Whether `--strict` refuses to write output while a risk of this kind
+    /// remains; rewritten paths are informational
+    pub fn blocks_strict(self) -> bool {
+        self != RiskKind::PathRewrite
+    }
+
+    /// Whether the refactor report already lists risks of this kind in a
+    /// section of their own
+    pub fn is_reported_elsewhere(self) -> bool {
+        matches!(
+            self,
+            RiskKind::VisibilityEscalation | RiskKind::UnresolvedImport
+        )
+    }
+}
+
+/// A detected risk in one generated module
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Risk {
+    pub kind: RiskKind,
+    pub module: String,
+    pub detail: String,
+}
+
+impl fmt::Display for Risk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{}] {}.rs: {}",
+            self.kind.label(),
+            self.module,
+            self.detail
+        )
+    }
+}
+
+/// Macros, private callables and private fields a module defines, and what
+/// it uses
+#[derive(Default)]
+pub struct RiskScan {
+    /// `macro_rules!` macros without `#[macro_export]`
+    pub macros: BTreeSet<String>,
+
+    /// Single-segment macro invocations
+    pub invoked_macros: BTreeSet<String>,
+
+    /// Private free functions and inherent methods
+    pub private_fns: BTreeSet<String>,
+
+    /// Called functions and `self`/`Self` methods
+    pub calls: BTreeSet<String>,
+
+    /// Private fields of the structs defined here, as (type, field)
+    pub private_fields: BTreeSet<(String, String)>,
+
+    /// Fields used through `self` or named in struct literals and patterns
+    /// inside impl blocks, as (type, field)
+    pub field_uses: BTreeSet<(String, String)>,
+
+    in_trait_impl: bool,
+
+    /// Self type of the impl block being visited
+    impl_type: Option<String>,
+}
+
+impl RiskScan {
+    /// Type a struct path names, with `Self` resolved to the impl's type
+    fn struct_type(&self, path: &syn::Path) -> Option<String> {
+        let name = path.segments.last()?.ident.to_string();
+        if name == "Self" {
+            self.impl_type.clone()
+        } else {
+            Some(name)
+        }
+    }
+
+    fn record_field_use(&mut self, type_name: Option<String>, member: &syn::Member) {
+        if let Some(type_name) = type_name {
+            self.field_uses.insert((type_name, member_name(member)));
+        }
+    }
+}
+
+/// Name of a field: its ident, or its index in a tuple struct
+fn member_name(member: &syn::Member) -> String {
+    match member {
+        syn::Member::Named(ident) => ident.to_string(),
+        syn::Member::Unnamed(index) => index.index.to_string(),
+    }
+}
+
+impl<'ast> Visit<'ast> for RiskScan {
+    fn visit_item_macro(&mut self, node: &'ast syn::ItemMacro) {
+        if let Some(ident) = &node.ident {
+            if node.mac.path.is_ident("macro_rules") && !has_attr(&node.attrs, "macro_export") {
+                self.macros.insert(ident.to_string());
+            }
+            return;
+        }
+        syn::visit::visit_item_macro(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if let Some(ident) = node.path.get_ident() {
+            self.invoked_macros.insert(ident.to_string());
+        }
+        syn::visit::visit_macro(self, node);
+    }
+
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        if matches!(node.vis, syn::Visibility::Inherited) {
+            self.private_fns.insert(node.sig.ident.to_string());
+        }
+        syn::visit::visit_item_fn(self, node);
+    }
+
+    fn visit_item_struct(&mut self, node: &'ast syn::ItemStruct) {
+        for (index, field) in node.fields.iter().enumerate() {
+            if matches!(field.vis, syn::Visibility::Inherited) {
+                let name = match &field.ident {
+                    Some(ident) => ident.to_string(),
+                    None => index.to_string(),
+                };
+                self.private_fields.insert((node.ident.to_string(), name));
+            }
+        }
+        syn::visit::visit_item_struct(self, node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        let outer = std::mem::replace(&mut self.in_trait_impl, node.trait_.is_some());
+        let impl_type = match &*node.self_ty {
+            syn::Type::Path(ty) => ty.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        };
+        let outer_type = std::mem::replace(&mut self.impl_type, impl_type);
+        syn::visit::visit_item_impl(self, node);
+        self.in_trait_impl = outer;
+        self.impl_type = outer_type;
+    }
+
+    fn visit_expr_field(&mut self, node: &'ast syn::ExprField) {
+        if matches!(&*node.base, syn::Expr::Path(p) if p.path.is_ident("self")) {
+            self.record_field_use(self.impl_type.clone(), &node.member);
+        }
+        syn::visit::visit_expr_field(self, node);
+    }
+
+    fn visit_expr_struct(&mut self, node: &'ast syn::ExprStruct) {
+        if self.impl_type.is_some() {
+            let type_name = self.struct_type(&node.path);
+            for field in &node.fields {
+                self.record_field_use(type_name.clone(), &field.member);
+            }
+        }
+        syn::visit::visit_expr_struct(self, node);
+    }
+
+    fn visit_pat_struct(&mut self, node: &'ast syn::PatStruct) {
+        if self.impl_type.is_some() {
+            let type_name = self.struct_type(&node.path);
+            for field in &node.fields {
+                self.record_field_use(type_name.clone(), &field.member);
+            }
+        }
+        syn::visit::visit_pat_struct(self, node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        if !self.in_trait_impl && matches!(node.vis, syn::Visibility::Inherited) {
+            self.private_fns.insert(node.sig.ident.to_string());
+        }
+        syn::visit::visit_impl_item_fn(self, node);
+    }
+
+    fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+        let segments = &node.path.segments;
+        let callable = match segments.len() {
+            1 => node.qself.is_none(),
+            2 => segments[0].ident == "Self",
+            _ => false,
+        };
+        if callable {
+            if let Some(last) = segments.last() {
+                self.calls.insert(last.ident.to_string());
+            }
+        }
+        syn::visit::visit_expr_path(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        if matches!(&*node.receiver, syn::Expr::Path(p) if p.path.is_ident("self")) {
+            self.calls.insert(node.method.to_string());
+        }
+        syn::visit::visit_expr_method_call(self, node);
+    }
+}
+
+/// Whether the attributes contain `#[name]` or `#[name(...)]`
+fn has_attr(attrs: &[Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident(name))
+}
+
+/// Macros, private callables and private fields used across module
+/// boundaries
+///
+/// # Arguments
+///
+/// * `scans` - Module names with what each module defines and uses
+///
+/// # Returns
+///
+/// One risk per module and name it uses from a sibling that cannot see it.
+/// Names the using module defines itself are not reported.
+pub fn cross_module_risks(scans: &[(String, RiskScan)]) -> Vec<Risk> {
+    let mut risks = Vec::new();
+    for (user, used) in scans {
+        for (owner, defined) in scans {
+            if owner == user {
+                continue;
+            }
+            for name in used.invoked_macros.intersection(&defined.macros) {
+                if !used.macros.contains(name) {
+                    risks.push(Risk {
+                        kind: RiskKind::MacroScoping,
+                        module: user.clone(),
+                        detail: format!("macro `{}!` is defined in {}.rs", name, owner),
+                    });
+                }
+            }
+            for name in used.calls.intersection(&defined.private_fns) {
+                if !used.private_fns.contains(name) {
+                    risks.push(Risk {
+                        kind: RiskKind::PrivateCall,
+                        module: user.clone(),
+                        detail: format!("calls `{}`, which is private to {}.rs", name, owner),
+                    });
+                }
+            }
+            for field in used.field_uses.intersection(&defined.private_fields) {
+                if !used.private_fields.contains(field) {
+                    let (type_name, name) = field;
+                    risks.push(Risk {
+                        kind: RiskKind::PrivateField,
+                        module: user.clone(),
+                        detail: format!(
+                            "uses field `{}` of `{}`, which is private to {}.rs",
+                            name, type_name, owner
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    risks
+}
+
+/// Names of types whose definition or inherent impl is `#[cfg]`-gated
+///
+/// Split impl blocks are generated without the original's attributes, so
+/// methods of these types would be compiled unconditionally.
+pub fn cfg_gated_types(file: &File) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    for item in &file.items {
+        match item {
+            Item::Impl(impl_block)
+                if impl_block.trait_.is_none() && has_attr(&impl_block.attrs, "cfg") =>
+            {
+                if let syn::Type::Path(ty) = &*impl_block.self_ty {
+                    if let Some(last) = ty.path.segments.last() {
+                        names.insert(last.ident.to_string());
+                    }
+                }
+            }
+            Item::Struct(_) | Item::Enum(_) | Item::Union(_)
+                if has_attr(item_attrs(item), "cfg") =>
+            {
+                names.extend(crate::import_analyzer::declared_ident(item));
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+/// Human-readable list of risks
+pub fn render_risks(risks: &[Risk]) -> String {
+    risks.iter().map(|risk| format!("  {}\n", risk)).collect()
+}
+
+/// Every risk of a split in one report, grouped by kind
+///
+/// # Arguments
+///
+/// * `risks` - The risks, sorted
+pub fn render_safety_report(risks: &[Risk]) -> String {
+    if risks.is_empty() {
+        return "  No semantic risks found\n".to_string();
+    }
+    let mut report = String::new();
+    let mut kinds: Vec<RiskKind> = risks.iter().map(|risk| risk.kind).collect();
+    kinds.dedup();
+    for kind in kinds {
+        let of_kind: Vec<&Risk> = risks.iter().filter(|risk| risk.kind == kind).collect();
+        let note = if kind.blocks_strict() {
+            ""
+        } else {
+            ", informational"
+        };
+        report.push_str(&format!(
+            "  {} ({}{}):\n",
+            kind.label(),
+            of_kind.len(),
+            note
+        ));
+        for risk in of_kind {
+            report.push_str(&format!("    {}.rs: {}\n", risk.module, risk.detail));
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(file: File) -> RiskScan {
+        let mut scan = RiskScan::default();
+        scan.visit_file(&file);
+        scan
+    }
+
+    #[test]
+    fn test_detects_macros_and_private_calls_across_modules() {
+        let functions = scan(syn::parse_quote! {
+            macro_rules! check { ($e:expr) => { assert!($e) }; }
+            #[macro_export]
+            macro_rules! shared { () => {}; }
+            fn helper() -> u32 { 1 }
+            pub fn api() -> u32 { helper() }
+        });
+        let parser = scan(syn::parse_quote! {
+            impl Parser {
+                fn step(&self) { check!(self.len() == 1); shared!(); helper(); }
+            }
+            impl Drop for Parser { fn drop(&mut self) {} }
+        });
+        let mut risks = cross_module_risks(&[
+            ("functions".to_string(), functions),
+            ("parser_impl".to_string(), parser),
+        ]);
+        risks.sort();
+
+        let kinds: Vec<RiskKind> = risks.iter().map(|r| r.kind).collect();
+        assert_eq!(kinds, vec![RiskKind::MacroScoping, RiskKind::PrivateCall]);
+        assert!(risks.iter().all(|r| r.module == "parser_impl"));
+        assert_eq!(
+            risks[1].to_string(),
+            "[private call] parser_impl.rs: calls `helper`, which is private to functions.rs"
+        );
+        assert!(
+            render_safety_report(&risks).starts_with("  macro scoping (1):\n    parser_impl.rs: ")
+        );
+    }
+
+    #[test]
+    fn test_private_methods_split_across_groups() {
+        let accessors = scan(syn::parse_quote! {
+            impl Buffer {
+                fn grow(&mut self) {}
+                pub fn len(&self) -> usize { 0 }
+            }
+        });
+        let mutators = scan(syn::parse_quote! {
+            impl Buffer {
+                pub fn push(&mut self) { self.grow(); Self::len(self); }
+            }
+        });
+        let risks = cross_module_risks(&[
+            ("buffer_accessors".to_string(), accessors),
+            ("buffer_mutators".to_string(), mutators),
+        ]);
+        assert_eq!(risks.len(), 1);
+        assert!(risks[0].detail.contains("`grow`"));
+    }
+
+    #[test]
+    fn test_private_fields_used_by_moved_impls() {
+        let types = scan(syn::parse_quote! {
+            pub struct Store { items: Vec<u32>, pub name: String }
+            pub struct Id(u64);
+        });
+        let traits = scan(syn::parse_quote! {
+            impl Default for Store {
+                fn default() -> Self { Self { items: Vec::new(), name: String::new() } }
+            }
+            impl Clone for Id {
+                fn clone(&self) -> Self { Id(self.0) }
+            }
+            impl Store {
+                pub fn named(&self) -> &str { &self.name }
+            }
+        });
+        let mut risks = cross_module_risks(&[
+            ("types".to_string(), types),
+            ("store_traits".to_string(), traits),
+        ]);
+        risks.sort();
+
+        let details: Vec<&str> = risks.iter().map(|r| r.detail.as_str()).collect();
+        assert_eq!(
+            details,
+            vec![
+                "uses field `0` of `Id`, which is private to types.rs",
+                "uses field `items` of `Store`, which is private to types.rs",
+            ]
+        );
+        assert!(risks.iter().all(|r| r.kind == RiskKind::PrivateField));
+    }
+
+    #[test]
+    fn test_cfg_gated_types() {
+        let file: File = syn::parse_quote! {
+            #[cfg(feature = "serde")]
+            pub struct Wire;
+            pub struct Plain;
+            #[cfg(unix)]
+            impl Plain { fn fd(&self) {} }
+            #[cfg(test)]
+            impl Default for Wire { fn default() -> Self { Wire } }
+        };
+        let names: Vec<String> = cfg_gated_types(&file).into_iter().collect();
+        assert_eq!(names, vec!["Plain", "Wire"]);
+    }
+}
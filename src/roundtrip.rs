@@ -0,0 +1,271 @@
+//! Round-trip verification of a split
+//!
+//! The generated files are parsed again and their items are compared with
+//! the input's, token stream by token stream. Impl blocks are compared per
+//! associated item (under the impl header), since splitting spreads them
+//! over several blocks. The changes SplitRS makes on purpose are normalized
+//! away first: `use` statements, visibility, doc text, `#[path]` attributes and
+//! the module prefixes of `crate::`/`self::`/`super::` paths. Anything else
+//! that differs was dropped, duplicated or altered.
+
+use quote::ToTokens;
+use std::collections::{BTreeMap, BTreeSet};
+use syn::visit_mut::VisitMut;
+use syn::{File, Item};
+
+/// How many differences to list per category
+const MAX_LISTED: usize = 10;
+
+/// Items whose count differs between the input and the output
+#[derive(Debug, Default)]
+pub struct RoundtripReport {
+    /// Items of the input missing from the output (or altered there)
+    pub missing: Vec<String>,
+
+    /// Items that appear more often in the output than in the input
+    pub duplicated: Vec<String>,
+
+    /// Items of the output that do not match any input item
+    pub unexpected: Vec<String>,
+}
+
+impl RoundtripReport {
+    /// Whether the output holds exactly the input's items
+    pub fn is_clean(&self) -> bool {
+        self.missing.is_empty() && self.duplicated.is_empty() && self.unexpected.is_empty()
+    }
+
+    /// Human-readable list of the differences
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (label, units) in [
+            ("Missing or altered", &self.missing),
+            ("Duplicated", &self.duplicated),
+            ("Unexpected", &self.unexpected),
+        ] {
+            if units.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("{} ({}):\n", label, units.len()));
+            for unit in units.iter().take(MAX_LISTED) {
+                out.push_str(&format!("  - {}\n", abbreviate(unit)));
+            }
+            if units.len() > MAX_LISTED {
+                out.push_str(&format!("  ... and {} more\n", units.len() - MAX_LISTED));
+            }
+        }
+        out
+    }
+}
+
+/// Compare the items of the input with those of the generated files
+///
+/// # Arguments
+///
+/// * `original` - The parsed input
+/// * `generated` - The parsed generated files (modules and `mod.rs`)
+/// * `module_names` - Names of the generated modules and the modules on
+///   the output's path, whose declarations and path prefixes are ignored
+pub fn verify(
+    original: &File,
+    generated: &[File],
+    module_names: &BTreeSet<String>,
+) -> RoundtripReport {
+    let expected = count_units(&original.items, module_names);
+    let actual = count_units(
+        generated.iter().flat_map(|file| file.items.iter()),
+        module_names,
+    );
+
+    let mut report = RoundtripReport::default();
+    for (unit, &count) in &expected {
+        match actual.get(unit) {
+            None => report.missing.push(unit.clone()),
+            Some(&found) if found < count => report.missing.push(unit.clone()),
+            Some(&found) if found > count => report.duplicated.push(unit.clone()),
+            _ => {}
+        }
+    }
+    for unit in actual.keys() {
+        if !expected.contains_key(unit) {
+            report.unexpected.push(unit.clone());
+        }
+    }
+    report
+}
+
+/// Normalized comparison units of a set of items, with their counts
+fn count_units<'a>(
+    items: impl IntoIterator<Item = &'a Item>,
+    module_names: &BTreeSet<String>,
+) -> BTreeMap<String, usize> {
+    let mut normalizer = Normalizer { module_names };
+    let mut units = BTreeMap::new();
+    for item in items {
+        match item {
+            Item::Use(_) => {}
+            Item::Mod(m) if m.content.is_none() && module_names.contains(&m.ident.to_string()) => {}
+            _ => {
+                let mut item = item.clone();
+                normalizer.visit_item_mut(&mut item);
+                for unit in item_units(&reformatted(item)) {
+                    *units.entry(unit).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+    units
+}
+
+/// An item as comparison units: one per associated item for impl blocks
+fn item_units(item: &Item) -> Vec<String> {
+    let Item::Impl(impl_block) = item else {
+        return vec![item.to_token_stream().to_string()];
+    };
+    let mut header = impl_block.clone();
+    header.items.clear();
+    let header = header.to_token_stream().to_string();
+    if impl_block.items.is_empty() {
+        return vec![header];
+    }
+    impl_block
+        .items
+        .iter()
+        .map(|impl_item| format!("{} => {}", header, impl_item.to_token_stream()))
+        .collect()
+}
+
+/// The item as prettyplease prints it, parsed back
+///
+/// Pretty-printing changes tokens (trailing commas in wrapped lists, braces
+/// around long match arms), so both sides go through it before comparing.
+fn reformatted(item: Item) -> Item {
+    let text = prettyplease::unparse(&File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![item.clone()],
+    });
+    match syn::parse_file(&text) {
+        Ok(mut file) if file.items.len() == 1 => file.items.remove(0),
+        _ => item,
+    }
+}
+
+/// Erases the differences SplitRS introduces on purpose
+struct Normalizer<'a> {
+    module_names: &'a BTreeSet<String>,
+}
+
+impl VisitMut for Normalizer<'_> {
+    fn visit_visibility_mut(&mut self, vis: &mut syn::Visibility) {
+        *vis = syn::Visibility::Inherited;
+    }
+
+    fn visit_attribute_mut(&mut self, attr: &mut syn::Attribute) {
+        if attr.path().is_ident("doc") {
+            *attr = syn::parse_quote!(#[doc = ""]);
+        }
+    }
+
+    fn visit_item_mod_mut(&mut self, module: &mut syn::ItemMod) {
+        module.attrs.retain(|attr| !attr.path().is_ident("path"));
+        syn::visit_mut::visit_item_mod_mut(self, module);
+    }
+
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        if path.leading_colon.is_none() && path.segments.len() > 1 {
+            let prefix = path
+                .segments
+                .iter()
+                .take(path.segments.len() - 1)
+                .take_while(|segment| {
+                    let name = segment.ident.to_string();
+                    matches!(name.as_str(), "crate" | "self" | "super")
+                        || self.module_names.contains(&name)
+                })
+                .count();
+            if prefix > 0 {
+                path.segments = path.segments.iter().skip(prefix).cloned().collect();
+            }
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+}
+
+/// A unit shortened for display
+fn abbreviate(unit: &str) -> String {
+    const MAX_CHARS: usize = 120;
+    if unit.chars().count() <= MAX_CHARS {
+        unit.to_string()
+    } else {
+        format!("{}...", unit.chars().take(MAX_CHARS).collect::<String>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> BTreeSet<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_faithful_split_is_clean() {
+        let original: File = syn::parse_quote! {
+            use std::fmt;
+            /// A [`Point`]
+            pub struct Point { x: i32 }
+            impl Point {
+                pub fn new() -> Self { Point { x: crate::big::ORIGIN } }
+                fn x(&self) -> i32 { self.x }
+            }
+            pub const ORIGIN: i32 = 0;
+        };
+        let types: File = syn::parse_quote! {
+            use super::functions::ORIGIN;
+            /// A [`Point`](super::types::Point)
+            pub struct Point { pub(super) x: i32 }
+            impl Point { pub fn new() -> Self { Point { x: crate::big_split::ORIGIN } } }
+        };
+        let accessors: File = syn::parse_quote! {
+            impl Point { pub(super) fn x(&self) -> i32 { self.x } }
+        };
+        let functions: File = syn::parse_quote! { pub const ORIGIN: i32 = 0; };
+        let mod_rs: File = syn::parse_quote! {
+            pub mod types;
+            pub mod functions;
+            pub use types::*;
+        };
+
+        let report = verify(
+            &original,
+            &[types, accessors, functions, mod_rs],
+            &names(&["types", "functions", "big", "big_split"]),
+        );
+        assert!(report.is_clean(), "{}", report.render());
+    }
+
+    #[test]
+    fn test_lost_and_duplicated_items_are_reported() {
+        let original: File = syn::parse_quote! {
+            pub struct Config;
+            impl Config {
+                pub const LIMIT: u32 = 3;
+                pub fn new() -> Self { Config }
+            }
+        };
+        let generated: File = syn::parse_quote! {
+            pub struct Config;
+            pub struct Config;
+            impl Config { pub fn new() -> Self { Config } }
+        };
+
+        let report = verify(&original, &[generated], &BTreeSet::new());
+        assert_eq!(report.missing.len(), 1);
+        assert!(report.missing[0].contains("LIMIT"));
+        assert_eq!(report.duplicated.len(), 1);
+        assert!(report.unexpected.is_empty());
+        assert!(report.render().starts_with("Missing or altered (1):"));
+    }
+}
@@ -81,18 +81,10 @@ impl ScopeAnalyzer {
         }
     }
 
-    /// Analyze a parsed file to determine type locations
-    pub fn analyze_types(&mut self, items: &[Item]) {
-        for item in items {
-            match item {
-                Item::Struct(s) => {
-                    self.register_type(&s.ident.to_string(), "types");
-                }
-                Item::Enum(e) => {
-                    self.register_type(&e.ident.to_string(), "types");
-                }
-                _ => {}
-            }
+    /// Register the struct and enum names found in the file
+    pub fn register_types(&mut self, type_names: &[String]) {
+        for type_name in type_names {
+            self.register_type(type_name, "types");
         }
     }
 
@@ -0,0 +1,162 @@
+//! Grouping the methods of state-machine types
+//!
+//! Connection handlers, parsers and protocol drivers tend to be one type
+//! with an event method per state (`on_handshake_ack`, `enter_closing`)
+//! next to a `State` enum. Their call graph says little about how they are
+//! organized, so with state-machine grouping each method is assigned the
+//! state or event family it deals with, and each family gets a module
+//! (`connection_handshake.rs`, `connection_closing.rs`).
+
+use crate::method_analyzer::{handled_variants, MethodInfo};
+
+/// Prefixes of methods that react to an event or a state change
+const EVENT_PREFIXES: &[&str] = &["on_", "enter_", "exit_", "leave_", "handle_"];
+
+/// Fewest methods with a family for a type to count as a state machine
+const MIN_FAMILY_METHODS: usize = 3;
+
+/// A state enum of the input: its name and variants in declaration order
+pub struct StateEnum {
+    pub name: String,
+    pub variants: Vec<String>,
+}
+
+impl StateEnum {
+    /// Whether an enum looks like the state of a state machine
+    pub fn from_enum(item: &syn::ItemEnum) -> Option<Self> {
+        let name = item.ident.to_string();
+        if !name.ends_with("State") {
+            return None;
+        }
+        Some(Self {
+            name,
+            variants: item.variants.iter().map(|v| v.ident.to_string()).collect(),
+        })
+    }
+}
+
+/// `HandshakeSent` -> `handshake_sent`
+pub fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// Family of one method
+///
+/// An event method belongs to the first word after its prefix
+/// (`on_handshake_ack` -> `handshake`); any other method naming exactly one
+/// state (`ConnState::Closing`) belongs to that state. Dispatchers matching
+/// on several states and plain helpers have no family.
+fn method_family(method: &MethodInfo, states: &[StateEnum]) -> Option<String> {
+    let event = EVENT_PREFIXES
+        .iter()
+        .find_map(|prefix| method.name.strip_prefix(prefix))
+        .and_then(|rest| rest.split('_').find(|word| !word.is_empty()));
+    if let Some(event) = event {
+        return Some(event.to_string());
+    }
+    let named: Vec<String> = states
+        .iter()
+        .flat_map(|state| handled_variants(&method.item, &state.name, &state.variants))
+        .collect();
+    match named.as_slice() {
+        [state] => Some(snake_case(state)),
+        _ => None,
+    }
+}
+
+/// Families of a type's methods, if the type looks like a state machine
+///
+/// # Arguments
+///
+/// * `methods` - The methods of the impl being split
+/// * `states` - State enums of the input
+///
+/// # Returns
+///
+/// The family of each method in order, or `None` when fewer than three
+/// methods have a family or they all share one.
+pub fn method_families(
+    methods: &[MethodInfo],
+    states: &[StateEnum],
+) -> Option<Vec<Option<String>>> {
+    let families: Vec<Option<String>> = methods
+        .iter()
+        .map(|method| method_family(method, states))
+        .collect();
+    let with_family: Vec<&String> = families.iter().flatten().collect();
+    let distinct = with_family
+        .iter()
+        .collect::<std::collections::BTreeSet<_>>()
+        .len();
+    (with_family.len() >= MIN_FAMILY_METHODS && distinct > 1).then_some(families)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::method_analyzer::ImplBlockAnalyzer;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_families_from_states_and_event_names() {
+        let state: syn::ItemEnum = parse_quote! {
+            enum ConnState { Idle, Handshake, Closing }
+        };
+        let impl_block: syn::ItemImpl = parse_quote! {
+            impl Connection {
+                fn on_handshake_ack(&mut self) { self.state = ConnState::Idle; }
+                fn send_hello(&mut self) { self.state = ConnState::Handshake; }
+                fn enter_closing(&mut self) { self.flush(); }
+                fn on_closing_timeout(&mut self) { self.state = ConnState::Closing; }
+                fn step(&mut self) {
+                    match self.state { ConnState::Idle => {}, ConnState::Handshake => {}, _ => {} }
+                }
+                fn flush(&mut self) {}
+            }
+        };
+        let mut analyzer = ImplBlockAnalyzer::new();
+        analyzer.analyze(&impl_block);
+
+        let states = vec![StateEnum::from_enum(&state).unwrap()];
+        let families = method_families(analyzer.methods(), &states).unwrap();
+        assert_eq!(
+            families,
+            vec![
+                Some("handshake".to_string()),
+                Some("handshake".to_string()),
+                Some("closing".to_string()),
+                Some("closing".to_string()),
+                None,
+                None,
+            ]
+        );
+
+        let groups = analyzer.group_methods_by_family(&families, 1000);
+        let names: Vec<String> = groups.iter().map(|g| g.suggest_name()).collect();
+        assert_eq!(
+            names,
+            vec!["handshake", "closing", "step_group", "flush_group"]
+        );
+    }
+
+    #[test]
+    fn test_plain_types_are_not_state_machines() {
+        let impl_block: syn::ItemImpl = parse_quote! {
+            impl Store {
+                fn on_insert(&mut self) {}
+                fn get(&self) {}
+                fn len(&self) {}
+            }
+        };
+        let mut analyzer = ImplBlockAnalyzer::new();
+        analyzer.analyze(&impl_block);
+        assert!(method_families(analyzer.methods(), &[]).is_none());
+    }
+}
@@ -0,0 +1,126 @@
+//! Run statistics for tracking refactoring debt over time
+//!
+//! When `output.stats_file` is set, every run (previews included) appends
+//! one JSON line to it: when it ran, what was split, how large the input
+//! was and how the output came out. Charting the file shows how oversized
+//! files shrink across a project's history.
+
+use crate::file_system::FileSystem;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// One line of the stats file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunStats {
+    /// Seconds since the Unix epoch when the run finished
+    pub timestamp: u64,
+
+    /// `"dry_run"` for previews, `"split"` for runs that wrote files
+    pub mode: String,
+
+    /// Input files
+    pub inputs: Vec<PathBuf>,
+
+    /// Total line count of the inputs
+    pub original_lines: usize,
+
+    /// Number of generated modules
+    pub module_count: usize,
+
+    /// Total line count of the generated modules
+    pub output_lines: usize,
+
+    /// Line count of the largest generated module
+    pub largest_module_lines: usize,
+
+    /// Number of warnings the run reported
+    pub warnings: usize,
+}
+
+impl RunStats {
+    /// Record of a run that finished now
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - `"dry_run"` or `"split"`
+    /// * `inputs` - Input files
+    /// * `original_lines` - Total line count of the inputs
+    /// * `module_lines` - Line count of each generated module
+    /// * `warnings` - Number of warnings the run reported
+    pub fn new(
+        mode: &str,
+        inputs: &[PathBuf],
+        original_lines: usize,
+        module_lines: &[usize],
+        warnings: usize,
+    ) -> Self {
+        Self {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            mode: mode.to_string(),
+            inputs: inputs.to_vec(),
+            original_lines,
+            module_count: module_lines.len(),
+            output_lines: module_lines.iter().sum(),
+            largest_module_lines: module_lines.iter().copied().max().unwrap_or_default(),
+            warnings,
+        }
+    }
+
+    /// Append this record as a JSON line to `path`, creating the file (and
+    /// its directory) if needed
+    pub fn append(&self, file_system: &dyn FileSystem, path: &Path) -> Result<()> {
+        let mut content = if file_system.exists(path) {
+            file_system.read(path)?
+        } else {
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                file_system.create_dir(dir)?;
+            }
+            String::new()
+        };
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content.push_str(&serde_json::to_string(self).context("Failed to serialize run stats")?);
+        content.push('\n');
+        file_system
+            .write(path, &content)
+            .context(format!("Failed to append run stats to {:?}", path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_system::MemoryFileSystem;
+
+    fn stats(mode: &str, module_lines: &[usize]) -> RunStats {
+        RunStats::new(mode, &[PathBuf::from("src/big.rs")], 1800, module_lines, 1)
+    }
+
+    #[test]
+    fn test_appends_one_line_per_run() {
+        let file_system = MemoryFileSystem::new();
+        let path = Path::new("target/splitrs/stats.jsonl");
+        stats("dry_run", &[600, 500])
+            .append(&file_system, path)
+            .unwrap();
+        stats("split", &[600, 500, 400])
+            .append(&file_system, path)
+            .unwrap();
+
+        let content = file_system.read(path).unwrap();
+        let records: Vec<RunStats> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].mode, "dry_run");
+        assert_eq!(records[1].module_count, 3);
+        assert_eq!(records[1].output_lines, 1500);
+        assert_eq!(records[1].largest_module_lines, 600);
+    }
+}
@@ -0,0 +1,294 @@
+//! Keeping test-only helpers with the tests that use them
+//!
+//! Private functions that only `#[cfg(test)]` code calls would otherwise
+//! land in `functions.rs` next to the real API, where non-test builds warn
+//! about them as dead code. They move into the test module that uses them,
+//! or, when several test items share them, become `#[cfg(test)]` items
+//! placed just before the first of those. Private associated functions
+//! only tests call move to a `#[cfg(test)] impl` block next to the tests.
+
+use std::collections::{BTreeMap, BTreeSet};
+use syn::visit::Visit;
+use syn::{Attribute, ImplItem, Item, ItemImpl, Visibility};
+
+/// Whether the attributes contain `#[cfg(test)]`
+pub fn is_cfg_test(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("cfg")
+            && attr
+                .parse_args::<syn::Ident>()
+                .is_ok_and(|ident| ident == "test")
+    })
+}
+
+/// Whether a top-level item only exists in test builds
+pub fn is_test_item(item: &Item) -> bool {
+    match item {
+        Item::Mod(m) => is_cfg_test(&m.attrs),
+        Item::Impl(i) => is_cfg_test(&i.attrs),
+        Item::Use(u) => is_cfg_test(&u.attrs),
+        _ => is_cfg_test(crate::internal_items::item_attrs(item)),
+    }
+}
+
+/// Names of functions and methods the visited code calls or refers to
+/// (`helper()`, `super::helper()`, `Self::build()`, `.reset()`)
+#[derive(Default)]
+pub struct UsedNames {
+    pub names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for UsedNames {
+    fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+        if let Some(last) = node.path.segments.last() {
+            self.names.insert(last.ident.to_string());
+        }
+        syn::visit::visit_expr_path(self, node);
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        self.names.insert(node.method.to_string());
+        syn::visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        // Tests call most code inside `assert!`-style macros, whose arguments
+        // are expression lists
+        if let Ok(args) = node.parse_body_with(
+            syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+        ) {
+            for arg in &args {
+                self.visit_expr(arg);
+            }
+        }
+        syn::visit::visit_macro(self, node);
+    }
+}
+
+/// Names used by one item
+fn used_names(item: &Item) -> BTreeSet<String> {
+    let mut used = UsedNames::default();
+    used.visit_item(item);
+    used.names
+}
+
+/// Name of a private free function
+fn private_fn_name(item: &Item) -> Option<String> {
+    match item {
+        Item::Fn(f) if matches!(f.vis, Visibility::Inherited) && !is_cfg_test(&f.attrs) => {
+            Some(f.sig.ident.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Takes the private methods only test code uses out of an inherent impl
+///
+/// # Arguments
+///
+/// * `impl_block` - The impl; the methods are removed from it
+/// * `test_names` - Names used by test code
+/// * `other_names` - Names used by all other code
+///
+/// # Returns
+///
+/// A `#[cfg(test)]` copy of the impl holding the removed methods, if any
+pub fn take_test_methods(
+    impl_block: &mut ItemImpl,
+    test_names: &BTreeSet<String>,
+    other_names: &BTreeSet<String>,
+) -> Option<ItemImpl> {
+    if impl_block.trait_.is_some() || is_cfg_test(&impl_block.attrs) {
+        return None;
+    }
+    let (moved, kept): (Vec<ImplItem>, Vec<ImplItem>) = std::mem::take(&mut impl_block.items)
+        .into_iter()
+        .partition(|item| match item {
+            ImplItem::Fn(f) => {
+                let name = f.sig.ident.to_string();
+                matches!(f.vis, Visibility::Inherited)
+                    && test_names.contains(&name)
+                    && !other_names.contains(&name)
+            }
+            _ => false,
+        });
+    impl_block.items = kept;
+    if moved.is_empty() {
+        return None;
+    }
+    let mut test_impl = impl_block.clone();
+    test_impl.attrs.push(syn::parse_quote!(#[cfg(test)]));
+    test_impl.items = moved;
+    Some(test_impl)
+}
+
+/// Move test-only helpers next to (or into) the test items using them
+///
+/// # Arguments
+///
+/// * `items` - Standalone items, in source order
+/// * `test_impls` - `#[cfg(test)]` impls built by `take_test_methods`
+/// * `other_names` - Names used by code that is not among `items`
+///
+/// # Returns
+///
+/// The items with the helpers relocated
+pub fn place_test_helpers(
+    items: Vec<Item>,
+    test_impls: Vec<ItemImpl>,
+    other_names: &BTreeSet<String>,
+) -> Vec<Item> {
+    // The test impls go before the first test item and count as test code
+    let mut items = items;
+    let position = items.iter().position(is_test_item).unwrap_or(items.len());
+    items.splice(position..position, test_impls.into_iter().map(Item::Impl));
+
+    // Helpers used, directly or through other helpers, only by test items
+    let mut helpers: BTreeSet<String> = items.iter().filter_map(private_fn_name).collect();
+    loop {
+        let mut used: BTreeSet<String> = other_names.clone();
+        for item in items.iter().filter(|item| !is_test_item(item)) {
+            if !private_fn_name(item).is_some_and(|name| helpers.contains(&name)) {
+                used.extend(used_names(item));
+            }
+        }
+        let before = helpers.len();
+        helpers.retain(|name| !used.contains(name));
+        if helpers.len() == before {
+            break;
+        }
+    }
+
+    // Helpers each test item reaches, directly or through other helpers
+    let helper_calls: BTreeMap<String, BTreeSet<String>> = items
+        .iter()
+        .filter_map(|item| Some((private_fn_name(item)?, used_names(item))))
+        .filter(|(name, _)| helpers.contains(name))
+        .collect();
+    let reach = |item: &Item| -> BTreeSet<String> {
+        let mut reached = BTreeSet::new();
+        let mut pending: Vec<String> = used_names(item).into_iter().collect();
+        while let Some(name) = pending.pop() {
+            if let Some(calls) = helper_calls.get(&name) {
+                if reached.insert(name) {
+                    pending.extend(calls.iter().cloned());
+                }
+            }
+        }
+        reached
+    };
+    let reached: BTreeSet<String> = items
+        .iter()
+        .filter(|item| is_test_item(item))
+        .flat_map(reach)
+        .collect();
+
+    let (moving, mut result): (Vec<Item>, Vec<Item>) = items
+        .into_iter()
+        .partition(|item| private_fn_name(item).is_some_and(|name| reached.contains(&name)));
+    let test_users = |name: &str, result: &[Item]| -> Vec<usize> {
+        result
+            .iter()
+            .enumerate()
+            .filter(|(_, item)| is_test_item(item) && reach(item).contains(name))
+            .map(|(i, _)| i)
+            .collect()
+    };
+
+    let moved: BTreeSet<String> = moving.iter().filter_map(private_fn_name).collect();
+    for helper in moving {
+        let name = private_fn_name(&helper).unwrap_or_default();
+        let users = test_users(&name, &result);
+        if let [user] = users.as_slice() {
+            if let Item::Mod(module) = &mut result[*user] {
+                if let Some((_, content)) = &mut module.content {
+                    // After the imports and the helpers moved in before
+                    let position = content
+                        .iter()
+                        .position(|item| {
+                            !matches!(item, Item::Use(_))
+                                && !private_fn_name(item).is_some_and(|n| moved.contains(&n))
+                        })
+                        .unwrap_or(content.len());
+                    content.insert(position, helper);
+                    continue;
+                }
+            }
+        }
+        let mut helper = helper;
+        if let Item::Fn(f) = &mut helper {
+            f.attrs.push(syn::parse_quote!(#[cfg(test)]));
+        }
+        let position = users.first().copied().unwrap_or(result.len());
+        result.insert(position, helper);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::ToTokens;
+
+    fn render(items: &[Item]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| item.to_token_stream().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_helpers_move_into_their_test_module() {
+        let file: syn::File = syn::parse_quote! {
+            pub fn parse(s: &str) -> u32 { s.len() as u32 + used() }
+            fn sample() -> String { pad("x") }
+            fn pad(s: &str) -> String { format!(" {}", s) }
+            fn used() -> u32 { 1 }
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+                #[test]
+                fn parses() { assert_eq!(parse(&sample()), 3); }
+            }
+        };
+        let items = place_test_helpers(file.items, Vec::new(), &BTreeSet::new());
+        let rendered = render(&items);
+        assert_eq!(rendered.len(), 3);
+        assert!(rendered[1].starts_with("fn used"));
+        let Item::Mod(tests) = &items[2] else {
+            panic!("expected the test module last");
+        };
+        let inner = render(&tests.content.as_ref().unwrap().1);
+        assert!(inner[0].starts_with("use super"));
+        assert!(inner[1].starts_with("fn sample"));
+        assert!(inner[2].starts_with("fn pad"));
+    }
+
+    #[test]
+    fn test_shared_helpers_and_methods_get_cfg_test() {
+        let file: syn::File = syn::parse_quote! {
+            fn fixture() -> u32 { 1 }
+            #[cfg(test)]
+            fn check() -> bool { fixture() == 1 }
+            #[cfg(test)]
+            mod tests {
+                #[test]
+                fn fixture_is_one() { assert_eq!(super::fixture(), 1); }
+            }
+        };
+        let mut impl_block: ItemImpl = syn::parse_quote! {
+            impl Parser {
+                pub fn run(&self) {}
+                fn with_input(s: &str) -> Self { Parser }
+            }
+        };
+        let test_names: BTreeSet<String> = ["with_input".to_string()].into_iter().collect();
+        let test_impl = take_test_methods(&mut impl_block, &test_names, &BTreeSet::new()).unwrap();
+        assert_eq!(impl_block.items.len(), 1);
+
+        let items = place_test_helpers(file.items, vec![test_impl], &BTreeSet::new());
+        let rendered = render(&items);
+        assert!(rendered[0].starts_with("# [cfg (test)] impl Parser"));
+        assert!(rendered[1].starts_with("# [cfg (test)] fn fixture"));
+    }
+}
@@ -0,0 +1,205 @@
+//! Classifying trait impls by where the trait comes from
+//!
+//! Impls of std traits (`Display`, `Iterator`), of traits from other crates
+//! (`serde::Serialize`, `rand::Distribution`) and of the crate's own traits
+//! tend to change for different reasons and are reviewed by different
+//! people. With origin grouping enabled, each type's trait impls are split
+//! into one module per origin.
+
+use crate::import_analyzer::ImportAnalyzer;
+use std::collections::BTreeSet;
+
+/// Std traits that are in scope without an import
+const PRELUDE_TRAITS: &[&str] = &[
+    "Clone",
+    "Copy",
+    "Default",
+    "Drop",
+    "Eq",
+    "PartialEq",
+    "Ord",
+    "PartialOrd",
+    "From",
+    "Into",
+    "TryFrom",
+    "TryInto",
+    "AsRef",
+    "AsMut",
+    "Iterator",
+    "IntoIterator",
+    "DoubleEndedIterator",
+    "ExactSizeIterator",
+    "Extend",
+    "FromIterator",
+    "Send",
+    "Sync",
+    "Sized",
+    "Unpin",
+    "Fn",
+    "FnMut",
+    "FnOnce",
+    "ToString",
+    "ToOwned",
+];
+
+/// Formatting traits of `std::fmt`, kept together by fmt grouping
+pub const FMT_TRAITS: &[&str] = &[
+    "Binary", "Debug", "Display", "LowerExp", "LowerHex", "Octal", "Pointer", "UpperExp",
+    "UpperHex",
+];
+
+/// Where an implemented trait is defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TraitOrigin {
+    /// `std`, `core` or `alloc`
+    Std,
+
+    /// Another crate
+    External,
+
+    /// The crate being split
+    Local,
+}
+
+impl TraitOrigin {
+    /// Suffix of the module holding a type's impls of this origin
+    pub fn module_suffix(self) -> &'static str {
+        match self {
+            TraitOrigin::Std => "_std_traits",
+            TraitOrigin::External => "_external_traits",
+            TraitOrigin::Local => "_local_traits",
+        }
+    }
+}
+
+/// Origin of a path's root segment (`std`, `crate`, a crate name)
+fn root_origin(root: &str) -> TraitOrigin {
+    match root {
+        "std" | "core" | "alloc" => TraitOrigin::Std,
+        "crate" | "self" | "super" => TraitOrigin::Local,
+        _ => TraitOrigin::External,
+    }
+}
+
+/// Whether an impl's trait is one of the `std::fmt` formatting traits
+///
+/// # Arguments
+///
+/// * `path` - The implemented trait
+/// * `origin` - Its origin, as [`classify`] determined it
+pub fn is_fmt_trait(path: &syn::Path, origin: TraitOrigin) -> bool {
+    origin == TraitOrigin::Std
+        && path
+            .segments
+            .last()
+            .is_some_and(|segment| FMT_TRAITS.contains(&segment.ident.to_string().as_str()))
+}
+
+/// Classify the trait of an impl
+///
+/// # Arguments
+///
+/// * `path` - The trait path as written in the impl (`fmt::Display`)
+/// * `local_traits` - Traits defined in the input
+/// * `imports` - Import model of the input, to follow `use` statements
+pub fn classify(
+    path: &syn::Path,
+    local_traits: &BTreeSet<String>,
+    imports: &ImportAnalyzer,
+) -> TraitOrigin {
+    let Some(first) = path.segments.first() else {
+        return TraitOrigin::Local;
+    };
+    let first = first.ident.to_string();
+    let root = if path.leading_colon.is_some() {
+        Some(first.clone())
+    } else if path.segments.len() == 1 {
+        if local_traits.contains(&first) {
+            return TraitOrigin::Local;
+        }
+        imports.external_path(&first)
+    } else {
+        Some(
+            imports
+                .module_path(&first)
+                .map(str::to_string)
+                .unwrap_or_else(|| first.clone()),
+        )
+    };
+    match root {
+        Some(root) => root_origin(root.split("::").find(|s| !s.is_empty()).unwrap_or_default()),
+        None if PRELUDE_TRAITS.contains(&first.as_str()) => TraitOrigin::Std,
+        None => TraitOrigin::Local,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin_of(imports: &ImportAnalyzer, path: syn::Path) -> TraitOrigin {
+        let local: BTreeSet<String> = ["Shape".to_string()].into_iter().collect();
+        classify(&path, &local, imports)
+    }
+
+    #[test]
+    fn test_classifies_by_imports() {
+        let file: syn::File = syn::parse_quote! {
+            use std::fmt;
+            use std::ops::Add;
+            use serde::{Deserialize, Serialize};
+            use crate::codec::Encode;
+        };
+        let mut imports = ImportAnalyzer::new();
+        imports.analyze_file(&file);
+
+        assert_eq!(
+            origin_of(&imports, syn::parse_quote!(fmt::Display)),
+            TraitOrigin::Std
+        );
+        assert_eq!(
+            origin_of(&imports, syn::parse_quote!(Add)),
+            TraitOrigin::Std
+        );
+        assert_eq!(
+            origin_of(&imports, syn::parse_quote!(Clone)),
+            TraitOrigin::Std
+        );
+        assert_eq!(
+            origin_of(&imports, syn::parse_quote!(Serialize)),
+            TraitOrigin::External
+        );
+        assert_eq!(
+            origin_of(
+                &imports,
+                syn::parse_quote!(rand::distributions::Distribution)
+            ),
+            TraitOrigin::External
+        );
+        assert_eq!(
+            origin_of(&imports, syn::parse_quote!(Encode)),
+            TraitOrigin::Local
+        );
+        assert_eq!(
+            origin_of(&imports, syn::parse_quote!(Shape)),
+            TraitOrigin::Local
+        );
+    }
+
+    #[test]
+    fn test_absolute_paths() {
+        let imports = ImportAnalyzer::new();
+        assert_eq!(
+            origin_of(&imports, syn::parse_quote!(std::hash::Hash)),
+            TraitOrigin::Std
+        );
+        assert_eq!(
+            origin_of(&imports, syn::parse_quote!(::serde::Serialize)),
+            TraitOrigin::External
+        );
+        assert_eq!(
+            origin_of(&imports, syn::parse_quote!(crate::codec::Encode)),
+            TraitOrigin::Local
+        );
+    }
+}
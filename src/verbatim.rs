@@ -0,0 +1,233 @@
+//! Copying relocated items byte-for-byte from the input
+//!
+//! Items SplitRS only moves (types, trait impls, free functions it does not
+//! alter) are taken verbatim from the original source instead of being
+//! reformatted by prettyplease. Diffs then show pure moves, and comments,
+//! custom formatting and macro layouts inside the items survive. An item is
+//! considered unmodified when its tokens equal those of an input item;
+//! anything SplitRS changed (field visibility, rewritten paths or doc links)
+//! is still pretty-printed.
+//!
+//! With `format = "preserve"`, the blank lines between items are kept as
+//! in the input, and the methods of split impls are sliced from the input
+//! one by one, so their comments survive even though the impl around them
+//! is rebuilt.
+
+use crate::lenient::{body_start, item_spans};
+use quote::ToTokens;
+use std::collections::HashMap;
+use syn::{ImplItem, Item, ItemImpl};
+
+/// Source text of the input's top-level items, keyed by their tokens
+#[derive(Debug, Default)]
+pub struct VerbatimSource {
+    texts: HashMap<String, String>,
+
+    /// Whether to keep blank lines and slice impl members (`preserve`)
+    preserve: bool,
+
+    /// Blank lines before each item or impl member in the input
+    gaps: HashMap<String, usize>,
+
+    /// Source text of impl members, indented as in the input
+    members: HashMap<String, String>,
+}
+
+/// Blank lines in the whitespace before an item
+fn blank_lines(leading: &str) -> usize {
+    leading.matches('\n').count().saturating_sub(1)
+}
+
+impl VerbatimSource {
+    /// Source that also keeps blank lines and impl members (`preserve`)
+    pub fn preserving() -> Self {
+        Self {
+            preserve: true,
+            ..Self::default()
+        }
+    }
+
+    /// Index the top-level items of an input file
+    pub fn add(&mut self, source: &str) {
+        for (index, (start, end)) in item_spans(source).into_iter().enumerate() {
+            let span = &source[start..end];
+            // Inner docs and attributes belong to the file, not the item
+            let text = without_inner_attributes(span.trim());
+            let Ok(parsed) = syn::parse_file(text) else {
+                continue;
+            };
+            if let ([item], true) = (parsed.items.as_slice(), parsed.attrs.is_empty()) {
+                let key = item.to_token_stream().to_string();
+                if self.preserve {
+                    let leading = &span[..span.len() - span.trim_start().len()];
+                    if index > 0 {
+                        self.gaps.insert(key.clone(), blank_lines(leading));
+                    }
+                    if matches!(item, Item::Impl(_)) {
+                        self.add_members(text);
+                    }
+                }
+                self.texts.insert(key, text.to_string());
+            }
+        }
+    }
+
+    /// Index the members of an impl block's source text
+    fn add_members(&mut self, text: &str) {
+        let (Some(open), Some(close)) = (body_start(text), text.rfind('}')) else {
+            return;
+        };
+        let body = &text[open + 1..close];
+        for (index, (start, end)) in item_spans(body).into_iter().enumerate() {
+            let span = &body[start..end];
+            let Ok(member) = syn::parse_str::<ImplItem>(span.trim()) else {
+                continue;
+            };
+            // From the start of the member's first line, keeping its indentation
+            let first = span.len() - span.trim_start().len();
+            let line_start = span[..first].rfind('\n').map_or(0, |n| n + 1);
+            let key = member.to_token_stream().to_string();
+            // The first member follows the brace, not a blank line of its own
+            if index > 0 {
+                self.gaps
+                    .insert(key.clone(), blank_lines(&span[..line_start]));
+            }
+            self.members
+                .insert(key, span[line_start..].trim_end().to_string());
+        }
+    }
+
+    /// Blank lines to put before an item, as in the input when preserving
+    fn gap_before(&self, key: &str) -> usize {
+        if self.preserve {
+            self.gaps.get(key).copied().unwrap_or(1)
+        } else {
+            1
+        }
+    }
+
+    /// An inherent impl rebuilt from members sliced from the input
+    ///
+    /// `None` unless preserving and every member is found unmodified.
+    pub fn render_impl(&self, impl_block: &ItemImpl) -> Option<String> {
+        if !self.preserve {
+            return None;
+        }
+        let mut header = impl_block.clone();
+        header.items.clear();
+        let header = prettyplease::unparse(&syn::File {
+            shebang: None,
+            attrs: Vec::new(),
+            items: vec![Item::Impl(header)],
+        });
+        let mut out = header.trim_end().strip_suffix("{}")?.to_string();
+        out.push_str("{\n");
+        for (index, member) in impl_block.items.iter().enumerate() {
+            let key = member.to_token_stream().to_string();
+            let text = self.members.get(&key)?;
+            if index > 0 {
+                out.push_str(&"\n".repeat(self.gap_before(&key)));
+            }
+            out.push_str(text);
+            out.push('\n');
+        }
+        out.push_str("}\n");
+        Some(out)
+    }
+
+    /// Original text of an item, if it was not modified
+    pub fn text_of(&self, item: &Item) -> Option<&str> {
+        self.texts
+            .get(&item.to_token_stream().to_string())
+            .map(String::as_str)
+    }
+
+    /// Render items, copying unmodified ones verbatim
+    pub fn render(&self, items: &[Item]) -> String {
+        let mut out = String::new();
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                let key = item.to_token_stream().to_string();
+                out.push_str(&"\n".repeat(self.gap_before(&key)));
+            }
+            match self.text_of(item) {
+                Some(text) => {
+                    out.push_str(text);
+                    out.push('\n');
+                }
+                None => out.push_str(&prettyplease::unparse(&syn::File {
+                    shebang: None,
+                    attrs: Vec::new(),
+                    items: vec![item.clone()],
+                })),
+            }
+        }
+        out
+    }
+}
+
+/// `text` without its leading `//!` lines and single-line `#![...]` attributes
+pub fn without_inner_attributes(text: &str) -> &str {
+    let mut rest = text;
+    while rest.starts_with("//!") || rest.starts_with("#!") {
+        rest = rest
+            .split_once('\n')
+            .map_or("", |(_, tail)| tail)
+            .trim_start();
+    }
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unmodified_items_keep_their_layout() {
+        let source = "//! Docs\n\n/// A point\npub struct Point {\n    x: i32, // horizontal\n    y: i32,\n}\n\nfn  spaced ( ) {}\n";
+        let mut verbatim = VerbatimSource::default();
+        verbatim.add(source);
+
+        let file = syn::parse_file(source).unwrap();
+        assert_eq!(
+            verbatim.render(&file.items),
+            "/// A point\npub struct Point {\n    x: i32, // horizontal\n    y: i32,\n}\n\nfn  spaced ( ) {}\n"
+        );
+    }
+
+    #[test]
+    fn test_modified_items_are_pretty_printed() {
+        let mut verbatim = VerbatimSource::default();
+        verbatim.add("fn  helper ( ) { crate::big::f() }\n");
+
+        let item: Item = syn::parse_quote! { fn helper() { crate::big_split::f() } };
+        assert!(verbatim.text_of(&item).is_none());
+        assert_eq!(
+            verbatim.render(&[item]),
+            "fn helper() {\n    crate::big_split::f()\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_preserve_keeps_blank_lines_and_method_comments() {
+        let source = "struct A;\nstruct B;\n\n\n// Free comment\nfn f() {}\n\nimpl A {\n    // Counts things\n    fn count(&self) -> u32 {\n        1 // one\n    }\n\n\n    fn  other (&self) {}\n}\n";
+        let mut verbatim = VerbatimSource::preserving();
+        verbatim.add(source);
+
+        let file = syn::parse_file(source).unwrap();
+        assert_eq!(
+            verbatim.render(&file.items[..3]),
+            "struct A;\nstruct B;\n\n\n// Free comment\nfn f() {}\n"
+        );
+        let Item::Impl(impl_block) = &file.items[3] else {
+            unreachable!()
+        };
+        let mut split = impl_block.clone();
+        split.items.reverse();
+        assert_eq!(
+            verbatim.render_impl(&split).unwrap(),
+            "impl A {\n    fn  other (&self) {}\n\n    // Counts things\n    fn count(&self) -> u32 {\n        1 // one\n    }\n}\n"
+        );
+        assert!(VerbatimSource::default().render_impl(&split).is_none());
+    }
+}
@@ -0,0 +1,287 @@
+//! Compiling the generated modules
+//!
+//! `--verify` runs `cargo check` once the files are written. When the
+//! output directory is declared as a module of its crate, the crate itself
+//! is checked; an input still sitting next to the directory (`big.rs` next
+//! to `big/`) would make `mod big;` ambiguous, so it is moved aside for the
+//! check and put back afterwards. Otherwise a scaffolding crate in a
+//! temporary directory includes the output with `#[path]`; names from the
+//! rest of the crate and from dependencies do not resolve there. Each error
+//! is mapped to the generated module it points into.
+
+use crate::existing_module::absolute;
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// One compile error of `cargo check`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    /// Generated module the error points into (`units::display_impls`)
+    pub module: Option<String>,
+
+    /// File and line of the primary span, if any
+    pub location: Option<(PathBuf, usize)>,
+
+    /// Error code (`E0616`), if any
+    pub code: Option<String>,
+
+    /// The compiler's message
+    pub message: String,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(module) = &self.module {
+            write!(f, "{}: ", module)?;
+        }
+        match &self.code {
+            Some(code) => write!(f, "error[{}]: {}", code, self.message)?,
+            None => write!(f, "error: {}", self.message)?,
+        }
+        if let Some((file, line)) = &self.location {
+            write!(f, " ({}:{})", file.display(), line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Crate to check the output in, if the output is one of its modules
+///
+/// The nearest ancestor with a `Cargo.toml`, when the parent module (its
+/// `mod.rs`, `lib.rs`, `main.rs` or `<parent>.rs`) declares the output
+/// directory.
+pub fn containing_crate(output_dir: &Path) -> Option<PathBuf> {
+    let output_dir = absolute(output_dir);
+    let name = output_dir.file_name()?.to_str()?;
+    let parent = output_dir.parent()?;
+    let declared = ["mod.rs", "lib.rs", "main.rs"]
+        .iter()
+        .map(|file| parent.join(file))
+        .chain([parent.with_extension("rs")])
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|source| syn::parse_file(&source).ok())
+        .any(|file| {
+            file.items
+                .iter()
+                .any(|item| matches!(item, syn::Item::Mod(m) if m.ident == name))
+        });
+    if !declared {
+        return None;
+    }
+    output_dir
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Run `cargo check` on the crate holding the output
+///
+/// # Arguments
+///
+/// * `crate_dir` - Directory of the crate's `Cargo.toml`
+/// * `shadowing` - Files to move aside during the check (inputs next to
+///   the module directories that replace them)
+/// * `output_dirs` - Directories of generated modules, for mapping errors
+///
+/// # Returns
+///
+/// The compile errors, empty when the crate checks cleanly
+pub fn check_crate(
+    crate_dir: &Path,
+    shadowing: &[PathBuf],
+    output_dirs: &[PathBuf],
+) -> Result<Vec<CompileError>> {
+    let mut moved = Vec::new();
+    for file in shadowing.iter().filter(|file| file.is_file()) {
+        let aside = file.with_extension("rs.splitrs-verify");
+        fs::rename(file, &aside).context(format!("Failed to move {:?} aside", file))?;
+        moved.push((file.clone(), aside));
+    }
+    let result = cargo_check(crate_dir, None, output_dirs);
+    for (file, aside) in moved {
+        fs::rename(&aside, &file).context(format!("Failed to restore {:?}", file))?;
+    }
+    result
+}
+
+/// Run `cargo check` on a scaffolding crate that includes the output
+///
+/// The crate lives in a temporary directory and is removed afterwards.
+pub fn check_scaffold(output_dir: &Path) -> Result<Vec<CompileError>> {
+    let output_dir = absolute(output_dir);
+    let name = output_dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| syn::parse_str::<syn::Ident>(name).is_ok())
+        .unwrap_or("generated");
+    let scaffold = std::env::temp_dir().join(format!("splitrs_verify_{}", std::process::id()));
+    fs::create_dir_all(&scaffold).context(format!("Failed to create {:?}", scaffold))?;
+    fs::write(
+        scaffold.join("Cargo.toml"),
+        "[package]\nname = \"splitrs_verify\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+         [lib]\npath = \"lib.rs\"\n\n[workspace]\n",
+    )?;
+    fs::write(
+        scaffold.join("lib.rs"),
+        format!(
+            "#[path = {:?}]\npub mod {};\n",
+            output_dir.join("mod.rs").display().to_string(),
+            name
+        ),
+    )?;
+    let result = cargo_check(&scaffold, Some(&scaffold.join("target")), &[output_dir]);
+    let _ = fs::remove_dir_all(&scaffold);
+    result
+}
+
+/// Run `cargo check --message-format=json` in `dir` and collect its errors
+fn cargo_check(
+    dir: &Path,
+    target_dir: Option<&Path>,
+    output_dirs: &[PathBuf],
+) -> Result<Vec<CompileError>> {
+    let mut command = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".into()));
+    command
+        .args(["check", "--all-targets", "--message-format=json"])
+        .current_dir(dir);
+    if let Some(target_dir) = target_dir {
+        command.env("CARGO_TARGET_DIR", target_dir);
+    }
+    let output = command
+        .output()
+        .context(format!("Failed to run cargo check in {:?}", dir))?;
+    let errors = parse_messages(&String::from_utf8_lossy(&output.stdout), dir, output_dirs);
+    if !output.status.success() && errors.is_empty() {
+        anyhow::bail!(
+            "cargo check failed in {:?}:\n{}",
+            dir,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(errors)
+}
+
+/// Errors among the JSON messages of `cargo check`
+///
+/// # Arguments
+///
+/// * `stdout` - Output of `cargo check --message-format=json`
+/// * `dir` - Directory cargo ran in; span paths are relative to it or to
+///   its workspace root
+/// * `output_dirs` - Directories of generated modules
+pub fn parse_messages(stdout: &str, dir: &Path, output_dirs: &[PathBuf]) -> Vec<CompileError> {
+    let dir = absolute(dir);
+    let output_dirs: Vec<PathBuf> = output_dirs.iter().map(|d| absolute(d)).collect();
+    let mut errors = Vec::new();
+    for line in stdout.lines() {
+        let Ok(record) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if record["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &record["message"];
+        let text = message["message"].as_str().unwrap_or_default();
+        if message["level"] != "error" || text.starts_with("aborting due to") {
+            continue;
+        }
+        let location = message["spans"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|span| span["is_primary"] == true)
+            .and_then(|span| {
+                let file = Path::new(span["file_name"].as_str()?);
+                let line = span["line_start"].as_u64()? as usize;
+                let file = if file.is_absolute() {
+                    file.to_path_buf()
+                } else {
+                    dir.ancestors()
+                        .map(|root| root.join(file))
+                        .find(|path| path.is_file())
+                        .unwrap_or_else(|| dir.join(file))
+                };
+                // `#[path]` modules report paths such as `src/ip/../shapes/a.rs`
+                Some((absolute(&file), line))
+            });
+        let module = location
+            .as_ref()
+            .and_then(|(file, _)| module_of(file, &output_dirs));
+        let error = CompileError {
+            module,
+            location,
+            code: message["code"]["code"].as_str().map(str::to_string),
+            message: text.to_string(),
+        };
+        if !errors.contains(&error) {
+            errors.push(error);
+        }
+    }
+    errors
+}
+
+/// Module path of a generated file (`units/display_impls.rs` ->
+/// `units::display_impls`), if it lies in one of `output_dirs`
+fn module_of(file: &Path, output_dirs: &[PathBuf]) -> Option<String> {
+    output_dirs.iter().find_map(|output_dir| {
+        let relative = file.strip_prefix(output_dir).ok()?;
+        let mut segments: Vec<String> = output_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .into_iter()
+            .collect();
+        for component in relative.with_extension("").components() {
+            segments.push(component.as_os_str().to_string_lossy().to_string());
+        }
+        if segments.last().is_some_and(|last| last == "mod") {
+            segments.pop();
+        }
+        Some(segments.join("::"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_messages_maps_errors_to_modules() {
+        let dir = std::env::temp_dir().join(format!("splitrs_verify_test_{}", std::process::id()));
+        let output_dir = dir.join("src/units");
+        fs::create_dir_all(&output_dir).unwrap();
+        fs::write(output_dir.join("display_impls.rs"), "").unwrap();
+        let stdout = [
+            r#"{"reason":"compiler-artifact","target":{}}"#,
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"field `0` of struct `Grams` is private","code":{"code":"E0616"},"spans":[{"file_name":"src/units/display_impls.rs","line_start":20,"is_primary":true}]}}"#,
+            r#"{"reason":"compiler-message","message":{"level":"warning","message":"unused import","code":null,"spans":[]}}"#,
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"cannot find type `Grams` in this scope","code":{"code":"E0412"},"spans":[{"file_name":"src/other/../units/display_impls.rs","line_start":4,"is_primary":true}]}}"#,
+            r#"{"reason":"compiler-message","message":{"level":"error","message":"aborting due to 1 previous error","code":null,"spans":[]}}"#,
+        ]
+        .join("\n");
+
+        let errors = parse_messages(&stdout, &dir, std::slice::from_ref(&output_dir));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].module.as_deref(), Some("units::display_impls"));
+        assert_eq!(errors[1].module, errors[0].module);
+        assert_eq!(
+            errors[0].to_string(),
+            format!(
+                "units::display_impls: error[E0616]: field `0` of struct `Grams` is private ({}:20)",
+                output_dir.join("display_impls.rs").display()
+            )
+        );
+        assert_eq!(
+            module_of(
+                &output_dir.join("mod.rs"),
+                std::slice::from_ref(&output_dir)
+            )
+            .as_deref(),
+            Some("units")
+        );
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,158 @@
+//! Grouping the methods of visitor types
+//!
+//! Compiler and AST crates have visitors with dozens of `visit_*` methods
+//! that mostly call the matching `walk_*` function and little else, so
+//! their call graph is flat and says nothing about how they belong
+//! together. With visitor grouping each method is assigned the category of
+//! the node it visits (expressions, items, types, ...), inferred from its
+//! name or its parameter type, and each category gets a module.
+
+use crate::method_analyzer::MethodInfo;
+use std::collections::BTreeSet;
+
+/// Prefixes of methods that visit one kind of node
+const VISIT_PREFIXES: &[&str] = &["visit_", "walk_", "fold_"];
+
+/// Fewest visit methods for a type to count as a visitor
+const MIN_VISIT_METHODS: usize = 6;
+
+/// Node categories by the first word of a node name (`expr_call`, `ItemFn`)
+const CATEGORIES: &[(&str, &str)] = &[
+    ("expr", "expressions"),
+    ("item", "items"),
+    ("type", "types"),
+    ("ty", "types"),
+    ("pat", "patterns"),
+    ("stmt", "statements"),
+    ("local", "statements"),
+    ("block", "statements"),
+    ("path", "paths"),
+    ("generic", "generics"),
+    ("generics", "generics"),
+    ("lifetime", "generics"),
+    ("where", "generics"),
+    ("bound", "generics"),
+    ("attr", "attributes"),
+    ("attribute", "attributes"),
+    ("meta", "attributes"),
+    ("lit", "literals"),
+];
+
+/// Category of a node name's first word
+fn category(word: &str) -> Option<&'static str> {
+    let word = word.to_lowercase();
+    CATEGORIES
+        .iter()
+        .find(|(prefix, _)| *prefix == word)
+        .map(|(_, category)| *category)
+}
+
+/// First word of the type of a method's first non-receiver parameter
+/// (`&ExprCall` -> `Expr`)
+fn parameter_word(method: &MethodInfo) -> Option<String> {
+    let ty = method
+        .item
+        .sig
+        .inputs
+        .iter()
+        .find_map(|input| match input {
+            syn::FnArg::Typed(arg) => Some(&*arg.ty),
+            syn::FnArg::Receiver(_) => None,
+        })?;
+    let mut ty = ty;
+    while let syn::Type::Reference(reference) = ty {
+        ty = &reference.elem;
+    }
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let name = path.path.segments.last()?.ident.to_string();
+    let end = name
+        .char_indices()
+        .skip(1)
+        .find(|(_, c)| c.is_uppercase())
+        .map_or(name.len(), |(i, _)| i);
+    Some(name[..end].to_string())
+}
+
+/// Category of one method: from the word after its `visit_` prefix, else
+/// from its parameter type; `None` for other methods
+fn method_category(method: &MethodInfo) -> Option<String> {
+    let rest = VISIT_PREFIXES
+        .iter()
+        .find_map(|prefix| method.name.strip_prefix(prefix))?;
+    rest.split('_')
+        .next()
+        .and_then(category)
+        .or_else(|| parameter_word(method).as_deref().and_then(category))
+        .map(str::to_string)
+}
+
+/// Categories of a type's methods, if the type looks like a visitor
+///
+/// # Returns
+///
+/// The category of each method in order, or `None` when the type has
+/// fewer than six visit methods or they all fall into one category.
+pub fn method_families(methods: &[MethodInfo]) -> Option<Vec<Option<String>>> {
+    let visit_methods = methods
+        .iter()
+        .filter(|m| VISIT_PREFIXES.iter().any(|p| m.name.starts_with(p)))
+        .count();
+    let families: Vec<Option<String>> = methods.iter().map(method_category).collect();
+    let distinct: BTreeSet<&String> = families.iter().flatten().collect();
+    (visit_methods >= MIN_VISIT_METHODS && distinct.len() > 1).then_some(families)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::method_analyzer::ImplBlockAnalyzer;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_categories_from_names_and_parameters() {
+        let impl_block: syn::ItemImpl = parse_quote! {
+            impl Printer {
+                fn visit_expr_call(&mut self, node: &ExprCall) {}
+                fn visit_expr_binary(&mut self, node: &ExprBinary) {}
+                fn visit_item_fn(&mut self, node: &ItemFn) {}
+                fn visit_type_path(&mut self, node: &TypePath) {}
+                fn visit_call(&mut self, node: &ExprCall) {}
+                fn visit_signature(&mut self, node: &Signature) {}
+                fn indent(&mut self) {}
+            }
+        };
+        let mut analyzer = ImplBlockAnalyzer::new();
+        analyzer.analyze(&impl_block);
+
+        let families = method_families(analyzer.methods()).unwrap();
+        let expected: Vec<Option<String>> = [
+            Some("expressions"),
+            Some("expressions"),
+            Some("items"),
+            Some("types"),
+            Some("expressions"),
+            None,
+            None,
+        ]
+        .iter()
+        .map(|f| f.map(str::to_string))
+        .collect();
+        assert_eq!(families, expected);
+    }
+
+    #[test]
+    fn test_few_visit_methods_are_not_a_visitor() {
+        let impl_block: syn::ItemImpl = parse_quote! {
+            impl Printer {
+                fn visit_expr(&mut self, node: &Expr) {}
+                fn visit_item(&mut self, node: &Item) {}
+                fn print(&mut self) {}
+            }
+        };
+        let mut analyzer = ImplBlockAnalyzer::new();
+        analyzer.analyze(&impl_block);
+        assert!(method_families(analyzer.methods()).is_none());
+    }
+}